@@ -0,0 +1,216 @@
+/*
+ * Description: Derive macro for graphvizier's Graphable trait.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! `#[derive(Graphable)]` for structs containing collections of vertices and
+//! edges.
+//!
+//! Each field to include in the graph is annotated with one of:
+//! - `#[graphvizier(node)]`: each item is converted with `Into<Vertex>`.
+//! - `#[graphvizier(node(id))]` or `#[graphvizier(node(id, label))]`: each
+//!   item's `id` field is converted with `Into<Id>`, and its `label` field (if
+//!   given) is displayed with `ToString`.
+//! - `#[graphvizier(edge(source, target))]` or `#[graphvizier(edge(source,
+//!   target, label))]`: each item's `source` and `target` fields are converted
+//!   with `Into<Id>`, and its `label` field (if given) is displayed with
+//!   `ToString`.
+//!
+//! Fields without an annotation are ignored.
+
+/* These clippy lint descriptions are purely non-functional and do not affect the functionality
+ * or correctness of the code. */
+#![warn(missing_docs)]
+#![deny(unsafe_code)]
+#![deny(
+  clippy::all,
+  clippy::default_trait_access,
+  clippy::expl_impl_clone_on_copy,
+  clippy::if_not_else,
+  clippy::needless_continue,
+  clippy::single_match_else,
+  clippy::unseparated_literal_suffix,
+  clippy::used_underscore_binding
+)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+  parse_macro_input, punctuated::Punctuated, Data, DeriveInput, Fields, Ident, Meta, Token,
+};
+
+enum FieldKind {
+  Node {
+    id: Option<Ident>,
+    label: Option<Ident>,
+  },
+  Edge {
+    source: Ident,
+    target: Ident,
+    label: Option<Ident>,
+  },
+}
+
+fn parse_idents(meta: &Meta) -> syn::Result<Vec<Ident>> {
+  let list = meta.require_list()?;
+  let idents = list.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)?;
+  Ok(idents.into_iter().collect())
+}
+
+fn parse_kind(attr: &syn::Attribute) -> syn::Result<FieldKind> {
+  let meta: Meta = attr.parse_args()?;
+  if meta.path().is_ident("node") {
+    if let Meta::Path(_) = meta {
+      return Ok(FieldKind::Node {
+        id: None,
+        label: None,
+      });
+    }
+    let mut idents = parse_idents(&meta)?.into_iter();
+    match (idents.next(), idents.next(), idents.next()) {
+      (Some(id), label, None) => Ok(FieldKind::Node {
+        id: Some(id),
+        label,
+      }),
+      _ => Err(syn::Error::new_spanned(
+        meta,
+        "expected node, node(id), or node(id, label)",
+      )),
+    }
+  } else if meta.path().is_ident("edge") {
+    let mut idents = parse_idents(&meta)?.into_iter();
+    match (idents.next(), idents.next(), idents.next(), idents.next()) {
+      (Some(source), Some(target), label, None) => Ok(FieldKind::Edge {
+        source,
+        target,
+        label,
+      }),
+      _ => Err(syn::Error::new_spanned(
+        meta,
+        "expected edge(source, target) or edge(source, target, label)",
+      )),
+    }
+  } else {
+    Err(syn::Error::new_spanned(meta, "expected node or edge"))
+  }
+}
+
+fn expand_field(field: &Ident, kind: FieldKind) -> TokenStream2 {
+  match kind {
+    FieldKind::Node {
+      id: None,
+      label: None,
+    } => quote! {
+      for item in self.#field {
+        let vertex: ::graphvizier::entities::Vertex = item.into();
+        gb.accept_entity(::graphvizier::entities::Entity::Vertex(vertex));
+      }
+    },
+    FieldKind::Node { id, label } => {
+      let set_label = label.map(|label| {
+        quote! {
+          vertex.label = Some(::graphvizier::entities::Label(
+            ::graphvizier::entities::Label::escape_text(item.#label.to_string()),
+          ));
+        }
+      });
+      quote! {
+        for item in self.#field {
+          #[allow(unused_mut)]
+          let mut vertex = ::graphvizier::entities::Vertex::new(item.#id);
+          #set_label
+          gb.accept_entity(::graphvizier::entities::Entity::Vertex(vertex));
+        }
+      }
+    },
+    FieldKind::Edge {
+      source,
+      target,
+      label,
+    } => {
+      let set_label = label.map(|label| {
+        quote! {
+          edge.label = Some(::graphvizier::entities::Label(
+            ::graphvizier::entities::Label::escape_text(item.#label.to_string()),
+          ));
+        }
+      });
+      quote! {
+        for item in self.#field {
+          #[allow(unused_mut)]
+          let mut edge = ::graphvizier::entities::Edge::between(item.#source, item.#target);
+          #set_label
+          gb.accept_entity(::graphvizier::entities::Entity::Edge(edge));
+        }
+      }
+    },
+  }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+  let fields = match &input.data {
+    Data::Struct(data) => match &data.fields {
+      Fields::Named(fields) => &fields.named,
+      _ => {
+        return Err(syn::Error::new_spanned(
+          &input.ident,
+          "Graphable can only be derived for structs with named fields",
+        ))
+      },
+    },
+    _ => {
+      return Err(syn::Error::new_spanned(
+        &input.ident,
+        "Graphable can only be derived for structs",
+      ))
+    },
+  };
+
+  let mut body = Vec::new();
+  for field in fields.iter() {
+    for attr in field
+      .attrs
+      .iter()
+      .filter(|a| a.path().is_ident("graphvizier"))
+    {
+      let kind = parse_kind(attr)?;
+      body.push(expand_field(field.ident.as_ref().unwrap(), kind));
+    }
+  }
+
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  Ok(quote! {
+    impl #impl_generics ::graphvizier::Graphable for #name #ty_generics #where_clause {
+      fn build_graph(self) -> ::graphvizier::generator::GraphBuilder {
+        let mut gb = ::graphvizier::generator::GraphBuilder::new();
+        #(#body)*
+        gb
+      }
+    }
+  })
+}
+
+/// Implement `graphvizier::Graphable` for a struct with annotated fields.
+#[proc_macro_derive(Graphable, attributes(graphvizier))]
+pub fn derive_graphable(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  expand(input)
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
@@ -0,0 +1,62 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/* C interface to graphvizier, built with the `capi` feature. See the docs of
+ * the `graphvizier::capi` module for details. All strings are NUL-terminated
+ * UTF-8. */
+
+#ifndef GRAPHVIZIER_H
+#define GRAPHVIZIER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct graphvizier_builder graphvizier_builder;
+
+/* A new, empty builder. Free it with graphvizier_builder_free(). */
+graphvizier_builder *graphvizier_builder_new(void);
+
+/* Add a vertex, with a label if `label` is not NULL. Returns 0, or -1 on
+ * invalid arguments. */
+int graphvizier_builder_add_vertex(graphvizier_builder *builder, const char *id,
+                                   const char *label);
+
+/* Add an edge, with a label if `label` is not NULL. Returns 0, or -1 on
+ * invalid arguments. */
+int graphvizier_builder_add_edge(graphvizier_builder *builder, const char *source,
+                                 const char *target, const char *label);
+
+/* Write the DOT document into `buffer` like snprintf(), returning its full
+ * length without the NUL, -1 on invalid arguments, or -2 if the graph could
+ * not be built. */
+ptrdiff_t graphvizier_builder_build_into_buffer(const graphvizier_builder *builder,
+                                                const char *graph_name, char *buffer,
+                                                size_t len);
+
+/* Free a builder. Does nothing if `builder` is NULL. */
+void graphvizier_builder_free(graphvizier_builder *builder);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GRAPHVIZIER_H */
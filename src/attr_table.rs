@@ -0,0 +1,373 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The attributes Graphviz understands, and which kinds of entity each
+//! applies to.
+//!
+//! The table mirrors the [attribute
+//! reference](https://graphviz.org/doc/info/attrs.html), using its `E`, `N`,
+//! `G`, `S`, and `C` notation for edges, nodes, the root graph, subgraphs,
+//! and clusters. [`GraphBuilder::validate_attributes`] checks the names set
+//! through [`Attributes`] maps against it.
+//!
+//!```
+//! use graphvizier::attr_table::*;
+//!
+//! assert!(check("color", UsedBy::EDGE).is_ok());
+//! assert_eq!(check("rankdir", UsedBy::NODE), Err(AttrProblem::NotApplicable));
+//! assert_eq!(
+//!   check("fontsze", UsedBy::NODE),
+//!   Err(AttrProblem::Unknown { suggestion: Some("fontsize") })
+//! );
+//! ```
+//!
+//! [`GraphBuilder::validate_attributes`]: crate::generator::GraphBuilder::validate_attributes
+//! [`Attributes`]: crate::entities::Attributes
+
+use std::fmt;
+
+/// A set of the kinds of entity an attribute applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UsedBy(u8);
+
+impl UsedBy {
+  #[allow(missing_docs)]
+  pub const CLUSTER: Self = Self(1 << 4);
+  #[allow(missing_docs)]
+  pub const EDGE: Self = Self(1 << 0);
+  /// The root graph.
+  pub const GRAPH: Self = Self(1 << 2);
+  #[allow(missing_docs)]
+  pub const NODE: Self = Self(1 << 1);
+  /// A subgraph which is not a cluster.
+  pub const SUBGRAPH: Self = Self(1 << 3);
+
+  /// Parse the reference's notation, e.g. `"ENC"`.
+  const fn parse(kinds: &str) -> Self {
+    let kinds = kinds.as_bytes();
+    let mut bits = 0;
+    let mut i = 0;
+    while i < kinds.len() {
+      bits |= match kinds[i] {
+        b'E' => Self::EDGE.0,
+        b'N' => Self::NODE.0,
+        b'G' => Self::GRAPH.0,
+        b'S' => Self::SUBGRAPH.0,
+        b'C' => Self::CLUSTER.0,
+        _ => panic!("unknown entity kind"),
+      };
+      i += 1;
+    }
+    Self(bits)
+  }
+
+  /// Whether any kind in `other` is also in `self`.
+  pub const fn intersects(self, other: Self) -> bool { self.0 & other.0 != 0 }
+}
+
+impl std::ops::BitOr for UsedBy {
+  type Output = Self;
+
+  fn bitor(self, rhs: Self) -> Self { Self(self.0 | rhs.0) }
+}
+
+/// A known Graphviz attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttrInfo {
+  #[allow(missing_docs)]
+  pub name: &'static str,
+  #[allow(missing_docs)]
+  pub used_by: UsedBy,
+}
+
+const fn attr(name: &'static str, used_by: &str) -> AttrInfo {
+  AttrInfo {
+    name,
+    used_by: UsedBy::parse(used_by),
+  }
+}
+
+/// Every attribute in the Graphviz reference, sorted by name.
+pub static ATTRIBUTES: &[AttrInfo] = &[
+  attr("Damping", "G"),
+  attr("K", "GC"),
+  attr("TBbalance", "G"),
+  attr("URL", "ENGC"),
+  attr("_background", "G"),
+  attr("area", "NC"),
+  attr("arrowhead", "E"),
+  attr("arrowsize", "E"),
+  attr("arrowtail", "E"),
+  attr("bb", "GC"),
+  attr("beautify", "G"),
+  attr("bgcolor", "GC"),
+  attr("center", "G"),
+  attr("charset", "G"),
+  attr("class", "ENCG"),
+  attr("cluster", "SC"),
+  attr("clusterrank", "G"),
+  attr("color", "ENC"),
+  attr("colorscheme", "ENCG"),
+  attr("comment", "ENG"),
+  attr("compound", "G"),
+  attr("concentrate", "G"),
+  attr("constraint", "E"),
+  attr("decorate", "E"),
+  attr("defaultdist", "G"),
+  attr("dim", "G"),
+  attr("dimen", "G"),
+  attr("dir", "E"),
+  attr("diredgeconstraints", "G"),
+  attr("distortion", "N"),
+  attr("dpi", "G"),
+  attr("edgeURL", "E"),
+  attr("edgehref", "E"),
+  attr("edgetarget", "E"),
+  attr("edgetooltip", "E"),
+  attr("epsilon", "G"),
+  attr("esep", "G"),
+  attr("fillcolor", "NEC"),
+  attr("fixedsize", "N"),
+  attr("fontcolor", "ENGC"),
+  attr("fontname", "ENGC"),
+  attr("fontnames", "G"),
+  attr("fontpath", "G"),
+  attr("fontsize", "ENGC"),
+  attr("forcelabels", "G"),
+  attr("gradientangle", "NCG"),
+  attr("group", "N"),
+  attr("headURL", "E"),
+  attr("head_lp", "E"),
+  attr("headclip", "E"),
+  attr("headhref", "E"),
+  attr("headlabel", "E"),
+  attr("headport", "E"),
+  attr("headtarget", "E"),
+  attr("headtooltip", "E"),
+  attr("height", "N"),
+  attr("href", "GCNE"),
+  attr("id", "GCNE"),
+  attr("image", "N"),
+  attr("imagepath", "G"),
+  attr("imagepos", "N"),
+  attr("imagescale", "N"),
+  attr("inputscale", "G"),
+  attr("label", "ENGC"),
+  attr("labelURL", "E"),
+  attr("label_scheme", "G"),
+  attr("labelangle", "E"),
+  attr("labeldistance", "E"),
+  attr("labelfloat", "E"),
+  attr("labelfontcolor", "E"),
+  attr("labelfontname", "E"),
+  attr("labelfontsize", "E"),
+  attr("labelhref", "E"),
+  attr("labeljust", "GC"),
+  attr("labelloc", "NGC"),
+  attr("labeltarget", "E"),
+  attr("labeltooltip", "E"),
+  attr("landscape", "G"),
+  attr("layer", "ENC"),
+  attr("layerlistsep", "G"),
+  attr("layers", "G"),
+  attr("layerselect", "G"),
+  attr("layersep", "G"),
+  attr("layout", "G"),
+  attr("len", "E"),
+  attr("levels", "G"),
+  attr("levelsgap", "G"),
+  attr("lhead", "E"),
+  attr("lheight", "GC"),
+  attr("linelength", "G"),
+  attr("lp", "EGC"),
+  attr("ltail", "E"),
+  attr("lwidth", "GC"),
+  attr("margin", "NCG"),
+  attr("maxiter", "G"),
+  attr("mclimit", "G"),
+  attr("mindist", "G"),
+  attr("minlen", "E"),
+  attr("mode", "G"),
+  attr("model", "G"),
+  attr("newrank", "G"),
+  attr("nodesep", "G"),
+  attr("nojustify", "GCNE"),
+  attr("normalize", "G"),
+  attr("notranslate", "G"),
+  attr("nslimit", "G"),
+  attr("nslimit1", "G"),
+  attr("oneblock", "G"),
+  attr("ordering", "GN"),
+  attr("orientation", "NG"),
+  attr("outputorder", "G"),
+  attr("overlap", "G"),
+  attr("overlap_scaling", "G"),
+  attr("overlap_shrink", "G"),
+  attr("pack", "G"),
+  attr("packmode", "G"),
+  attr("pad", "G"),
+  attr("page", "G"),
+  attr("pagedir", "G"),
+  attr("pencolor", "C"),
+  attr("penwidth", "CNE"),
+  attr("peripheries", "NC"),
+  attr("pin", "N"),
+  attr("pos", "EN"),
+  attr("quadtree", "G"),
+  attr("quantum", "G"),
+  attr("rank", "S"),
+  attr("rankdir", "G"),
+  attr("ranksep", "G"),
+  attr("ratio", "G"),
+  attr("rects", "N"),
+  attr("regular", "N"),
+  attr("remincross", "G"),
+  attr("repulsiveforce", "G"),
+  attr("resolution", "G"),
+  attr("root", "GN"),
+  attr("rotate", "G"),
+  attr("rotation", "G"),
+  attr("samehead", "E"),
+  attr("sametail", "E"),
+  attr("samplepoints", "N"),
+  attr("scale", "G"),
+  attr("searchsize", "G"),
+  attr("sep", "G"),
+  attr("shape", "N"),
+  attr("shapefile", "N"),
+  attr("showboxes", "ENG"),
+  attr("sides", "N"),
+  attr("size", "G"),
+  attr("skew", "N"),
+  attr("smoothing", "G"),
+  attr("sortv", "GCN"),
+  attr("splines", "G"),
+  attr("start", "G"),
+  attr("style", "ENCG"),
+  attr("stylesheet", "G"),
+  attr("tailURL", "E"),
+  attr("tail_lp", "E"),
+  attr("tailclip", "E"),
+  attr("tailhref", "E"),
+  attr("taillabel", "E"),
+  attr("tailport", "E"),
+  attr("tailtarget", "E"),
+  attr("tailtooltip", "E"),
+  attr("target", "ENGC"),
+  attr("tooltip", "NEC"),
+  attr("truecolor", "G"),
+  attr("vertices", "N"),
+  attr("viewport", "G"),
+  attr("voro_margin", "G"),
+  attr("weight", "E"),
+  attr("width", "N"),
+  attr("xdotversion", "G"),
+  attr("xlabel", "EN"),
+  attr("xlp", "NE"),
+  attr("z", "N"),
+];
+
+/// Look up a known attribute by its exact, case-sensitive name.
+pub fn lookup(name: &str) -> Option<&'static AttrInfo> {
+  ATTRIBUTES
+    .binary_search_by(|a| a.name.cmp(name))
+    .ok()
+    .map(|i| &ATTRIBUTES[i])
+}
+
+/// Why an attribute name was rejected by [`check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttrProblem {
+  /// Not a Graphviz attribute at all.
+  Unknown {
+    /// The closest known name, if any is within a couple of typos.
+    suggestion: Option<&'static str>,
+  },
+  /// A Graphviz attribute, but not one which applies to this kind of entity.
+  NotApplicable,
+}
+
+impl fmt::Display for AttrProblem {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Unknown { suggestion: None } => write!(f, "unknown attribute"),
+      Self::Unknown {
+        suggestion: Some(s),
+      } => write!(f, "unknown attribute (did you mean {:?}?)", s),
+      Self::NotApplicable => write!(f, "attribute does not apply here"),
+    }
+  }
+}
+
+/// Check that `name` is a known attribute which applies to `kind`.
+pub fn check(name: &str, kind: UsedBy) -> Result<(), AttrProblem> {
+  match lookup(name) {
+    Some(info) if info.used_by.intersects(kind) => Ok(()),
+    Some(_) => Err(AttrProblem::NotApplicable),
+    None => Err(AttrProblem::Unknown {
+      suggestion: suggest(name),
+    }),
+  }
+}
+
+/// The known attribute closest to `name` by edit distance, ignoring case, if
+/// any is at most two edits away.
+pub fn suggest(name: &str) -> Option<&'static str> {
+  let name = name.to_ascii_lowercase();
+  ATTRIBUTES
+    .iter()
+    .map(|a| (edit_distance(&name, &a.name.to_ascii_lowercase()), a.name))
+    .filter(|(d, _)| *d <= 2)
+    .min_by_key(|(d, _)| *d)
+    .map(|(_, name)| name)
+}
+
+/// Levenshtein distance between `a` and `b`, by chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut prev: Vec<usize> = (0..=b.len()).collect();
+  let mut cur = vec![0; b.len() + 1];
+  for (i, ca) in a.chars().enumerate() {
+    cur[0] = i + 1;
+    for (j, cb) in b.iter().enumerate() {
+      let substitution = prev[j] + usize::from(ca != *cb);
+      cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+    }
+    std::mem::swap(&mut prev, &mut cur);
+  }
+  prev[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn table_is_sorted() {
+    assert!(ATTRIBUTES.windows(2).all(|w| w[0].name < w[1].name));
+  }
+
+  #[test]
+  fn suggests_typos() {
+    assert_eq!(suggest("colour"), Some("color"));
+    assert_eq!(suggest("url"), Some("URL"));
+    assert_eq!(suggest("xyzzy_plugh"), None);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+  }
+}
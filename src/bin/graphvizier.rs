@@ -0,0 +1,454 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Build, theme, validate, and convert graphs from the command line.
+//!
+//! Each subcommand reads a single document from a file, or from stdin if none
+//! is given, and writes its result to stdout. Graph-level attributes of parsed
+//! documents, such as `rankdir`, are carried over, and take precedence over
+//! those from a theme, and undirected input is written as an undirected
+//! graph. Graphs are never written as `strict`, so `theme` and `convert`
+//! refuse `strict` input rather than changing its meaning.
+//!
+//!```sh
+//! printf 'source,target\na,b\nb,c\n' | graphvizier build > deps.dot
+//! graphvizier theme --theme dark deps.dot | dot -Tsvg > deps.svg
+//! graphvizier validate deps.dot
+//! graphvizier convert --to mermaid deps.dot
+//! ```
+
+use graphvizier::{
+  entities::{GraphAttributes, Id},
+  export::{
+    csv::to_csv,
+    d2::to_d2,
+    d3::to_d3_force,
+    dimacs::to_dimacs,
+    gexf::to_gexf,
+    graph6::{to_graph6, to_sparse6},
+    json::to_json,
+    mermaid::to_mermaid,
+    pajek::to_pajek,
+    plantuml::{to_plantuml, Diagram},
+    tgf::to_tgf,
+    tikz::{to_tikz, Layout},
+    vis::to_vis_network,
+  },
+  generator::GraphBuilder,
+  import::{
+    csv::{from_csv, CsvFormat},
+    gml::from_gml,
+    graphml::from_graphml,
+    json::{from_json, from_json_edges},
+  },
+  parser::{self, ParsedGraph},
+  theme::Theme,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::Value;
+
+use std::{
+  collections::HashSet,
+  error::Error,
+  fs,
+  io::{self, Read},
+  path::PathBuf,
+  process::ExitCode,
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "graphvizier", version, about)]
+struct Cli {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+  /// Build a DOT document from an edge list.
+  Build {
+    #[arg(long, value_enum, default_value = "csv")]
+    format: EdgeList,
+    #[command(flatten)]
+    columns: Columns,
+    /// The id of the generated graph.
+    #[arg(long, default_value = "G")]
+    name: String,
+    #[command(flatten)]
+    io: InputOutput,
+  },
+  /// Fill in the colors and fonts of a DOT document from a theme.
+  Theme {
+    #[arg(long, value_enum, default_value = "light")]
+    theme: ThemeName,
+    #[command(flatten)]
+    io: InputOutput,
+  },
+  /// Check that a DOT document parses, and report any conflicting vertices
+  /// or unknown attributes as errors, and any dangling edges as warnings.
+  ///
+  /// Exits with status 1 if there are any errors.
+  Validate {
+    /// Read from this file rather than stdin.
+    input: Option<PathBuf>,
+  },
+  /// Convert a graph from one format to another.
+  Convert {
+    #[arg(long, value_enum, default_value = "dot")]
+    from: InputFormat,
+    #[arg(long, value_enum)]
+    to: OutputFormat,
+    /// Columns of `csv` or `tsv` input or output.
+    #[command(flatten)]
+    columns: Columns,
+    /// Lay out `tikz` output with this graph drawing algorithm, e.g.
+    /// `layered`. Defaults to each vertex's `pos` if every vertex has one,
+    /// or `layered` otherwise.
+    #[arg(long)]
+    tikz_layout: Option<String>,
+    #[command(flatten)]
+    io: InputOutput,
+  },
+}
+
+#[derive(Debug, Args)]
+struct InputOutput {
+  /// Read from this file rather than stdin.
+  input: Option<PathBuf>,
+  /// Write to this file rather than stdout.
+  #[arg(short, long)]
+  output: Option<PathBuf>,
+}
+
+impl InputOutput {
+  fn write(&self, text: &str) -> io::Result<()> {
+    match &self.output {
+      Some(path) => fs::write(path, text),
+      None => io::Write::write_all(&mut io::stdout().lock(), text.as_bytes()),
+    }
+  }
+}
+
+/// Which columns of a CSV or TSV edge list hold what, numbered from 0.
+/// Missing label and weight columns are ignored.
+#[derive(Debug, Args)]
+struct Columns {
+  /// The first row is an edge, rather than naming the columns.
+  #[arg(long)]
+  no_header: bool,
+  #[arg(long, default_value = "0")]
+  source: usize,
+  #[arg(long, default_value = "1")]
+  target: usize,
+  #[arg(long, default_value = "2")]
+  label: usize,
+  #[arg(long, default_value = "3")]
+  weight: usize,
+}
+
+impl Columns {
+  fn format(&self, delimiter: char) -> CsvFormat {
+    CsvFormat {
+      delimiter,
+      header: !self.no_header,
+      source: self.source,
+      target: self.target,
+      label: Some(self.label),
+      weight: Some(self.weight),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum EdgeList {
+  Csv,
+  Tsv,
+  /// An array of `[source, target, label?]` arrays or
+  /// `{"source", "target", "label"?}` objects.
+  Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ThemeName {
+  Light,
+  Dark,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum InputFormat {
+  Dot,
+  /// The output of `dot -Tjson`.
+  Json,
+  Graphml,
+  Gml,
+  Csv,
+  Tsv,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+  Dot,
+  /// A standalone page drawing the graph in the browser.
+  Html,
+  /// In the format of `dot -Tjson`.
+  Json,
+  /// For d3-force.
+  D3,
+  /// For vis-network.
+  Vis,
+  Gexf,
+  Mermaid,
+  D2,
+  Plantuml,
+  Tikz,
+  Tgf,
+  Pajek,
+  Dimacs,
+  Graph6,
+  Sparse6,
+  Csv,
+  Tsv,
+}
+
+fn read_input(input: &Option<PathBuf>) -> io::Result<String> {
+  match input {
+    Some(path) => fs::read_to_string(path),
+    None => {
+      let mut text = String::new();
+      io::stdin().read_to_string(&mut text)?;
+      Ok(text)
+    },
+  }
+}
+
+/// A builder with the entities, graph-level attributes, and direction of
+/// `graph`.
+fn builder(graph: ParsedGraph) -> GraphBuilder {
+  let ParsedGraph {
+    directed,
+    mut attributes,
+    entities,
+    ..
+  } = graph;
+  /* GraphBuilder::build always sets this itself. */
+  attributes.remove("compound");
+  let mut gb: GraphBuilder = entities.into_iter().collect();
+  gb.set_directed(directed);
+  gb.set_graph_attributes(GraphAttributes {
+    attributes,
+    ..Default::default()
+  });
+  gb
+}
+
+/// Like [`builder`], but refuse graphs which wouldn't mean the same thing
+/// once written without `strict`.
+fn nonstrict_builder(graph: ParsedGraph) -> Result<GraphBuilder, String> {
+  if graph.strict {
+    return Err("strict graphs are not supported".to_string());
+  }
+  Ok(builder(graph))
+}
+
+fn graph_name(graph: &ParsedGraph) -> Id { graph.id.clone().unwrap_or_else(|| Id::new("G")) }
+
+/// The layout named by `--tikz-layout`, or else the vertices' own positions
+/// if every vertex, including those only named by an edge, has one.
+fn tikz_layout(gb: &GraphBuilder, algorithm: Option<&str>) -> Layout {
+  if let Some(algorithm) = algorithm {
+    return Layout::GraphDrawing(algorithm.to_string());
+  }
+  let mut placed = HashSet::new();
+  for v in gb.vertices() {
+    if !v.attributes.contains_key("pos") {
+      return Layout::GraphDrawing("layered".to_string());
+    }
+    placed.insert(&v.id);
+  }
+  if gb
+    .edges()
+    .all(|e| placed.contains(&e.source) && placed.contains(&e.target))
+  {
+    Layout::Positions
+  } else {
+    Layout::GraphDrawing("layered".to_string())
+  }
+}
+
+fn convert(
+  graph: ParsedGraph,
+  to: OutputFormat,
+  columns: &Columns,
+  tikz: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+  let name = graph_name(&graph);
+  let gb = nonstrict_builder(graph)?;
+  let json = |v: Value| -> Result<String, Box<dyn Error>> {
+    Ok(format!("{}\n", serde_json::to_string_pretty(&v)?))
+  };
+  Ok(match to {
+    OutputFormat::Dot => gb.build(name)?.0,
+    OutputFormat::Html => gb.build_html(name)?,
+    OutputFormat::Json => json(to_json(&gb, &name))?,
+    OutputFormat::D3 => json(to_d3_force(&gb))?,
+    OutputFormat::Vis => json(to_vis_network(&gb))?,
+    OutputFormat::Gexf => to_gexf(&gb),
+    OutputFormat::Mermaid => to_mermaid(&gb),
+    OutputFormat::D2 => to_d2(&gb),
+    OutputFormat::Plantuml => to_plantuml(&gb, Diagram::Component),
+    OutputFormat::Tikz => to_tikz(&gb, &tikz_layout(&gb, tikz)),
+    OutputFormat::Tgf => to_tgf(&gb),
+    OutputFormat::Pajek => to_pajek(&gb),
+    OutputFormat::Dimacs => to_dimacs(&gb).document,
+    OutputFormat::Graph6 => to_graph6(&gb),
+    OutputFormat::Sparse6 => to_sparse6(&gb),
+    OutputFormat::Csv => to_csv(&gb, &columns.format(',')),
+    OutputFormat::Tsv => to_csv(&gb, &columns.format('\t')),
+  })
+}
+
+fn run(command: Command) -> Result<ExitCode, Box<dyn Error>> {
+  match command {
+    Command::Build {
+      format,
+      columns,
+      name,
+      io,
+    } => {
+      let text = read_input(&io.input)?;
+      let graph = match format {
+        EdgeList::Csv => from_csv(&text, &columns.format(','))?,
+        EdgeList::Tsv => from_csv(&text, &columns.format('\t'))?,
+        EdgeList::Json => from_json_edges(&text)?,
+      };
+      io.write(&builder(graph).build(Id::new(name))?.0)?;
+    },
+    Command::Theme { theme, io } => {
+      let graph = parser::parse(&read_input(&io.input)?)?;
+      let name = graph_name(&graph);
+      let mut gb = nonstrict_builder(graph)?;
+      gb.set_theme(match theme {
+        ThemeName::Light => Theme::light(),
+        ThemeName::Dark => Theme::dark(),
+      });
+      io.write(&gb.build(name)?.0)?;
+    },
+    Command::Validate { input } => {
+      let graph = parser::parse(&read_input(&input)?)?;
+      let name = graph_name(&graph);
+      let mut gb = builder(graph);
+      gb.validate_vertices(true);
+      gb.validate_attributes(true);
+      let (_, diagnostics) = gb.build_with_diagnostics(name);
+      for d in diagnostics.iter() {
+        eprintln!("{}", d);
+      }
+      if diagnostics.has_errors() {
+        return Ok(ExitCode::FAILURE);
+      }
+    },
+    Command::Convert {
+      from,
+      to,
+      columns,
+      tikz_layout,
+      io,
+    } => {
+      let text = read_input(&io.input)?;
+      let graph = match from {
+        InputFormat::Dot => parser::parse(&text)?,
+        InputFormat::Json => from_json(&text)?,
+        InputFormat::Graphml => from_graphml(&text)?,
+        InputFormat::Gml => from_gml(&text)?,
+        InputFormat::Csv => from_csv(&text, &columns.format(','))?,
+        InputFormat::Tsv => from_csv(&text, &columns.format('\t'))?,
+      };
+      io.write(&convert(graph, to, &columns, tikz_layout.as_deref())?)?;
+    },
+  }
+  Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+  match run(Cli::parse().command) {
+    Ok(code) => code,
+    Err(e) => {
+      eprintln!("graphvizier: {}", e);
+      ExitCode::FAILURE
+    },
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn convert_dot() {
+    let columns = Columns {
+      no_header: false,
+      source: 0,
+      target: 1,
+      label: 2,
+      weight: 3,
+    };
+    let graph = parser::parse("digraph g { a -> b; }").unwrap();
+    assert_eq!(
+      convert(graph, OutputFormat::Tgf, &columns, None).unwrap(),
+      "1 a\n2 b\n#\n1 2\n"
+    );
+    let graph = parser::parse("graph g { a -- b; }").unwrap();
+    let dot = convert(graph, OutputFormat::Dot, &columns, None).unwrap();
+    assert!(dot.starts_with("graph g {"), "{}", dot);
+    assert!(dot.contains("a -- b;"), "{}", dot);
+    let graph = parser::parse("graph g { a -- b; }").unwrap();
+    assert_eq!(
+      convert(graph, OutputFormat::Pajek, &columns, None).unwrap(),
+      "*Vertices 2\n1 \"a\"\n2 \"b\"\n*Edges\n1 2\n"
+    );
+    let graph = parser::parse("digraph g { a -> b; }").unwrap();
+    let tikz = convert(graph, OutputFormat::Tikz, &columns, None).unwrap();
+    assert!(tikz.contains("[layered layout]"), "{}", tikz);
+    let graph = parser::parse("digraph g { a -> b; }").unwrap();
+    let tikz = convert(graph, OutputFormat::Tikz, &columns, Some("tree")).unwrap();
+    assert!(tikz.contains("[tree layout]"), "{}", tikz);
+    let graph = parser::parse(r#"digraph g { a [pos="0,0"]; b [pos="1,1"]; a -> b; }"#).unwrap();
+    let tikz = convert(graph, OutputFormat::Tikz, &columns, None).unwrap();
+    assert!(!tikz.contains("layout]"), "{}", tikz);
+    let graph = parser::parse("strict digraph g { a -> b; }").unwrap();
+    let e = convert(graph, OutputFormat::Dot, &columns, None).unwrap_err();
+    assert_eq!(e.to_string(), "strict graphs are not supported");
+  }
+
+  #[test]
+  fn graph_attributes() {
+    let graph = parser::parse("digraph g { rankdir=LR; bgcolor=black; a -> b; }").unwrap();
+    let mut gb = builder(graph);
+    gb.set_theme(Theme::dark());
+    let dot = gb.build(Id::new("g")).unwrap().0;
+    assert!(
+      dot.contains("  bgcolor = \"black\";\n  rankdir = \"LR\";\n"),
+      "{}",
+      dot
+    );
+    assert!(!dot.contains("#1e1e1e"), "{}", dot);
+    assert_eq!(dot.matches("compound").count(), 1, "{}", dot);
+  }
+}
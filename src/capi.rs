@@ -0,0 +1,246 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A C interface to [`GraphBuilder`], for build tools written in other
+//! languages. The declarations are in `include/graphvizier.h`.
+//!
+//! Requires the `capi` feature. Build a shared library, e.g.
+//! `target/release/libgraphvizier.so` on Linux, with:
+//!
+//!```sh
+//! cargo build --lib --release --features capi
+//! ```
+//!
+//! All strings are NUL-terminated UTF-8. Labels are written to the document
+//! as given, so they may contain DOT escape sequences such as `\n`.
+
+#![allow(unsafe_code)]
+
+use crate::{
+  entities::{Edge, Entity, Id, Vertex},
+  generator::GraphBuilder,
+};
+
+use std::{
+  ffi::{c_char, c_int, CStr},
+  ptr,
+};
+
+/// The entities added so far. Opaque to C.
+pub struct Builder {
+  entities: Vec<Entity>,
+}
+
+/// Borrow `s` as a `&str`, or `None` if it is null or not UTF-8.
+///
+/// # Safety
+/// `s` must be null or point to a NUL-terminated string which outlives `'a`.
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+  if s.is_null() {
+    return None;
+  }
+  unsafe { CStr::from_ptr(s) }.to_str().ok()
+}
+
+/// A new, empty builder. Free it with [`graphvizier_builder_free`].
+#[no_mangle]
+pub extern "C" fn graphvizier_builder_new() -> *mut Builder {
+  Box::into_raw(Box::new(Builder {
+    entities: Vec::new(),
+  }))
+}
+
+/// Add a vertex with id `id`, and `label` if it is not null. Returns 0, or -1
+/// if `builder` or `id` is null or a string is not UTF-8.
+///
+/// # Safety
+/// `builder` must come from [`graphvizier_builder_new`] and not have been
+/// freed. `id` and `label` must be null or NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn graphvizier_builder_add_vertex(
+  builder: *mut Builder,
+  id: *const c_char,
+  label: *const c_char,
+) -> c_int {
+  let Some(builder) = (unsafe { builder.as_mut() }) else {
+    return -1;
+  };
+  let Some(id) = (unsafe { str_arg(id) }) else {
+    return -1;
+  };
+  let mut vertex = Vertex::new(id);
+  if !label.is_null() {
+    let Some(label) = (unsafe { str_arg(label) }) else {
+      return -1;
+    };
+    vertex = vertex.label(label);
+  }
+  builder.entities.push(Entity::Vertex(vertex));
+  0
+}
+
+/// Add an edge from `source` to `target`, with `label` if it is not null.
+/// Returns 0, or -1 if `builder`, `source`, or `target` is null or a string
+/// is not UTF-8.
+///
+/// # Safety
+/// `builder` must come from [`graphvizier_builder_new`] and not have been
+/// freed. `source`, `target`, and `label` must be null or NUL-terminated
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn graphvizier_builder_add_edge(
+  builder: *mut Builder,
+  source: *const c_char,
+  target: *const c_char,
+  label: *const c_char,
+) -> c_int {
+  let Some(builder) = (unsafe { builder.as_mut() }) else {
+    return -1;
+  };
+  let (Some(source), Some(target)) = (unsafe { str_arg(source) }, unsafe { str_arg(target) })
+  else {
+    return -1;
+  };
+  let mut edge = Edge::between(source, target);
+  if !label.is_null() {
+    let Some(label) = (unsafe { str_arg(label) }) else {
+      return -1;
+    };
+    edge = edge.label(label);
+  }
+  builder.entities.push(Entity::Edge(edge));
+  0
+}
+
+/// Write the DOT document for the graph named `graph_name` into `buffer`,
+/// like `snprintf`: at most `len - 1` bytes are written, followed by a NUL,
+/// and the full length of the document (without the NUL) is returned, so a
+/// return value of `len` or more means the output was truncated. `buffer`
+/// may be null if `len` is 0, to measure the document. Returns -1 if
+/// `builder` or `graph_name` is null or not UTF-8, or -2 if the graph could
+/// not be built.
+///
+/// The builder is left unchanged, so this may be called again with a larger
+/// buffer.
+///
+/// # Safety
+/// `builder` must come from [`graphvizier_builder_new`] and not have been
+/// freed. `graph_name` must be null or a NUL-terminated string. `buffer` must
+/// be valid for writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn graphvizier_builder_build_into_buffer(
+  builder: *const Builder,
+  graph_name: *const c_char,
+  buffer: *mut c_char,
+  len: usize,
+) -> isize {
+  let Some(builder) = (unsafe { builder.as_ref() }) else {
+    return -1;
+  };
+  let Some(graph_name) = (unsafe { str_arg(graph_name) }) else {
+    return -1;
+  };
+  let mut gb = GraphBuilder::new();
+  for entity in &builder.entities {
+    gb.accept_entity(entity.clone());
+  }
+  let Ok(output) = gb.build(Id::new(graph_name)) else {
+    return -2;
+  };
+  let bytes = output.0.as_bytes();
+  if len > 0 && !buffer.is_null() {
+    let n = bytes.len().min(len - 1);
+    unsafe {
+      ptr::copy_nonoverlapping(bytes.as_ptr().cast::<c_char>(), buffer, n);
+      *buffer.add(n) = 0;
+    }
+  }
+  isize::try_from(bytes.len()).unwrap_or(isize::MAX)
+}
+
+/// Free a builder from [`graphvizier_builder_new`]. Does nothing if `builder`
+/// is null.
+///
+/// # Safety
+/// `builder` must be null or come from [`graphvizier_builder_new`] and not
+/// have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn graphvizier_builder_free(builder: *mut Builder) {
+  if !builder.is_null() {
+    drop(unsafe { Box::from_raw(builder) });
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::ffi::CString;
+
+  #[test]
+  fn build_through_c_abi() {
+    let s = |s: &str| CString::new(s).unwrap();
+    let (a, b, label, name) = (s("a"), s("b c"), s("to \\\"b\\\""), s("g"));
+    unsafe {
+      let builder = graphvizier_builder_new();
+      assert_eq!(
+        graphvizier_builder_add_vertex(builder, a.as_ptr(), ptr::null()),
+        0
+      );
+      assert_eq!(
+        graphvizier_builder_add_edge(builder, a.as_ptr(), b.as_ptr(), label.as_ptr()),
+        0
+      );
+      assert_eq!(
+        graphvizier_builder_add_vertex(builder, ptr::null(), ptr::null()),
+        -1
+      );
+      assert_eq!(
+        graphvizier_builder_add_vertex(builder, b"\xff\0".as_ptr().cast(), ptr::null()),
+        -1
+      );
+
+      let expected = "digraph g {\n  \
+                        compound = true;\n\n  \
+                        a;\n\n  \
+                        a -> \"b c\"[label=\"to \\\"b\\\"\"];\n\
+                      }\n";
+      let needed =
+        graphvizier_builder_build_into_buffer(builder, name.as_ptr(), ptr::null_mut(), 0);
+      assert_eq!(needed, expected.len() as isize);
+
+      let mut small = [1 as c_char; 8];
+      graphvizier_builder_build_into_buffer(builder, name.as_ptr(), small.as_mut_ptr(), 8);
+      assert_eq!(CStr::from_ptr(small.as_ptr()).to_str().unwrap(), "digraph");
+
+      let mut buffer = vec![0 as c_char; expected.len() + 1];
+      let written = graphvizier_builder_build_into_buffer(
+        builder,
+        name.as_ptr(),
+        buffer.as_mut_ptr(),
+        buffer.len(),
+      );
+      assert_eq!(written, needed);
+      assert_eq!(CStr::from_ptr(buffer.as_ptr()).to_str().unwrap(), expected);
+
+      graphvizier_builder_free(builder);
+      graphvizier_builder_free(ptr::null_mut());
+    }
+  }
+}
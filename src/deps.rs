@@ -0,0 +1,244 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A registry of running tasks and what they're waiting on, which can be drawn
+//! at any time.
+//!
+//! Long-running programs register a [`Task`] for each unit of work, declare
+//! dependencies between them as they arise, and call [`Registry::dot`] when
+//! asked, e.g. from a thread woken by a signal, or a debug endpoint. A task
+//! is removed from the registry, along with its dependencies, when its handle
+//! is dropped.
+//!
+//!```
+//! use graphvizier::deps::Registry;
+//!
+//! let registry = Registry::new();
+//! let fetch = registry.register("fetch");
+//! let parse = registry.register("parse");
+//! parse.depends_on(&fetch);
+//! fetch.set_status("waiting on socket");
+//!
+//! assert_eq!(
+//!   registry.dot(),
+//!   "digraph tasks {\n  \
+//!      compound = true;\n\n  \
+//!      t0[label=\"fetch\\nwaiting on socket\"];\n\n  \
+//!      t1[label=\"parse\"];\n\n  \
+//!      t1 -> t0;\n\
+//!    }\n"
+//! );
+//!
+//! drop(fetch);
+//! assert_eq!(registry.graph().edges().count(), 0);
+//! ```
+
+use crate::{
+  entities::{style::Label, Edge, Entity, Id, Vertex},
+  generator::GraphBuilder,
+};
+
+use lazy_static::lazy_static;
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Default)]
+struct TaskState {
+  name: String,
+  status: Option<String>,
+  dependencies: BTreeSet<u64>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+  next: u64,
+  tasks: BTreeMap<u64, TaskState>,
+}
+
+/// A set of tasks and their dependencies, shared between threads. Clones
+/// refer to the same registry.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+  state: Arc<Mutex<State>>,
+}
+
+lazy_static! {
+  static ref GLOBAL: Registry = Registry::new();
+}
+
+impl Registry {
+  /// An empty registry.
+  pub fn new() -> Self { Self::default() }
+
+  /// A registry shared by the whole process.
+  pub fn global() -> &'static Self { &GLOBAL }
+
+  /// Add a task named `name`, which is removed when the returned handle is
+  /// dropped. Names need not be unique.
+  pub fn register(&self, name: impl Into<String>) -> Task {
+    let mut state = self.state.lock().unwrap();
+    let key = state.next;
+    state.next += 1;
+    state.tasks.insert(key, TaskState {
+      name: name.into(),
+      ..TaskState::default()
+    });
+    Task {
+      registry: self.clone(),
+      key,
+    }
+  }
+
+  /// The number of tasks registered.
+  pub fn len(&self) -> usize { self.state.lock().unwrap().tasks.len() }
+
+  /// Whether no tasks are registered.
+  pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+  /// The current tasks, as boxes labelled with their name and status, with an
+  /// edge from each task to each task it depends on. Vertex ids are `t`
+  /// followed by a number assigned in order of registration.
+  pub fn graph(&self) -> GraphBuilder {
+    let state = self.state.lock().unwrap();
+    let id = |key: u64| Id::new(format!("t{}", key));
+    let mut gb = GraphBuilder::new();
+    for (key, task) in &state.tasks {
+      let mut label = Label::escape_text(&task.name);
+      if let Some(status) = &task.status {
+        label = format!("{}\\n{}", label, Label::escape_text(status));
+      }
+      gb.accept_entity(Entity::Vertex(Vertex::new(id(*key)).label(label)));
+    }
+    for (key, task) in &state.tasks {
+      for dependency in &task.dependencies {
+        gb.accept_entity(Entity::Edge(Edge::between(id(*key), id(*dependency))));
+      }
+    }
+    gb
+  }
+
+  /// [`Self::graph`] as a DOT document named `tasks`.
+  pub fn dot(&self) -> String {
+    self
+      .graph()
+      .build(Id::new("tasks"))
+      .expect("building without validation cannot fail")
+      .0
+  }
+}
+
+/// A handle to a task in a [`Registry`], which removes the task when dropped.
+#[derive(Debug)]
+pub struct Task {
+  registry: Registry,
+  key: u64,
+}
+
+impl Task {
+  /// Record that this task is waiting on `other`. Does nothing if `other`
+  /// belongs to a different registry.
+  pub fn depends_on(&self, other: &Task) {
+    if !Arc::ptr_eq(&self.registry.state, &other.registry.state) {
+      return;
+    }
+    let mut state = self.registry.state.lock().unwrap();
+    if state.tasks.contains_key(&other.key) {
+      if let Some(task) = state.tasks.get_mut(&self.key) {
+        task.dependencies.insert(other.key);
+      }
+    }
+  }
+
+  /// Record that this task no longer waits on `other`. Does nothing if
+  /// `other` belongs to a different registry.
+  pub fn no_longer_depends_on(&self, other: &Task) {
+    if !Arc::ptr_eq(&self.registry.state, &other.registry.state) {
+      return;
+    }
+    if let Some(task) = self.registry.state.lock().unwrap().tasks.get_mut(&self.key) {
+      task.dependencies.remove(&other.key);
+    }
+  }
+
+  /// Show `status` below this task's name.
+  pub fn set_status(&self, status: impl Into<String>) {
+    if let Some(task) = self.registry.state.lock().unwrap().tasks.get_mut(&self.key) {
+      task.status = Some(status.into());
+    }
+  }
+}
+
+impl Drop for Task {
+  fn drop(&mut self) {
+    /* This may run while unwinding from a panic which poisoned the lock. */
+    let mut state = self
+      .registry
+      .state
+      .lock()
+      .unwrap_or_else(|e| e.into_inner());
+    state.tasks.remove(&self.key);
+    for task in state.tasks.values_mut() {
+      task.dependencies.remove(&self.key);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn threads() {
+    let registry = Registry::new();
+    let root = registry.register("root");
+    std::thread::scope(|s| {
+      let workers: Vec<_> = (0..4)
+        .map(|i| {
+          let registry = registry.clone();
+          s.spawn(move || registry.register(format!("worker {}", i)))
+        })
+        .map(|h| h.join().unwrap())
+        .collect();
+      for worker in &workers {
+        root.depends_on(worker);
+      }
+      assert_eq!(registry.len(), 5);
+      assert_eq!(registry.graph().edges().count(), 4);
+    });
+    assert_eq!(registry.len(), 1);
+    assert_eq!(registry.graph().edges().count(), 0);
+  }
+
+  #[test]
+  fn separate_registries() {
+    let (a, b) = (Registry::new(), Registry::new());
+    let (x, y) = (a.register("x"), b.register("y \"quoted\""));
+    x.depends_on(&y);
+    assert_eq!(a.graph().edges().count(), 0);
+    assert!(b.dot().contains("t0[label=\"y \\\"quoted\\\"\"];"));
+    /* These share a key, but not a registry. */
+    let (z, w) = (a.register("z"), b.register("w"));
+    x.depends_on(&z);
+    x.no_longer_depends_on(&w);
+    assert_eq!(a.graph().edges().count(), 1);
+  }
+}
@@ -0,0 +1,211 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Findings reported while building a graph.
+//!
+//! [`GraphBuilder::build_with_diagnostics`] returns everything its validation
+//! passes noticed alongside the [`DotOutput`], so that e.g. a CI job can
+//! surface that some edges point nowhere instead of silently producing an odd
+//! layout. Findings from checks enabled on the builder (like
+//! [`GraphBuilder::validate_edges`]) are errors and fail the build; the rest
+//! are warnings.
+//!
+//!```
+//! use graphvizier::{diagnostics::*, entities::*, generator::*};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.add_vertex(Vertex::new("a"));
+//! gb.add_edge(Edge::between("a", "b"));
+//! let (output, diagnostics) = gb.build_with_diagnostics(Id::new("g"));
+//! assert!(output.is_ok());
+//! assert_eq!(diagnostics.count(Severity::Warning), 1);
+//! assert_eq!(
+//!   diagnostics.to_string(),
+//!   "warning: edge \"a\" -> \"b\": refers to undefined \"b\"\n"
+//! );
+//! ```
+//!
+//! [`GraphBuilder::build_with_diagnostics`]: crate::generator::GraphBuilder::build_with_diagnostics
+//! [`GraphBuilder::validate_edges`]: crate::generator::GraphBuilder::validate_edges
+//! [`DotOutput`]: crate::generator::DotOutput
+
+use crate::entities::Id;
+
+use std::fmt;
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+  /// Informational, e.g. a fix applied to the graph.
+  Note,
+  /// The output is valid, but probably not what was intended.
+  Warning,
+  /// The graph could not be built.
+  Error,
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(match self {
+      Self::Note => "note",
+      Self::Warning => "warning",
+      Self::Error => "error",
+    })
+  }
+}
+
+/// Which entity a finding refers to.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub enum EntityRef {
+  Vertex(Id),
+  Edge { source: Id, target: Id },
+  Subgraph(Id),
+}
+
+impl fmt::Display for EntityRef {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Vertex(id) => write!(f, "vertex {:?}", id.as_str()),
+      Self::Edge { source, target } => {
+        write!(f, "edge {:?} -> {:?}", source.as_str(), target.as_str())
+      },
+      Self::Subgraph(id) => write!(f, "subgraph {:?}", id.as_str()),
+    }
+  }
+}
+
+/// A single finding, with the entity and attribute it concerns if any.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub entity: Option<EntityRef>,
+  pub attribute: Option<String>,
+  pub message: String,
+}
+
+impl Diagnostic {
+  /// Create a finding which doesn't concern any particular entity.
+  pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+    Self {
+      severity,
+      entity: None,
+      attribute: None,
+      message: message.into(),
+    }
+  }
+
+  #[allow(missing_docs)]
+  pub fn entity(mut self, entity: EntityRef) -> Self {
+    self.entity = Some(entity);
+    self
+  }
+
+  #[allow(missing_docs)]
+  pub fn attribute(mut self, name: impl Into<String>) -> Self {
+    self.attribute = Some(name.into());
+    self
+  }
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}: ", self.severity)?;
+    if let Some(ref entity) = self.entity {
+      write!(f, "{}: ", entity)?;
+    }
+    if let Some(ref attribute) = self.attribute {
+      write!(f, "{:?}: ", attribute)?;
+    }
+    f.write_str(&self.message)
+  }
+}
+
+/// Every finding from a build, in the order they were found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+  #[allow(missing_docs)]
+  pub fn new() -> Self { Self::default() }
+
+  #[allow(missing_docs)]
+  pub fn push(&mut self, diagnostic: Diagnostic) { self.0.push(diagnostic); }
+
+  #[allow(missing_docs)]
+  pub fn iter(&self) -> impl Iterator<Item=&Diagnostic> { self.0.iter() }
+
+  #[allow(missing_docs)]
+  pub fn len(&self) -> usize { self.0.len() }
+
+  #[allow(missing_docs)]
+  pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+  /// The number of findings with exactly this severity.
+  pub fn count(&self, severity: Severity) -> usize {
+    self.iter().filter(|d| d.severity == severity).count()
+  }
+
+  /// Whether any finding is a [`Severity::Error`].
+  pub fn has_errors(&self) -> bool { self.count(Severity::Error) > 0 }
+}
+
+impl IntoIterator for Diagnostics {
+  type IntoIter = std::vec::IntoIter<Diagnostic>;
+  type Item = Diagnostic;
+
+  fn into_iter(self) -> Self::IntoIter { self.0.into_iter() }
+}
+
+impl Extend<Diagnostic> for Diagnostics {
+  fn extend<I: IntoIterator<Item=Diagnostic>>(&mut self, iter: I) { self.0.extend(iter); }
+}
+
+/// One finding per line.
+impl fmt::Display for Diagnostics {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for d in self.iter() {
+      writeln!(f, "{}", d)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn display_with_context() {
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.push(Diagnostic::new(Severity::Note, "created 2 vertices"));
+    diagnostics.push(
+      Diagnostic::new(Severity::Error, "unknown attribute")
+        .entity(EntityRef::Vertex(Id::new("a")))
+        .attribute("colour"),
+    );
+    assert!(diagnostics.has_errors());
+    assert_eq!(
+      diagnostics.to_string(),
+      "note: created 2 vertices\n\
+       error: vertex \"a\": \"colour\": unknown attribute\n"
+    );
+  }
+}
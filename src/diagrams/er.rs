@@ -0,0 +1,255 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! [Entity-relationship diagrams](https://en.wikipedia.org/wiki/Entity%E2%80%93relationship_model)
+//! for documenting database schemas.
+//!
+//! Each table is drawn as an HTML table with one row per column. Every row
+//! has a `PORT` named after its column, so relationships connect the
+//! specific columns involved.
+
+use crate::{entities::*, generator::GraphBuilder};
+
+/// Whether a column is part of a key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyKind {
+  /// Part of the table's primary key.
+  Primary,
+  /// References a column in another table.
+  Foreign,
+}
+
+/// A single column of a [`Table`].
+#[derive(Debug, Clone)]
+pub struct Column {
+  /// The column name, which is also used as its port name.
+  pub name: String,
+  /// The SQL type of the column.
+  pub ty: String,
+  /// Whether the column is part of a key.
+  pub key: Option<KeyKind>,
+}
+
+impl Column {
+  /// Create a column which isn't part of any key.
+  pub fn new<S: AsRef<str>, T: AsRef<str>>(name: S, ty: T) -> Self {
+    Self {
+      name: name.as_ref().to_string(),
+      ty: ty.as_ref().to_string(),
+      key: None,
+    }
+  }
+}
+
+/// A database table, drawn with one row per column.
+#[derive(Debug, Clone)]
+pub struct Table {
+  /// The name of the table, shown in the header row.
+  pub name: String,
+  /// Each column in order.
+  pub columns: Vec<Column>,
+}
+
+impl Table {
+  /// Create a table with no columns.
+  pub fn new<S: AsRef<str>>(name: S) -> Self {
+    Self {
+      name: name.as_ref().to_string(),
+      columns: Vec::new(),
+    }
+  }
+
+  /// Generate the HTML table for this entity.
+  pub fn html_label(&self) -> HtmlLabel {
+    let mut rows = format!(
+      "<TR><TD COLSPAN=\"2\" BGCOLOR=\"lightgrey\"><B>{}</B></TD></TR>",
+      HtmlLabel::escape_text(&self.name)
+    );
+    for Column { name, ty, key } in self.columns.iter() {
+      let name_cell = match key {
+        Some(KeyKind::Primary) => format!("<U>{}</U> (PK)", HtmlLabel::escape_text(name)),
+        Some(KeyKind::Foreign) => format!("{} (FK)", HtmlLabel::escape_text(name)),
+        None => HtmlLabel::escape_text(name),
+      };
+      rows.push_str(&format!(
+        "<TR><TD ALIGN=\"LEFT\" PORT=\"{}\">{}</TD><TD ALIGN=\"LEFT\">{}</TD></TR>",
+        HtmlLabel::escape_text(name),
+        name_cell,
+        HtmlLabel::escape_text(ty),
+      ));
+    }
+    HtmlLabel(format!(
+      "<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\">{}</TABLE>",
+      rows
+    ))
+  }
+
+  /// Generate a `shape = plain` vertex displaying this table.
+  pub fn into_vertex(self, id: Id) -> Vertex {
+    Vertex {
+      html_label: Some(self.html_label()),
+      shape: Some(Shape("plain".to_string())),
+      ..Vertex::new(id)
+    }
+  }
+}
+
+/// How many rows on one side of a relationship may match a row on the other
+/// side, drawn in [crow's foot notation](https://en.wikipedia.org/wiki/Entity%E2%80%93relationship_model#Crow's_foot_notation).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Cardinality {
+  ExactlyOne,
+  ZeroOrOne,
+  OneOrMany,
+  ZeroOrMany,
+}
+
+impl Cardinality {
+  /// The arrowhead drawn at this end of the relationship.
+  pub fn arrow(self) -> ArrowType {
+    ArrowType(
+      match self {
+        Self::ExactlyOne => "teetee",
+        Self::ZeroOrOne => "teeodot",
+        Self::OneOrMany => "crowtee",
+        Self::ZeroOrMany => "crowodot",
+      }
+      .to_string(),
+    )
+  }
+
+  /// The textual cardinality drawn next to this end of the relationship.
+  pub fn label(self) -> Label {
+    Label(
+      match self {
+        Self::ExactlyOne => "1",
+        Self::ZeroOrOne => "0..1",
+        Self::OneOrMany => "1..*",
+        Self::ZeroOrMany => "0..*",
+      }
+      .to_string(),
+    )
+  }
+}
+
+/// One end of a relationship: a column of a table, and how many of its rows
+/// participate.
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+  /// The id of the table's vertex.
+  pub table: Id,
+  /// The name of the column.
+  pub column: String,
+  /// How many rows of this table match a single row of the other table.
+  pub cardinality: Cardinality,
+}
+
+/// Generate an edge connecting the columns of two tables.
+pub fn relationship(from: Endpoint, to: Endpoint) -> Edge {
+  Edge {
+    tailport: Some(Port(from.column)),
+    headport: Some(Port(to.column)),
+    arrowtail: Some(from.cardinality.arrow()),
+    arrowhead: Some(to.cardinality.arrow()),
+    taillabel: Some(from.cardinality.label()),
+    headlabel: Some(to.cardinality.label()),
+    dir: Some(DirType("both".to_string())),
+    ..Edge::between(from.table, to.table)
+  }
+}
+
+/// Accumulates tables and the relationships between them.
+#[derive(Debug, Clone, Default)]
+pub struct ErDiagram {
+  entities: Vec<Entity>,
+}
+
+impl ErDiagram {
+  /// Create an empty diagram.
+  pub fn new() -> Self { Self::default() }
+
+  /// Add a table, which can be referred to by `id` in relationships.
+  pub fn add_table(&mut self, id: Id, table: Table) {
+    self.entities.push(Entity::Vertex(table.into_vertex(id)));
+  }
+
+  /// Add a relationship between columns of two tables.
+  pub fn add_relationship(&mut self, from: Endpoint, to: Endpoint) {
+    self.entities.push(Entity::Edge(relationship(from, to)));
+  }
+
+  /// Add every table and relationship to the top level of `graph`.
+  pub fn append_to(self, graph: &mut GraphBuilder) { graph.extend(self.entities); }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::generator::DotOutput;
+
+  #[test]
+  fn table_html_label() {
+    let mut table = Table::new("users");
+    table.columns.push(Column {
+      key: Some(KeyKind::Primary),
+      ..Column::new("id", "int")
+    });
+    table.columns.push(Column::new("name", "varchar<255>"));
+
+    let HtmlLabel(label) = table.html_label();
+    assert_eq!(
+      label,
+      "<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\">\
+         <TR><TD COLSPAN=\"2\" BGCOLOR=\"lightgrey\"><B>users</B></TD></TR>\
+         <TR><TD ALIGN=\"LEFT\" PORT=\"id\"><U>id</U> (PK)</TD><TD ALIGN=\"LEFT\">int</TD></TR>\
+         <TR><TD ALIGN=\"LEFT\" PORT=\"name\">name</TD>\
+           <TD ALIGN=\"LEFT\">varchar&lt;255&gt;</TD></TR>\
+       </TABLE>"
+    );
+  }
+
+  #[test]
+  fn render_relationship() {
+    let mut diagram = ErDiagram::new();
+    diagram.add_table(Id::new("users"), Table::new("users"));
+    diagram.add_table(Id::new("posts"), Table::new("posts"));
+    diagram.add_relationship(
+      Endpoint {
+        table: Id::new("users"),
+        column: "id".to_string(),
+        cardinality: Cardinality::ExactlyOne,
+      },
+      Endpoint {
+        table: Id::new("posts"),
+        column: "author_id".to_string(),
+        cardinality: Cardinality::ZeroOrMany,
+      },
+    );
+
+    let mut gb = GraphBuilder::new();
+    diagram.append_to(&mut gb);
+    let DotOutput(output) = gb.build(Id::new("schema")).unwrap();
+
+    assert!(output.contains(
+      "users -> posts[arrowhead=\"crowodot\", arrowtail=\"teetee\", dir=\"both\", \
+       headport=\"author_id\", tailport=\"id\", headlabel=\"0..*\", taillabel=\"1\"];"
+    ));
+  }
+}
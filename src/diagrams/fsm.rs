@@ -0,0 +1,247 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! [State machine diagrams](https://en.wikipedia.org/wiki/State_diagram).
+//!
+//! States are drawn as circles, with final states drawn as double circles.
+//! The initial state is marked by an edge from a small filled point, whose
+//! id is `__initial` followed by as many `_` as it takes to differ from
+//! every state.
+
+use crate::{entities::*, generator::GraphBuilder, Graphable};
+
+use std::{collections::HashSet, fmt::Display};
+
+/// An edge between two states, taken when `event` occurs and `guard` (if
+/// any) holds.
+#[derive(Debug, Clone)]
+pub struct Transition<S, E> {
+  #[allow(missing_docs)]
+  pub from: S,
+  #[allow(missing_docs)]
+  pub to: S,
+  /// The event triggering this transition.
+  pub event: E,
+  /// A condition which must hold to take this transition, displayed in
+  /// brackets after the event.
+  pub guard: Option<String>,
+}
+
+/// A specification of a state machine. The [`Display`] impl of each state is
+/// used as both its id and its label.
+#[derive(Debug, Clone)]
+pub struct StateMachine<S, E> {
+  /// The state the machine starts in.
+  pub initial: S,
+  /// Every state other than the initial state, which is skipped if listed
+  /// here too. States only referenced by transitions don't need to be
+  /// declared here.
+  pub states: Vec<S>,
+  /// States in which the machine may stop.
+  pub finals: Vec<S>,
+  /// The edges between states.
+  pub transitions: Vec<Transition<S, E>>,
+}
+
+impl<S, E> StateMachine<S, E> {
+  /// Create a state machine with no transitions.
+  pub fn new(initial: S) -> Self {
+    Self {
+      initial,
+      states: Vec::new(),
+      finals: Vec::new(),
+      transitions: Vec::new(),
+    }
+  }
+
+  /// Declare a state.
+  pub fn add_state(&mut self, state: S) { self.states.push(state); }
+
+  /// Declare a state in which the machine may stop.
+  pub fn add_final(&mut self, state: S) { self.finals.push(state); }
+
+  /// Declare a transition between two states.
+  pub fn add_transition(&mut self, from: S, to: S, event: E, guard: Option<String>) {
+    self.transitions.push(Transition {
+      from,
+      to,
+      event,
+      guard,
+    });
+  }
+}
+
+impl<S: Display, E: Display> StateMachine<S, E> {
+  fn state_vertex(state: &S, shape: &str) -> Vertex {
+    let name = state.to_string();
+    Vertex::new(Id::new(&name))
+      .label(Label::escape_text(name))
+      .shape(shape)
+  }
+
+  fn transition_edge(transition: Transition<S, E>) -> Edge {
+    let Transition {
+      from,
+      to,
+      event,
+      guard,
+    } = transition;
+    let label = match guard {
+      Some(guard) => format!("{} [{}]", event, guard),
+      None => event.to_string(),
+    };
+    Edge::between(from.to_string(), to.to_string()).label(Label::escape_text(label))
+  }
+}
+
+impl<S: Display, E: Display> Graphable for StateMachine<S, E> {
+  fn build_graph(self) -> GraphBuilder {
+    let Self {
+      initial,
+      states,
+      finals,
+      transitions,
+    } = self;
+    let mut gb = GraphBuilder::new();
+
+    let is_final = |s: &S| {
+      let name = s.to_string();
+      finals.iter().any(|f| f.to_string() == name)
+    };
+    let shape_for = |s: &S| {
+      if is_final(s) {
+        "doublecircle"
+      } else {
+        "circle"
+      }
+    };
+
+    /* The start marker must not merge with any state, even one named like
+     * it. */
+    let names: HashSet<String> = std::iter::once(&initial)
+      .chain(states.iter())
+      .chain(finals.iter())
+      .chain(transitions.iter().flat_map(|t| [&t.from, &t.to]))
+      .map(|s| s.to_string())
+      .collect();
+    let mut marker = "__initial".to_string();
+    while names.contains(&marker) {
+      marker.push('_');
+    }
+
+    gb.accept_entity(Entity::Vertex(Vertex::new(Id::new(&marker)).shape("point")));
+    gb.accept_entity(Entity::Vertex(Self::state_vertex(
+      &initial,
+      shape_for(&initial),
+    )));
+    gb.accept_entity(Entity::Edge(Edge::between(marker, initial.to_string())));
+
+    let initial_name = initial.to_string();
+    for s in states.iter().filter(|s| s.to_string() != initial_name) {
+      gb.accept_entity(Entity::Vertex(Self::state_vertex(s, shape_for(s))));
+    }
+    for s in finals.iter().filter(|f| {
+      let name = f.to_string();
+      name != initial_name && !states.iter().any(|s| s.to_string() == name)
+    }) {
+      gb.accept_entity(Entity::Vertex(Self::state_vertex(s, "doublecircle")));
+    }
+
+    for t in transitions.into_iter() {
+      gb.accept_entity(Entity::Edge(Self::transition_edge(t)));
+    }
+
+    gb
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::generator::DotOutput;
+
+  #[test]
+  fn render_turnstile() {
+    let mut sm: StateMachine<&str, &str> = StateMachine::new("locked");
+    sm.add_state("unlocked");
+    sm.add_final("broken");
+    sm.add_transition("locked", "unlocked", "coin", None);
+    sm.add_transition("unlocked", "locked", "push", None);
+    sm.add_transition("locked", "broken", "kick", Some("hard".to_string()));
+
+    let DotOutput(output) = sm.build_graph().build(Id::new("turnstile")).unwrap();
+
+    assert_eq!(
+      output,
+      "digraph turnstile {\n  \
+           compound = true;\n\n  \
+           __initial[shape=\"point\"];\n\n  \
+           locked[label=\"locked\", shape=\"circle\"];\n\n  \
+           __initial -> locked;\n\n  \
+           unlocked[label=\"unlocked\", shape=\"circle\"];\n\n  \
+           broken[label=\"broken\", shape=\"doublecircle\"];\n\n  \
+           locked -> unlocked[label=\"coin\"];\n\n  \
+           unlocked -> locked[label=\"push\"];\n\n  \
+           locked -> broken[label=\"kick [hard]\"];\n\
+         }\n"
+    );
+  }
+
+  #[test]
+  fn escaped_labels() {
+    let mut sm: StateMachine<&str, &str> = StateMachine::new("a\\b");
+    sm.add_state("a\\b");
+    sm.add_transition("a\\b", "\"c\"", "go", Some("x == \"y\"".to_string()));
+
+    let DotOutput(output) = sm.build_graph().build(Id::new("g")).unwrap();
+
+    assert_eq!(
+      output,
+      "digraph g {\n  \
+           compound = true;\n\n  \
+           __initial[shape=\"point\"];\n\n  \
+           \"a\\\\b\"[label=\"a\\\\b\", shape=\"circle\"];\n\n  \
+           __initial -> \"a\\\\b\";\n\n  \
+           \"a\\\\b\" -> \"\\\"c\\\"\"[label=\"go [x == \\\"y\\\"]\"];\n\
+         }\n"
+    );
+    crate::parser::parse(&output).unwrap();
+  }
+
+  #[test]
+  fn state_named_like_marker() {
+    let mut sm: StateMachine<&str, &str> = StateMachine::new("a");
+    sm.add_transition("a", "__initial", "go", None);
+    sm.add_transition("__initial", "__initial_", "go", None);
+
+    let DotOutput(output) = sm.build_graph().build(Id::new("g")).unwrap();
+
+    assert_eq!(
+      output,
+      "digraph g {\n  \
+           compound = true;\n\n  \
+           __initial__[shape=\"point\"];\n\n  \
+           a[label=\"a\", shape=\"circle\"];\n\n  \
+           __initial__ -> a;\n\n  \
+           a -> __initial[label=\"go\"];\n\n  \
+           __initial -> __initial_[label=\"go\"];\n\
+         }\n"
+    );
+  }
+}
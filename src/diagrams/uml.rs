@@ -0,0 +1,257 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! [UML class diagrams](https://en.wikipedia.org/wiki/Class_diagram).
+//!
+//! Relationship edges point from the more general class to the more specific
+//! one with `dir = back`, so that supertypes and owners are ranked above the
+//! classes which refer to them.
+//!
+//! Classes are drawn with [record labels](ClassBox::record_label) by default,
+//! or as [HTML tables](ClassBox::html_label) with
+//! [`ClassDiagram::html_labels`].
+
+use crate::{entities::*, generator::GraphBuilder};
+
+/// A class drawn as a box with three compartments: its name, its fields, and
+/// its methods.
+#[derive(Debug, Clone)]
+pub struct ClassBox {
+  /// The name of the class, shown in the top compartment.
+  pub name: String,
+  /// Each field, shown left-justified in the middle compartment.
+  pub fields: Vec<String>,
+  /// Each method, shown left-justified in the bottom compartment.
+  pub methods: Vec<String>,
+}
+
+impl ClassBox {
+  /// Create a class with no fields or methods.
+  pub fn new<S: AsRef<str>>(name: S) -> Self {
+    Self {
+      name: name.as_ref().to_string(),
+      fields: Vec::new(),
+      methods: Vec::new(),
+    }
+  }
+
+  /// Escape characters which have special meaning in a
+  /// [record label](https://www.graphviz.org/doc/info/shapes.html#record).
+  fn escape_record(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+      match c {
+        '{' | '}' | '|' | '<' | '>' | '"' | '\\' => {
+          ret.push('\\');
+          ret.push(c);
+        },
+        c => ret.push(c),
+      }
+    }
+    ret
+  }
+
+  fn compartment(lines: &[String]) -> String {
+    lines
+      .iter()
+      .map(|l| format!("{}\\l", Self::escape_record(l)))
+      .collect()
+  }
+
+  /// Generate the record label for this class.
+  pub fn record_label(&self) -> Label {
+    Label(format!(
+      "{{{}|{}|{}}}",
+      Self::escape_record(&self.name),
+      Self::compartment(&self.fields),
+      Self::compartment(&self.methods),
+    ))
+  }
+
+  /// Generate a `shape = record` vertex for this class.
+  pub fn into_vertex(self, id: Id) -> Vertex {
+    Vertex {
+      label: Some(self.record_label()),
+      shape: Some(Shape("record".to_string())),
+      ..Vertex::new(id)
+    }
+  }
+
+  fn html_compartment(lines: &[String]) -> String {
+    lines
+      .iter()
+      .map(|l| format!("{}<BR ALIGN=\"LEFT\"/>", HtmlLabel::escape_text(l)))
+      .collect()
+  }
+
+  /// Generate an HTML table for this class, with a row for each compartment.
+  pub fn html_label(&self) -> HtmlLabel {
+    HtmlLabel(format!(
+      "<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\">\
+       <TR><TD><B>{}</B></TD></TR>\
+       <TR><TD ALIGN=\"LEFT\">{}</TD></TR>\
+       <TR><TD ALIGN=\"LEFT\">{}</TD></TR>\
+       </TABLE>",
+      HtmlLabel::escape_text(&self.name),
+      Self::html_compartment(&self.fields),
+      Self::html_compartment(&self.methods),
+    ))
+  }
+
+  /// Generate a `shape = plain` vertex displaying this class as an HTML
+  /// table.
+  pub fn into_html_vertex(self, id: Id) -> Vertex {
+    Vertex {
+      html_label: Some(self.html_label()),
+      shape: Some(Shape("plain".to_string())),
+      ..Vertex::new(id)
+    }
+  }
+}
+
+/// The kinds of relationships between classes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Relation {
+  /// The child class extends the parent: drawn with a hollow triangle at the
+  /// parent.
+  Inheritance,
+  /// The child class implements the parent interface: drawn dashed with a
+  /// hollow triangle at the parent.
+  Realization,
+  /// The parent owns the child, which cannot exist without it: drawn with a
+  /// filled diamond at the parent.
+  Composition,
+  /// The parent refers to the child, which exists independently: drawn with a
+  /// hollow diamond at the parent.
+  Aggregation,
+}
+
+impl Relation {
+  /// Generate an edge between `parent` and `child` styled for this
+  /// relationship.
+  pub fn edge(self, parent: Id, child: Id) -> Edge {
+    let (arrowtail, style) = match self {
+      Self::Inheritance => ("empty", None),
+      Self::Realization => ("empty", Some(Style("dashed".to_string()))),
+      Self::Composition => ("diamond", None),
+      Self::Aggregation => ("odiamond", None),
+    };
+    Edge {
+      style,
+      arrowhead: Some(ArrowType("none".to_string())),
+      arrowtail: Some(ArrowType(arrowtail.to_string())),
+      dir: Some(DirType("back".to_string())),
+      ..Edge::between(parent, child)
+    }
+  }
+}
+
+/// Accumulates classes and the relationships between them.
+#[derive(Debug, Clone, Default)]
+pub struct ClassDiagram {
+  entities: Vec<Entity>,
+  html_labels: bool,
+}
+
+impl ClassDiagram {
+  /// Create an empty diagram.
+  pub fn new() -> Self { Self::default() }
+
+  /// Draw classes added from now on as HTML tables, with
+  /// [`ClassBox::into_html_vertex`], rather than as records.
+  pub fn html_labels(&mut self, enabled: bool) { self.html_labels = enabled; }
+
+  /// Add a class, which can be referred to by `id` in relationships.
+  pub fn add_class(&mut self, id: Id, class: ClassBox) {
+    let v = if self.html_labels {
+      class.into_html_vertex(id)
+    } else {
+      class.into_vertex(id)
+    };
+    self.entities.push(Entity::Vertex(v));
+  }
+
+  /// Add a relationship between two classes.
+  pub fn add_relation(&mut self, relation: Relation, parent: Id, child: Id) {
+    self
+      .entities
+      .push(Entity::Edge(relation.edge(parent, child)));
+  }
+
+  /// Add every class and relationship to the top level of `graph`.
+  pub fn append_to(self, graph: &mut GraphBuilder) { graph.extend(self.entities); }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::generator::DotOutput;
+
+  #[test]
+  fn class_record_label() {
+    let mut class = ClassBox::new("Vec<T>");
+    class.fields.push("len: usize".to_string());
+    class.methods.push("push(T)".to_string());
+    class.methods.push("pop() -> Option<T>".to_string());
+
+    let Label(label) = class.record_label();
+    assert_eq!(
+      label,
+      "{Vec\\<T\\>|len: usize\\l|push(T)\\lpop() -\\> Option\\<T\\>\\l}"
+    );
+  }
+
+  #[test]
+  fn class_html_label() {
+    let mut class = ClassBox::new("Vec<T>");
+    class.fields.push("len: usize".to_string());
+    class.methods.push("pop() -> Option<T>".to_string());
+
+    let mut diagram = ClassDiagram::new();
+    diagram.html_labels(true);
+    diagram.add_class(Id::new("Vec"), class);
+    let mut gb = GraphBuilder::new();
+    diagram.append_to(&mut gb);
+    let DotOutput(output) = gb.build(Id::new("classes")).unwrap();
+
+    assert!(output.contains(
+      "Vec[label=<<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\">\
+       <TR><TD><B>Vec&lt;T&gt;</B></TD></TR>\
+       <TR><TD ALIGN=\"LEFT\">len: usize<BR ALIGN=\"LEFT\"/></TD></TR>\
+       <TR><TD ALIGN=\"LEFT\">pop() -&gt; Option&lt;T&gt;<BR ALIGN=\"LEFT\"/></TD></TR>\
+       </TABLE>>, shape=\"plain\"];"
+    ));
+  }
+
+  #[test]
+  fn render_inheritance() {
+    let mut diagram = ClassDiagram::new();
+    diagram.add_class(Id::new("Base"), ClassBox::new("Base"));
+    diagram.add_class(Id::new("Derived"), ClassBox::new("Derived"));
+    diagram.add_relation(Relation::Inheritance, Id::new("Base"), Id::new("Derived"));
+
+    let mut gb = GraphBuilder::new();
+    diagram.append_to(&mut gb);
+    let DotOutput(output) = gb.build(Id::new("classes")).unwrap();
+
+    assert!(
+      output.contains("Base -> Derived[arrowhead=\"none\", arrowtail=\"empty\", dir=\"back\"];")
+    );
+  }
+}
@@ -0,0 +1,244 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Attributes which only apply to specific [layout
+//! engines](https://www.graphviz.org/docs/layouts/).
+//!
+//! Setting [`GraphAttributes::engine`](crate::entities::GraphAttributes::engine)
+//! also selects the engine with the `layout` attribute, so the attributes
+//! always match the engine which reads them.
+
+use crate::entities::{Id, Label};
+
+/// Attributes for the spring-model `neato` engine.
+pub mod neato {
+  /// How the layout [energy is minimized](https://www.graphviz.org/docs/attrs/mode/).
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  #[allow(missing_docs)]
+  pub enum Mode {
+    Major,
+    KK,
+    Sgd,
+    Hier,
+    Ipsep,
+  }
+
+  /// How the [distance matrix](https://www.graphviz.org/docs/attrs/model/) is computed.
+  #[derive(Debug, Clone, Copy, PartialEq)]
+  #[allow(missing_docs)]
+  pub enum Model {
+    ShortPath,
+    Circuit,
+    Subset,
+    Mds,
+  }
+
+  #[derive(Debug, Clone, Default, PartialEq)]
+  #[allow(missing_docs)]
+  pub struct Attributes {
+    pub mode: Option<Mode>,
+    pub model: Option<Model>,
+    /// Terminating condition for the solver.
+    pub epsilon: Option<f64>,
+  }
+
+  impl Attributes {
+    pub(crate) fn statements(&self) -> Vec<(&'static str, String)> {
+      let Self {
+        mode,
+        model,
+        epsilon,
+      } = self;
+      let mut ret = Vec::new();
+      if let Some(mode) = mode {
+        let mode = match mode {
+          Mode::Major => "major",
+          Mode::KK => "KK",
+          Mode::Sgd => "sgd",
+          Mode::Hier => "hier",
+          Mode::Ipsep => "ipsep",
+        };
+        ret.push(("mode", mode.to_string()));
+      }
+      if let Some(model) = model {
+        let model = match model {
+          Model::ShortPath => "shortpath",
+          Model::Circuit => "circuit",
+          Model::Subset => "subset",
+          Model::Mds => "mds",
+        };
+        ret.push(("model", model.to_string()));
+      }
+      if let Some(epsilon) = epsilon {
+        ret.push(("epsilon", format!("{}", epsilon)));
+      }
+      ret
+    }
+  }
+}
+
+/// Attributes for the force-directed `fdp` engine.
+pub mod fdp {
+  #[derive(Debug, Clone, Default, PartialEq)]
+  #[allow(missing_docs)]
+  pub struct Attributes {
+    /// The `K` spring constant, i.e. the ideal edge length in inches.
+    pub k: Option<f64>,
+    /// Number of iterations of the solver.
+    pub maxiter: Option<u32>,
+  }
+
+  impl Attributes {
+    pub(crate) fn statements(&self) -> Vec<(&'static str, String)> {
+      let Self { k, maxiter } = self;
+      let mut ret = Vec::new();
+      if let Some(k) = k {
+        ret.push(("K", format!("{}", k)));
+      }
+      if let Some(maxiter) = maxiter {
+        ret.push(("maxiter", format!("{}", maxiter)));
+      }
+      ret
+    }
+  }
+}
+
+/// Attributes for the multiscale force-directed `sfdp` engine.
+pub mod sfdp {
+  #[derive(Debug, Clone, Default, PartialEq)]
+  #[allow(missing_docs)]
+  pub struct Attributes {
+    /// The `K` spring constant, i.e. the ideal edge length in inches.
+    pub k: Option<f64>,
+    /// The strength of the repulsive force relative to the attractive force.
+    pub repulsiveforce: Option<f64>,
+    /// The number of levels in the multilevel scheme.
+    pub levels: Option<u32>,
+  }
+
+  impl Attributes {
+    pub(crate) fn statements(&self) -> Vec<(&'static str, String)> {
+      let Self {
+        k,
+        repulsiveforce,
+        levels,
+      } = self;
+      let mut ret = Vec::new();
+      if let Some(k) = k {
+        ret.push(("K", format!("{}", k)));
+      }
+      if let Some(repulsiveforce) = repulsiveforce {
+        ret.push(("repulsiveforce", format!("{}", repulsiveforce)));
+      }
+      if let Some(levels) = levels {
+        ret.push(("levels", format!("{}", levels)));
+      }
+      ret
+    }
+  }
+}
+
+/// Attributes for the radial `twopi` engine.
+pub mod twopi {
+  use super::{Id, Label};
+
+  #[derive(Debug, Clone, Default, PartialEq)]
+  #[allow(missing_docs)]
+  pub struct Attributes {
+    /// The vertex at the center of the layout. The radial distance between
+    /// its concentric circles is set by
+    /// [`GraphAttributes::ranksep`](crate::entities::GraphAttributes::ranksep).
+    pub root: Option<Id>,
+  }
+
+  impl Attributes {
+    pub(crate) fn statements(&self) -> Vec<(&'static str, String)> {
+      let Self { root } = self;
+      let mut ret = Vec::new();
+      if let Some(root) = root {
+        ret.push(("root", Label::escape_text(root.as_str())));
+      }
+      ret
+    }
+  }
+}
+
+/// Attributes for the circular `circo` engine.
+pub mod circo {
+  use super::{Id, Label};
+
+  #[derive(Debug, Clone, Default, PartialEq)]
+  #[allow(missing_docs)]
+  pub struct Attributes {
+    /// A vertex to place on the outer circle of its component.
+    pub root: Option<Id>,
+    /// The minimum separation between all vertices, in inches.
+    pub mindist: Option<f64>,
+  }
+
+  impl Attributes {
+    pub(crate) fn statements(&self) -> Vec<(&'static str, String)> {
+      let Self { root, mindist } = self;
+      let mut ret = Vec::new();
+      if let Some(root) = root {
+        ret.push(("root", Label::escape_text(root.as_str())));
+      }
+      if let Some(mindist) = mindist {
+        ret.push(("mindist", format!("{}", mindist)));
+      }
+      ret
+    }
+  }
+}
+
+/// Selects a non-`dot` layout engine along with its specific attributes.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub enum EngineAttributes {
+  Neato(neato::Attributes),
+  Fdp(fdp::Attributes),
+  Sfdp(sfdp::Attributes),
+  Twopi(twopi::Attributes),
+  Circo(circo::Attributes),
+}
+
+impl EngineAttributes {
+  /// The value of the `layout` attribute selecting this engine.
+  pub fn layout(&self) -> &'static str {
+    match self {
+      Self::Neato(_) => "neato",
+      Self::Fdp(_) => "fdp",
+      Self::Sfdp(_) => "sfdp",
+      Self::Twopi(_) => "twopi",
+      Self::Circo(_) => "circo",
+    }
+  }
+
+  pub(crate) fn statements(&self) -> Vec<(&'static str, String)> {
+    let mut ret = vec![("layout", self.layout().to_string())];
+    ret.extend(match self {
+      Self::Neato(a) => a.statements(),
+      Self::Fdp(a) => a.statements(),
+      Self::Sfdp(a) => a.statements(),
+      Self::Twopi(a) => a.statements(),
+      Self::Circo(a) => a.statements(),
+    });
+    ret
+  }
+}
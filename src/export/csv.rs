@@ -0,0 +1,138 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write CSV or TSV edge lists, with one edge per row.
+//!
+//! Columns are laid out as in [`CsvFormat`], which is shared with
+//! [`import::csv`](crate::import::csv) so edge lists can be read back the
+//! same way. The header row, if any, names each column `source`, `target`,
+//! `label`, or `weight`. Fields are quoted if they contain the delimiter, a
+//! double quote, or a line break. Labels are written as plain text, undoing
+//! [`Label::escape_text`](crate::entities::Label::escape_text). Vertices
+//! without edges are dropped, as are all attributes besides labels and
+//! weights.
+//!
+//!```
+//! use graphvizier::{entities::*, export::csv::to_csv, generator::GraphBuilder, import::csv::CsvFormat};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b").label("x, y").weight(2.5)));
+//! gb.accept_entity(Entity::Edge(Edge::between("b", "c")));
+//! assert_eq!(
+//!   to_csv(&gb, &CsvFormat::default()),
+//!   "source,target,label,weight\na,b,\"x, y\",2.5\nb,c,,\n"
+//! );
+//! ```
+
+use super::{all_edges, edge_attributes, lookup};
+use crate::{generator::GraphBuilder, import::csv::CsvFormat};
+
+fn field(s: &str, delimiter: char) -> String {
+  if s.contains([delimiter, '"', '\n', '\r']) {
+    format!("\"{}\"", s.replace('"', "\"\""))
+  } else {
+    s.to_string()
+  }
+}
+
+/// Write the edge list of `graph` laid out as `format`. See the [module
+/// docs](self).
+pub fn to_csv(graph: &GraphBuilder, format: &CsvFormat) -> String {
+  let columns = [
+    Some(format.source),
+    Some(format.target),
+    format.label,
+    format.weight,
+  ];
+  let width = columns.iter().flatten().max().map_or(0, |max| max + 1);
+  let delimiter = format.delimiter.to_string();
+  let row = |values: [Option<&str>; 4]| -> String {
+    let mut fields = vec![String::new(); width];
+    for (column, value) in columns.iter().zip(values) {
+      if let (Some(column), Some(value)) = (column, value) {
+        fields[*column] = field(value, format.delimiter);
+      }
+    }
+    fields.join(&delimiter) + "\n"
+  };
+
+  let mut out = String::new();
+  if format.header {
+    out.push_str(&row([
+      Some("source"),
+      Some("target"),
+      Some("label"),
+      Some("weight"),
+    ]));
+  }
+  for e in all_edges(graph.entities()) {
+    let attrs = edge_attributes(e);
+    out.push_str(&row([
+      Some(e.source.as_str()),
+      Some(e.target.as_str()),
+      lookup(&attrs, "label"),
+      lookup(&attrs, "weight"),
+    ]));
+  }
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{entities::*, import::csv::from_csv};
+
+  #[test]
+  fn round_trip() {
+    let format = CsvFormat {
+      header: false,
+      target: 0,
+      source: 2,
+      label: Some(1),
+      weight: None,
+      ..CsvFormat::tsv()
+    };
+    let mut gb = GraphBuilder::new();
+    let label = Label::escape_text("say \"hi\"\tthere");
+    gb.accept_entity(Entity::Edge(
+      Edge::between("a", "b").label(label.as_str()).weight(1.0),
+    ));
+    let tsv = to_csv(&gb, &format);
+    assert_eq!(tsv, "b\t\"say \"\"hi\"\"\tthere\"\ta\n");
+    assert_eq!(
+      from_csv(&tsv, &format).unwrap().entities[2],
+      Entity::Edge(Edge::between("a", "b").label(label.as_str()))
+    );
+  }
+
+  #[test]
+  fn commas_and_newlines() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Edge(Edge::between("a,1", "b").label("one\ntwo")));
+    gb.accept_entity(Entity::Edge(Edge::between("c", "d").label("x, y")));
+    assert_eq!(
+      to_csv(&gb, &CsvFormat::default()),
+      "source,target,label,weight\n\"a,1\",b,\"one\ntwo\",\nc,d,\"x, y\",\n"
+    );
+    assert_eq!(
+      to_csv(&gb, &CsvFormat::tsv()),
+      "source\ttarget\tlabel\tweight\na,1\tb\t\"one\ntwo\"\t\nc\td\tx, y\t\n"
+    );
+  }
+}
@@ -0,0 +1,300 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write a [D2](https://d2lang.com/) diagram.
+//!
+//! Subgraphs become nested containers, and vertices within them are
+//! referred to by their full path, like `cluster.vertex`. Since D2 scopes
+//! connections to the container they're written in, every edge is written
+//! at the top level after all the declarations, as `--` rather than `->`
+//! if the graph is [undirected](GraphBuilder::set_directed). Common shapes, colors,
+//! dashed lines, and invisible edges are mapped onto D2 `shape` and `style`
+//! fields.
+//!
+//!```
+//! use graphvizier::{entities::*, export::d2::to_d2, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("start").shape("circle")));
+//! gb.accept_entity(Entity::Subgraph(Subgraph {
+//!   entities: vec![Entity::Vertex(Vertex::new("my node").label("Mine"))],
+//!   ..Subgraph::new("cluster_a").label("A")
+//! }));
+//! gb.accept_entity(Entity::Edge(Edge::between("start", "my node").label("go")));
+//! assert_eq!(
+//!   to_d2(&gb),
+//!   "start: {\n  \
+//!      shape: circle\n\
+//!    }\n\
+//!    cluster_a: \"A\" {\n  \
+//!      \"my node\": \"Mine\"\n\
+//!    }\n\
+//!    start -> cluster_a.\"my node\": \"go\"\n"
+//! );
+//! ```
+
+use super::{edge_attributes, label_lines, lookup, subgraph_attributes, vertex_attributes};
+use crate::{
+  entities::{Entity, Id, Vertex},
+  generator::GraphBuilder,
+};
+
+use std::{collections::HashMap, fmt::Write};
+
+/// Keys which D2 interprets as fields rather than names of shapes.
+const RESERVED: [&str; 17] = [
+  "label",
+  "shape",
+  "style",
+  "icon",
+  "width",
+  "height",
+  "direction",
+  "near",
+  "tooltip",
+  "link",
+  "constraint",
+  "class",
+  "classes",
+  "vars",
+  "top",
+  "left",
+  "null",
+];
+
+/// `id` as a D2 key, quoted unless it's a plain identifier.
+fn key(id: &str) -> String {
+  let plain = id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+    && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    && !RESERVED.contains(&id);
+  if plain {
+    id.to_string()
+  } else {
+    string(id)
+  }
+}
+
+/// `text` as a double-quoted D2 string, with DOT line breaks as `\n`.
+fn string(text: &str) -> String {
+  let lines: Vec<String> = label_lines(text)
+    .iter()
+    .map(|line| line.replace('\\', "\\\\").replace('"', "\\\""))
+    .collect();
+  format!("\"{}\"", lines.join("\\n"))
+}
+
+fn shape(dot: &str) -> Option<&'static str> {
+  Some(match dot {
+    "box" | "rect" | "rectangle" => "rectangle",
+    "square" => "square",
+    "circle" | "doublecircle" | "point" => "circle",
+    "ellipse" | "oval" => "oval",
+    "diamond" => "diamond",
+    "hexagon" => "hexagon",
+    "cylinder" => "cylinder",
+    "parallelogram" => "parallelogram",
+    "note" => "page",
+    _ => return None,
+  })
+}
+
+/// D2 fields for the shape and style set in `attrs`.
+fn fields(attrs: &[(String, String)]) -> Vec<String> {
+  let mut ret = Vec::new();
+  if let Some(shape) = lookup(attrs, "shape").and_then(shape) {
+    ret.push(format!("shape: {}", shape));
+  }
+  if let Some(fill) = lookup(attrs, "fillcolor") {
+    ret.push(format!("style.fill: {}", string(fill)));
+  }
+  if let Some(stroke) = lookup(attrs, "color") {
+    ret.push(format!("style.stroke: {}", string(stroke)));
+  }
+  if let Some(font) = lookup(attrs, "fontcolor") {
+    ret.push(format!("style.font-color: {}", string(font)));
+  }
+  let style = lookup(attrs, "style").unwrap_or("");
+  if style.contains("dashed") || style.contains("dotted") {
+    ret.push("style.stroke-dash: 3".to_string());
+  }
+  if style.contains("bold") {
+    ret.push("style.stroke-width: 3".to_string());
+  }
+  if style.contains("invis") {
+    ret.push("style.opacity: 0".to_string());
+  }
+  ret
+}
+
+struct Writer {
+  out: String,
+  /* The full path of the first declaration of each vertex. */
+  paths: HashMap<Id, String>,
+}
+
+impl Writer {
+  fn indent(&mut self, depth: usize) {
+    for _ in 0..depth {
+      self.out.push_str("  ");
+    }
+  }
+
+  /// Write `name`, an optional `label`, and a block of `fields` if any.
+  fn declare(&mut self, name: &str, label: Option<&str>, fields: &[String], depth: usize) {
+    self.indent(depth);
+    self.out.push_str(name);
+    if label.is_some() || !fields.is_empty() {
+      self.out.push(':');
+    }
+    if let Some(label) = label {
+      write!(self.out, " {}", string(label)).unwrap();
+    }
+    if !fields.is_empty() {
+      self.out.push_str(" {\n");
+      for f in fields.iter() {
+        self.indent(depth + 1);
+        writeln!(self.out, "{}", f).unwrap();
+      }
+      self.indent(depth);
+      self.out.push('}');
+    }
+    self.out.push('\n');
+  }
+
+  fn vertex(&mut self, v: &Vertex, prefix: &str, depth: usize) {
+    let attrs = vertex_attributes(v);
+    let name = key(v.id.as_str());
+    self
+      .paths
+      .entry(v.id.clone())
+      .or_insert_with(|| format!("{}{}", prefix, name));
+    let label = lookup(&attrs, "label").filter(|l| *l != v.id.as_str());
+    self.declare(&name, label, &fields(&attrs), depth);
+  }
+
+  fn entities<'a>(&mut self, entities: impl Iterator<Item=&'a Entity>, prefix: &str, depth: usize) {
+    for e in entities {
+      match e {
+        Entity::Vertex(v) => self.vertex(v, prefix, depth),
+        Entity::Subgraph(sg) => {
+          let attrs = subgraph_attributes(sg);
+          let name = key(sg.id.as_str());
+          self.indent(depth);
+          self.out.push_str(&name);
+          self.out.push(':');
+          if let Some(label) = lookup(&attrs, "label") {
+            write!(self.out, " {}", string(label)).unwrap();
+          }
+          self.out.push_str(" {\n");
+          for f in fields(&attrs).iter() {
+            self.indent(depth + 1);
+            writeln!(self.out, "{}", f).unwrap();
+          }
+          let prefix = format!("{}{}.", prefix, name);
+          self.entities(sg.entities.iter(), &prefix, depth + 1);
+          self.indent(depth);
+          self.out.push_str("}\n");
+        },
+        Entity::Group(g) => self.entities(g.entities.iter(), prefix, depth),
+        Entity::Comment(text) => {
+          for line in text.lines() {
+            self.indent(depth);
+            writeln!(self.out, "# {}", line).unwrap();
+          }
+        },
+        Entity::Edge(_) => (),
+      }
+    }
+  }
+}
+
+/// Write the D2 diagram for `graph`. See the [module docs](self).
+pub fn to_d2(graph: &GraphBuilder) -> String {
+  let mut w = Writer {
+    out: String::new(),
+    paths: HashMap::new(),
+  };
+  w.entities(graph.entities(), "", 0);
+  let arrow = if graph.is_directed() { "->" } else { "--" };
+  for e in graph.edges() {
+    let attrs = edge_attributes(e);
+    let path = |id: &Id| w.paths.get(id).cloned().unwrap_or_else(|| key(id.as_str()));
+    let name = format!("{} {} {}", path(&e.source), arrow, path(&e.target));
+    let label = lookup(&attrs, "label");
+    let fields = fields(&attrs);
+    w.declare(&name, label, &fields, 0);
+  }
+  w.out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn nested_paths_and_styles() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Comment("top".to_string()));
+    gb.accept_entity(Entity::Subgraph(Subgraph {
+      entities: vec![Entity::Subgraph(Subgraph {
+        entities: vec![Entity::Vertex(
+          Vertex::new("a.b")
+            .label("say \"hi\"\\nthere")
+            .attr("fillcolor", "#eeeeee"),
+        )],
+        ..Subgraph::new("label")
+      })],
+      ..Subgraph::new("outer").color(Color::red())
+    }));
+    gb.accept_entity(Entity::Edge(
+      Edge::between("a.b", "c")
+        .style("dashed")
+        .color(Color::blue()),
+    ));
+    assert_eq!(
+      to_d2(&gb),
+      "# top\n\
+       outer: {\n  \
+         style.stroke: \"red\"\n  \
+         \"label\": {\n    \
+           \"a.b\": \"say \\\"hi\\\"\\nthere\" {\n      \
+             style.fill: \"#eeeeee\"\n    \
+           }\n  \
+         }\n\
+       }\n\
+       outer.\"label\".\"a.b\" -> c: {\n  \
+         style.stroke: \"blue\"\n  \
+         style.stroke-dash: 3\n\
+       }\n"
+    );
+  }
+
+  #[test]
+  fn quoted_keys_and_line_breaks() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("my node").label("two\\nlines")));
+    gb.accept_entity(Entity::Edge(Edge::between("my node", "b.c")));
+    assert_eq!(
+      to_d2(&gb),
+      "\"my node\": \"two\\nlines\"\n\
+       \"my node\" -> \"b.c\"\n"
+    );
+  }
+}
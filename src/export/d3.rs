@@ -0,0 +1,141 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write the `{"nodes": [...], "links": [...]}` JSON used by
+//! [d3-force](https://d3js.org/d3-force) examples, to drop graphs into
+//! existing D3 visualizations.
+//!
+//! Each node has its `id` and `label` (which defaults to the id), its
+//! `color` if set, and the id of the innermost subgraph it was declared in
+//! as its `group`, if any. Each link refers to its `source` and `target` by
+//! id, with its `label` and `color` if set, and its `weight` as its `value`.
+//!
+//! Requires the `json` feature.
+//!
+//!```
+//! use graphvizier::{entities::*, export::d3::to_d3_force, generator::GraphBuilder};
+//! use serde_json::json;
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Subgraph(Subgraph {
+//!   entities: vec![Entity::Vertex(Vertex::new("a").color(Color::red()))],
+//!   ..Subgraph::new("team")
+//! }));
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b").weight(3.0)));
+//! assert_eq!(to_d3_force(&gb), json!({
+//!   "nodes": [
+//!     {"id": "a", "label": "a", "color": "red", "group": "team"},
+//!     {"id": "b", "label": "b"},
+//!   ],
+//!   "links": [{"source": "a", "target": "b", "value": 3.0}],
+//! }));
+//! ```
+
+use super::{
+  all_edges, all_vertices, edge_attributes, lookup, subgraph_of, vertex_attributes, vertex_label,
+};
+use crate::generator::GraphBuilder;
+
+use serde_json::{json, Map, Value};
+
+use std::collections::HashMap;
+
+/// Write the d3-force JSON for `graph`. See the [module docs](self).
+pub fn to_d3_force(graph: &GraphBuilder) -> Value {
+  let mut group_of = HashMap::new();
+  subgraph_of(graph.entities(), None, &mut group_of);
+
+  let nodes: Vec<Value> = all_vertices(graph.entities())
+    .iter()
+    .map(|v| {
+      let attrs = vertex_attributes(v);
+      let mut node = Map::new();
+      node.insert("id".to_string(), json!(v.id.as_str()));
+      node.insert("label".to_string(), json!(vertex_label(v)));
+      if let Some(color) = lookup(&attrs, "color") {
+        node.insert("color".to_string(), json!(color));
+      }
+      if let Some(Some(group)) = group_of.get(&v.id) {
+        node.insert("group".to_string(), json!(group.as_str()));
+      }
+      Value::Object(node)
+    })
+    .collect();
+
+  let links: Vec<Value> = all_edges(graph.entities())
+    .into_iter()
+    .map(|e| {
+      let attrs = edge_attributes(e);
+      let mut link = Map::new();
+      link.insert("source".to_string(), json!(e.source.as_str()));
+      link.insert("target".to_string(), json!(e.target.as_str()));
+      for key in ["label", "color"] {
+        if let Some(value) = lookup(&attrs, key) {
+          link.insert(key.to_string(), json!(value));
+        }
+      }
+      if let Some(weight) = lookup(&attrs, "weight").and_then(|w| w.parse::<f64>().ok()) {
+        link.insert("value".to_string(), json!(weight));
+      }
+      Value::Object(link)
+    })
+    .collect();
+
+  json!({ "nodes": nodes, "links": links })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn nested_groups() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Subgraph(Subgraph {
+      entities: vec![
+        Entity::Group(Group {
+          node_defaults: None,
+          entities: vec![Entity::Vertex(Vertex::new("a").label("A"))],
+        }),
+        Entity::Subgraph(Subgraph {
+          entities: vec![Entity::Vertex(Vertex::new("b"))],
+          ..Subgraph::new("inner")
+        }),
+      ],
+      ..Subgraph::new("outer")
+    }));
+    gb.accept_entity(Entity::Edge(
+      Edge::between("a", "b")
+        .label("x")
+        .color(Color::blue())
+        .attr("weight", "heavy"),
+    ));
+    assert_eq!(
+      to_d3_force(&gb),
+      json!({
+        "nodes": [
+          {"id": "a", "label": "A", "group": "outer"},
+          {"id": "b", "label": "b", "group": "inner"},
+        ],
+        "links": [{"source": "a", "target": "b", "label": "x", "color": "blue"}],
+      })
+    );
+  }
+}
@@ -0,0 +1,100 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write the [DIMACS](http://prolland.free.fr/works/research/dsat/dimacs.html)
+//! edge format, as read by graph coloring, clique, and matching solvers.
+//!
+//! DIMACS vertices are numbered from 1, so [`to_dimacs`] returns the table
+//! of which [`Id`] each number stands for alongside the document, in order
+//! to map a solver's answer back onto the graph. Vertices are numbered in
+//! the order they were declared, followed by edge endpoints which were never
+//! declared. Attributes are dropped.
+//!
+//!```
+//! use graphvizier::{entities::*, export::dimacs::to_dimacs, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("a")));
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+//! let dimacs = to_dimacs(&gb);
+//! assert_eq!(dimacs.document, "p edge 2 1\ne 1 2\n");
+//! assert_eq!(dimacs.id(2), Some(&Id::new("b")));
+//! assert_eq!(dimacs.number(&Id::new("a")), Some(1));
+//! ```
+
+use super::{all_edges, all_vertices};
+use crate::{entities::Id, generator::GraphBuilder};
+
+use std::{collections::HashMap, fmt::Write};
+
+/// A DIMACS document, and the ids its vertex numbers stand for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dimacs {
+  #[allow(missing_docs)]
+  pub document: String,
+  /// The id of vertex `i + 1` is at index `i`.
+  pub ids: Vec<Id>,
+}
+
+impl Dimacs {
+  /// The id of the DIMACS vertex numbered `n`.
+  pub fn id(&self, n: usize) -> Option<&Id> { n.checked_sub(1).and_then(|i| self.ids.get(i)) }
+
+  /// The DIMACS vertex number of `id`.
+  pub fn number(&self, id: &Id) -> Option<usize> {
+    self.ids.iter().position(|x| x == id).map(|i| i + 1)
+  }
+}
+
+/// Write the DIMACS document for `graph`. See the [module docs](self).
+pub fn to_dimacs(graph: &GraphBuilder) -> Dimacs {
+  let ids: Vec<Id> = all_vertices(graph.entities())
+    .into_iter()
+    .map(|v| v.id)
+    .collect();
+  let numbers: HashMap<&Id, usize> = ids.iter().enumerate().map(|(i, id)| (id, i + 1)).collect();
+  let edges = all_edges(graph.entities());
+
+  let mut document = String::new();
+  writeln!(document, "p edge {} {}", ids.len(), edges.len()).unwrap();
+  for e in edges {
+    writeln!(document, "e {} {}", numbers[&e.source], numbers[&e.target]).unwrap();
+  }
+  Dimacs { document, ids }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn numbering() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Edge(Edge::between("z", "y")));
+    gb.accept_entity(Entity::Vertex(Vertex::new("y")));
+    gb.accept_entity(Entity::Edge(Edge::between("y", "y")));
+    let dimacs = to_dimacs(&gb);
+    assert_eq!(dimacs.document, "p edge 2 2\ne 2 1\ne 1 1\n");
+    assert_eq!(dimacs.ids, vec![Id::new("y"), Id::new("z")]);
+    assert_eq!(dimacs.id(0), None);
+    assert_eq!(dimacs.id(3), None);
+    assert_eq!(dimacs.number(&Id::new("x")), None);
+  }
+}
@@ -0,0 +1,283 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write [GEXF 1.3](https://gexf.net/) for exploring large graphs in
+//! [Gephi](https://gephi.org/).
+//!
+//! Vertex and edge labels become GEXF labels, and edge weights become GEXF
+//! weights. Colors, sizes, and positions are written with the `viz`
+//! extension: `color` if it is a `#rrggbb[aa]` value or a basic HTML color
+//! name, the larger of `width` and `height` in points, and `pos` (as
+//! computed by a layout engine). Every other attribute is written as a
+//! string-typed GEXF attribute with the same name.
+//!
+//! Subgraphs are flattened, and edges whose endpoints were never declared
+//! get plain nodes of their own.
+//!
+//!```
+//! use graphvizier::{entities::*, export::gexf::to_gexf, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("a").label("A").color(Color::red()).shape("box")));
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b").weight(2.0)));
+//! let gexf = to_gexf(&gb);
+//! assert!(gexf.contains(r#"<attvalue for="shape" value="box"/>"#));
+//! assert!(gexf.contains(r#"<viz:color r="255" g="0" b="0" a="1"/>"#));
+//! assert!(gexf.contains(r#"<node id="b" label="b"/>"#));
+//! assert!(gexf.contains(r#"<edge id="0" source="a" target="b" weight="2"/>"#));
+//! ```
+
+use super::{
+  all_edges, all_vertices, edge_attributes, lookup, rgba, vertex_attributes, vertex_label,
+};
+use crate::{entities::style::HtmlLabel, generator::GraphBuilder};
+
+use std::{collections::BTreeSet, fmt::Write};
+
+/// Attributes written as part of GEXF itself, rather than as attvalues.
+const NODE_BUILTINS: [&str; 3] = ["label", "color", "pos"];
+const EDGE_BUILTINS: [&str; 3] = ["label", "color", "weight"];
+
+fn escape(s: &str) -> String { HtmlLabel::escape_text(s) }
+
+fn write_declarations(out: &mut String, class: &str, names: &BTreeSet<&str>) {
+  if names.is_empty() {
+    return;
+  }
+  writeln!(out, "    <attributes class=\"{}\">", class).unwrap();
+  for name in names.iter() {
+    let name = escape(name);
+    writeln!(
+      out,
+      "      <attribute id=\"{}\" title=\"{}\" type=\"string\"/>",
+      name, name
+    )
+    .unwrap();
+  }
+  out.push_str("    </attributes>\n");
+}
+
+/// The attvalues and viz elements for an entity with `attributes`, each on
+/// its own line.
+fn write_body(out: &mut String, attributes: &[(String, String)], builtins: &[&str]) -> bool {
+  let start = out.len();
+  let values: Vec<_> = attributes
+    .iter()
+    .filter(|(k, _)| !builtins.contains(&k.as_str()))
+    .collect();
+  if !values.is_empty() {
+    out.push_str("        <attvalues>\n");
+    for (k, v) in values {
+      writeln!(
+        out,
+        "          <attvalue for=\"{}\" value=\"{}\"/>",
+        escape(k),
+        escape(v)
+      )
+      .unwrap();
+    }
+    out.push_str("        </attvalues>\n");
+  }
+  if let Some([r, g, b, a]) = lookup(attributes, "color").and_then(rgba) {
+    let a = f64::from(a) / 255.0;
+    writeln!(
+      out,
+      "        <viz:color r=\"{}\" g=\"{}\" b=\"{}\" a=\"{}\"/>",
+      r, g, b, a
+    )
+    .unwrap();
+  }
+  out.len() > start
+}
+
+/// Write the GEXF document for `graph`. See the [module docs](self).
+pub fn to_gexf(graph: &GraphBuilder) -> String {
+  let vertices = all_vertices(graph.entities());
+  let edges = all_edges(graph.entities());
+  let vertex_attrs: Vec<_> = vertices.iter().map(vertex_attributes).collect();
+  let edge_attrs: Vec<_> = edges.iter().map(|e| edge_attributes(e)).collect();
+
+  let declared = |attrs: &[Vec<(String, String)>], builtins: &[&str]| -> BTreeSet<String> {
+    attrs
+      .iter()
+      .flatten()
+      .map(|(k, _)| k.clone())
+      .filter(|k| !builtins.contains(&k.as_str()))
+      .collect()
+  };
+  let node_names = declared(&vertex_attrs, &NODE_BUILTINS);
+  let edge_names = declared(&edge_attrs, &EDGE_BUILTINS);
+
+  let mut out = String::new();
+  out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+  out.push_str(
+    "<gexf xmlns=\"http://gexf.net/1.3\" xmlns:viz=\"http://gexf.net/1.3/viz\" version=\"1.3\">\n",
+  );
+  writeln!(
+    out,
+    "  <graph defaultedgetype=\"{}\" mode=\"static\">",
+    if graph.is_directed() {
+      "directed"
+    } else {
+      "undirected"
+    }
+  )
+  .unwrap();
+  write_declarations(
+    &mut out,
+    "node",
+    &node_names.iter().map(String::as_str).collect(),
+  );
+  write_declarations(
+    &mut out,
+    "edge",
+    &edge_names.iter().map(String::as_str).collect(),
+  );
+
+  out.push_str("    <nodes>\n");
+  for (v, attrs) in vertices.iter().zip(vertex_attrs.iter()) {
+    let open = format!(
+      "      <node id=\"{}\" label=\"{}\"",
+      escape(v.id.as_str()),
+      escape(&vertex_label(v))
+    );
+    let mut body = String::new();
+    let mut has_body = write_body(&mut body, attrs, &NODE_BUILTINS);
+    let size = [lookup(attrs, "width"), lookup(attrs, "height")]
+      .iter()
+      .flatten()
+      .filter_map(|x| x.parse::<f64>().ok())
+      .fold(None, |max: Option<f64>, x| {
+        Some(max.map_or(x, |m| m.max(x)))
+      });
+    if let Some(size) = size {
+      writeln!(body, "        <viz:size value=\"{}\"/>", size * 72.0).unwrap();
+      has_body = true;
+    }
+    let position = lookup(attrs, "pos").and_then(|pos| {
+      let (x, y) = pos.trim_end_matches('!').split_once(',')?;
+      Some((x.trim().parse::<f64>().ok()?, y.trim().parse::<f64>().ok()?))
+    });
+    if let Some((x, y)) = position {
+      writeln!(
+        body,
+        "        <viz:position x=\"{}\" y=\"{}\" z=\"0\"/>",
+        x, y
+      )
+      .unwrap();
+      has_body = true;
+    }
+    if has_body {
+      writeln!(out, "{}>\n{}      </node>", open, body).unwrap();
+    } else {
+      writeln!(out, "{}/>", open).unwrap();
+    }
+  }
+  out.push_str("    </nodes>\n");
+
+  if !edges.is_empty() {
+    out.push_str("    <edges>\n");
+    for (i, (e, attrs)) in edges.iter().zip(edge_attrs.iter()).enumerate() {
+      let mut open = format!(
+        "      <edge id=\"{}\" source=\"{}\" target=\"{}\"",
+        i,
+        escape(e.source.as_str()),
+        escape(e.target.as_str())
+      );
+      if let Some(label) = lookup(attrs, "label") {
+        write!(open, " label=\"{}\"", escape(label)).unwrap();
+      }
+      if let Some(weight) = lookup(attrs, "weight") {
+        write!(open, " weight=\"{}\"", escape(weight)).unwrap();
+      }
+      let mut body = String::new();
+      if write_body(&mut body, attrs, &EDGE_BUILTINS) {
+        writeln!(out, "{}>\n{}      </edge>", open, body).unwrap();
+      } else {
+        writeln!(out, "{}/>", open).unwrap();
+      }
+    }
+    out.push_str("    </edges>\n");
+  }
+  out.push_str("  </graph>\n</gexf>\n");
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn viz_and_edge_attributes() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Subgraph(Subgraph {
+      entities: vec![Entity::Vertex(Vertex {
+        width: Some(0.5),
+        height: Some(1.0),
+        ..Vertex::new("x & y").attr("pos", "10,20!")
+      })],
+      ..Subgraph::new("s")
+    }));
+    gb.accept_entity(Entity::Edge(
+      Edge::between("x & y", "x & y")
+        .label("<loop>")
+        .color(Color::new("#00ff0080")),
+    ));
+    assert_eq!(
+      to_gexf(&gb),
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+       <gexf xmlns=\"http://gexf.net/1.3\" xmlns:viz=\"http://gexf.net/1.3/viz\" version=\"1.3\">\n  \
+         <graph defaultedgetype=\"directed\" mode=\"static\">\n    \
+           <attributes class=\"node\">\n      \
+             <attribute id=\"height\" title=\"height\" type=\"string\"/>\n      \
+             <attribute id=\"width\" title=\"width\" type=\"string\"/>\n    \
+           </attributes>\n    \
+           <nodes>\n      \
+             <node id=\"x &amp; y\" label=\"x &amp; y\">\n        \
+               <attvalues>\n          \
+                 <attvalue for=\"width\" value=\"0.5\"/>\n          \
+                 <attvalue for=\"height\" value=\"1\"/>\n        \
+               </attvalues>\n        \
+               <viz:size value=\"72\"/>\n        \
+               <viz:position x=\"10\" y=\"20\" z=\"0\"/>\n      \
+             </node>\n    \
+           </nodes>\n    \
+           <edges>\n      \
+             <edge id=\"0\" source=\"x &amp; y\" target=\"x &amp; y\" label=\"&lt;loop&gt;\">\n        \
+               <viz:color r=\"0\" g=\"255\" b=\"0\" a=\"0.5019607843137255\"/>\n      \
+             </edge>\n    \
+           </edges>\n  \
+         </graph>\n\
+       </gexf>\n"
+    );
+  }
+
+  #[test]
+  fn xml_entities() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("a&b").label("<i> & \"j\"")));
+    let out = to_gexf(&gb);
+    assert!(
+      out.contains("<node id=\"a&amp;b\" label=\"&lt;i&gt; &amp; &quot;j&quot;\"/>"),
+      "{}",
+      out
+    );
+  }
+}
@@ -0,0 +1,214 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write the compact [graph6 and
+//! sparse6](https://users.cecs.anu.edu.au/~bdm/data/formats.txt) formats,
+//! as read by nauty, SageMath, NetworkX, and other combinatorics tools.
+//!
+//! Both formats only describe the structure of an undirected graph, so edge
+//! directions, subgraphs, and all attributes are dropped. graph6 can't
+//! represent loops or parallel edges either, so those are dropped too;
+//! sparse6 keeps them. Vertices are numbered from 0 in the order they were
+//! declared, followed by edge endpoints which were never declared.
+//!
+//! Neither function adds a `>>graph6<<` header or a trailing newline.
+//!
+//!```
+//! use graphvizier::{entities::*, export::graph6::{to_graph6, to_sparse6}, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+//! gb.accept_entity(Entity::Edge(Edge::between("b", "c")));
+//! assert_eq!(to_graph6(&gb), "Bg");
+//! assert_eq!(to_sparse6(&gb), ":Bd");
+//! ```
+
+use super::{all_edges, all_vertices};
+use crate::generator::GraphBuilder;
+
+use std::collections::HashMap;
+
+/// Bits packed six at a time into printable characters.
+#[derive(Default)]
+struct Bits {
+  out: String,
+  current: u8,
+  len: u8,
+}
+
+impl Bits {
+  fn push(&mut self, bit: bool) {
+    self.current = (self.current << 1) | bit as u8;
+    self.len += 1;
+    if self.len == 6 {
+      self.out.push((self.current + 63) as char);
+      self.current = 0;
+      self.len = 0;
+    }
+  }
+
+  /// The low `width` bits of `x`, most significant first.
+  fn push_int(&mut self, x: usize, width: u32) {
+    for i in (0..width).rev() {
+      self.push((x >> i) & 1 == 1);
+    }
+  }
+
+  /// The number of bits needed to fill the last character.
+  fn remaining(&self) -> u8 {
+    if self.len == 0 {
+      0
+    } else {
+      6 - self.len
+    }
+  }
+
+  /// Pad the last character with `fill` bits.
+  fn finish(mut self, fill: bool) -> String {
+    while self.len != 0 {
+      self.push(fill);
+    }
+    self.out
+  }
+}
+
+/// The graph6 encoding of the vertex count `n`.
+fn size(n: usize) -> String {
+  let mut bits = Bits::default();
+  if n <= 62 {
+    return ((n as u8 + 63) as char).to_string();
+  } else if n <= 258047 {
+    bits.out.push('~');
+    bits.push_int(n, 18);
+  } else {
+    bits.out.push_str("~~");
+    bits.push_int(n, 36);
+  }
+  bits.out
+}
+
+/// The number of vertices, and each edge as a pair of vertex numbers.
+fn numbered(graph: &GraphBuilder) -> (usize, Vec<(usize, usize)>) {
+  let vertices = all_vertices(graph.entities());
+  let numbers: HashMap<_, _> = vertices
+    .iter()
+    .enumerate()
+    .map(|(i, v)| (&v.id, i))
+    .collect();
+  let edges = all_edges(graph.entities())
+    .into_iter()
+    .map(|e| (numbers[&e.source], numbers[&e.target]))
+    .collect();
+  (vertices.len(), edges)
+}
+
+/// Write the graph6 encoding of `graph`. See the [module docs](self).
+pub fn to_graph6(graph: &GraphBuilder) -> String {
+  let (n, edges) = numbered(graph);
+  let mut adjacent = vec![false; n * n];
+  for (u, v) in edges {
+    adjacent[u * n + v] = true;
+    adjacent[v * n + u] = true;
+  }
+  let mut bits = Bits::default();
+  for j in 1..n {
+    for i in 0..j {
+      bits.push(adjacent[i * n + j]);
+    }
+  }
+  size(n) + &bits.finish(false)
+}
+
+/// Write the sparse6 encoding of `graph`. See the [module docs](self).
+pub fn to_sparse6(graph: &GraphBuilder) -> String {
+  let (n, edges) = numbered(graph);
+  /* Each edge is ordered by its larger endpoint, then its smaller. */
+  let mut edges: Vec<(usize, usize)> = edges
+    .into_iter()
+    .map(|(u, v)| (u.max(v), u.min(v)))
+    .collect();
+  edges.sort_unstable();
+  /* The number of bits needed to write n - 1. */
+  let k = usize::BITS - n.saturating_sub(1).leading_zeros();
+
+  let mut bits = Bits::default();
+  let mut current = 0;
+  for (v, u) in edges {
+    if v == current {
+      bits.push(false);
+    } else {
+      bits.push(true);
+      if v > current + 1 {
+        bits.push_int(v, k);
+        bits.push(false);
+      }
+      current = v;
+    }
+    bits.push_int(u, k);
+  }
+  /* Padding of k or more 1 bits would read as another edge to vertex n - 1
+   * in this case, so start it with a 0. */
+  let remaining = u32::from(bits.remaining());
+  if k < 6 && n == 1 << k && remaining > k && current + 2 == n {
+    bits.push(false);
+  }
+  format!(":{}{}", size(n), bits.finish(true))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  fn graph(n: usize, edges: &[(usize, usize)]) -> GraphBuilder {
+    let mut gb = GraphBuilder::new();
+    for i in 0..n {
+      gb.accept_entity(Entity::Vertex(Vertex::new(i.to_string())));
+    }
+    for (u, v) in edges.iter() {
+      gb.accept_entity(Entity::Edge(Edge::between(u.to_string(), v.to_string())));
+    }
+    gb
+  }
+
+  #[test]
+  fn examples_from_spec() {
+    assert_eq!(
+      to_graph6(&graph(5, &[(0, 2), (0, 4), (1, 3), (3, 4)])),
+      "DQc"
+    );
+    assert_eq!(
+      to_sparse6(&graph(7, &[(0, 1), (0, 2), (1, 2), (5, 6)])),
+      ":Fa@x^"
+    );
+  }
+
+  #[test]
+  fn edge_cases() {
+    assert_eq!(to_graph6(&graph(0, &[])), "?");
+    assert_eq!(to_graph6(&graph(1, &[(0, 0)])), "@");
+    assert_eq!(size(63), "~??~");
+    /* The loop is kept, and the padding after the edge between 1 and 2
+     * starts with a 0. */
+    assert_eq!(to_sparse6(&graph(4, &[(0, 0), (1, 2)])), ":CEJ");
+    /* With a single vertex, endpoints are written in k = 0 bits, as by
+     * nauty. */
+    assert_eq!(to_sparse6(&graph(1, &[(0, 0)])), ":@^");
+  }
+}
@@ -0,0 +1,250 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write JSON in the object schema of Graphviz's own `-Tjson0` output, for
+//! tools which already consume it.
+//!
+//! As in Graphviz, subgraphs come first in `objects`, in depth-first order,
+//! followed by the vertices; `_subgraph_cnt` says where one ends and the
+//! other begins. Each subgraph lists every vertex and edge within it
+//! (including within its own subgraphs) by `_gvid`, and each edge refers to
+//! its `tail` and `head` by `_gvid`. Every attribute value is written as a
+//! string, and clusters get a `cluster` attribute of `"true"` and subgraphs
+//! with [`Subgraph::same_rank`](crate::entities::Subgraph::same_rank) a
+//! `rank` of `"same"`, as in the generated DOT. Graph attributes and themes are not included.
+//!
+//! The result can be read back with [`import::json`](crate::import::json).
+//! Requires the `json` feature.
+//!
+//!```
+//! use graphvizier::{entities::*, export::json::to_json, generator::GraphBuilder};
+//! use serde_json::json;
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("a").label("A")));
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+//! assert_eq!(to_json(&gb, &Id::new("g")), json!({
+//!   "name": "g",
+//!   "directed": true,
+//!   "strict": false,
+//!   "_subgraph_cnt": 0,
+//!   "objects": [
+//!     {"_gvid": 0, "name": "a", "label": "A"},
+//!     {"_gvid": 1, "name": "b"},
+//!   ],
+//!   "edges": [{"_gvid": 0, "tail": 0, "head": 1}],
+//! }));
+//! ```
+
+use super::{all_edges, all_vertices, edge_attributes, subgraph_attributes, vertex_attributes};
+use crate::{
+  entities::{Edge, Entity, Id, Subgraph},
+  generator::GraphBuilder,
+};
+
+use serde_json::{json, Map, Value};
+
+use std::collections::HashMap;
+
+/// Every subgraph within `entities`, each before its own subgraphs.
+fn preorder<'a>(entities: impl Iterator<Item=&'a Entity>, out: &mut Vec<&'a Subgraph>) {
+  for e in entities {
+    match e {
+      Entity::Subgraph(sg) => {
+        out.push(sg);
+        preorder(sg.entities.iter(), out);
+      },
+      Entity::Group(g) => preorder(g.entities.iter(), out),
+      Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => (),
+    }
+  }
+}
+
+/// The subgraphs directly within `entities`, looking through groups.
+fn children<'a>(entities: impl Iterator<Item=&'a Entity>, out: &mut Vec<&'a Subgraph>) {
+  for e in entities {
+    match e {
+      Entity::Subgraph(sg) => out.push(sg),
+      Entity::Group(g) => children(g.entities.iter(), out),
+      Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => (),
+    }
+  }
+}
+
+fn object(gvid: usize, name: Option<&Id>, attrs: Vec<(String, String)>) -> Map<String, Value> {
+  let mut ret = Map::new();
+  ret.insert("_gvid".to_string(), json!(gvid));
+  if let Some(name) = name {
+    ret.insert("name".to_string(), json!(name.as_str()));
+  }
+  for (k, v) in attrs {
+    ret.insert(k, Value::String(v));
+  }
+  ret
+}
+
+/// Write the `-Tjson0` object for `graph`, named `graph_name`. See the
+/// [module docs](self).
+pub fn to_json(graph: &GraphBuilder, graph_name: &Id) -> Value {
+  let vertices = all_vertices(graph.entities());
+  let edges = all_edges(graph.entities());
+  let mut subgraphs = Vec::new();
+  preorder(graph.entities(), &mut subgraphs);
+
+  let subgraph_gvids: HashMap<*const Subgraph, usize> = subgraphs
+    .iter()
+    .enumerate()
+    .map(|(i, sg)| (*sg as *const Subgraph, i))
+    .collect();
+  let vertex_gvids: HashMap<&Id, usize> = vertices
+    .iter()
+    .enumerate()
+    .map(|(i, v)| (&v.id, subgraphs.len() + i))
+    .collect();
+  let edge_gvids: HashMap<*const Edge, usize> = edges
+    .iter()
+    .enumerate()
+    .map(|(i, e)| (*e as *const Edge, i))
+    .collect();
+
+  let mut objects = Vec::new();
+  for (i, sg) in subgraphs.iter().enumerate() {
+    let mut attrs = subgraph_attributes(sg);
+    if sg.is_cluster {
+      attrs.push(("cluster".to_string(), "true".to_string()));
+    }
+    if sg.same_rank {
+      attrs.push(("rank".to_string(), "same".to_string()));
+    }
+    let mut obj = object(i, Some(&sg.id), attrs);
+    let mut nested = Vec::new();
+    children(sg.entities.iter(), &mut nested);
+    let members = [
+      (
+        "subgraphs",
+        nested
+          .into_iter()
+          .map(|c| subgraph_gvids[&(c as *const Subgraph)])
+          .collect::<Vec<_>>(),
+      ),
+      (
+        "nodes",
+        all_vertices(sg.entities.iter())
+          .iter()
+          .map(|v| vertex_gvids[&v.id])
+          .collect(),
+      ),
+      (
+        "edges",
+        all_edges(sg.entities.iter())
+          .into_iter()
+          .map(|e| edge_gvids[&(e as *const Edge)])
+          .collect(),
+      ),
+    ];
+    for (key, gvids) in members {
+      if !gvids.is_empty() {
+        obj.insert(key.to_string(), json!(gvids));
+      }
+    }
+    objects.push(Value::Object(obj));
+  }
+  for v in vertices.iter() {
+    let gvid = vertex_gvids[&v.id];
+    objects.push(Value::Object(object(
+      gvid,
+      Some(&v.id),
+      vertex_attributes(v),
+    )));
+  }
+
+  let edges: Vec<Value> = edges
+    .iter()
+    .enumerate()
+    .map(|(i, e)| {
+      let mut obj = object(i, None, edge_attributes(e));
+      obj.insert("tail".to_string(), json!(vertex_gvids[&e.source]));
+      obj.insert("head".to_string(), json!(vertex_gvids[&e.target]));
+      Value::Object(obj)
+    })
+    .collect();
+
+  json!({
+    "name": graph_name.as_str(),
+    "directed": graph.is_directed(),
+    "strict": false,
+    "_subgraph_cnt": subgraphs.len(),
+    "objects": objects,
+    "edges": edges,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{entities::*, import::json::from_json};
+
+  #[test]
+  fn round_trip() {
+    let inner = Subgraph {
+      entities: vec![
+        Entity::Vertex(Vertex::new("b").attr("color", "red")),
+        Entity::Edge(Edge::between("b", "b")),
+      ],
+      ..Subgraph::new("inner").cluster(false)
+    };
+    let outer = Subgraph {
+      entities: vec![
+        Entity::Vertex(Vertex::new("a")),
+        Entity::Subgraph(inner.clone()),
+      ],
+      ..Subgraph::new("cluster_outer").label("Outer")
+    };
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("c")));
+    gb.accept_entity(Entity::Subgraph(outer));
+    gb.accept_entity(Entity::Edge(Edge::between("a", "c").weight(2.0)));
+
+    let value = to_json(&gb, &Id::new("g"));
+    assert_eq!(
+      value["objects"][0],
+      json!({
+        "_gvid": 0,
+        "name": "cluster_outer",
+        "label": "Outer",
+        "cluster": "true",
+        "rank": "same",
+        "subgraphs": [1],
+        "nodes": [3, 4],
+        "edges": [0],
+      })
+    );
+
+    let parsed = from_json(&value.to_string()).unwrap();
+    assert_eq!(parsed.id, Some(Id::new("g")));
+    assert_eq!(parsed.entities, vec![
+      Entity::Vertex(Vertex::new("c")),
+      Entity::Subgraph(Subgraph {
+        entities: vec![Entity::Vertex(Vertex::new("a")), Entity::Subgraph(inner)],
+        ..Subgraph::new("cluster_outer").attr("label", "Outer")
+      }),
+      Entity::Edge(Edge::between("a", "c").attr("weight", "2")),
+    ]);
+  }
+}
@@ -0,0 +1,346 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write a [Mermaid](https://mermaid.js.org/syntax/flowchart.html)
+//! flowchart, for Markdown renderers like GitHub's which draw Mermaid but
+//! not Graphviz.
+//!
+//! Subgraphs become Mermaid subgraphs, and edges become `-->` links (`-.->`
+//! if dashed, `==>` if bold, and `~~~` if invisible), with their labels. If
+//! the graph is [undirected](GraphBuilder::set_directed), they're drawn
+//! without arrowheads, as `---`, `-.-`, and `===`.
+//! Common shapes are mapped to their closest Mermaid equivalent, and vertex
+//! colors become `classDef` classes shared by every vertex drawn alike. Ids
+//! which Mermaid can't accept verbatim are renamed to `_N`, with the
+//! original id kept as the label.
+//!
+//!```
+//! use graphvizier::{entities::*, export::mermaid::to_mermaid, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("start").shape("circle").color(Color::red())));
+//! gb.accept_entity(Entity::Subgraph(Subgraph {
+//!   entities: vec![Entity::Vertex(Vertex::new("my node").label("Mine"))],
+//!   ..Subgraph::new("cluster_a").label("A")
+//! }));
+//! gb.accept_entity(Entity::Edge(Edge::between("start", "my node").label("go")));
+//! assert_eq!(
+//!   to_mermaid(&gb),
+//!   "flowchart TD\n  \
+//!      start((\"start\"))\n  \
+//!      subgraph cluster_a[\"A\"]\n    \
+//!        _1[\"Mine\"]\n  \
+//!      end\n  \
+//!      start -->|\"go\"| _1\n  \
+//!      classDef c0 stroke:red\n  \
+//!      class start c0\n"
+//! );
+//! ```
+
+use super::{
+  all_vertices, edge_attributes, label_lines, lookup, subgraph_attributes, vertex_attributes,
+  vertex_label, Aliases,
+};
+use crate::{
+  entities::{Edge, Entity, Subgraph, Vertex},
+  generator::GraphBuilder,
+};
+
+use std::{collections::HashSet, fmt::Write};
+
+/// Identifiers which can't name a vertex or subgraph.
+const RESERVED: [&str; 10] = [
+  "end",
+  "graph",
+  "subgraph",
+  "flowchart",
+  "direction",
+  "style",
+  "class",
+  "classdef",
+  "click",
+  "linkstyle",
+];
+
+fn is_plain(id: &str) -> bool {
+  id.starts_with(|c: char| c.is_ascii_alphabetic())
+    && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    && !RESERVED.contains(&id.to_ascii_lowercase().as_str())
+}
+
+/// `text` within double quotes, with DOT line breaks as `<br>`.
+fn quote(text: &str) -> String {
+  let text = label_lines(text).join("<br>");
+  format!("\"{}\"", text.replace('"', "#quot;"))
+}
+
+/// The brackets around the label of a vertex with `shape`.
+fn brackets(shape: Option<&str>) -> (&'static str, &'static str) {
+  match shape {
+    Some("circle" | "point") => ("((", "))"),
+    Some("doublecircle") => ("(((", ")))"),
+    Some("ellipse" | "oval") => ("([", "])"),
+    Some("diamond") => ("{", "}"),
+    Some("hexagon") => ("{{", "}}"),
+    Some("cylinder") => ("[(", ")]"),
+    Some("parallelogram") => ("[/", "/]"),
+    Some("trapezium") => ("[/", "\\]"),
+    Some("invtrapezium") => ("[\\", "/]"),
+    _ => ("[", "]"),
+  }
+}
+
+/// The Mermaid style properties for a vertex with `attrs`.
+fn vertex_style(attrs: &[(String, String)]) -> Vec<String> {
+  let mut ret = Vec::new();
+  if let Some(fill) = lookup(attrs, "fillcolor") {
+    ret.push(format!("fill:{}", fill));
+  }
+  if let Some(color) = lookup(attrs, "color") {
+    ret.push(format!("stroke:{}", color));
+  }
+  if let Some(fontcolor) = lookup(attrs, "fontcolor") {
+    ret.push(format!("color:{}", fontcolor));
+  }
+  if lookup(attrs, "style").map_or(false, |s| s.contains("dashed")) {
+    ret.push("stroke-dasharray:5 5".to_string());
+  }
+  ret
+}
+
+struct Writer<'a> {
+  out: String,
+  vertices: Aliases,
+  subgraphs: Aliases,
+  /* Distinct vertex styles, and the vertices drawn with each. */
+  classes: Vec<(String, Vec<String>)>,
+  /* The style of each edge with one, by its index among all edges. */
+  link_styles: Vec<(usize, String)>,
+  edges: Vec<&'a Edge>,
+  directed: bool,
+}
+
+impl<'a> Writer<'a> {
+  fn indent(&mut self, depth: usize) {
+    for _ in 0..depth {
+      self.out.push_str("  ");
+    }
+  }
+
+  fn vertex(&mut self, v: &'a Vertex, depth: usize) {
+    let attrs = vertex_attributes(v);
+    let name = self.vertices.get(&v.id).to_string();
+    let (open, close) = brackets(lookup(&attrs, "shape"));
+    self.indent(depth);
+    writeln!(
+      self.out,
+      "{}{}{}{}",
+      name,
+      open,
+      quote(&vertex_label(v)),
+      close
+    )
+    .unwrap();
+
+    let style = vertex_style(&attrs).join(",");
+    if style.is_empty() {
+      return;
+    }
+    match self.classes.iter_mut().find(|(s, _)| *s == style) {
+      Some((_, members)) => members.push(name),
+      None => self.classes.push((style, vec![name])),
+    }
+  }
+
+  fn subgraph(&mut self, sg: &'a Subgraph, depth: usize) {
+    let attrs = subgraph_attributes(sg);
+    let label = lookup(&attrs, "label").unwrap_or(sg.id.as_str());
+    let name = self.subgraphs.get(&sg.id).to_string();
+    self.indent(depth);
+    writeln!(self.out, "subgraph {}[{}]", name, quote(label)).unwrap();
+    self.entities(sg.entities.iter(), depth + 1);
+    self.indent(depth);
+    self.out.push_str("end\n");
+  }
+
+  fn edge(&mut self, e: &'a Edge, depth: usize) {
+    let attrs = edge_attributes(e);
+    let style = lookup(&attrs, "style").unwrap_or("");
+    let arrow = match (self.directed, style) {
+      (_, s) if s.contains("invis") => "~~~",
+      (true, s) if s.contains("dashed") || s.contains("dotted") => "-.->",
+      (false, s) if s.contains("dashed") || s.contains("dotted") => "-.-",
+      (true, s) if s.contains("bold") => "==>",
+      (false, s) if s.contains("bold") => "===",
+      (true, _) => "-->",
+      (false, _) => "---",
+    };
+    self.indent(depth);
+    write!(self.out, "{} {}", self.vertices.get(&e.source), arrow).unwrap();
+    if let Some(label) = lookup(&attrs, "label") {
+      write!(self.out, "|{}|", quote(label)).unwrap();
+    }
+    writeln!(self.out, " {}", self.vertices.get(&e.target)).unwrap();
+
+    if let Some(color) = lookup(&attrs, "color") {
+      self
+        .link_styles
+        .push((self.edges.len(), format!("stroke:{}", color)));
+    }
+    self.edges.push(e);
+  }
+
+  fn entities(&mut self, entities: impl Iterator<Item=&'a Entity>, depth: usize) {
+    for e in entities {
+      match e {
+        Entity::Vertex(v) => self.vertex(v, depth),
+        Entity::Edge(e) => self.edge(e, depth),
+        Entity::Subgraph(sg) => self.subgraph(sg, depth),
+        Entity::Group(g) => self.entities(g.entities.iter(), depth),
+        Entity::Comment(text) => {
+          for line in text.lines() {
+            self.indent(depth);
+            writeln!(self.out, "%% {}", line).unwrap();
+          }
+        },
+      }
+    }
+  }
+}
+
+/// Write the Mermaid flowchart for `graph`. See the [module docs](self).
+pub fn to_mermaid(graph: &GraphBuilder) -> String {
+  let vertices = all_vertices(graph.entities());
+  let subgraphs: Vec<&Subgraph> = graph.subgraphs().collect();
+  let vertex_aliases = Aliases::new(vertices.iter().map(|v| &v.id), "_", is_plain);
+  /* Vertices and subgraphs share one Mermaid namespace, so a subgraph only
+   * keeps its id if no vertex is already using it. */
+  let taken: HashSet<&str> = vertices.iter().map(|v| vertex_aliases.get(&v.id)).collect();
+  let subgraph_aliases = Aliases::new(subgraphs.iter().map(|sg| &sg.id), "_s", |id| {
+    is_plain(id) && !taken.contains(id)
+  });
+  let mut w = Writer {
+    out: "flowchart TD\n".to_string(),
+    vertices: vertex_aliases,
+    subgraphs: subgraph_aliases,
+    classes: Vec::new(),
+    link_styles: Vec::new(),
+    edges: Vec::new(),
+    directed: graph.is_directed(),
+  };
+  w.entities(graph.entities(), 1);
+
+  /* Declare endpoints which were never declared as vertices themselves, so
+   * they get their original id as a label. */
+  let declared: Vec<&Vertex> = graph.vertices().collect();
+  for v in vertices.iter() {
+    if !declared.iter().any(|d| d.id == v.id) && w.vertices.get(&v.id) != v.id.as_str() {
+      let name = w.vertices.get(&v.id).to_string();
+      writeln!(w.out, "  {}[{}]", name, quote(v.id.as_str())).unwrap();
+    }
+  }
+  for (i, (style, members)) in w.classes.iter().enumerate() {
+    writeln!(w.out, "  classDef c{} {}", i, style).unwrap();
+    writeln!(w.out, "  class {} c{}", members.join(","), i).unwrap();
+  }
+  for (i, style) in w.link_styles.iter() {
+    writeln!(w.out, "  linkStyle {} {}", i, style).unwrap();
+  }
+  w.out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn renamed_ids_and_styles() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Comment("two\nlines".to_string()));
+    gb.accept_entity(Entity::Vertex(
+      Vertex::new("end")
+        .label("say \"bye\"\\nnow")
+        .attr("fillcolor", "#eeeeee"),
+    ));
+    gb.accept_entity(Entity::Vertex(
+      Vertex::new("b")
+        .shape("diamond")
+        .attr("fillcolor", "#eeeeee"),
+    ));
+    gb.accept_entity(Entity::Edge(Edge::between("end", "b").style("dashed")));
+    gb.accept_entity(Entity::Edge(Edge::between("b", "x-1").color(Color::blue())));
+    assert_eq!(
+      to_mermaid(&gb),
+      "flowchart TD\n  \
+         %% two\n  \
+         %% lines\n  \
+         _0[\"say #quot;bye#quot;<br>now\"]\n  \
+         b{\"b\"}\n  \
+         _0 -.-> b\n  \
+         b --> _2\n  \
+         _2[\"x-1\"]\n  \
+         classDef c0 fill:#eeeeee\n  \
+         class _0,b c0\n  \
+         linkStyle 1 stroke:blue\n"
+    );
+  }
+
+  #[test]
+  fn vertex_and_subgraph_share_namespace() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("a")));
+    gb.accept_entity(Entity::Subgraph(Subgraph::new("a")));
+    let out = to_mermaid(&gb);
+    assert!(out.contains("  a[\"a\"]\n"), "{}", out);
+    assert!(out.contains("subgraph _s0"), "{}", out);
+  }
+
+  #[test]
+  fn undirected_links() {
+    let mut gb = GraphBuilder::new();
+    gb.set_directed(false);
+    gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+    gb.accept_entity(Entity::Edge(Edge::between("b", "c").style("dashed")));
+    gb.accept_entity(Entity::Edge(Edge::between("c", "a").style("bold")));
+    assert_eq!(
+      to_mermaid(&gb),
+      "flowchart TD\n  \
+         a --- b\n  \
+         b -.- c\n  \
+         c === a\n"
+    );
+  }
+
+  #[test]
+  fn quotes_and_line_breaks() {
+    let label = Label::escape_text("say \"hi\"");
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(
+      Vertex::new("a").label(format!("{}\\nbye", label).as_str()),
+    ));
+    gb.accept_entity(Entity::Edge(Edge::between("a", "b").label("\"x\" or\\ny")));
+    assert_eq!(
+      to_mermaid(&gb),
+      "flowchart TD\n  \
+         a[\"say #quot;hi#quot;<br>bye\"]\n  \
+         a -->|\"#quot;x#quot; or<br>y\"| b\n"
+    );
+  }
+}
@@ -0,0 +1,518 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Writers converting the entity tree into other graph formats.
+//!
+//! Each exporter reads the entities of a
+//! [`GraphBuilder`](crate::generator::GraphBuilder), including every attribute
+//! which would be written to DOT. Themes are not applied.
+
+use crate::entities::{
+  style::{Color, DirType, FontName, FontSize, HtmlLabel, Label, Polygon, Port, Shape, Style},
+  Edge, Entity, Id, Subgraph, Vertex,
+};
+
+use std::collections::{HashMap, HashSet};
+
+pub mod csv;
+pub mod d2;
+#[cfg(feature = "json")]
+pub mod d3;
+pub mod dimacs;
+pub mod gexf;
+pub mod graph6;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod mermaid;
+pub mod pajek;
+pub mod plantuml;
+pub mod tgf;
+pub mod tikz;
+#[cfg(feature = "json")]
+pub mod vis;
+
+/// The name and unquoted value of every attribute `v` would be written with,
+/// in the same order as in DOT. Labels are unescaped with
+/// [`Label::unescape_text`].
+pub(crate) fn vertex_attributes(v: &Vertex) -> Vec<(String, String)> {
+  let Vertex {
+    id: _,
+    label,
+    html_label,
+    color,
+    fontcolor,
+    shape,
+    fontname,
+    fontsize,
+    style,
+    layer,
+    fixedsize,
+    width,
+    height,
+    peripheries,
+    polygon,
+    shapefile,
+    attributes,
+  } = v;
+  let mut ret: Vec<(&str, String)> = Vec::new();
+  if let Some(HtmlLabel(html_label)) = html_label {
+    ret.push(("label", html_label.clone()));
+  } else if let Some(Label(label)) = label {
+    ret.push(("label", Label::unescape_text(label)));
+  }
+  if let Some(Color(color)) = color {
+    ret.push(("color", color.clone()));
+  }
+  if let Some(Color(fontcolor)) = fontcolor {
+    ret.push(("fontcolor", fontcolor.clone()));
+  }
+  if let Some(Shape(shape)) = shape {
+    ret.push(("shape", shape.clone()));
+  } else if let Some(shapefile) = shapefile {
+    ret.push(("shape", shapefile.shape().to_string()));
+  } else if polygon.is_some() {
+    ret.push(("shape", "polygon".to_string()));
+  }
+  if let Some(shapefile) = shapefile {
+    ret.push(("shapefile", shapefile.path().to_string_lossy().into_owned()));
+  }
+  if let Some(Polygon {
+    sides,
+    skew,
+    distortion,
+    orientation,
+    regular,
+  }) = polygon
+  {
+    ret.extend(sides.map(|x| ("sides", x.to_string())));
+    ret.extend(skew.map(|x| ("skew", x.to_string())));
+    ret.extend(distortion.map(|x| ("distortion", x.to_string())));
+    ret.extend(orientation.map(|x| ("orientation", x.to_string())));
+    ret.extend(regular.map(|x| ("regular", x.to_string())));
+  }
+  if let Some(FontName(fontname)) = fontname {
+    ret.push(("fontname", fontname.clone()));
+  }
+  if let Some(FontSize(fontsize)) = fontsize {
+    ret.push(("fontsize", fontsize.to_string()));
+  }
+  if let Some(Style(style)) = style {
+    ret.push(("style", style.clone()));
+  }
+  ret.extend(layer.clone().map(|x| ("layer", x)));
+  ret.extend(fixedsize.as_ref().map(|x| ("fixedsize", x.format())));
+  ret.extend(width.map(|x| ("width", x.to_string())));
+  ret.extend(height.map(|x| ("height", x.to_string())));
+  ret.extend(peripheries.map(|x| ("peripheries", x.to_string())));
+  ret
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .chain(extra(attributes))
+    .collect()
+}
+
+/// The name and unquoted value of every attribute `e` would be written with,
+/// in the same order as in DOT. Labels are unescaped with
+/// [`Label::unescape_text`].
+pub(crate) fn edge_attributes(e: &Edge) -> Vec<(String, String)> {
+  let Edge {
+    source: _,
+    target: _,
+    label,
+    color,
+    fontcolor,
+    fontname,
+    fontsize,
+    style,
+    arrowhead,
+    arrowtail,
+    dir,
+    headport,
+    tailport,
+    headlabel,
+    taillabel,
+    weight,
+    minlen,
+    constraint,
+    samehead,
+    sametail,
+    layer,
+    attributes,
+  } = e;
+  let mut ret: Vec<(&str, String)> = Vec::new();
+  if let Some(Label(label)) = label {
+    ret.push(("label", Label::unescape_text(label)));
+  }
+  if let Some(Color(color)) = color {
+    ret.push(("color", color.clone()));
+  }
+  if let Some(Color(fontcolor)) = fontcolor {
+    ret.push(("fontcolor", fontcolor.clone()));
+  }
+  if let Some(FontName(fontname)) = fontname {
+    ret.push(("fontname", fontname.clone()));
+  }
+  if let Some(FontSize(fontsize)) = fontsize {
+    ret.push(("fontsize", fontsize.to_string()));
+  }
+  if let Some(Style(style)) = style {
+    ret.push(("style", style.clone()));
+  }
+  ret.extend(arrowhead.as_ref().map(|x| ("arrowhead", x.0.clone())));
+  ret.extend(arrowtail.as_ref().map(|x| ("arrowtail", x.0.clone())));
+  if let Some(DirType(dir)) = dir {
+    ret.push(("dir", dir.clone()));
+  }
+  if let Some(Port(headport)) = headport {
+    ret.push(("headport", headport.clone()));
+  }
+  if let Some(Port(tailport)) = tailport {
+    ret.push(("tailport", tailport.clone()));
+  }
+  ret.extend(
+    headlabel
+      .as_ref()
+      .map(|x| ("headlabel", Label::unescape_text(&x.0))),
+  );
+  ret.extend(
+    taillabel
+      .as_ref()
+      .map(|x| ("taillabel", Label::unescape_text(&x.0))),
+  );
+  ret.extend(weight.map(|x| ("weight", x.to_string())));
+  ret.extend(minlen.map(|x| ("minlen", x.to_string())));
+  ret.extend(constraint.map(|x| ("constraint", x.to_string())));
+  ret.extend(samehead.clone().map(|x| ("samehead", x)));
+  ret.extend(sametail.clone().map(|x| ("sametail", x)));
+  ret.extend(layer.clone().map(|x| ("layer", x)));
+  ret
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .chain(extra(attributes))
+    .collect()
+}
+
+/// The name and unquoted value of every attribute of `sg` itself, except
+/// `cluster` and its node defaults. Labels are unescaped with
+/// [`Label::unescape_text`].
+pub(crate) fn subgraph_attributes(sg: &Subgraph) -> Vec<(String, String)> {
+  let mut ret: Vec<(&str, String)> = Vec::new();
+  ret.extend(
+    sg.label
+      .as_ref()
+      .map(|x| ("label", Label::unescape_text(&x.0))),
+  );
+  ret.extend(sg.color.as_ref().map(|x| ("color", x.0.clone())));
+  ret.extend(sg.fontcolor.as_ref().map(|x| ("fontcolor", x.0.clone())));
+  ret.extend(sg.fontname.as_ref().map(|x| ("fontname", x.0.clone())));
+  ret.extend(sg.fontsize.map(|x| ("fontsize", x.0.to_string())));
+  ret
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .chain(extra(&sg.attributes))
+    .collect()
+}
+
+/// Free-form attributes, with quoted values unescaped like labels.
+fn extra(attributes: &crate::entities::Attributes) -> impl Iterator<Item=(String, String)>+'_ {
+  attributes.iter().map(|(k, v)| {
+    let v = match v {
+      crate::entities::AttrValue::Quoted(s) => Label::unescape_text(s),
+      crate::entities::AttrValue::Html(s) => s.clone(),
+    };
+    (k.clone(), v)
+  })
+}
+
+/// The value of the attribute `name`, if present.
+pub(crate) fn lookup<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+  attributes
+    .iter()
+    .find(|(k, _)| k == name)
+    .map(|(_, v)| v.as_str())
+}
+
+/// The text displayed for `v`: its label, unescaped with
+/// [`Label::unescape_text`], or else its id.
+pub(crate) fn vertex_label(v: &Vertex) -> String {
+  match (&v.html_label, &v.label) {
+    (Some(HtmlLabel(label)), _) => label.clone(),
+    (None, Some(Label(label))) => Label::unescape_text(label),
+    (None, None) => v.id.as_str().to_string(),
+  }
+}
+
+/// Names for ids in formats which restrict the characters of identifiers.
+pub(crate) struct Aliases(HashMap<Id, String>);
+
+impl Aliases {
+  /// Each of `ids` keeps its own text if `keep` accepts it, and is otherwise
+  /// named `prefix` followed by its index. `keep` must reject every name
+  /// starting with `prefix`.
+  pub(crate) fn new<'a>(
+    ids: impl Iterator<Item=&'a Id>,
+    prefix: &str,
+    keep: impl Fn(&str) -> bool,
+  ) -> Self {
+    let mut ret = HashMap::new();
+    for (i, id) in ids.enumerate() {
+      let name = if keep(id.as_str()) {
+        id.as_str().to_string()
+      } else {
+        format!("{}{}", prefix, i)
+      };
+      ret.entry(id.clone()).or_insert(name);
+    }
+    Self(ret)
+  }
+
+  /// The name for `id`, which must have been passed to [`Self::new`].
+  pub(crate) fn get(&self, id: &Id) -> &str { &self.0[id] }
+}
+
+/// The lines of a DOT label, split at its `\\n`, `\\l`, and `\\r` escapes.
+pub(crate) fn label_lines(label: &str) -> Vec<&str> {
+  let mut ret = Vec::new();
+  let mut start = 0;
+  let bytes = label.as_bytes();
+  let mut i = 0;
+  while i + 1 < bytes.len() {
+    if bytes[i] == b'\\' && b"nlr".contains(&bytes[i + 1]) {
+      ret.push(&label[start..i]);
+      start = i + 2;
+      i += 2;
+    } else {
+      i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+  }
+  if start < label.len() || ret.is_empty() {
+    ret.push(&label[start..]);
+  }
+  ret
+}
+
+/// Every vertex in `entities` and their subgraphs (the first, for any
+/// duplicated id), followed by a plain vertex for every edge endpoint which
+/// was never declared.
+pub(crate) fn all_vertices<'a>(entities: impl Iterator<Item=&'a Entity>) -> Vec<Vertex> {
+  fn walk<'a>(
+    entities: impl Iterator<Item=&'a Entity>,
+    seen: &mut HashSet<Id>,
+    vertices: &mut Vec<Vertex>,
+    endpoints: &mut Vec<Id>,
+  ) {
+    for e in entities {
+      match e {
+        Entity::Vertex(v) => {
+          if seen.insert(v.id.clone()) {
+            vertices.push(v.clone());
+          }
+        },
+        Entity::Edge(e) => endpoints.extend([e.source.clone(), e.target.clone()]),
+        Entity::Subgraph(sg) => walk(sg.entities.iter(), seen, vertices, endpoints),
+        Entity::Group(g) => walk(g.entities.iter(), seen, vertices, endpoints),
+        Entity::Comment(_) => (),
+      }
+    }
+  }
+  let mut seen = HashSet::new();
+  let mut vertices = Vec::new();
+  let mut endpoints = Vec::new();
+  walk(entities, &mut seen, &mut vertices, &mut endpoints);
+  for id in endpoints {
+    if seen.insert(id.clone()) {
+      vertices.push(Vertex::new(id));
+    }
+  }
+  vertices
+}
+
+/// The innermost subgraph each vertex in `entities` was first declared in.
+#[cfg(feature = "json")]
+pub(crate) fn subgraph_of<'a>(
+  entities: impl Iterator<Item=&'a Entity>,
+  group: Option<&'a Id>,
+  out: &mut HashMap<&'a Id, Option<&'a Id>>,
+) {
+  for e in entities {
+    match e {
+      Entity::Vertex(v) => {
+        out.entry(&v.id).or_insert(group);
+      },
+      Entity::Subgraph(sg) => subgraph_of(sg.entities.iter(), Some(&sg.id), out),
+      Entity::Group(g) => subgraph_of(g.entities.iter(), group, out),
+      Entity::Edge(_) | Entity::Comment(_) => (),
+    }
+  }
+}
+
+/// Every edge in `entities` and their subgraphs.
+pub(crate) fn all_edges<'a>(entities: impl Iterator<Item=&'a Entity>) -> Vec<&'a Edge> {
+  let mut ret = Vec::new();
+  for e in entities {
+    match e {
+      Entity::Edge(e) => ret.push(e),
+      Entity::Subgraph(sg) => ret.extend(all_edges(sg.entities.iter())),
+      Entity::Group(g) => ret.extend(all_edges(g.entities.iter())),
+      Entity::Vertex(_) | Entity::Comment(_) => (),
+    }
+  }
+  ret
+}
+
+/// Red, green, blue, and alpha components of a `#rrggbb` or `#rrggbbaa`
+/// color, or one of the basic HTML color names.
+pub(crate) fn rgba(color: &str) -> Option<[u8; 4]> {
+  const NAMED: [(&str, &str); 18] = [
+    ("black", "#000000"),
+    ("silver", "#c0c0c0"),
+    ("gray", "#808080"),
+    ("grey", "#808080"),
+    ("white", "#ffffff"),
+    ("maroon", "#800000"),
+    ("red", "#ff0000"),
+    ("purple", "#800080"),
+    ("fuchsia", "#ff00ff"),
+    ("green", "#008000"),
+    ("lime", "#00ff00"),
+    ("olive", "#808000"),
+    ("yellow", "#ffff00"),
+    ("navy", "#000080"),
+    ("blue", "#0000ff"),
+    ("teal", "#008080"),
+    ("aqua", "#00ffff"),
+    ("orange", "#ffa500"),
+  ];
+  let lower = color.trim().to_ascii_lowercase();
+  let hex = match NAMED.iter().find(|(name, _)| *name == lower) {
+    Some((_, hex)) => hex,
+    None => lower.as_str(),
+  };
+  let digits = hex.strip_prefix('#')?;
+  if !(digits.len() == 6 || digits.len() == 8) || !digits.is_ascii() {
+    return None;
+  }
+  let byte = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+  let alpha = if digits.len() == 8 { byte(6)? } else { 255 };
+  Some([byte(0)?, byte(2)?, byte(4)?, alpha])
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn parse_colors() {
+    assert_eq!(rgba("#FF8000"), Some([255, 128, 0, 255]));
+    assert_eq!(rgba("#00000080"), Some([0, 0, 0, 128]));
+    assert_eq!(rgba("Orange"), Some([255, 165, 0, 255]));
+    assert_eq!(rgba("burlywood"), None);
+    assert_eq!(rgba("#12345"), None);
+  }
+
+  #[test]
+  fn split_label_lines() {
+    assert_eq!(label_lines("a\\nb\\l"), vec!["a", "b"]);
+    assert_eq!(label_lines("a\\\\nb"), vec!["a\\\\nb"]);
+    assert_eq!(label_lines(""), vec![""]);
+  }
+
+  #[test]
+  fn collect_vertices() {
+    let entities = [
+      Entity::Edge(Edge::between("a", "c")),
+      Entity::Subgraph(Subgraph {
+        entities: vec![Entity::Vertex(Vertex::new("a").label("A"))],
+        ..Subgraph::new("s")
+      }),
+      Entity::Vertex(Vertex::new("b")),
+    ];
+    let vertices = all_vertices(entities.iter());
+    assert_eq!(vertices, vec![
+      Vertex::new("a").label("A"),
+      Vertex::new("b"),
+      Vertex::new("c"),
+    ]);
+    assert_eq!(vertex_attributes(&vertices[0]), vec![(
+      "label".to_string(),
+      "A".to_string()
+    )]);
+  }
+
+  #[test]
+  fn escaped_labels() {
+    use crate::{generator::GraphBuilder, import::csv::CsvFormat};
+
+    let label = Label::escape_text("say \"hi\" C:\\");
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("a").label(label.as_str())));
+    gb.accept_entity(Entity::Edge(Edge::between("a", "b").label(label.as_str())));
+
+    /* Each exporter's output, and how the labels should appear within it. */
+    #[allow(unused_mut)]
+    let mut cases: Vec<(&str, String, &[&str])> = vec![
+      ("mermaid", mermaid::to_mermaid(&gb), &[
+        "a[\"say #quot;hi#quot; C:\\\"]",
+        "a -->|\"say #quot;hi#quot; C:\\\"| b",
+      ]),
+      ("tgf", tgf::to_tgf(&gb), &[
+        "1 say \"hi\" C:\\\n",
+        "1 2 say \"hi\" C:\\\n",
+      ]),
+      ("d2", d2::to_d2(&gb), &[
+        "a: \"say \\\"hi\\\" C:\\\\\"\n",
+        "a -> b: \"say \\\"hi\\\" C:\\\\\"\n",
+      ]),
+      ("gexf", gexf::to_gexf(&gb), &[
+        "<node id=\"a\" label=\"say &quot;hi&quot; C:\\\"/>",
+        "target=\"b\" label=\"say &quot;hi&quot; C:\\\"/>",
+      ]),
+      ("plantuml", plantuml::to_plantuml(&gb, plantuml::Diagram::State), &[
+        "state \"say 'hi' C:\\\" as a\n",
+        "a --> b : say \"hi\" C:\\\n",
+      ]),
+      ("tikz", tikz::to_tikz(&gb, &tikz::Layout::Positions), &[
+        "(a) at (0, 0) {say \"hi\" C:\\textbackslash{}};",
+        "node[auto] {say \"hi\" C:\\textbackslash{}} (b);",
+      ]),
+      ("csv", csv::to_csv(&gb, &CsvFormat::default()), &[
+        "a,b,\"say \"\"hi\"\" C:\\\",\n",
+      ]),
+      ("pajek", pajek::to_pajek(&gb), &[
+        "1 \"say 'hi' C:\\\"\n",
+        "1 2 1 l \"say 'hi' C:\\\"\n",
+      ]),
+    ];
+    #[cfg(feature = "json")]
+    {
+      let json_label: &[&str] = &["\"label\":\"say \\\"hi\\\" C:\\\\\""];
+      cases.extend([
+        ("json", json::to_json(&gb, &Id::new("g")).to_string(), json_label),
+        ("d3", d3::to_d3_force(&gb).to_string(), json_label),
+        ("vis", vis::to_vis_network(&gb).to_string(), json_label),
+      ]);
+    }
+
+    for (name, out, expected) in cases {
+      for &e in expected {
+        assert!(out.contains(e), "{} output lacks {:?}:\n{}", name, e, out);
+      }
+    }
+  }
+}
@@ -0,0 +1,122 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write [Pajek](http://mrvar.fdv.uni-lj.si/pajek/) `.net` networks, as read
+//! by Pajek, NetworkX, igraph, and other social network analysis tools.
+//!
+//! Vertices are numbered from 1 in the order they were declared, followed by
+//! edge endpoints which were never declared, and written with their label
+//! (or id). Every edge is written under `*Arcs`, or `*Edges` if the graph
+//! is [undirected](GraphBuilder::set_directed), with its `weight` and label
+//! if set. Other attributes are dropped.
+//!
+//!```
+//! use graphvizier::{entities::*, export::pajek::to_pajek, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("a").label("Alice")));
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b").weight(2.5)));
+//! assert_eq!(to_pajek(&gb), "*Vertices 2\n1 \"Alice\"\n2 \"b\"\n*Arcs\n1 2 2.5\n");
+//! ```
+
+use super::{all_edges, all_vertices, edge_attributes, label_lines, lookup, vertex_label};
+use crate::generator::GraphBuilder;
+
+use std::{collections::HashMap, fmt::Write};
+
+/// Pajek has no way to escape a double quote within a label.
+fn quote(label: &str) -> String {
+  format!("\"{}\"", label_lines(label).join(" ").replace('"', "'"))
+}
+
+/// Write the Pajek network for `graph`. See the [module docs](self).
+pub fn to_pajek(graph: &GraphBuilder) -> String {
+  let vertices = all_vertices(graph.entities());
+  let numbers: HashMap<_, _> = vertices
+    .iter()
+    .enumerate()
+    .map(|(i, v)| (&v.id, i + 1))
+    .collect();
+
+  let mut out = String::new();
+  writeln!(out, "*Vertices {}", vertices.len()).unwrap();
+  for v in vertices.iter() {
+    writeln!(out, "{} {}", numbers[&v.id], quote(&vertex_label(v))).unwrap();
+  }
+  out.push_str(if graph.is_directed() {
+    "*Arcs\n"
+  } else {
+    "*Edges\n"
+  });
+  for e in all_edges(graph.entities()) {
+    let attrs = edge_attributes(e);
+    write!(out, "{} {}", numbers[&e.source], numbers[&e.target]).unwrap();
+    let weight = lookup(&attrs, "weight");
+    let label = lookup(&attrs, "label");
+    /* A label can only follow a weight. */
+    if weight.is_some() || label.is_some() {
+      write!(out, " {}", weight.unwrap_or("1")).unwrap();
+    }
+    if let Some(label) = label {
+      write!(out, " l {}", quote(label)).unwrap();
+    }
+    out.push('\n');
+  }
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn labels_and_weights() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("x").label("say \"hi\"")));
+    gb.accept_entity(Entity::Edge(Edge::between("x", "y").label("to y")));
+    gb.accept_entity(Entity::Edge(Edge::between("y", "x")));
+    assert_eq!(
+      to_pajek(&gb),
+      "*Vertices 2\n\
+       1 \"say 'hi'\"\n\
+       2 \"y\"\n\
+       *Arcs\n\
+       1 2 1 l \"to y\"\n\
+       2 1\n"
+    );
+  }
+
+  #[test]
+  fn line_breaks_and_quoted_ids() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("x").label("two\\nlines")));
+    gb.accept_entity(Entity::Edge(
+      Edge::between("x", "say \"y\"").label("\"to\" y"),
+    ));
+    assert_eq!(
+      to_pajek(&gb),
+      "*Vertices 2\n\
+       1 \"two lines\"\n\
+       2 \"say 'y'\"\n\
+       *Arcs\n\
+       1 2 1 l \"'to' y\"\n"
+    );
+  }
+}
@@ -0,0 +1,269 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write a [PlantUML](https://plantuml.com/) component or state diagram.
+//!
+//! Vertices become components (or states), subgraphs become packages (or
+//! composite states), and edges become arrows with their labels: dotted if
+//! the edge is dashed or dotted, colored after its `color`, and without
+//! arrowheads if the graph is [undirected](GraphBuilder::set_directed). Vertices are
+//! colored after their `fillcolor` and `color`. Ids which PlantUML can't
+//! accept as aliases are renamed to `_N`, with the original id kept as the
+//! label.
+//!
+//!```
+//! use graphvizier::{entities::*, export::plantuml::{to_plantuml, Diagram}, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Subgraph(Subgraph {
+//!   entities: vec![Entity::Vertex(Vertex::new("db").label("Database"))],
+//!   ..Subgraph::new("backend")
+//! }));
+//! gb.accept_entity(Entity::Edge(Edge::between("web app", "db").label("queries")));
+//! assert_eq!(
+//!   to_plantuml(&gb, Diagram::Component),
+//!   "@startuml\n\
+//!    component \"web app\" as _1\n\
+//!    package \"backend\" as backend {\n  \
+//!      component \"Database\" as db\n\
+//!    }\n\
+//!    _1 --> db : queries\n\
+//!    @enduml\n"
+//! );
+//! ```
+
+use super::{
+  all_vertices, edge_attributes, label_lines, lookup, subgraph_attributes, vertex_attributes,
+  vertex_label, Aliases,
+};
+use crate::{
+  entities::{Entity, Vertex},
+  generator::GraphBuilder,
+};
+
+use std::fmt::Write;
+
+/// Which kind of PlantUML diagram to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagram {
+  /// Vertices are `component`s, and subgraphs are `package`s.
+  Component,
+  /// Vertices are `state`s, and subgraphs are composite states.
+  State,
+}
+
+impl Diagram {
+  fn vertex_keyword(self) -> &'static str {
+    match self {
+      Self::Component => "component",
+      Self::State => "state",
+    }
+  }
+
+  fn subgraph_keyword(self) -> &'static str {
+    match self {
+      Self::Component => "package",
+      Self::State => "state",
+    }
+  }
+}
+
+fn is_plain(id: &str) -> bool {
+  id.starts_with(|c: char| c.is_ascii_alphabetic())
+    && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `text` within double quotes, with DOT line breaks as `\n`.
+fn quote(text: &str) -> String {
+  format!("\"{}\"", label_lines(text).join("\\n").replace('"', "'"))
+}
+
+/// PlantUML accepts both color names and `#rrggbb` after a `#`.
+fn color(c: &str) -> &str { c.strip_prefix('#').unwrap_or(c) }
+
+struct Writer {
+  out: String,
+  diagram: Diagram,
+  vertices: Aliases,
+  subgraphs: Aliases,
+  directed: bool,
+}
+
+impl Writer {
+  fn indent(&mut self, depth: usize) {
+    for _ in 0..depth {
+      self.out.push_str("  ");
+    }
+  }
+
+  fn vertex(&mut self, v: &Vertex, depth: usize) {
+    let attrs = vertex_attributes(v);
+    self.indent(depth);
+    write!(
+      self.out,
+      "{} {} as {}",
+      self.diagram.vertex_keyword(),
+      quote(&vertex_label(v)),
+      self.vertices.get(&v.id)
+    )
+    .unwrap();
+    let fill = lookup(&attrs, "fillcolor").map(|c| color(c).to_string());
+    let line = lookup(&attrs, "color").map(|c| format!("line:{}", color(c)));
+    let colors: Vec<String> = fill.into_iter().chain(line).collect();
+    if !colors.is_empty() {
+      write!(self.out, " #{}", colors.join(";")).unwrap();
+    }
+    self.out.push('\n');
+  }
+
+  fn entities<'a>(&mut self, entities: impl Iterator<Item=&'a Entity>, depth: usize) {
+    for e in entities {
+      match e {
+        Entity::Vertex(v) => self.vertex(v, depth),
+        Entity::Edge(e) => {
+          let attrs = edge_attributes(e);
+          let style = lookup(&attrs, "style").unwrap_or("");
+          let mut modifiers = Vec::new();
+          if let Some(c) = lookup(&attrs, "color") {
+            modifiers.push(format!("#{}", color(c)));
+          }
+          if style.contains("dashed") || style.contains("dotted") {
+            modifiers.push("dashed".to_string());
+          }
+          if style.contains("invis") {
+            modifiers.push("hidden".to_string());
+          }
+          self.indent(depth);
+          write!(self.out, "{} ", self.vertices.get(&e.source)).unwrap();
+          let head = if self.directed { ">" } else { "" };
+          if modifiers.is_empty() {
+            write!(self.out, "--{}", head).unwrap();
+          } else {
+            write!(self.out, "-[{}]-{}", modifiers.join(","), head).unwrap();
+          }
+          write!(self.out, " {}", self.vertices.get(&e.target)).unwrap();
+          if let Some(label) = lookup(&attrs, "label") {
+            write!(self.out, " : {}", label_lines(label).join("\\n")).unwrap();
+          }
+          self.out.push('\n');
+        },
+        Entity::Subgraph(sg) => {
+          let attrs = subgraph_attributes(sg);
+          let label = lookup(&attrs, "label").unwrap_or(sg.id.as_str());
+          self.indent(depth);
+          writeln!(
+            self.out,
+            "{} {} as {} {{",
+            self.diagram.subgraph_keyword(),
+            quote(label),
+            self.subgraphs.get(&sg.id)
+          )
+          .unwrap();
+          self.entities(sg.entities.iter(), depth + 1);
+          self.indent(depth);
+          self.out.push_str("}\n");
+        },
+        Entity::Group(g) => self.entities(g.entities.iter(), depth),
+        Entity::Comment(text) => {
+          for line in text.lines() {
+            self.indent(depth);
+            writeln!(self.out, "' {}", line).unwrap();
+          }
+        },
+      }
+    }
+  }
+}
+
+/// Write `graph` as a PlantUML `diagram`. See the [module docs](self).
+pub fn to_plantuml(graph: &GraphBuilder, diagram: Diagram) -> String {
+  let vertices = all_vertices(graph.entities());
+  let subgraphs: Vec<_> = graph.subgraphs().map(|sg| sg.id.clone()).collect();
+  let mut w = Writer {
+    out: "@startuml\n".to_string(),
+    diagram,
+    vertices: Aliases::new(vertices.iter().map(|v| &v.id), "_", is_plain),
+    subgraphs: Aliases::new(subgraphs.iter(), "_s", is_plain),
+    directed: graph.is_directed(),
+  };
+  /* Aliases must be declared before they're used, so declare edge endpoints
+   * which were never declared as vertices up front. */
+  let declared: Vec<&Vertex> = graph.vertices().collect();
+  for v in vertices.iter() {
+    if !declared.iter().any(|d| d.id == v.id) {
+      w.vertex(v, 0);
+    }
+  }
+  w.entities(graph.entities(), 0);
+  w.out.push_str("@enduml\n");
+  w.out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn state_diagram() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Comment("states".to_string()));
+    gb.accept_entity(Entity::Vertex(
+      Vertex::new("idle")
+        .color(Color::new("#ff0000"))
+        .attr("fillcolor", "yellow"),
+    ));
+    gb.accept_entity(Entity::Subgraph(Subgraph {
+      entities: vec![
+        Entity::Vertex(Vertex::new("running").label("Running\\nfast")),
+        Entity::Edge(
+          Edge::between("running", "idle")
+            .style("dashed")
+            .color(Color::blue()),
+        ),
+      ],
+      ..Subgraph::new("busy state").label("Busy")
+    }));
+    gb.accept_entity(Entity::Edge(Edge::between("idle", "running")));
+    assert_eq!(
+      to_plantuml(&gb, Diagram::State),
+      "@startuml\n\
+       ' states\n\
+       state \"idle\" as idle #yellow;line:ff0000\n\
+       state \"Busy\" as _s0 {\n  \
+         state \"Running\\nfast\" as running\n  \
+         running -[#blue,dashed]-> idle\n\
+       }\n\
+       idle --> running\n\
+       @enduml\n"
+    );
+  }
+
+  #[test]
+  fn quotes_and_line_breaks() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("a b").label("say \"hi\"\\nbye")));
+    assert_eq!(
+      to_plantuml(&gb, Diagram::State),
+      "@startuml\n\
+       state \"say 'hi'\\nbye\" as _0\n\
+       @enduml\n"
+    );
+  }
+}
@@ -0,0 +1,101 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write the [Trivial Graph
+//! Format](https://en.wikipedia.org/wiki/Trivial_Graph_Format), as read by
+//! yEd and others.
+//!
+//! Vertices are numbered from 1 in the order they were declared, followed by
+//! edge endpoints which were never declared. Each is written with its label
+//! (or id), then a `#` line, then each edge with its label. Line breaks
+//! within labels become spaces, and every other attribute is dropped.
+//!
+//!```
+//! use graphvizier::{entities::*, export::tgf::to_tgf, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("a").label("Start")));
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b").label("next")));
+//! assert_eq!(to_tgf(&gb), "1 Start\n2 b\n#\n1 2 next\n");
+//! ```
+
+use super::{all_edges, all_vertices, edge_attributes, label_lines, lookup, vertex_label};
+use crate::generator::GraphBuilder;
+
+use std::{collections::HashMap, fmt::Write};
+
+fn one_line(label: &str) -> String { label_lines(label).join(" ").replace('\n', " ") }
+
+/// Write the TGF document for `graph`. See the [module docs](self).
+pub fn to_tgf(graph: &GraphBuilder) -> String {
+  let vertices = all_vertices(graph.entities());
+  let numbers: HashMap<_, _> = vertices
+    .iter()
+    .enumerate()
+    .map(|(i, v)| (&v.id, i + 1))
+    .collect();
+
+  let mut out = String::new();
+  for v in vertices.iter() {
+    writeln!(out, "{} {}", numbers[&v.id], one_line(&vertex_label(v))).unwrap();
+  }
+  out.push_str("#\n");
+  for e in all_edges(graph.entities()) {
+    write!(out, "{} {}", numbers[&e.source], numbers[&e.target]).unwrap();
+    if let Some(label) = lookup(&edge_attributes(e), "label") {
+      write!(out, " {}", one_line(label)).unwrap();
+    }
+    out.push('\n');
+  }
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn nested_and_multiline() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Subgraph(Subgraph {
+      entities: vec![
+        Entity::Vertex(Vertex::new("x").label("two\\nlines")),
+        Entity::Edge(Edge::between("x", "x")),
+      ],
+      ..Subgraph::new("s")
+    }));
+    gb.accept_entity(Entity::Vertex(Vertex::new("y")));
+    gb.accept_entity(Entity::Edge(Edge::between("y", "x").label("back")));
+    assert_eq!(to_tgf(&gb), "1 two lines\n2 y\n#\n1 1\n2 1 back\n");
+  }
+
+  #[test]
+  fn whitespace_and_newlines() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("x").label("first\nsecond\\lthird")));
+    gb.accept_entity(Entity::Edge(
+      Edge::between("x", "two words").label("one\r\ntwo"),
+    ));
+    assert_eq!(
+      to_tgf(&gb),
+      "1 first second third\n2 two words\n#\n1 2 one\r two\n"
+    );
+  }
+}
@@ -0,0 +1,275 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write a [TikZ](https://tikz.dev/) picture, so documents can include
+//! graphs natively in LaTeX.
+//!
+//! Vertices can either be placed by TikZ's [graph
+//! drawing](https://tikz.dev/gd-usage-tikz) library, which requires LuaLaTeX
+//! and `\usetikzlibrary{graphs,graphdrawing}` along with the library for
+//! the chosen algorithm, or at the positions a layout engine computed for
+//! them in their `pos` attributes (e.g. from `dot -Tdot`, read back with the
+//! [`parser`](crate::parser)). Either way, shapes besides rectangles
+//! require `\usetikzlibrary{shapes.geometric}`.
+//!
+//! Labels are escaped for LaTeX, with line breaks kept. Colors are written
+//! as `xcolor` names, or as RGB values if given as `#rrggbb`. Edges only
+//! have arrowheads if the graph is [directed](GraphBuilder::set_directed).
+//! Subgraphs are flattened.
+//!
+//!```
+//! use graphvizier::{entities::*, export::tikz::{to_tikz, Layout}, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("a").label("50% off").shape("box")));
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b").label("go")));
+//! assert_eq!(
+//!   to_tikz(&gb, &Layout::GraphDrawing("layered".to_string())),
+//!   "\\begin{tikzpicture}\n\
+//!    \\graph [layered layout] {\n  \
+//!      a [as={50\\% off}, draw, rectangle];\n  \
+//!      b [as={b}, draw, ellipse];\n  \
+//!      a -> [edge label={go}] b;\n\
+//!    };\n\
+//!    \\end{tikzpicture}\n"
+//! );
+//! ```
+
+use super::{
+  all_edges, all_vertices, edge_attributes, label_lines, lookup, rgba, vertex_attributes,
+  vertex_label, Aliases,
+};
+use crate::generator::GraphBuilder;
+
+use std::fmt::Write;
+
+/// How to place the vertices of a TikZ picture.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layout {
+  /// Lay the graph out with the named graph drawing algorithm, e.g.
+  /// `layered`, `spring`, or `tree`.
+  GraphDrawing(String),
+  /// Place each vertex at its `pos` attribute, in points, or at the origin
+  /// if it has none.
+  Positions,
+}
+
+fn is_plain(id: &str) -> bool {
+  id.starts_with(|c: char| c.is_ascii_alphabetic()) && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// `text` with LaTeX special characters escaped, and DOT line breaks as
+/// `\\`.
+fn escape(text: &str) -> String {
+  let lines: Vec<String> = label_lines(text)
+    .into_iter()
+    .map(|line| {
+      let mut ret = String::with_capacity(line.len());
+      for c in line.chars() {
+        match c {
+          '\\' => ret.push_str("\\textbackslash{}"),
+          '~' => ret.push_str("\\textasciitilde{}"),
+          '^' => ret.push_str("\\textasciicircum{}"),
+          '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+            ret.push('\\');
+            ret.push(c);
+          },
+          c => ret.push(c),
+        }
+      }
+      ret
+    })
+    .collect();
+  lines.join("\\\\")
+}
+
+fn color(c: &str) -> String {
+  match rgba(c) {
+    Some([r, g, b, _]) if c.starts_with('#') => {
+      format!("{{rgb,255:red,{};green,{};blue,{}}}", r, g, b)
+    },
+    _ => c.to_ascii_lowercase(),
+  }
+}
+
+/// TikZ options for line style and color set in `attrs`.
+fn line_options(attrs: &[(String, String)]) -> Vec<String> {
+  let mut ret = Vec::new();
+  if let Some(c) = lookup(attrs, "color") {
+    ret.push(format!("draw={}", color(c)));
+  }
+  let style = lookup(attrs, "style").unwrap_or("");
+  for (dot, tikz) in [
+    ("dashed", "dashed"),
+    ("dotted", "dotted"),
+    ("bold", "thick"),
+  ] {
+    if style.contains(dot) {
+      ret.push(tikz.to_string());
+    }
+  }
+  if let Some(c) = lookup(attrs, "fontcolor") {
+    ret.push(format!("text={}", color(c)));
+  }
+  ret
+}
+
+fn vertex_options(attrs: &[(String, String)]) -> Vec<String> {
+  let shape = match lookup(attrs, "shape") {
+    Some("box" | "rect" | "rectangle" | "square") => "rectangle",
+    Some("circle" | "doublecircle" | "point") => "circle",
+    Some("diamond") => "diamond",
+    _ => "ellipse",
+  };
+  let mut ret = vec!["draw".to_string(), shape.to_string()];
+  if let Some(c) = lookup(attrs, "fillcolor") {
+    ret.push(format!("fill={}", color(c)));
+  }
+  ret.extend(line_options(attrs));
+  if lookup(attrs, "label").map_or(false, |l| label_lines(l).len() > 1) {
+    ret.push("align=center".to_string());
+  }
+  ret
+}
+
+/// The `x,y` of a `pos` attribute.
+fn position(pos: &str) -> Option<(f64, f64)> {
+  let (x, y) = pos.trim_end_matches('!').split_once(',')?;
+  Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+/// Write the TikZ picture for `graph`. See the [module docs](self).
+pub fn to_tikz(graph: &GraphBuilder, layout: &Layout) -> String {
+  let vertices = all_vertices(graph.entities());
+  let names = Aliases::new(vertices.iter().map(|v| &v.id), "v_", is_plain);
+  let mut out = String::new();
+  match layout {
+    Layout::GraphDrawing(algorithm) => {
+      writeln!(
+        out,
+        "\\begin{{tikzpicture}}\n\\graph [{} layout] {{",
+        algorithm
+      )
+      .unwrap();
+      for v in vertices.iter() {
+        let mut options = vec![format!("as={{{}}}", escape(&vertex_label(v)))];
+        options.extend(vertex_options(&vertex_attributes(v)));
+        writeln!(out, "  {} [{}];", names.get(&v.id), options.join(", ")).unwrap();
+      }
+      for e in all_edges(graph.entities()) {
+        let attrs = edge_attributes(e);
+        let mut options = line_options(&attrs);
+        if let Some(label) = lookup(&attrs, "label") {
+          options.push(format!("edge label={{{}}}", escape(label)));
+        }
+        let arrow = if graph.is_directed() { "->" } else { "--" };
+        write!(out, "  {} {}", names.get(&e.source), arrow).unwrap();
+        if !options.is_empty() {
+          write!(out, " [{}]", options.join(", ")).unwrap();
+        }
+        writeln!(out, " {};", names.get(&e.target)).unwrap();
+      }
+      out.push_str("};\n");
+    },
+    Layout::Positions => {
+      out.push_str("\\begin{tikzpicture}[x=1pt, y=1pt]\n");
+      for v in vertices.iter() {
+        let attrs = vertex_attributes(v);
+        let (x, y) = lookup(&attrs, "pos")
+          .and_then(position)
+          .unwrap_or((0.0, 0.0));
+        writeln!(
+          out,
+          "  \\node[{}] ({}) at ({}, {}) {{{}}};",
+          vertex_options(&attrs).join(", "),
+          names.get(&v.id),
+          x,
+          y,
+          escape(&vertex_label(v))
+        )
+        .unwrap();
+      }
+      for e in all_edges(graph.entities()) {
+        let attrs = edge_attributes(e);
+        let mut options = line_options(&attrs);
+        if graph.is_directed() {
+          options.insert(0, "->".to_string());
+        }
+        write!(
+          out,
+          "  \\draw[{}] ({}) --",
+          options.join(", "),
+          names.get(&e.source)
+        )
+        .unwrap();
+        if let Some(label) = lookup(&attrs, "label") {
+          write!(out, " node[auto] {{{}}}", escape(label)).unwrap();
+        }
+        writeln!(out, " ({});", names.get(&e.target)).unwrap();
+      }
+    },
+  }
+  out.push_str("\\end{tikzpicture}\n");
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn positions() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(
+      Vertex::new("my_node")
+        .label("a_b\\n{c}")
+        .color(Color::new("#ff8000"))
+        .attr("pos", "27,90!"),
+    ));
+    gb.accept_entity(Entity::Edge(
+      Edge::between("my_node", "x")
+        .style("dashed")
+        .color(Color::blue()),
+    ));
+    assert_eq!(
+      to_tikz(&gb, &Layout::Positions),
+      "\\begin{tikzpicture}[x=1pt, y=1pt]\n  \
+         \\node[draw, ellipse, draw={rgb,255:red,255;green,128;blue,0}, align=center] (v_0) at (27, 90) {a\\_b\\\\\\{c\\}};\n  \
+         \\node[draw, ellipse] (x) at (0, 0) {x};\n  \
+         \\draw[->, draw=blue, dashed] (v_0) -- (x);\n\
+       \\end{tikzpicture}\n"
+    );
+  }
+
+  #[test]
+  fn latex_specials() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(
+      Vertex::new("a").label("50% of $5 & #1_{x}~^\\nok"),
+    ));
+    assert_eq!(
+      to_tikz(&gb, &Layout::Positions),
+      "\\begin{tikzpicture}[x=1pt, y=1pt]\n  \
+         \\node[draw, ellipse, align=center] (a) at (0, 0) \
+         {50\\% of \\$5 \\& \\#1\\_\\{x\\}\\textasciitilde{}\\textasciicircum{}\\\\ok};\n\
+       \\end{tikzpicture}\n"
+    );
+  }
+}
@@ -0,0 +1,185 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Write the `nodes` and `edges` arrays expected by the
+//! [vis-network](https://visjs.github.io/vis-network/docs/network/) widget.
+//!
+//! Each node has its `id` and `label` (which defaults to the id), its
+//! `shape` if vis-network has an equivalent, its `fillcolor` and `color` as
+//! the `background` and `border` of its `color`, and the id of the
+//! innermost subgraph it was declared in as its `group`. Each edge has
+//! `arrows` following its `dir` (`"to"` by default, or none if the graph is
+//! [undirected](GraphBuilder::set_directed)), its `label`, its
+//! `color`, and `dashes` if it is dashed or dotted. Invisible vertices and
+//! edges are `hidden`.
+//!
+//! Requires the `json` feature.
+//!
+//!```
+//! use graphvizier::{entities::*, export::vis::to_vis_network, generator::GraphBuilder};
+//! use serde_json::json;
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Vertex(Vertex::new("a").shape("box").color(Color::red())));
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b").label("next")));
+//! assert_eq!(to_vis_network(&gb), json!({
+//!   "nodes": [
+//!     {"id": "a", "label": "a", "shape": "box", "color": {"border": "red"}},
+//!     {"id": "b", "label": "b"},
+//!   ],
+//!   "edges": [{"from": "a", "to": "b", "arrows": "to", "label": "next"}],
+//! }));
+//! ```
+
+use super::{
+  all_edges, all_vertices, edge_attributes, lookup, subgraph_of, vertex_attributes, vertex_label,
+};
+use crate::generator::GraphBuilder;
+
+use serde_json::{json, Map, Value};
+
+use std::collections::HashMap;
+
+fn shape(dot: &str) -> Option<&'static str> {
+  Some(match dot {
+    "box" | "rect" | "rectangle" | "square" => "box",
+    "circle" | "doublecircle" => "circle",
+    "ellipse" | "oval" => "ellipse",
+    "diamond" => "diamond",
+    "point" => "dot",
+    "triangle" => "triangle",
+    "invtriangle" => "triangleDown",
+    "cylinder" => "database",
+    "plaintext" | "plain" | "none" => "text",
+    _ => return None,
+  })
+}
+
+fn is_invisible(attrs: &[(String, String)]) -> bool {
+  lookup(attrs, "style").map_or(false, |s| s.contains("invis"))
+}
+
+/// Write the vis-network data for `graph`. See the [module docs](self).
+pub fn to_vis_network(graph: &GraphBuilder) -> Value {
+  let mut group_of = HashMap::new();
+  subgraph_of(graph.entities(), None, &mut group_of);
+
+  let nodes: Vec<Value> = all_vertices(graph.entities())
+    .iter()
+    .map(|v| {
+      let attrs = vertex_attributes(v);
+      let mut node = Map::new();
+      node.insert("id".to_string(), json!(v.id.as_str()));
+      node.insert("label".to_string(), json!(vertex_label(v)));
+      if let Some(shape) = lookup(&attrs, "shape").and_then(shape) {
+        node.insert("shape".to_string(), json!(shape));
+      }
+      let mut color = Map::new();
+      if let Some(fill) = lookup(&attrs, "fillcolor") {
+        color.insert("background".to_string(), json!(fill));
+      }
+      if let Some(border) = lookup(&attrs, "color") {
+        color.insert("border".to_string(), json!(border));
+      }
+      if !color.is_empty() {
+        node.insert("color".to_string(), Value::Object(color));
+      }
+      if let Some(Some(group)) = group_of.get(&v.id) {
+        node.insert("group".to_string(), json!(group.as_str()));
+      }
+      if is_invisible(&attrs) {
+        node.insert("hidden".to_string(), json!(true));
+      }
+      Value::Object(node)
+    })
+    .collect();
+
+  let edges: Vec<Value> = all_edges(graph.entities())
+    .into_iter()
+    .map(|e| {
+      let attrs = edge_attributes(e);
+      let mut edge = Map::new();
+      edge.insert("from".to_string(), json!(e.source.as_str()));
+      edge.insert("to".to_string(), json!(e.target.as_str()));
+      let arrows = match lookup(&attrs, "dir") {
+        Some("both") => Some("to, from"),
+        Some("back") => Some("from"),
+        Some("none") => None,
+        _ if graph.is_directed() => Some("to"),
+        _ => None,
+      };
+      if let Some(arrows) = arrows {
+        edge.insert("arrows".to_string(), json!(arrows));
+      }
+      if let Some(label) = lookup(&attrs, "label") {
+        edge.insert("label".to_string(), json!(label));
+      }
+      if let Some(color) = lookup(&attrs, "color") {
+        edge.insert("color".to_string(), json!({ "color": color }));
+      }
+      let style = lookup(&attrs, "style").unwrap_or("");
+      if style.contains("dashed") || style.contains("dotted") {
+        edge.insert("dashes".to_string(), json!(true));
+      }
+      if is_invisible(&attrs) {
+        edge.insert("hidden".to_string(), json!(true));
+      }
+      Value::Object(edge)
+    })
+    .collect();
+
+  json!({ "nodes": nodes, "edges": edges })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::*;
+
+  #[test]
+  fn styles_and_directions() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Subgraph(Subgraph {
+      entities: vec![Entity::Vertex(
+        Vertex::new("a").style("invis").attr("fillcolor", "#eeeeee"),
+      )],
+      ..Subgraph::new("s")
+    }));
+    gb.accept_entity(Entity::Edge(
+      Edge::between("a", "b")
+        .style("dashed")
+        .color(Color::blue())
+        .attr("dir", "both"),
+    ));
+    gb.accept_entity(Entity::Edge(Edge::between("b", "a").attr("dir", "none")));
+    assert_eq!(
+      to_vis_network(&gb),
+      json!({
+        "nodes": [
+          {"id": "a", "label": "a", "color": {"background": "#eeeeee"}, "group": "s", "hidden": true},
+          {"id": "b", "label": "b"},
+        ],
+        "edges": [
+          {"from": "a", "to": "b", "arrows": "to, from", "color": {"color": "blue"}, "dashes": true},
+          {"from": "b", "to": "a"},
+        ],
+      })
+    );
+  }
+}
@@ -0,0 +1,205 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Strategies for minting fresh [`Id`]s.
+//!
+//! [`Vertex::default()`](crate::entities::Vertex) and
+//! [`Subgraph::default()`](crate::entities::Subgraph) use random UUIDs, which
+//! makes output differ from run to run. A [`GraphBuilder`] can instead be
+//! given a deterministic generator with
+//! [`GraphBuilder::set_id_generator`](crate::generator::GraphBuilder::set_id_generator).
+//!
+//!```
+//! use graphvizier::{entities::*, generator::*, ids::*};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.set_id_generator(Sequential::new("n"));
+//! let a = gb.fresh_vertex().label("a");
+//! let b = gb.fresh_vertex().label("b");
+//! assert_eq!(a.id, Id::new("n0"));
+//! assert_eq!(b.id, Id::new("n1"));
+//! ```
+//!
+//! [`GraphBuilder`]: crate::generator::GraphBuilder
+
+use crate::entities::{Entity, Id};
+
+/// Produces a new [`Id`] each time it is called.
+///
+/// Only ids minted through a [`GraphBuilder`] come from its generator, e.g.
+/// with [`GraphBuilder::fresh_id`] or [`GraphBuilder::fresh_vertex`].
+/// [`Vertex::default()`](crate::entities::Vertex) and
+/// [`Subgraph::default()`](crate::entities::Subgraph) have no builder to ask,
+/// so anything built with `..Default::default()` and no explicit `id` still
+/// gets a random UUID. For deterministic output, always set the id, e.g.
+/// `Vertex { id: gb.fresh_id(), ..Default::default() }`.
+///
+/// [`GraphBuilder`]: crate::generator::GraphBuilder
+/// [`GraphBuilder::fresh_id`]: crate::generator::GraphBuilder::fresh_id
+/// [`GraphBuilder::fresh_vertex`]: crate::generator::GraphBuilder::fresh_vertex
+pub trait IdGenerator {
+  /// Mint an id which this generator has not returned before.
+  fn next_id(&mut self) -> Id;
+}
+
+/// Random v4 UUIDs. This is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Uuids;
+
+impl IdGenerator for Uuids {
+  fn next_id(&mut self) -> Id { Id::random() }
+}
+
+/// Ids formed from a prefix and an incrementing counter: `n0`, `n1`, ...
+#[derive(Debug, Clone)]
+pub struct Sequential {
+  prefix: String,
+  next: u64,
+}
+
+impl Sequential {
+  #[allow(missing_docs)]
+  pub fn new(prefix: impl Into<String>) -> Self {
+    Self {
+      prefix: prefix.into(),
+      next: 0,
+    }
+  }
+}
+
+impl Default for Sequential {
+  fn default() -> Self { Self::new("n") }
+}
+
+impl IdGenerator for Sequential {
+  fn next_id(&mut self) -> Id {
+    let id = Id::new(format!("{}{}", self.prefix, self.next));
+    self.next += 1;
+    id
+  }
+}
+
+/// Opaque pseudo-random ids which are reproducible given the same seed.
+///
+/// Useful when ids should not reveal insertion order, but output must still be
+/// stable across runs.
+#[derive(Debug, Clone)]
+pub struct Seeded {
+  prefix: String,
+  state: u64,
+}
+
+impl Seeded {
+  #[allow(missing_docs)]
+  pub fn new(prefix: impl Into<String>, seed: u64) -> Self {
+    Self {
+      prefix: prefix.into(),
+      state: seed,
+    }
+  }
+}
+
+impl IdGenerator for Seeded {
+  fn next_id(&mut self) -> Id {
+    /* splitmix64: a bijection on the state, so ids never repeat within 2^64
+     * calls. */
+    self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+    Id::new(format!("{}{:016x}", self.prefix, z))
+  }
+}
+
+/// A prefix applied to every id within a scope, so that graphs from separate
+/// sources can be merged without their ids colliding.
+///
+/// See [`GraphBuilder::scoped`](crate::generator::GraphBuilder::scoped).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdNamespace {
+  prefix: String,
+  separator: String,
+}
+
+impl IdNamespace {
+  /// A namespace which joins `prefix` to each id with `_`.
+  pub fn new(prefix: impl Into<String>) -> Self {
+    Self {
+      prefix: prefix.into(),
+      separator: "_".to_string(),
+    }
+  }
+
+  /// Join the prefix to each id with `separator` instead of `_`.
+  pub fn separator(mut self, separator: impl Into<String>) -> Self {
+    self.separator = separator.into();
+    self
+  }
+
+  /// The id `id` refers to within this namespace.
+  pub fn qualify(&self, id: &Id) -> Id {
+    id.prefixed(&format!("{}{}", self.prefix, self.separator))
+  }
+
+  /// Qualify every vertex and subgraph id within `e`, along with every edge
+  /// endpoint.
+  pub fn apply(&self, e: Entity) -> Entity {
+    match e {
+      Entity::Vertex(mut v) => {
+        v.id = self.qualify(&v.id);
+        Entity::Vertex(v)
+      },
+      Entity::Edge(mut edge) => {
+        edge.source = self.qualify(&edge.source);
+        edge.target = self.qualify(&edge.target);
+        Entity::Edge(edge)
+      },
+      Entity::Subgraph(mut sg) => {
+        sg.id = self.qualify(&sg.id);
+        sg.entities = sg.entities.into_iter().map(|e| self.apply(e)).collect();
+        Entity::Subgraph(sg)
+      },
+      Entity::Group(mut g) => {
+        g.entities = g.entities.into_iter().map(|e| self.apply(e)).collect();
+        Entity::Group(g)
+      },
+      Entity::Comment(text) => Entity::Comment(text),
+    }
+  }
+}
+
+impl<S: Into<String>> From<S> for IdNamespace {
+  fn from(prefix: S) -> Self { Self::new(prefix) }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn seeded_is_reproducible() {
+    let mut a = Seeded::new("v", 42);
+    let mut b = Seeded::new("v", 42);
+    let first = a.next_id();
+    assert_eq!(first, b.next_id());
+    assert_ne!(first, a.next_id());
+    assert_ne!(a.next_id(), Seeded::new("v", 43).next_id());
+  }
+}
@@ -0,0 +1,234 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Read CSV or TSV edge lists, with one edge per row.
+//!
+//! Which columns hold the source, target, label, and weight of each edge is
+//! set by [`CsvFormat`], which is also used to write edge lists with
+//! [`export::csv`](crate::export::csv). Fields may be quoted as in
+//! [RFC 4180](https://www.rfc-editor.org/rfc/rfc4180), and blank lines are
+//! skipped. A vertex is declared for each endpoint, in the order they first
+//! appear. Labels are taken as plain text, so any `\` or `"` is escaped.
+//!
+//!```
+//! use graphvizier::{entities::*, import::csv::{from_csv, CsvFormat}};
+//!
+//! let graph = from_csv("source,target,label,weight\na,b,\"x, y\",2.5\n", &CsvFormat::default()).unwrap();
+//! assert_eq!(graph.entities, vec![
+//!   Entity::Vertex(Vertex::new("a")),
+//!   Entity::Vertex(Vertex::new("b")),
+//!   Entity::Edge(Edge::between("a", "b").label("x, y").weight(2.5)),
+//! ]);
+//! ```
+
+use super::{edge_list, ImportError};
+use crate::{
+  entities::{Edge, Label},
+  parser::ParsedGraph,
+};
+
+/// The layout of an edge list. Columns are numbered from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvFormat {
+  /// The character between fields, usually `,` or `\t`.
+  pub delimiter: char,
+  /// Whether the first row names the columns, rather than being an edge.
+  pub header: bool,
+  #[allow(missing_docs)]
+  pub source: usize,
+  #[allow(missing_docs)]
+  pub target: usize,
+  /// The column holding edge labels, if any. Empty fields leave the edge
+  /// unlabeled.
+  pub label: Option<usize>,
+  /// The column holding edge weights, if any. Empty fields leave the weight
+  /// unset.
+  pub weight: Option<usize>,
+}
+
+impl CsvFormat {
+  /// Comma-separated `source,target,label,weight` with a header row.
+  pub fn csv() -> Self {
+    Self {
+      delimiter: ',',
+      header: true,
+      source: 0,
+      target: 1,
+      label: Some(2),
+      weight: Some(3),
+    }
+  }
+
+  /// Like [`Self::csv`], but separated by tabs.
+  pub fn tsv() -> Self {
+    Self {
+      delimiter: '\t',
+      ..Self::csv()
+    }
+  }
+}
+
+impl Default for CsvFormat {
+  fn default() -> Self { Self::csv() }
+}
+
+/// Each non-blank row of `text` with its line number, split into fields.
+fn records(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+  let mut ret = Vec::new();
+  let mut line = 1;
+  let mut chars = text.chars().peekable();
+  while chars.peek().is_some() {
+    let start = line;
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    loop {
+      let Some(c) = chars.next() else {
+        if quoted {
+          return Err(ImportError::Syntax(format!(
+            "line {}: unterminated quoted field",
+            start
+          )));
+        }
+        break;
+      };
+      match c {
+        '"' if quoted && chars.peek() == Some(&'"') => {
+          chars.next();
+          fields.last_mut().unwrap().push('"');
+        },
+        '"' if quoted => quoted = false,
+        '"' if fields.last().unwrap().is_empty() => quoted = true,
+        '\n' if !quoted => {
+          line += 1;
+          break;
+        },
+        '\r' if !quoted && chars.peek() == Some(&'\n') => (),
+        c if c == delimiter && !quoted => fields.push(String::new()),
+        c => {
+          if c == '\n' {
+            line += 1;
+          }
+          fields.last_mut().unwrap().push(c);
+        },
+      }
+    }
+    if !(fields.len() == 1 && fields[0].trim().is_empty()) {
+      ret.push((start, fields));
+    }
+  }
+  Ok(ret)
+}
+
+/// Parse an edge list laid out as `format`. See the [module docs](self).
+pub fn from_csv(text: &str, format: &CsvFormat) -> Result<ParsedGraph, ImportError> {
+  let mut edges = Vec::new();
+  let rows = records(text, format.delimiter)?;
+  for (line, fields) in rows.into_iter().skip(format.header as usize) {
+    let field = |column: usize| -> Result<&str, ImportError> {
+      fields
+        .get(column)
+        .map(String::as_str)
+        .ok_or_else(|| ImportError::Structure(format!("line {}: no column {}", line, column)))
+    };
+    let optional = |column: Option<usize>| match column.map(|c| fields.get(c)) {
+      Some(Some(f)) if !f.is_empty() => Some(f.as_str()),
+      _ => None,
+    };
+
+    let mut e = Edge::between(field(format.source)?, field(format.target)?);
+    if let Some(label) = optional(format.label) {
+      e = e.label(Label::escape_text(label).as_str());
+    }
+    if let Some(weight) = optional(format.weight) {
+      let weight = weight.trim().parse::<f64>().map_err(|_| {
+        ImportError::Structure(format!("line {}: invalid weight {:?}", line, weight))
+      })?;
+      e = e.weight(weight);
+    }
+    edges.push(e);
+  }
+  Ok(edge_list(edges))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    entities::{Entity, Id, Vertex},
+    generator::{DotOutput, GraphBuilder},
+  };
+
+  #[test]
+  fn custom_columns() {
+    let format = CsvFormat {
+      header: false,
+      source: 1,
+      target: 0,
+      label: Some(2),
+      weight: None,
+      ..CsvFormat::tsv()
+    };
+    let graph = from_csv("b\ta\t\"two\r\nlines\"\r\n\r\nc\tb\t\n", &format).unwrap();
+    assert_eq!(graph.entities, vec![
+      Entity::Vertex(Vertex::new("a")),
+      Entity::Vertex(Vertex::new("b")),
+      Entity::Vertex(Vertex::new("c")),
+      Entity::Edge(Edge::between("a", "b").label("two\r\nlines")),
+      Entity::Edge(Edge::between("b", "c")),
+    ]);
+  }
+
+  #[test]
+  fn quoted_labels() {
+    let graph = from_csv(
+      "source,target,label\na,b,\"say \"\"hi\"\"\\n\"\n",
+      &CsvFormat::csv(),
+    )
+    .unwrap();
+    assert_eq!(
+      graph.entities[2],
+      Entity::Edge(Edge::between("a", "b").label("say \\\"hi\\\"\\\\n"))
+    );
+    let gb: GraphBuilder = graph.entities.into_iter().collect();
+    let DotOutput(dot) = gb.build(Id::new("g")).unwrap();
+    assert!(dot.contains("a -> b[label=\"say \\\"hi\\\"\\\\n\"];"));
+    crate::parser::parse(&dot).unwrap();
+  }
+
+  #[test]
+  fn invalid_rows() {
+    let format = CsvFormat::default();
+    assert_eq!(
+      from_csv("s,t\na\n", &format),
+      Err(ImportError::Structure("line 2: no column 1".to_string()))
+    );
+    assert_eq!(
+      from_csv("s,t,l,w\na,b,,heavy\n", &format),
+      Err(ImportError::Structure(
+        "line 2: invalid weight \"heavy\"".to_string()
+      ))
+    );
+    assert_eq!(
+      from_csv("a,\"b\n", &format),
+      Err(ImportError::Syntax(
+        "line 1: unterminated quoted field".to_string()
+      ))
+    );
+  }
+}
@@ -0,0 +1,327 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Read [GML](https://en.wikipedia.org/wiki/Graph_Modelling_Language), as
+//! written by NetworkX, igraph, Cytoscape, and others.
+//!
+//! Each `node` becomes a vertex whose id is its GML `id`, and each `edge`
+//! connects the ids named by its `source` and `target`. Every other key is
+//! kept as an attribute, so a node's `label` is drawn in place of its id.
+//! Nested lists like `graphics [ fill "#ff0000" ]` are flattened into
+//! dotted names, like `graphics.fill`. Values are escaped with
+//! [`Label::escape_text`] so they're displayed as plain text.
+//!
+//!```
+//! use graphvizier::{entities::*, import::gml::from_gml};
+//!
+//! let graph = from_gml(r##"
+//!   graph [
+//!     directed 1
+//!     node [ id 0 label "start" ]
+//!     node [ id 1 label "end" graphics [ fill "#ff0000" ] ]
+//!     edge [ source 0 target 1 weight 2.5 ]
+//!   ]
+//! "##).unwrap();
+//! assert!(graph.directed);
+//! assert_eq!(graph.entities, vec![
+//!   Entity::Vertex(Vertex::new("0").attr("label", "start")),
+//!   Entity::Vertex(Vertex::new("1").attr("label", "end").attr("graphics.fill", "#ff0000")),
+//!   Entity::Edge(Edge::between("0", "1").attr("weight", "2.5")),
+//! ]);
+//! ```
+
+use super::ImportError;
+use crate::{
+  entities::{AttrValue, Attributes, Edge, Entity, Id, Label, Vertex},
+  parser::ParsedGraph,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+  /// A number, kept as written, or the contents of a string.
+  Scalar(String),
+  List(Vec<(String, Value)>),
+}
+
+struct Reader<'a> {
+  text: &'a str,
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn error(&self, message: impl std::fmt::Display) -> ImportError {
+    let line = self.text[..self.pos].matches('\n').count() + 1;
+    ImportError::Syntax(format!("line {}: {}", line, message))
+  }
+
+  fn rest(&self) -> &'a str { &self.text[self.pos..] }
+
+  /// Skip whitespace and `#` comments, then peek at the next character.
+  fn peek(&mut self) -> Option<char> {
+    loop {
+      let rest = self.rest();
+      let trimmed = rest.trim_start();
+      self.pos += rest.len() - trimmed.len();
+      if trimmed.starts_with('#') {
+        self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+      } else {
+        return trimmed.chars().next();
+      }
+    }
+  }
+
+  fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+    let rest = self.rest();
+    let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+    self.pos += len;
+    &rest[..len]
+  }
+
+  /// Key-value pairs until `]`, or the end of input if `nested` is false.
+  fn list(&mut self, nested: bool) -> Result<Vec<(String, Value)>, ImportError> {
+    let mut ret = Vec::new();
+    loop {
+      match self.peek() {
+        None if nested => return Err(self.error("unterminated list")),
+        None => return Ok(ret),
+        Some(']') if nested => {
+          self.pos += 1;
+          return Ok(ret);
+        },
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+          let key = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+          ret.push((key.to_string(), self.value()?));
+        },
+        Some(c) => return Err(self.error(format!("expected a key, found {:?}", c))),
+      }
+    }
+  }
+
+  fn value(&mut self) -> Result<Value, ImportError> {
+    match self.peek() {
+      Some('[') => {
+        self.pos += 1;
+        Ok(Value::List(self.list(true)?))
+      },
+      Some('"') => {
+        self.pos += 1;
+        let s = self.take_while(|c| c != '"');
+        if self.rest().is_empty() {
+          return Err(self.error("unterminated string"));
+        }
+        self.pos += 1;
+        Ok(Value::Scalar(unescape(s)))
+      },
+      Some(c) if c.is_ascii_digit() || "+-.".contains(c) => {
+        let n = self.take_while(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+        match n.parse::<f64>() {
+          Ok(_) => Ok(Value::Scalar(n.to_string())),
+          Err(_) => Err(self.error(format!("invalid number {:?}", n))),
+        }
+      },
+      Some(c) => Err(self.error(format!("expected a value, found {:?}", c))),
+      None => Err(self.error("expected a value")),
+    }
+  }
+}
+
+/// GML strings escape characters as HTML entities.
+fn unescape(s: &str) -> String {
+  [
+    ("&quot;", "\""),
+    ("&lt;", "<"),
+    ("&gt;", ">"),
+    ("&apos;", "'"),
+    ("&amp;", "&"),
+  ]
+  .iter()
+  .fold(s.to_string(), |s, (from, to)| s.replace(from, to))
+}
+
+fn flatten(prefix: &str, pairs: &[(String, Value)], skip: &[&str], out: &mut Attributes) {
+  for (key, value) in pairs.iter() {
+    if prefix.is_empty() && skip.contains(&key.as_str()) {
+      continue;
+    }
+    let name = if prefix.is_empty() {
+      key.clone()
+    } else {
+      format!("{}.{}", prefix, key)
+    };
+    match value {
+      Value::Scalar(s) => {
+        out.insert(name, AttrValue::Quoted(Label::escape_text(s)));
+      },
+      Value::List(pairs) => flatten(&name, pairs, skip, out),
+    }
+  }
+}
+
+fn attributes(pairs: &[(String, Value)], skip: &[&str]) -> Attributes {
+  let mut ret = Attributes::default();
+  flatten("", pairs, skip, &mut ret);
+  ret
+}
+
+fn scalar<'a>(pairs: &'a [(String, Value)], key: &str, what: &str) -> Result<&'a str, ImportError> {
+  match pairs.iter().find(|(k, _)| k == key) {
+    Some((_, Value::Scalar(s))) => Ok(s),
+    _ => Err(ImportError::Structure(format!(
+      "every {} must have a scalar {:?}",
+      what, key
+    ))),
+  }
+}
+
+/// Parse a GML document. See the [module docs](self).
+pub fn from_gml(text: &str) -> Result<ParsedGraph, ImportError> {
+  let document = Reader { text, pos: 0 }.list(false)?;
+  let graph = document
+    .iter()
+    .find_map(|(k, v)| match (k.as_str(), v) {
+      ("graph", Value::List(pairs)) => Some(pairs),
+      _ => None,
+    })
+    .ok_or_else(|| ImportError::Structure("the document contains no graph".to_string()))?;
+
+  let mut entities = Vec::new();
+  for (key, value) in graph.iter() {
+    let Value::List(pairs) = value else { continue };
+    match key.as_str() {
+      "node" => {
+        let mut v = Vertex::new(scalar(pairs, "id", "node")?);
+        v.attributes = attributes(pairs, &["id"]);
+        entities.push(Entity::Vertex(v));
+      },
+      "edge" => {
+        let mut e = Edge::between(
+          scalar(pairs, "source", "edge")?,
+          scalar(pairs, "target", "edge")?,
+        );
+        e.attributes = attributes(pairs, &["source", "target"]);
+        entities.push(Entity::Edge(e));
+      },
+      _ => (),
+    }
+  }
+
+  let flag =
+    |key| matches!(graph.iter().find(|(k, _)| k == key), Some((_, Value::Scalar(s))) if s == "1");
+  Ok(ParsedGraph {
+    strict: false,
+    directed: flag("directed"),
+    id: graph.iter().find_map(|(k, v)| match (k.as_str(), v) {
+      ("name", Value::Scalar(s)) => Some(Id::new(s)),
+      _ => None,
+    }),
+    attributes: attributes(graph, &["node", "edge", "directed", "name"]),
+    entities,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::generator::{DotOutput, GraphBuilder};
+
+  #[test]
+  fn networkx_output() {
+    let graph = from_gml(
+      r#"# written by networkx
+      graph [
+        name "karate &amp; co"
+        comment "an &quot;example&quot;"
+        node [
+          id 0
+          label "A"
+        ]
+        node [
+          id 1
+          label "B"
+        ]
+        edge [
+          source 1
+          target 0
+          weight -1.5E2
+        ]
+      ]"#,
+    )
+    .unwrap();
+    assert!(!graph.directed);
+    assert_eq!(graph.id, Some(Id::new("karate & co")));
+    assert_eq!(
+      graph.attributes.get("comment"),
+      Some(&AttrValue::from(Label::escape_text("an \"example\"")))
+    );
+    assert_eq!(graph.entities, vec![
+      Entity::Vertex(Vertex::new("0").attr("label", "A")),
+      Entity::Vertex(Vertex::new("1").attr("label", "B")),
+      Entity::Edge(Edge::between("1", "0").attr("weight", "-1.5E2")),
+    ]);
+  }
+
+  #[test]
+  fn nested_attributes() {
+    let graph = from_gml(
+      r##"graph [
+        node [
+          id 0
+          label "start"
+          graphics [ fill "#ff0000" ]
+        ]
+      ]"##,
+    )
+    .unwrap();
+    let gb: GraphBuilder = graph.entities.into_iter().collect();
+    let DotOutput(dot) = gb.build(Id::new("g")).unwrap();
+    assert!(dot.contains("0[\"graphics.fill\"=\"#ff0000\", label=\"start\"];"));
+    crate::parser::parse(&dot).unwrap();
+  }
+
+  #[test]
+  fn escaped_strings() {
+    let graph = from_gml(r#"graph [ node [ id 0 label "say &quot;hi&quot; C:\" ] ]"#).unwrap();
+    let gb: GraphBuilder = graph.entities.into_iter().collect();
+    let DotOutput(dot) = gb.build(Id::new("g")).unwrap();
+    assert!(dot.contains(r#"0[label="say \"hi\" C:\\"];"#), "{}", dot);
+    crate::parser::parse(&dot).unwrap();
+  }
+
+  #[test]
+  fn invalid_documents() {
+    assert_eq!(
+      from_gml("graph [\n  node [ id 0 label \"a ]"),
+      Err(ImportError::Syntax(
+        "line 2: unterminated string".to_string()
+      ))
+    );
+    assert_eq!(
+      from_gml("graph [ node [ id 0 ]\n  ]\n ]"),
+      Err(ImportError::Syntax(
+        "line 3: expected a key, found ']'".to_string()
+      ))
+    );
+    assert_eq!(
+      from_gml("graph [ edge [ source 0 ] ]"),
+      Err(ImportError::Structure(
+        "every edge must have a scalar \"target\"".to_string()
+      ))
+    );
+  }
+}
@@ -0,0 +1,402 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Read [GraphML](http://graphml.graphdrawing.org/), as written by yEd,
+//! Gephi, NetworkX, and others.
+//!
+//! Each `<data>` value becomes an attribute named after the `attr.name` of
+//! its `<key>` (or the key's `id`, if it has no name), and key `<default>`s
+//! are applied to every element lacking a value. Tool-specific markup nested
+//! within a `<data>` element, like yEd's `<y:ShapeNode>`, contributes only its
+//! text. A node containing a nested `<graph>` becomes a cluster with the
+//! node's id, written with `rank = same` only if its data has a `rank` of
+//! `same`.
+//!
+//! Only the first `<graph>` of a document is read. Since DOT can't mix edge
+//! directions, every edge follows the `edgedefault` of the top-level graph.
+//!
+//! Requires the `graphml` feature.
+//!
+//!```
+//! use graphvizier::{entities::*, import::graphml::from_graphml};
+//!
+//! let graph = from_graphml(r#"<?xml version="1.0" encoding="UTF-8"?>
+//! <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+//!   <key id="d0" for="node" attr.name="color" attr.type="string">
+//!     <default>yellow</default>
+//!   </key>
+//!   <graph id="G" edgedefault="undirected">
+//!     <node id="a"><data key="d0">green</data></node>
+//!     <node id="b"/>
+//!     <edge source="a" target="b"/>
+//!   </graph>
+//! </graphml>"#).unwrap();
+//! assert!(!graph.directed);
+//! assert_eq!(graph.entities, vec![
+//!   Entity::Vertex(Vertex::new("a").attr("color", "green")),
+//!   Entity::Vertex(Vertex::new("b").attr("color", "yellow")),
+//!   Entity::Edge(Edge::between("a", "b")),
+//! ]);
+//! ```
+
+use super::ImportError;
+use crate::{
+  entities::{AttrValue, Attributes, Edge, Entity, Id, Label, Subgraph, Vertex},
+  parser::ParsedGraph,
+};
+
+use quick_xml::events::{BytesStart, Event};
+
+use std::collections::HashMap;
+
+/// An XML element, with namespace prefixes removed from its name.
+#[derive(Debug, Default)]
+struct Element {
+  name: String,
+  attributes: Vec<(String, String)>,
+  children: Vec<Element>,
+  text: String,
+}
+
+impl Element {
+  fn start(e: &BytesStart) -> Result<Self, ImportError> {
+    let syntax = |e: &dyn std::fmt::Display| ImportError::Syntax(e.to_string());
+    let mut attributes = Vec::new();
+    for a in e.attributes() {
+      let a = a.map_err(|e| syntax(&e))?;
+      let key = String::from_utf8_lossy(a.key.as_ref()).into_owned();
+      attributes.push((
+        key,
+        a.unescape_value().map_err(|e| syntax(&e))?.into_owned(),
+      ));
+    }
+    Ok(Self {
+      name: String::from_utf8_lossy(e.local_name().as_ref()).into_owned(),
+      attributes,
+      ..Self::default()
+    })
+  }
+
+  fn attr(&self, name: &str) -> Option<&str> {
+    self
+      .attributes
+      .iter()
+      .find(|(k, _)| k == name)
+      .map(|(_, v)| v.as_str())
+  }
+
+  fn required(&self, name: &str) -> Result<&str, ImportError> {
+    self.attr(name).ok_or_else(|| {
+      ImportError::Structure(format!(
+        "<{}> is missing the {:?} attribute",
+        self.name, name
+      ))
+    })
+  }
+
+  fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item=&'a Element> {
+    self.children.iter().filter(move |c| c.name == name)
+  }
+
+  /// The text of this element and all of its descendants, trimmed.
+  fn text_content(&self) -> String {
+    fn collect(e: &Element, out: &mut String) {
+      out.push_str(&e.text);
+      for c in e.children.iter() {
+        collect(c, out);
+      }
+    }
+    let mut out = String::new();
+    collect(self, &mut out);
+    out.trim().to_string()
+  }
+}
+
+fn read_tree(text: &str) -> Result<Element, ImportError> {
+  let syntax = |e: &dyn std::fmt::Display| ImportError::Syntax(e.to_string());
+  let mut reader = quick_xml::Reader::from_str(text);
+  let mut stack: Vec<Element> = vec![Element::default()];
+  loop {
+    match reader.read_event().map_err(|e| syntax(&e))? {
+      Event::Start(e) => stack.push(Element::start(&e)?),
+      Event::Empty(e) => {
+        let el = Element::start(&e)?;
+        stack.last_mut().unwrap().children.push(el);
+      },
+      Event::Text(t) => {
+        let t = t.unescape().map_err(|e| syntax(&e))?;
+        stack.last_mut().unwrap().text.push_str(&t);
+      },
+      Event::CData(t) => {
+        let t = t.decode().map_err(|e| syntax(&e))?;
+        stack.last_mut().unwrap().text.push_str(&t);
+      },
+      Event::End(_) => {
+        let el = stack.pop().unwrap();
+        stack
+          .last_mut()
+          .ok_or_else(|| ImportError::Syntax("unbalanced closing tag".to_string()))?
+          .children
+          .push(el);
+      },
+      Event::Eof => break,
+      Event::Decl(_) | Event::PI(_) | Event::DocType(_) | Event::Comment(_) => (),
+    }
+  }
+  match (stack.pop(), stack.is_empty()) {
+    (Some(mut document), true) => document
+      .children
+      .pop()
+      .filter(|_| document.children.is_empty())
+      .ok_or_else(|| ImportError::Syntax("expected exactly one root element".to_string())),
+    _ => Err(ImportError::Syntax(
+      "unclosed element at end of input".to_string(),
+    )),
+  }
+}
+
+struct Key {
+  name: String,
+  domain: String,
+  default: Option<String>,
+}
+
+struct Keys(HashMap<String, Key>);
+
+impl Keys {
+  /// The `<data>` of `el`, over the defaults of keys for `domain`, escaped
+  /// with [`Label::escape_text`] so they're displayed as plain text.
+  fn data(&self, el: &Element, domain: &str) -> Result<Attributes, ImportError> {
+    let mut ret: Attributes = self
+      .0
+      .values()
+      .filter(|k| k.domain == domain || k.domain == "all")
+      .filter_map(|k| {
+        k.default
+          .as_ref()
+          .map(|d| (k.name.clone(), AttrValue::Quoted(Label::escape_text(d))))
+      })
+      .collect();
+    for data in el.children("data") {
+      let id = data.required("key")?;
+      let key = self.0.get(id).ok_or_else(|| {
+        ImportError::Structure(format!("<data> refers to undeclared key {:?}", id))
+      })?;
+      ret.insert(
+        key.name.clone(),
+        AttrValue::Quoted(Label::escape_text(data.text_content())),
+      );
+    }
+    Ok(ret)
+  }
+
+  fn entities(&self, graph: &Element) -> Result<Vec<Entity>, ImportError> {
+    let mut ret = Vec::new();
+    for child in graph.children.iter() {
+      match child.name.as_str() {
+        "node" => {
+          let id = Id::new(child.required("id")?);
+          let mut attributes = self.data(child, "node")?;
+          match child.children("graph").next() {
+            None => {
+              let mut v = Vertex::new(id);
+              v.attributes = attributes;
+              ret.push(Entity::Vertex(v));
+            },
+            Some(nested) => {
+              attributes.extend(self.data(nested, "graph")?);
+              /* Only keep the printer's own `rank = same` if the data had it. */
+              let same_rank = attributes.get("rank") == Some(&AttrValue::from("same"));
+              if same_rank {
+                attributes.remove("rank");
+              }
+              ret.push(Entity::Subgraph(Subgraph {
+                attributes,
+                entities: self.entities(nested)?,
+                ..Subgraph::new(id).same_rank(same_rank)
+              }));
+            },
+          }
+        },
+        "edge" => {
+          let mut e = Edge::between(child.required("source")?, child.required("target")?);
+          e.attributes = self.data(child, "edge")?;
+          for (port, attr) in [("sourceport", "tailport"), ("targetport", "headport")] {
+            if let Some(port) = child.attr(port) {
+              e.attributes
+                .insert(attr.to_string(), AttrValue::Quoted(Label::escape_text(port)));
+            }
+          }
+          ret.push(Entity::Edge(e));
+        },
+        "hyperedge" => {
+          return Err(ImportError::Structure(
+            "hyperedges have no equivalent in DOT".to_string(),
+          ))
+        },
+        _ => (),
+      }
+    }
+    Ok(ret)
+  }
+}
+
+/// Parse a GraphML document. See the [module docs](self).
+pub fn from_graphml(text: &str) -> Result<ParsedGraph, ImportError> {
+  let root = read_tree(text)?;
+  if root.name != "graphml" {
+    return Err(ImportError::Structure(format!(
+      "expected a <graphml> document, not <{}>",
+      root.name
+    )));
+  }
+
+  let mut keys = HashMap::new();
+  for key in root.children("key") {
+    let id = key.required("id")?;
+    keys.insert(id.to_string(), Key {
+      name: key.attr("attr.name").unwrap_or(id).to_string(),
+      domain: key.attr("for").unwrap_or("all").to_string(),
+      default: key.children("default").next().map(Element::text_content),
+    });
+  }
+  let keys = Keys(keys);
+
+  let graph = root
+    .children("graph")
+    .next()
+    .ok_or_else(|| ImportError::Structure("the document contains no <graph>".to_string()))?;
+  Ok(ParsedGraph {
+    strict: false,
+    directed: graph.attr("edgedefault") != Some("undirected"),
+    id: graph.attr("id").map(Id::new),
+    attributes: keys.data(graph, "graph")?,
+    entities: keys.entities(graph)?,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::generator::{DotOutput, GraphBuilder};
+
+  #[test]
+  fn yed_group_nodes() {
+    let graph = from_graphml(
+      r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+      <graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:y="http://www.yworks.com/xml/graphml">
+        <key for="node" id="d6" yfiles.type="nodegraphics"/>
+        <key for="edge" id="d10" attr.name="weight"><default>1</default></key>
+        <graph edgedefault="directed" id="G">
+          <node id="n0">
+            <data key="d6"><y:ShapeNode><y:NodeLabel>First &amp; best</y:NodeLabel></y:ShapeNode></data>
+          </node>
+          <node id="n1" yfiles.foldertype="group">
+            <graph edgedefault="directed" id="n1:">
+              <node id="n1::n0"/>
+            </graph>
+          </node>
+          <edge id="e0" source="n0" target="n1::n0" sourceport="s"><data key="d10">3</data></edge>
+          <edge id="e1" source="n1::n0" target="n0"/>
+        </graph>
+      </graphml>"#,
+    )
+    .unwrap();
+    assert!(graph.directed);
+    assert_eq!(graph.id, Some(Id::new("G")));
+    assert_eq!(graph.entities, vec![
+      Entity::Vertex(Vertex::new("n0").attr("d6", "First & best")),
+      Entity::Subgraph(Subgraph {
+        entities: vec![Entity::Vertex(Vertex::new("n1::n0"))],
+        ..Subgraph::new("n1").same_rank(false)
+      }),
+      Entity::Edge(
+        Edge::between("n0", "n1::n0")
+          .attr("weight", "3")
+          .attr("tailport", "s")
+      ),
+      Entity::Edge(Edge::between("n1::n0", "n0").attr("weight", "1")),
+    ]);
+  }
+
+  #[test]
+  fn nested_graph_rank() {
+    let graph = from_graphml(
+      r#"<graphml>
+        <key for="graph" id="r" attr.name="rank"/>
+        <graph>
+          <node id="s"><graph><data key="r">same</data><node id="a"/></graph></node>
+          <node id="t"><graph><node id="b"/></graph></node>
+        </graph>
+      </graphml>"#,
+    )
+    .unwrap();
+    let gb: GraphBuilder = graph.entities.into_iter().collect();
+    let DotOutput(dot) = gb.build(Id::new("g")).unwrap();
+    assert_eq!(dot.matches("rank").count(), 1, "{}", dot);
+    assert!(dot.contains("rank = same;"), "{}", dot);
+  }
+
+  #[test]
+  fn escaped_data() {
+    let graph = from_graphml(
+      r#"<graphml>
+        <key for="node" id="l" attr.name="label"><default>C:\</default></key>
+        <graph><node id="a"><data key="l">say "hi"</data></node><node id="b"/></graph>
+      </graphml>"#,
+    )
+    .unwrap();
+    let gb: GraphBuilder = graph.entities.into_iter().collect();
+    let DotOutput(dot) = gb.build(Id::new("g")).unwrap();
+    assert!(dot.contains(r#"a[label="say \"hi\""];"#), "{}", dot);
+    assert!(dot.contains(r#"b[label="C:\\"];"#), "{}", dot);
+    crate::parser::parse(&dot).unwrap();
+  }
+
+  #[test]
+  fn key_names_with_spaces() {
+    let graph = from_graphml(
+      r#"<graphml>
+        <key for="node" id="k" attr.name="my key"/>
+        <graph><node id="a"><data key="k">x</data></node></graph>
+      </graphml>"#,
+    )
+    .unwrap();
+    let gb: GraphBuilder = graph.entities.into_iter().collect();
+    let DotOutput(dot) = gb.build(Id::new("g")).unwrap();
+    assert!(dot.contains("a[\"my key\"=\"x\"];"));
+    crate::parser::parse(&dot).unwrap();
+  }
+
+  #[test]
+  fn invalid_documents() {
+    assert!(matches!(
+      from_graphml("<graphml><graph>"),
+      Err(ImportError::Syntax(_))
+    ));
+    assert_eq!(
+      from_graphml(
+        r#"<graphml><graph><node id="a"><data key="k">x</data></node></graph></graphml>"#
+      ),
+      Err(ImportError::Structure(
+        "<data> refers to undeclared key \"k\"".to_string()
+      ))
+    );
+  }
+}
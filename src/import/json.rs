@@ -0,0 +1,405 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Read the JSON written by `dot -Tjson` or `dot -Tjson0`.
+//!
+//! This is mostly useful for reading a graph back after Graphviz has laid it
+//! out: computed attributes like `pos`, `width`, `height`, and `bb` are kept
+//! in the [`Attributes`] map of each entity, as is every other attribute.
+//! Attributes with non-string values, like the drawing operations `-Tjson`
+//! adds, are kept as their JSON text.
+//!
+//! Subgraphs are nested as they were in the original graph, and each vertex
+//! and edge is placed within the innermost subgraph containing it. Only
+//! subgraphs with a `rank` of `"same"` are written back with `rank = same`.
+//!
+//! [`from_json_edges`] reads a plain edge list instead.
+//!
+//! Requires the `json` feature.
+//!
+//!```
+//! use graphvizier::{entities::*, import::json::from_json};
+//!
+//! let graph = from_json(r#"{
+//!   "name": "g", "directed": true, "strict": false, "bb": "0,0,54,108",
+//!   "_subgraph_cnt": 0,
+//!   "objects": [
+//!     {"_gvid": 0, "name": "a", "pos": "27,90"},
+//!     {"_gvid": 1, "name": "b", "pos": "27,18"}
+//!   ],
+//!   "edges": [{"_gvid": 0, "tail": 0, "head": 1, "pos": "e,27,36.1 27,71.7 27,63.98 27,54.71 27,46.11"}]
+//! }"#).unwrap();
+//! assert_eq!(graph.attributes.get("bb"), Some(&AttrValue::from("0,0,54,108")));
+//! match &graph.entities[..] {
+//!   [Entity::Vertex(a), Entity::Vertex(_), Entity::Edge(e)] => {
+//!     assert_eq!(a.attributes.get("pos"), Some(&AttrValue::from("27,90")));
+//!     assert_eq!((e.source.as_str(), e.target.as_str()), ("a", "b"));
+//!   },
+//!   entities => panic!("{:?}", entities),
+//! }
+//! ```
+
+use super::{edge_list, ImportError};
+use crate::{
+  entities::{AttrValue, Attributes, Edge, Entity, Id, Label, Subgraph, Vertex},
+  parser::ParsedGraph,
+};
+
+use serde_json::{Map, Value};
+
+fn structure(message: impl Into<String>) -> ImportError { ImportError::Structure(message.into()) }
+
+/// Every entry of `obj` except those named in `skip`.
+fn attributes(obj: &Map<String, Value>, skip: &[&str]) -> Attributes {
+  obj
+    .iter()
+    .filter(|(k, _)| !skip.contains(&k.as_str()))
+    .map(|(k, v)| {
+      let value = match v {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+      };
+      (k.clone(), AttrValue::Quoted(value))
+    })
+    .collect()
+}
+
+fn indices(obj: &Map<String, Value>, key: &str) -> Result<Vec<usize>, ImportError> {
+  match obj.get(key) {
+    None => Ok(Vec::new()),
+    Some(Value::Array(values)) => values
+      .iter()
+      .map(|v| {
+        v.as_u64()
+          .map(|i| i as usize)
+          .ok_or_else(|| structure(format!("{:?} must contain only indices", key)))
+      })
+      .collect(),
+    Some(_) => Err(structure(format!("{:?} must be an array", key))),
+  }
+}
+
+fn objects<'a>(
+  root: &'a Map<String, Value>,
+  key: &str,
+) -> Result<Vec<&'a Map<String, Value>>, ImportError> {
+  match root.get(key) {
+    None => Ok(Vec::new()),
+    Some(Value::Array(values)) => values
+      .iter()
+      .map(|v| {
+        v.as_object()
+          .ok_or_else(|| structure(format!("{:?} must contain only objects", key)))
+      })
+      .collect(),
+    Some(_) => Err(structure(format!("{:?} must be an array", key))),
+  }
+}
+
+fn name(obj: &Map<String, Value>) -> Result<Id, ImportError> {
+  obj
+    .get("name")
+    .and_then(Value::as_str)
+    .map(Id::new)
+    .ok_or_else(|| structure("every object must have a string \"name\""))
+}
+
+/// For each of `count` members, the innermost subgraph listing it under
+/// `key`, if any.
+fn owners(
+  subgraphs: &[&Map<String, Value>],
+  depths: &[usize],
+  key: &str,
+  count: usize,
+) -> Result<Vec<Option<usize>>, ImportError> {
+  let mut ret: Vec<Option<usize>> = vec![None; count];
+  for (sg, obj) in subgraphs.iter().enumerate() {
+    for member in indices(obj, key)? {
+      let owner = ret
+        .get_mut(member)
+        .ok_or_else(|| structure(format!("{:?} index {} out of range", key, member)))?;
+      if owner.map(|o| depths[o] < depths[sg]).unwrap_or(true) {
+        *owner = Some(sg);
+      }
+    }
+  }
+  Ok(ret)
+}
+
+/// Parse the output of `dot -Tjson` or `dot -Tjson0`. See the [module
+/// docs](self).
+pub fn from_json(text: &str) -> Result<ParsedGraph, ImportError> {
+  let root: Value = serde_json::from_str(text).map_err(|e| ImportError::Syntax(e.to_string()))?;
+  let root = root
+    .as_object()
+    .ok_or_else(|| structure("the document must be an object"))?;
+
+  let objs = objects(root, "objects")?;
+  let subgraph_count = root
+    .get("_subgraph_cnt")
+    .and_then(Value::as_u64)
+    .unwrap_or(0) as usize;
+  if subgraph_count > objs.len() {
+    return Err(structure("\"_subgraph_cnt\" exceeds the number of objects"));
+  }
+  let (subgraph_objs, node_objs) = objs.split_at(subgraph_count);
+  let edge_objs = objects(root, "edges")?;
+
+  let mut parents: Vec<Option<usize>> = vec![None; subgraph_count];
+  for (sg, obj) in subgraph_objs.iter().enumerate() {
+    for child in indices(obj, "subgraphs")? {
+      *parents
+        .get_mut(child)
+        .ok_or_else(|| structure(format!("subgraph index {} out of range", child)))? = Some(sg);
+    }
+  }
+  let mut depths = vec![0; subgraph_count];
+  for (sg, depth) in depths.iter_mut().enumerate() {
+    let mut cur = sg;
+    while let Some(parent) = parents[cur] {
+      *depth += 1;
+      if *depth > subgraph_count {
+        return Err(structure("subgraphs contain each other"));
+      }
+      cur = parent;
+    }
+  }
+  /* Node indices count from the start of "objects", after the subgraphs. */
+  let node_owners = owners(subgraph_objs, &depths, "nodes", objs.len())?;
+  let edge_owners = owners(subgraph_objs, &depths, "edges", edge_objs.len())?;
+
+  /* Entities directly within each subgraph, with the top level last. */
+  let mut bodies: Vec<Vec<Entity>> = vec![Vec::new(); subgraph_count + 1];
+  let body = |owner: Option<usize>| owner.unwrap_or(subgraph_count);
+  for (i, obj) in node_objs.iter().enumerate() {
+    let i = subgraph_count + i;
+    let mut v = Vertex::new(name(obj)?);
+    v.attributes = attributes(obj, &["_gvid", "name"]);
+    bodies[body(node_owners[i])].push(Entity::Vertex(v));
+  }
+  let endpoint = |obj: &Map<String, Value>, key: &str| -> Result<Id, ImportError> {
+    let i = obj
+      .get(key)
+      .and_then(Value::as_u64)
+      .ok_or_else(|| structure(format!("every edge must have a {:?} index", key)))?
+      as usize;
+    match node_objs.get(i.wrapping_sub(subgraph_count)) {
+      Some(node) if i >= subgraph_count => name(node),
+      _ => Err(structure(format!("{:?} index {} is not a node", key, i))),
+    }
+  };
+  let mut edges: Vec<(usize, Entity)> = Vec::new();
+  for (i, obj) in edge_objs.iter().enumerate() {
+    let mut e = Edge::between(endpoint(obj, "tail")?, endpoint(obj, "head")?);
+    e.attributes = attributes(obj, &["_gvid", "tail", "head"]);
+    edges.push((body(edge_owners[i]), Entity::Edge(e)));
+  }
+
+  /* Assemble subgraphs innermost first, so each is complete before it is
+   * moved into its parent. */
+  let mut order: Vec<usize> = (0..subgraph_count).collect();
+  order.sort_by_key(|&sg| std::cmp::Reverse(depths[sg]));
+  let mut built: Vec<Option<Subgraph>> = vec![None; subgraph_count];
+  for &sg in order.iter() {
+    let obj = subgraph_objs[sg];
+    let mut entities = std::mem::take(&mut bodies[sg]);
+    for child in indices(obj, "subgraphs")? {
+      if let Some(child) = built[child].take() {
+        entities.push(Entity::Subgraph(child));
+      }
+    }
+    entities.extend(
+      edges
+        .iter()
+        .filter(|(owner, _)| *owner == sg)
+        .map(|(_, e)| e.clone()),
+    );
+    let id = name(obj)?;
+    let mut attributes = attributes(obj, &["_gvid", "name", "nodes", "edges", "subgraphs"]);
+    let is_cluster = match attributes.remove("cluster") {
+      Some(AttrValue::Quoted(v)) => v == "true",
+      _ => id.as_str().starts_with("cluster"),
+    };
+    /* Only keep the printer's own `rank = same` if the source had it. */
+    let same_rank = attributes.get("rank") == Some(&AttrValue::from("same"));
+    if same_rank {
+      attributes.remove("rank");
+    }
+    built[sg] = Some(Subgraph {
+      attributes,
+      entities,
+      ..Subgraph::new(id)
+        .cluster(is_cluster)
+        .same_rank(same_rank)
+    });
+  }
+
+  let mut entities = std::mem::take(&mut bodies[subgraph_count]);
+  for (sg, parent) in parents.iter().enumerate() {
+    if parent.is_none() {
+      if let Some(sg) = built[sg].take() {
+        entities.push(Entity::Subgraph(sg));
+      }
+    }
+  }
+  entities.extend(
+    edges
+      .into_iter()
+      .filter(|(owner, _)| *owner == subgraph_count)
+      .map(|(_, e)| e),
+  );
+
+  Ok(ParsedGraph {
+    strict: root.get("strict").and_then(Value::as_bool).unwrap_or(false),
+    directed: root
+      .get("directed")
+      .and_then(Value::as_bool)
+      .unwrap_or(true),
+    id: root.get("name").and_then(Value::as_str).map(Id::new),
+    attributes: attributes(root, &[
+      "name",
+      "directed",
+      "strict",
+      "_subgraph_cnt",
+      "objects",
+      "edges",
+    ]),
+    entities,
+  })
+}
+
+/// Read an edge list: an array of `[source, target, label?]` arrays or
+/// `{"source", "target", "label"?}` objects, whose endpoints are strings or
+/// numbers. As with [`from_csv`](super::csv::from_csv), a vertex is declared
+/// for each endpoint in the order they first appear, and labels are taken as
+/// plain text.
+pub fn from_json_edges(text: &str) -> Result<ParsedGraph, ImportError> {
+  let value: Value = serde_json::from_str(text).map_err(|e| ImportError::Syntax(e.to_string()))?;
+  let rows = value
+    .as_array()
+    .ok_or_else(|| structure("expected an array of edges"))?;
+
+  let mut edges = Vec::new();
+  for (i, row) in rows.iter().enumerate() {
+    let (source, target, label) = match row {
+      Value::Array(fields) => (fields.first(), fields.get(1), fields.get(2)),
+      Value::Object(fields) => (
+        fields.get("source"),
+        fields.get("target"),
+        fields.get("label"),
+      ),
+      _ => return Err(structure(format!("edge {}: expected an array or object", i))),
+    };
+    let endpoint = |v: Option<&Value>| -> Result<String, ImportError> {
+      match v {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(Value::Number(n)) => Ok(n.to_string()),
+        _ => Err(structure(format!("edge {}: missing source or target", i))),
+      }
+    };
+
+    let mut e = Edge::between(endpoint(source)?, endpoint(target)?);
+    match label {
+      None | Some(Value::Null) => (),
+      Some(Value::String(s)) => e = e.label(Label::escape_text(s).as_str()),
+      Some(v) => e = e.label(Label::escape_text(v.to_string()).as_str()),
+    }
+    edges.push(e);
+  }
+  Ok(edge_list(edges))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn json_edge_list() {
+    let graph =
+      from_json_edges(r#"[["a", "b"], {"source": "b", "target": 3, "label": "say \"hi\""}]"#)
+        .unwrap();
+    assert_eq!(graph.entities, vec![
+      Entity::Vertex(Vertex::new("a")),
+      Entity::Vertex(Vertex::new("b")),
+      Entity::Vertex(Vertex::new("3")),
+      Entity::Edge(Edge::between("a", "b")),
+      Entity::Edge(Edge::between("b", "3").label("say \\\"hi\\\"")),
+    ]);
+    assert!(matches!(
+      from_json_edges(r#"[["a"]]"#),
+      Err(ImportError::Structure(_))
+    ));
+  }
+
+  #[test]
+  fn nested_subgraphs() {
+    let graph = from_json(
+      r#"{
+        "name": "g", "directed": false, "strict": true, "_subgraph_cnt": 2,
+        "objects": [
+          {"_gvid": 0, "name": "cluster_outer", "label": "Outer", "nodes": [2, 3], "edges": [0], "subgraphs": [1]},
+          {"_gvid": 1, "name": "inner", "rank": "same", "nodes": [3], "edges": [0]},
+          {"_gvid": 2, "name": "a", "_draw_": [{"op": "c", "color": "black"}]},
+          {"_gvid": 3, "name": "b"},
+          {"_gvid": 4, "name": "c"}
+        ],
+        "edges": [
+          {"_gvid": 0, "tail": 3, "head": 3},
+          {"_gvid": 1, "tail": 2, "head": 4, "weight": "2"}
+        ]
+      }"#,
+    )
+    .unwrap();
+    assert!(graph.strict);
+    assert!(!graph.directed);
+
+    let inner = Subgraph {
+      entities: vec![
+        Entity::Vertex(Vertex::new("b")),
+        Entity::Edge(Edge::between("b", "b")),
+      ],
+      ..Subgraph::new("inner").cluster(false)
+    };
+    let outer = Subgraph {
+      entities: vec![
+        Entity::Vertex(Vertex::new("a").attr("_draw_", r#"[{"color":"black","op":"c"}]"#)),
+        Entity::Subgraph(inner),
+      ],
+      ..Subgraph::new("cluster_outer")
+        .same_rank(false)
+        .attr("label", "Outer")
+    };
+    assert_eq!(graph.entities, vec![
+      Entity::Vertex(Vertex::new("c")),
+      Entity::Subgraph(outer),
+      Entity::Edge(Edge::between("a", "c").attr("weight", "2")),
+    ]);
+  }
+
+  #[test]
+  fn invalid_documents() {
+    assert!(matches!(from_json("{"), Err(ImportError::Syntax(_))));
+    assert_eq!(
+      from_json(r#"{"objects": [{"name": "a"}], "edges": [{"tail": 0, "head": 1}]}"#),
+      Err(ImportError::Structure(
+        "\"head\" index 1 is not a node".to_string()
+      ))
+    );
+  }
+}
@@ -0,0 +1,77 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Readers producing entities from other graph formats.
+
+use crate::{
+  entities::{Attributes, Edge, Entity, Vertex},
+  parser::ParsedGraph,
+};
+
+use std::{collections::HashSet, fmt};
+
+pub mod csv;
+pub mod gml;
+#[cfg(feature = "graphml")]
+pub mod graphml;
+#[cfg(feature = "json")]
+pub mod json;
+
+/// Why a document could not be imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+  /// The document is not well-formed in its format.
+  Syntax(String),
+  /// The document is well-formed, but doesn't describe a graph the way the
+  /// format requires, e.g. an edge refers to a node which doesn't exist.
+  Structure(String),
+}
+
+impl fmt::Display for ImportError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Syntax(message) => write!(f, "syntax error: {}", message),
+      Self::Structure(message) => write!(f, "invalid graph: {}", message),
+    }
+  }
+}
+
+impl std::error::Error for ImportError {}
+
+/// A directed graph of `edges`, preceded by a vertex for each of their
+/// endpoints in the order they first appear.
+fn edge_list(edges: Vec<Edge>) -> ParsedGraph {
+  let mut seen = HashSet::new();
+  let mut entities = Vec::new();
+  for e in edges.iter() {
+    for id in [&e.source, &e.target] {
+      if seen.insert(id.clone()) {
+        entities.push(Entity::Vertex(Vertex::new(id.clone())));
+      }
+    }
+  }
+  entities.extend(edges.into_iter().map(Entity::Edge));
+  ParsedGraph {
+    strict: false,
+    directed: true,
+    id: None,
+    attributes: Attributes::new(),
+    entities,
+  }
+}
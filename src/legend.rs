@@ -0,0 +1,131 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Generate a key explaining the styles used in a graph.
+
+use crate::{entities::*, generator::GraphBuilder};
+
+/// A single row of a [`Legend`].
+#[derive(Debug, Clone)]
+pub enum Sample {
+  /// Draw a vertex with this styling, labelled with the description.
+  Node(Vertex),
+  /// Draw an edge with this styling between two points, labelled with the
+  /// description.
+  Edge(Edge),
+}
+
+/// A cluster of sample vertices and edges, each labelled with what their
+/// styling means.
+///
+/// Entries are laid out in the order they were added, chained together with
+/// invisible edges so graphviz doesn't reorder them.
+#[derive(Debug, Clone)]
+pub struct Legend {
+  prefix: String,
+  title: Label,
+  entries: Vec<(Label, Sample)>,
+}
+
+impl Legend {
+  /// Create an empty legend. `prefix` is used to generate the ids of every
+  /// entity in the legend, and should not collide with anything else in the
+  /// graph.
+  pub fn new<S: AsRef<str>>(prefix: S, title: Label) -> Self {
+    Self {
+      prefix: prefix.as_ref().to_string(),
+      title,
+      entries: Vec::new(),
+    }
+  }
+
+  /// Explain the styling of a vertex. Any id or label on `sample` is ignored.
+  pub fn add_node(&mut self, description: Label, sample: Vertex) {
+    self.entries.push((description, Sample::Node(sample)));
+  }
+
+  /// Explain the styling of an edge. Any endpoints or label on `sample` are
+  /// ignored.
+  pub fn add_edge(&mut self, description: Label, sample: Edge) {
+    self.entries.push((description, Sample::Edge(sample)));
+  }
+
+  fn endpoint(id: Id) -> Vertex {
+    Vertex {
+      shape: Some(Shape("point".to_string())),
+      ..Vertex::new(id)
+    }
+  }
+
+  /// Generate the cluster subgraph containing every entry.
+  pub fn into_subgraph(self) -> Subgraph {
+    let Self {
+      prefix,
+      title,
+      entries,
+    } = self;
+
+    let mut entities: Vec<Entity> = Vec::new();
+    /* The id of the rightmost vertex of the previous entry, which the next entry
+     * is chained to. */
+    let mut prev: Option<Id> = None;
+    for (i, (description, sample)) in entries.into_iter().enumerate() {
+      let (first, last) = match sample {
+        Sample::Node(vertex) => {
+          let id = Id::new(format!("{}_{}", prefix, i));
+          entities.push(Entity::Vertex(Vertex {
+            id: id.clone(),
+            label: Some(description),
+            html_label: None,
+            ..vertex
+          }));
+          (id.clone(), id)
+        },
+        Sample::Edge(edge) => {
+          let tail = Id::new(format!("{}_{}_tail", prefix, i));
+          let head = Id::new(format!("{}_{}_head", prefix, i));
+          entities.push(Entity::Vertex(Self::endpoint(tail.clone())));
+          entities.push(Entity::Vertex(Self::endpoint(head.clone())));
+          entities.push(Entity::Edge(Edge {
+            source: tail.clone(),
+            target: head.clone(),
+            label: Some(description),
+            ..edge
+          }));
+          (tail, head)
+        },
+      };
+      if let Some(prev) = prev.take() {
+        entities.push(Entity::Edge(Edge::invisible(prev, first)));
+      }
+      prev = Some(last);
+    }
+
+    Subgraph {
+      label: Some(title),
+      entities,
+      ..Subgraph::new(Id::new(format!("cluster_{}", prefix)))
+    }
+  }
+
+  /// Add the legend to the top level of `graph`.
+  pub fn append_to(self, graph: &mut GraphBuilder) {
+    graph.accept_entity(Entity::Subgraph(self.into_subgraph()));
+  }
+}
@@ -27,7 +27,8 @@
  * pair of delimiters)
  * Note: run clippy with: rustup run nightly cargo-clippy! */
 #![warn(missing_docs)]
-/* There should be no need to use unsafe code here! */
+/* There should be no need to use unsafe code here, outside the C API and the
+ * bindings to Graphviz! */
 #![deny(unsafe_code)]
 /* Ensure any doctest warnings fails the doctest! */
 #![doc(test(attr(deny(warnings))))]
@@ -48,7 +49,7 @@
 #![allow(clippy::match_ref_pats)]
 /* Subjective style. */
 #![allow(
-  clippy::derive_hash_xor_eq,
+  clippy::derived_hash_with_manual_eq,
   clippy::len_without_is_empty,
   clippy::redundant_field_names,
   clippy::too_many_arguments
@@ -60,396 +61,6485 @@
 
 /// [`Entity`](entities::Entity) defines all the top-level objects we know how to represent in a
 /// `.dot` file.
+///
+/// With the `serde` feature, entities implement `Serialize` and `Deserialize`,
+/// so graph definitions can be loaded from configuration at runtime. Plain
+/// [`Id`](entities::Id)s are stored as strings, and HTML ids as
+/// `{"html": "..."}`. The `schemars` feature additionally derives a
+/// `JsonSchema` describing that representation, e.g. to validate graph
+/// definitions received by a service.
 pub mod entities {
   /// Structs used to configure the presentation of objects.
   pub mod style {
     /// Text to display on or next to the object.
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
     pub struct Label(pub String);
 
+    impl Label {
+      /// Escape backslashes and double quotes so that `s` is displayed as
+      /// plain text, rather than interpreted as escape sequences like `\n`.
+      pub fn escape_text<S: AsRef<str>>(s: S) -> String {
+        let s = s.as_ref();
+        let mut ret = String::with_capacity(s.len());
+        for c in s.chars() {
+          if matches!(c, '\\' | '"') {
+            ret.push('\\');
+          }
+          ret.push(c);
+        }
+        ret
+      }
+
+      /// Undo [`Self::escape_text`], leaving any other escape sequences such
+      /// as `\n` as they are.
+      pub fn unescape_text<S: AsRef<str>>(s: S) -> String {
+        let s = s.as_ref();
+        let mut ret = String::with_capacity(s.len());
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+          match (c, chars.peek()) {
+            ('\\', Some(&next)) if matches!(next, '\\' | '"') => {
+              ret.push(next);
+              chars.next();
+            },
+            _ => ret.push(c),
+          }
+        }
+        ret
+      }
+    }
+
+    /// An [HTML-like label](https://www.graphviz.org/doc/info/shapes.html#html), emitted within
+    /// `<...>` instead of double quotes.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct HtmlLabel(pub String);
+
+    impl HtmlLabel {
+      /// Escape characters so that `s` is displayed as plain text within an
+      /// HTML label.
+      pub fn escape_text<S: AsRef<str>>(s: S) -> String {
+        let s = s.as_ref();
+        let mut ret = String::with_capacity(s.len());
+        for c in s.chars() {
+          match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            c => ret.push(c),
+          }
+        }
+        ret
+      }
+    }
+
+    /// The name of a [port](https://www.graphviz.org/docs/attr-types/portPos/) on a vertex,
+    /// such as a `PORT` within an HTML label.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct Port(pub String);
+
     /// An [HTML color name](https://en.wikipedia.org/wiki/Web_colors#Extended_colors).
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
     pub struct Color(pub String);
 
+    impl Color {
+      /// Construct a color from any name or `#rrggbb` string.
+      pub fn new<S: AsRef<str>>(s: S) -> Self { Self(s.as_ref().to_string()) }
+
+      #[allow(missing_docs)]
+      pub fn black() -> Self { Self::new("black") }
+
+      #[allow(missing_docs)]
+      pub fn white() -> Self { Self::new("white") }
+
+      #[allow(missing_docs)]
+      pub fn gray() -> Self { Self::new("gray") }
+
+      #[allow(missing_docs)]
+      pub fn red() -> Self { Self::new("red") }
+
+      #[allow(missing_docs)]
+      pub fn orange() -> Self { Self::new("orange") }
+
+      #[allow(missing_docs)]
+      pub fn yellow() -> Self { Self::new("yellow") }
+
+      #[allow(missing_docs)]
+      pub fn green() -> Self { Self::new("green") }
+
+      #[allow(missing_docs)]
+      pub fn blue() -> Self { Self::new("blue") }
+
+      #[allow(missing_docs)]
+      pub fn purple() -> Self { Self::new("purple") }
+    }
+
+    /// A [node shape](https://www.graphviz.org/doc/info/shapes.html) such as `box` or `ellipse`.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct Shape(pub String);
+
+    /// The name of a font family, as understood by the renderer.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct FontName(pub String);
+
+    /// A font size, in points.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct FontSize(pub f64);
+
+    /// A [style](https://www.graphviz.org/docs/attr-types/style/) such as `dashed` or `filled`.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct Style(pub String);
+
+    impl Style {
+      /// Hide the entity, while still taking it into account for layout.
+      pub fn invis() -> Self { Self("invis".to_string()) }
+    }
+
+    /// An [arrow shape](https://www.graphviz.org/docs/attr-types/arrowType/) such as `empty` or
+    /// `diamond`.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct ArrowType(pub String);
+
+    /// Which ends of an edge [get arrows](https://www.graphviz.org/docs/attr-types/dirType/):
+    /// `forward`, `back`, `both`, or `none`.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct DirType(pub String);
+
+    /// Whether a vertex's [size is fixed](https://www.graphviz.org/docs/attrs/fixedsize/) by its
+    /// `width` and `height` instead of growing to fit its label.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub enum FixedSize {
+      #[allow(missing_docs)]
+      Enabled(bool),
+      /// Only the shape is fixed in size; the label may extend past it.
+      Shape,
+    }
+
+    impl FixedSize {
+      pub(crate) fn format(&self) -> String {
+        match self {
+          Self::Enabled(b) => format!("{}", b),
+          Self::Shape => "shape".to_string(),
+        }
+      }
+    }
+
+    /// Parameters for a [`shape = polygon`](https://www.graphviz.org/doc/info/shapes.html#polygon)
+    /// vertex.
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct Polygon {
+      /// The number of sides, from 3 to 100.
+      pub sides: Option<u32>,
+      /// Positive values make the top wider than the bottom, negative values
+      /// the opposite.
+      pub skew: Option<f64>,
+      /// Positive values shift the top of the polygon to the right, negative
+      /// values to the left.
+      pub distortion: Option<f64>,
+      /// Rotation in degrees, clockwise.
+      pub orientation: Option<f64>,
+      /// Force the polygon to be regular, i.e. to have equal width and height.
+      pub regular: Option<bool>,
+    }
+
+    /// An external file drawn as the shape of a vertex.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub enum ShapeFile {
+      /// An image file, drawn with `shape = custom`.
+      Custom(std::path::PathBuf),
+      /// An encapsulated PostScript file, drawn with `shape = epsf`. Only
+      /// supported for PostScript output.
+      Epsf(std::path::PathBuf),
+    }
+
+    impl ShapeFile {
+      pub(crate) fn shape(&self) -> &'static str {
+        match self {
+          Self::Custom(_) => "custom",
+          Self::Epsf(_) => "epsf",
+        }
+      }
+
+      pub(crate) fn path(&self) -> &std::path::Path {
+        match self {
+          Self::Custom(p) => p,
+          Self::Epsf(p) => p,
+        }
+      }
+    }
+
     /// Default values to set for styling vertices using
     /// [`node [name0=val0]`](https://www.graphviz.org/docs/nodes/).
-    #[derive(Debug, Clone, Default)]
+    #[derive(Debug, Clone, Default, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
     #[allow(missing_docs)]
     pub struct NodeDefaults {
       pub color: Option<Color>,
       pub fontcolor: Option<Color>,
+      pub fixedsize: Option<FixedSize>,
+      /// In inches.
+      pub width: Option<f64>,
+      /// In inches.
+      pub height: Option<f64>,
+      /// The number of outlines drawn around the shape.
+      pub peripheries: Option<u32>,
     }
-  }
-  pub use style::*;
 
-  /// The key used to reference a vertex in a `.dot` file.
-  #[derive(Debug, Hash, PartialEq, Eq, Clone)]
-  pub struct Id(String);
-
-  impl Id {
-    /// Construct an ID from any string.
-    pub fn new<S: AsRef<str>>(s: S) -> Self { Self(s.as_ref().to_string()) }
+    /// Separation between ranks, in inches.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct RankSep {
+      #[allow(missing_docs)]
+      pub inches: f64,
+      /// Space all ranks equally far apart, instead of only using `inches` as
+      /// the minimum.
+      pub equally: bool,
+    }
 
-    /// Add double quotes around this string if needed to form a valid ID for a
-    /// [DOT language](https://www.graphviz.org/doc/info/lang.html) document.
-    ///
-    /// [`GraphBuilder`](super::generator::GraphBuilder) uses this method to
-    /// generate more readable documents by avoiding quotations unless
-    /// necessary.
-    pub fn maybe_escaped(self) -> String {
-      use lazy_static::lazy_static;
-      use regex::RegexSet;
+    impl RankSep {
+      pub(crate) fn format(&self) -> String {
+        let Self { inches, equally } = self;
+        if *equally {
+          format!("{} equally", inches)
+        } else {
+          format!("{}", inches)
+        }
+      }
+    }
 
-      static ALPHA_ID: &str = "[a-zA-Z_\\\\200-\\\\377][a-zA-Z_\\\\200-\\\\3770-9]*";
-      static NUMERAL_ID: &str = "[-]?(.[0-9]+|[0-9]+(.[0-9]*)?)";
+    /// A [point or
+    /// double](https://www.graphviz.org/docs/attr-types/point/) used for spacing, in inches.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub enum Padding {
+      /// The same amount on both axes.
+      Uniform(f64),
+      /// Separate horizontal and vertical amounts.
+      XY(f64, f64),
+    }
 
-      lazy_static! {
-        static ref UNQUOTED_IDS: RegexSet = RegexSet::new(&[ALPHA_ID, NUMERAL_ID]).unwrap();
+    impl Padding {
+      pub(crate) fn format(&self) -> String {
+        match self {
+          Self::Uniform(d) => format!("{}", d),
+          Self::XY(x, y) => format!("{},{}", x, y),
+        }
       }
+    }
 
-      let Self(s) = self;
-      if UNQUOTED_IDS.is_match(&s) {
-        s
-      } else {
-        /* Add double quotes around this string and escape any
-         * internal double quotes. */
-        format!("{:?}", s)
+    /// The maximum (or, with `fill`, exact) size of the drawing, in inches.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub struct Size {
+      #[allow(missing_docs)]
+      pub width: f64,
+      #[allow(missing_docs)]
+      pub height: f64,
+      /// Scale the drawing up until one dimension matches, instead of only
+      /// scaling it down.
+      pub fill: bool,
+    }
+
+    impl Size {
+      pub(crate) fn format(&self) -> String {
+        let Self {
+          width,
+          height,
+          fill,
+        } = self;
+        format!("{},{}{}", width, height, if *fill { "!" } else { "" })
       }
     }
-  }
 
+    /// How to fit the drawing to its [`Size`].
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub enum Ratio {
+      /// Scale to exactly this height/width ratio.
+      Numeric(f64),
+      /// Scale independently on each axis to fill the size.
+      Fill,
+      /// Compress the initial layout to fit the size.
+      Compress,
+      /// Scale up uniformly until one dimension matches the size.
+      Expand,
+      /// Pick `Fill` or `Compress` depending on the drawing.
+      Auto,
+    }
 
-  #[derive(Debug, Clone)]
-  pub struct Vertex {
-    pub id: Id,
-    pub label: Option<Label>,
-    pub color: Option<Color>,
-    pub fontcolor: Option<Color>,
-  }
+    impl Ratio {
+      pub(crate) fn format(&self) -> String {
+        match self {
+          Self::Numeric(r) => format!("{}", r),
+          Self::Fill => "fill".to_string(),
+          Self::Compress => "compress".to_string(),
+          Self::Expand => "expand".to_string(),
+          Self::Auto => "auto".to_string(),
+        }
+      }
+    }
 
-  impl Default for Vertex {
-    fn default() -> Self {
-      use uuid::Uuid;
+    /// Whether and how to [pack](https://www.graphviz.org/docs/attrs/pack/) disconnected
+    /// components separately.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub enum Pack {
+      /// Lay out each component separately and pack them together, or not.
+      Enabled(bool),
+      /// Pack components, leaving this margin between them in points.
+      Margin(u32),
+    }
 
-      let id = Id::new(Uuid::new_v4().to_string());
-      Self {
-        id,
-        label: None,
-        color: None,
-        fontcolor: None,
+    impl Pack {
+      pub(crate) fn format(&self) -> String {
+        match self {
+          Self::Enabled(b) => format!("{}", b),
+          Self::Margin(m) => format!("{}", m),
+        }
       }
     }
-  }
 
-  #[derive(Debug, Clone)]
-  pub enum Entity {
-    Subgraph(Subgraph),
-    Vertex(Vertex),
-    Edge(Edge),
-  }
+    /// The granularity at which components are
+    /// [packed](https://www.graphviz.org/docs/attr-types/packMode/).
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub enum PackMode {
+      /// Pack each vertex and edge tightly.
+      Node,
+      /// Pack each top-level cluster tightly.
+      Clust,
+      /// Pack each component's bounding box.
+      Graph,
+      /// Place components in a grid.
+      Array {
+        /// Fill the grid column by column instead of row by row.
+        column_major: bool,
+        /// Order components by their `sortv` attribute instead of by size.
+        user_order: bool,
+        /// The number of columns (or rows, if `column_major`).
+        count: Option<u32>,
+      },
+    }
 
-  #[derive(Debug, Clone)]
-  pub struct Subgraph {
-    pub id: Id,
-    pub label: Option<Label>,
-    pub color: Option<Color>,
-    pub fontcolor: Option<Color>,
-    pub node_defaults: Option<NodeDefaults>,
-    pub entities: Vec<Entity>,
-  }
+    impl PackMode {
+      pub(crate) fn format(&self) -> String {
+        match self {
+          Self::Node => "node".to_string(),
+          Self::Clust => "clust".to_string(),
+          Self::Graph => "graph".to_string(),
+          Self::Array {
+            column_major,
+            user_order,
+            count,
+          } => {
+            let mut ret = "array".to_string();
+            if *column_major || *user_order {
+              ret.push('_');
+              if *column_major {
+                ret.push('c');
+              }
+              if *user_order {
+                ret.push('u');
+              }
+            }
+            if let Some(count) = count {
+              ret.push_str(&format!("{}", count));
+            }
+            ret
+          },
+        }
+      }
+    }
 
-  impl Default for Subgraph {
-    fn default() -> Self {
-      use uuid::Uuid;
+    /// The order in which entities are
+    /// [drawn](https://www.graphviz.org/docs/attr-types/outputMode/).
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+    pub enum OutputOrder {
+      /// Draw each component in turn.
+      BreadthFirst,
+      /// Draw all vertices, then all edges.
+      NodesFirst,
+      /// Draw all edges, then all vertices.
+      EdgesFirst,
+    }
 
-      /* TODO: make this a utility method! */
-      let id = Id::new(Uuid::new_v4().to_string());
-      Self {
-        id,
-        label: None,
-        color: None,
-        fontcolor: None,
-        node_defaults: None,
-        entities: Vec::new(),
+    impl OutputOrder {
+      pub(crate) fn format(&self) -> &'static str {
+        match self {
+          Self::BreadthFirst => "breadthfirst",
+          Self::NodesFirst => "nodesfirst",
+          Self::EdgesFirst => "edgesfirst",
+        }
       }
     }
+
+    /// Attributes applying to the whole graph, set with
+    /// [`graph [name0=val0]`](https://www.graphviz.org/docs/graph/).
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct GraphAttributes {
+      /// The color of the canvas behind the graph.
+      pub bgcolor: Option<Color>,
+      /// Merge parallel edges into shared splines.
+      pub concentrate: Option<bool>,
+      /// Rank vertices globally instead of one cluster at a time. This is
+      /// necessary for the `rank = same` constraint on each
+      /// [`Subgraph`](super::Subgraph) to behave sanely when clusters are
+      /// nested or edges cross between them.
+      pub newrank: Option<bool>,
+      /// Minimum space between adjacent vertices in the same rank, in inches.
+      pub nodesep: Option<f64>,
+      /// Minimum space between ranks.
+      pub ranksep: Option<RankSep>,
+      /// Space between the drawing and the edge of the canvas.
+      pub margin: Option<Padding>,
+      /// Space added around the drawing when computing its size.
+      pub pad: Option<Padding>,
+      #[allow(missing_docs)]
+      pub size: Option<Size>,
+      #[allow(missing_docs)]
+      pub ratio: Option<Ratio>,
+      /// Pixels per inch for bitmap output formats.
+      pub dpi: Option<f64>,
+      /// The names of each layer, which can be rendered separately. Names
+      /// may not contain any of the characters in `layersep`.
+      pub layers: Option<Vec<String>>,
+      /// The characters separating layer names in `layers` and `layer`. The
+      /// first character is used to join `layers`. Defaults to `":\t "`.
+      pub layersep: Option<String>,
+      /// Only render entities in these layers, e.g. `"a:c"` or `"b"`.
+      pub layerselect: Option<String>,
+      #[allow(missing_docs)]
+      pub pack: Option<Pack>,
+      #[allow(missing_docs)]
+      pub packmode: Option<PackMode>,
+      #[allow(missing_docs)]
+      pub outputorder: Option<OutputOrder>,
+      /// Use a layout engine other than `dot`, along with attributes specific
+      /// to it.
+      pub engine: Option<crate::engines::EngineAttributes>,
+      /// Attributes without a dedicated field, emitted after all the others.
+      pub attributes: super::Attributes,
+    }
   }
+  pub use style::*;
 
-  #[derive(Debug, Clone)]
-  pub struct Edge {
-    pub source: Id,
-    pub target: Id,
-    pub label: Option<Label>,
-    pub color: Option<Color>,
-    pub fontcolor: Option<Color>,
+  use std::{borrow::Cow, collections::BTreeMap, fmt, sync::Arc};
+
+  /// The key used to reference a vertex in a `.dot` file.
+  ///
+  /// Backed by a reference-counted string, so cloning is cheap.
+  #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+  pub struct Id {
+    text: Arc<str>,
+    html: bool,
   }
 
-  impl Default for Edge {
-    fn default() -> Self {
-      Self {
-        source: Id::new(""),
-        target: Id::new(""),
-        label: None,
-        color: None,
-        fontcolor: None,
+  impl From<&str> for Id {
+    fn from(s: &str) -> Self { Self::new(s) }
+  }
+
+  /// Plain ids are stored as strings, and HTML ids as `{"html": "..."}`.
+  #[cfg(feature = "serde")]
+  #[derive(serde::Serialize, serde::Deserialize)]
+  #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+  #[serde(untagged)]
+  enum IdRepr<S> {
+    Text(S),
+    Html { html: S },
+  }
+
+  #[cfg(feature = "serde")]
+  impl serde::Serialize for Id {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+      let text: &str = &self.text;
+      if self.html {
+        IdRepr::Html { html: text }.serialize(serializer)
+      } else {
+        IdRepr::Text(text).serialize(serializer)
       }
     }
   }
-}
 
-pub mod generator {
-  use super::entities::*;
+  #[cfg(feature = "schemars")]
+  impl schemars::JsonSchema for Id {
+    fn schema_name() -> Cow<'static, str> { "Id".into() }
 
-  #[derive(Debug, Hash, PartialEq, Eq, Clone)]
-  pub struct DotOutput(pub String);
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+      IdRepr::<String>::json_schema(generator)
+    }
+  }
 
-  pub struct GraphBuilder {
-    entities: Vec<Entity>,
+  #[cfg(feature = "serde")]
+  impl<'de> serde::Deserialize<'de> for Id {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+      Ok(match IdRepr::<String>::deserialize(deserializer)? {
+        IdRepr::Text(s) => Self::from(s),
+        IdRepr::Html { html } => Self::html(html),
+      })
+    }
   }
 
-  impl GraphBuilder {
-    pub fn new() -> Self {
+  impl From<String> for Id {
+    fn from(s: String) -> Self {
       Self {
-        entities: Vec::new(),
+        text: s.into(),
+        html: false,
       }
     }
+  }
 
-    pub fn accept_entity(&mut self, e: Entity) { self.entities.push(e); }
+  /// Shares `s` without copying it, so ids can be built from an existing
+  /// in-memory model which already stores its keys as `Arc<str>`.
+  impl From<Arc<str>> for Id {
+    fn from(s: Arc<str>) -> Self {
+      Self {
+        text: s,
+        html: false,
+      }
+    }
+  }
 
-    fn newline(output: &mut String) { output.push('\n'); }
+  impl From<&Arc<str>> for Id {
+    fn from(s: &Arc<str>) -> Self { Arc::clone(s).into() }
+  }
 
-    fn newline_indent(output: &mut String, indent: usize) {
-      Self::newline(output);
-      for _ in 0..indent {
-        output.push(' ');
+  impl From<Cow<'_, str>> for Id {
+    fn from(s: Cow<'_, str>) -> Self {
+      match s {
+        Cow::Borrowed(s) => Self::new(s),
+        Cow::Owned(s) => s.into(),
       }
     }
+  }
 
-    fn bump_indent(indent: &mut usize) { *indent += 2; }
+  impl AsRef<str> for Id {
+    fn as_ref(&self) -> &str { &self.text }
+  }
 
-    fn unbump_indent(indent: &mut usize) {
-      assert!(*indent >= 2);
-      *indent -= 2;
+  impl Id {
+    /// Construct an ID from any string.
+    pub fn new<S: AsRef<str>>(s: S) -> Self {
+      Self {
+        text: s.as_ref().into(),
+        html: false,
+      }
     }
 
-    fn print_entity(entity: Entity, mut indent: usize) -> String {
-      match entity {
-        Entity::Vertex(Vertex {
-          id,
-          label,
-          color,
-          fontcolor,
-        }) => {
-          let mut output = id.maybe_escaped();
-
-          let mut modifiers: Vec<String> = Vec::new();
-          if let Some(Label(label)) = label {
-            modifiers.push(format!("label=\"{}\"", label));
-          }
-          if let Some(Color(color)) = color {
-            modifiers.push(format!("color=\"{}\"", color));
-          }
-          if let Some(Color(fontcolor)) = fontcolor {
-            modifiers.push(format!("fontcolor=\"{}\"", fontcolor));
-          }
+    /// An [HTML string](https://www.graphviz.org/doc/info/lang.html#html),
+    /// emitted verbatim within `<...>` instead of double quotes.
+    pub fn html<S: AsRef<str>>(s: S) -> Self {
+      Self {
+        text: s.as_ref().into(),
+        html: true,
+      }
+    }
 
-          if !modifiers.is_empty() {
-            output.push('[');
+    /// Whether this id was created with [`Self::html`].
+    pub fn is_html(&self) -> bool { self.html }
 
-            for m in modifiers.into_iter() {
-              output.push_str(format!("{}, ", m).as_str());
-            }
+    /// The unescaped text of this id, without any `<...>` for HTML strings.
+    pub fn as_str(&self) -> &str { &self.text }
 
-            output.push(']');
+    /// Map an arbitrary string (a path, a URL, a qualified symbol like
+    /// `a::b<C>`) to an id which never needs quoting.
+    ///
+    /// Runs of characters other than ASCII letters, digits, and `_` become a
+    /// single `_`. If anything had to be replaced, a short hash of the original
+    /// string is appended, so distinct inputs which mangle to the same text
+    /// still get distinct ids. The result is stable across runs and platforms.
+    ///
+    ///```
+    /// use graphvizier::entities::Id;
+    ///
+    /// assert_eq!(Id::sanitized("foo_bar").as_str(), "foo_bar");
+    /// let a = Id::sanitized("std::vec::Vec<T>");
+    /// let b = Id::sanitized("std::vec::Vec[T]");
+    /// assert!(a.as_str().starts_with("std_vec_Vec_T_"));
+    /// assert_ne!(a, b);
+    /// ```
+    pub fn sanitized<S: AsRef<str>>(s: S) -> Self {
+      let s = s.as_ref();
+      let mut out = String::with_capacity(s.len() + 9);
+      let mut changed = false;
+      let mut last_was_replaced = false;
+      for c in s.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+          out.push(c);
+          last_was_replaced = false;
+        } else {
+          changed = true;
+          if !last_was_replaced {
+            out.push('_');
           }
+          last_was_replaced = true;
+        }
+      }
+      if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        changed = true;
+        out.insert(0, '_');
+      }
+      if changed {
+        /* 32-bit FNV-1a: unlike std's hasher, it is stable across releases. */
+        let hash = s.bytes().fold(0x811c9dc5_u32, |h, b| {
+          (h ^ b as u32).wrapping_mul(0x01000193)
+        });
+        if !out.ends_with('_') {
+          out.push('_');
+        }
+        out.push_str(&format!("{:08x}", hash));
+      }
+      out.into()
+    }
 
-          output.push(';');
+    /// A random v4 UUID.
+    pub fn random() -> Self { uuid::Uuid::new_v4().to_string().into() }
 
-          output
-        },
-        Entity::Edge(Edge {
-          source,
-          target,
-          label,
-          color,
-          fontcolor,
-        }) => {
-          let mut output = format!("{} -> {}", source.maybe_escaped(), target.maybe_escaped());
+    /// Prepend `prefix` to this id.
+    pub fn prefixed(&self, prefix: &str) -> Self {
+      Self {
+        text: format!("{}{}", prefix, self.text).into(),
+        html: self.html,
+      }
+    }
 
-          let mut modifiers: Vec<String> = Vec::new();
-          if let Some(Label(label)) = label {
-            modifiers.push(format!("label=\"{}\"", label));
-          }
-          if let Some(Color(color)) = color {
-            modifiers.push(format!("color=\"{}\"", color));
-          }
-          if let Some(Color(fontcolor)) = fontcolor {
-            modifiers.push(format!("fontcolor=\"{}\"", fontcolor));
-          }
+    /// Add double quotes around this string if needed to form a valid ID for a
+    /// [DOT language](https://www.graphviz.org/doc/info/lang.html) document.
+    ///
+    /// [`GraphBuilder`](super::generator::GraphBuilder) uses this method to
+    /// generate more readable documents by avoiding quotations unless
+    /// necessary.
+    pub fn maybe_escaped(self) -> String { self.escaped(QuotingPolicy::Minimal) }
 
-          if !modifiers.is_empty() {
-            output.push('[');
+    /// Format this id for a DOT document, adding double quotes according to
+    /// `policy`. HTML strings are always emitted within `<...>`.
+    pub fn escaped(self, policy: QuotingPolicy) -> String {
+      let mut ret = String::with_capacity(self.text.len() + 2);
+      self
+        .write_escaped(&mut ret, policy)
+        .expect("writing to a String cannot fail");
+      ret
+    }
 
-            for m in modifiers.into_iter() {
-              output.push_str(format!("{}, ", m).as_str());
-            }
+    /// Write [`Self::escaped`] to `w` without allocating.
+    pub(crate) fn write_escaped<W: fmt::Write>(
+      &self,
+      w: &mut W,
+      policy: QuotingPolicy,
+    ) -> fmt::Result {
+      let Self { text: s, html } = self;
+      if *html {
+        return write!(w, "<{}>", s);
+      }
+      write_escaped_text(w, s, policy)
+    }
 
-            output.push(']');
+    /// Whether `token` lexes as a single DOT ID, checked character by
+    /// character independently of the regexes used by [`Self::escaped`].
+    pub fn is_valid_token(token: &str) -> bool {
+      let alpha = |c: char| c.is_ascii_alphabetic() || c == '_' || !c.is_ascii();
+      let mut chars = token.chars();
+      match chars.next() {
+        None => false,
+        Some('<') => {
+          let mut depth = 1_usize;
+          for c in chars {
+            if depth == 0 {
+              return false;
+            }
+            match c {
+              '<' => depth += 1,
+              '>' => depth -= 1,
+              _ => (),
+            }
           }
-
-          output.push(';');
-
-          output
+          depth == 0
         },
-        Entity::Subgraph(Subgraph {
-          id,
-          label,
-          color,
-          fontcolor,
-          node_defaults,
-          entities,
-        }) => {
-          let mut output = format!("subgraph {} {{", id.maybe_escaped());
-          Self::bump_indent(&mut indent);
-
-          Self::newline_indent(&mut output, indent);
-          if let Some(Label(label)) = label {
-            output.push_str(format!("label = \"{}\";", label).as_str());
-            Self::newline_indent(&mut output, indent);
-          }
-          output.push_str("cluster = true;");
-          Self::newline_indent(&mut output, indent);
-          output.push_str("rank = same;");
-          Self::newline(&mut output);
-
-          if let Some(Color(color)) = color {
-            Self::newline_indent(&mut output, indent);
-            output.push_str(format!("color = \"{}\";", color).as_str());
-          }
-          if let Some(Color(fontcolor)) = fontcolor {
-            Self::newline_indent(&mut output, indent);
-            output.push_str(format!("fontcolor = \"{}\";", fontcolor).as_str());
-          }
-          if let Some(NodeDefaults { color, fontcolor }) = node_defaults {
-            let mut modifiers: Vec<String> = Vec::new();
-            if let Some(Color(color)) = color {
-              modifiers.push(format!("color=\"{}\"", color));
+        Some('"') => {
+          let mut escaped = false;
+          let mut closed = false;
+          for c in chars {
+            if closed {
+              return false;
             }
-            if let Some(Color(fontcolor)) = fontcolor {
-              modifiers.push(format!("fontcolor=\"{}\"", fontcolor));
+            match c {
+              _ if escaped => escaped = false,
+              '\\' => escaped = true,
+              '"' => closed = true,
+              _ => (),
             }
-            if !modifiers.is_empty() {
-              Self::newline_indent(&mut output, indent);
-              output.push_str("node [");
-              for m in modifiers.into_iter() {
-                output.push_str(format!("{}, ", m).as_str());
-              }
-              output.push_str("];")
-            }
-          }
-          Self::newline(&mut output);
-
-          for e in entities.into_iter() {
-            Self::newline_indent(&mut output, indent);
-            let expr = Self::print_entity(e, indent);
-            output.push_str(expr.as_str());
           }
-
-          Self::unbump_indent(&mut indent);
-          Self::newline_indent(&mut output, indent);
-          output.push('}');
-
-          output
+          closed
+        },
+        Some(c) if alpha(c) => {
+          let is_keyword = ["node", "edge", "graph", "digraph", "subgraph", "strict"]
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(token));
+          !is_keyword && chars.all(|c| alpha(c) || c.is_ascii_digit())
+        },
+        Some(_) => {
+          let digits = token.strip_prefix('-').unwrap_or(token);
+          let (int, frac) = match digits.split_once('.') {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (digits, None),
+          };
+          let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+          all_digits(int)
+            && frac.map(all_digits).unwrap_or(true)
+            && (!int.is_empty() || frac.map(|f| !f.is_empty()).unwrap_or(false))
         },
       }
     }
+  }
 
-    pub fn build(self, graph_name: Id) -> DotOutput {
-      let mut output: String = String::new();
-      let mut indent: usize = 0;
+  /// Write `s` as a DOT ID, adding double quotes according to `policy`.
+  pub(crate) fn write_escaped_text<W: fmt::Write>(
+    w: &mut W,
+    s: &str,
+    policy: QuotingPolicy,
+  ) -> fmt::Result {
+    use lazy_static::lazy_static;
+    use regex::Regex;
 
-      output.push_str(format!("digraph {} {{", graph_name.maybe_escaped()).as_str());
-      Self::bump_indent(&mut indent);
+    lazy_static! {
+      /* Letters, digits, and underscores, not beginning with a digit. The
+       * spec's \200-\377 covers every byte of a multibyte UTF-8 sequence,
+       * i.e. any non-ASCII char. */
+      static ref ALPHA_ID: Regex =
+        Regex::new(r"^[a-zA-Z_\x{80}-\x{10FFFF}][a-zA-Z_0-9\x{80}-\x{10FFFF}]*$").unwrap();
+      static ref NUMERAL_ID: Regex = Regex::new(r"^-?(\.[0-9]+|[0-9]+(\.[0-9]*)?)$").unwrap();
+    }
 
-      Self::newline_indent(&mut output, indent);
-      output.push_str("compound = true;");
+    /* These are keywords in any case, and can only be used as IDs when
+     * quoted. */
+    static KEYWORDS: [&str; 6] = ["node", "edge", "graph", "digraph", "subgraph", "strict"];
 
-      for entity in self.entities.into_iter() {
-        Self::newline(&mut output);
-        Self::newline_indent(&mut output, indent);
+    let is_numeral = NUMERAL_ID.is_match(s);
+    let unquoted = match policy {
+      QuotingPolicy::Minimal => {
+        is_numeral || (ALPHA_ID.is_match(s) && !KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(s)))
+      },
+      QuotingPolicy::Always => false,
+      QuotingPolicy::AlwaysExceptNumerals => is_numeral,
+    };
+    if unquoted {
+      w.write_str(s)
+    } else {
+      /* Add double quotes around this string and escape any
+       * internal double quotes. */
+      write!(w, "{:?}", s)
+    }
+  }
 
-        let expr = Self::print_entity(entity, indent);
-        output.push_str(expr.as_str());
-      }
+  /// When to add double quotes around an [`Id`] in a DOT document.
+  #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+  pub enum QuotingPolicy {
+    /// Only quote ids which would be invalid otherwise.
+    #[default]
+    Minimal,
+    /// Quote every id.
+    Always,
+    /// Quote every id except numerals like `1` or `-2.5`.
+    AlwaysExceptNumerals,
+  }
 
-      Self::unbump_indent(&mut indent);
-      assert_eq!(indent, 0);
-      Self::newline_indent(&mut output, indent);
-      output.push('}');
-      Self::newline(&mut output);
+  /// The value of an attribute set through an [`Attributes`] map.
+  #[derive(Debug, Clone, PartialEq)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+  #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+  pub enum AttrValue {
+    /// Emitted within double quotes, with any internal double quotes escaped.
+    /// Escape sequences like `\n` are kept, but a trailing backslash is
+    /// escaped so it can't end the string early.
+    Quoted(String),
+    /// An [HTML string](https://www.graphviz.org/doc/info/lang.html#html),
+    /// emitted verbatim within `<...>`.
+    Html(String),
+  }
 
-      DotOutput(output)
+  impl AttrValue {
+    pub(crate) fn format(&self) -> String {
+      match self {
+        Self::Quoted(s) => {
+          let mut ret = String::with_capacity(s.len() + 2);
+          ret.push('"');
+          let mut chars = s.chars();
+          while let Some(c) = chars.next() {
+            match c {
+              '\\' => match chars.next() {
+                Some(next) => {
+                  ret.push('\\');
+                  ret.push(next);
+                },
+                None => ret.push_str("\\\\"),
+              },
+              '"' => ret.push_str("\\\""),
+              c => ret.push(c),
+            }
+          }
+          ret.push('"');
+          ret
+        },
+        Self::Html(s) => format!("<{}>", s),
+      }
     }
   }
 
-  #[cfg(test)]
-  mod test {
-    use super::*;
+  impl From<&str> for AttrValue {
+    fn from(s: &str) -> Self { Self::Quoted(s.to_string()) }
+  }
 
-    fn numeric_vertex(index: usize) -> Vertex {
-      let key = format!("node_{}", index);
-      Vertex {
-        id: Id::new(key.clone()),
-        label: Some(Label(key)),
-        color: None,
-        fontcolor: None,
+  impl From<String> for AttrValue {
+    fn from(s: String) -> Self { Self::Quoted(s) }
+  }
+
+  impl From<Id> for AttrValue {
+    fn from(id: Id) -> Self {
+      if id.is_html() {
+        Self::Html(id.as_str().to_string())
+      } else {
+        Self::Quoted(id.as_str().to_string())
       }
     }
+  }
 
-    #[test]
+  /// Attributes without a dedicated field, emitted after all the others in
+  /// name order.
+  pub type Attributes = BTreeMap<String, AttrValue>;
+
+
+  /// A single node in the graph.
+  #[derive(Debug, Clone, PartialEq)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+  #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+  #[allow(missing_docs)]
+  pub struct Vertex {
+    pub id: Id,
+    pub label: Option<Label>,
+    /// Takes precedence over [`Self::label`] if provided.
+    pub html_label: Option<HtmlLabel>,
+    pub color: Option<Color>,
+    pub fontcolor: Option<Color>,
+    pub shape: Option<Shape>,
+    pub fontname: Option<FontName>,
+    pub fontsize: Option<FontSize>,
+    pub style: Option<Style>,
+    /// The layers this vertex is drawn in, e.g. `"all"` or `"a:c"`.
+    pub layer: Option<String>,
+    pub fixedsize: Option<FixedSize>,
+    /// In inches.
+    pub width: Option<f64>,
+    /// In inches.
+    pub height: Option<f64>,
+    /// The number of outlines drawn around the shape.
+    pub peripheries: Option<u32>,
+    /// Parameters for a polygonal shape. If [`Self::shape`] is unset, `shape =
+    /// polygon` is emitted as well.
+    pub polygon: Option<Polygon>,
+    /// An external file to draw as this vertex's shape. If [`Self::shape`] is
+    /// unset, the appropriate shape for the file is emitted as well.
+    pub shapefile: Option<ShapeFile>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attributes: Attributes,
+  }
+
+  impl Vertex {
+    /// Create a vertex with no attributes set.
+    pub fn new(id: impl Into<Id>) -> Self {
+      Self {
+        id: id.into(),
+        label: None,
+        html_label: None,
+        color: None,
+        fontcolor: None,
+        shape: None,
+        fontname: None,
+        fontsize: None,
+        style: None,
+        layer: None,
+        fixedsize: None,
+        width: None,
+        height: None,
+        peripheries: None,
+        polygon: None,
+        shapefile: None,
+        attributes: Attributes::new(),
+      }
+    }
+
+    #[allow(missing_docs)]
+    pub fn label<S: AsRef<str>>(mut self, label: S) -> Self {
+      self.label = Some(Label(label.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn html_label<S: AsRef<str>>(mut self, html_label: S) -> Self {
+      self.html_label = Some(HtmlLabel(html_label.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn color(mut self, color: Color) -> Self {
+      self.color = Some(color);
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn fontcolor(mut self, fontcolor: Color) -> Self {
+      self.fontcolor = Some(fontcolor);
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn shape<S: AsRef<str>>(mut self, shape: S) -> Self {
+      self.shape = Some(Shape(shape.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn fontname<S: AsRef<str>>(mut self, fontname: S) -> Self {
+      self.fontname = Some(FontName(fontname.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn fontsize(mut self, fontsize: f64) -> Self {
+      self.fontsize = Some(FontSize(fontsize));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn style<S: AsRef<str>>(mut self, style: S) -> Self {
+      self.style = Some(Style(style.as_ref().to_string()));
+      self
+    }
+
+    /// Set an attribute which has no dedicated field.
+    pub fn attr(mut self, name: impl Into<String>, value: impl Into<AttrValue>) -> Self {
+      self.attributes.insert(name.into(), value.into());
+      self
+    }
+
+    /// A vertex which takes up space in the layout but isn't drawn.
+    pub fn invisible(id: Id) -> Self {
+      Self {
+        id,
+        label: None,
+        html_label: None,
+        color: None,
+        fontcolor: None,
+        shape: None,
+        fontname: None,
+        fontsize: None,
+        style: Some(Style::invis()),
+        layer: None,
+        fixedsize: None,
+        width: None,
+        height: None,
+        peripheries: None,
+        polygon: None,
+        shapefile: None,
+        attributes: Attributes::new(),
+      }
+    }
+  }
+
+  impl Default for Vertex {
+    fn default() -> Self { Self::new(Id::random()) }
+  }
+
+  /// Any statement which can be placed in the body of a graph or subgraph.
+  #[derive(Debug, Clone, PartialEq)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+  #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+  #[allow(missing_docs)]
+  pub enum Entity {
+    Subgraph(Subgraph),
+    Group(Group),
+    Vertex(Vertex),
+    Edge(Edge),
+    /// A note for human readers, emitted as `// ...`, or as a `/* ... */`
+    /// block if it spans multiple lines.
+    Comment(String),
+  }
+
+  /// An anonymous `{ ... }` block, which groups entities (e.g. to share node
+  /// defaults) without drawing a cluster around them.
+  #[derive(Debug, Clone, Default, PartialEq)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+  #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+  #[allow(missing_docs)]
+  pub struct Group {
+    pub node_defaults: Option<NodeDefaults>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub entities: Vec<Entity>,
+  }
+
+  /// A cluster of entities, drawn within a bounding box unless
+  /// [`Self::is_cluster`] is unset.
+  ///
+  /// Every vertex directly within a subgraph is constrained to the same rank
+  /// unless [`Self::same_rank`] is unset. Set [`GraphAttributes::newrank`] if
+  /// this produces odd layouts across multiple clusters.
+  #[derive(Debug, Clone, PartialEq)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+  #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+  #[allow(missing_docs)]
+  pub struct Subgraph {
+    pub id: Id,
+    /// Draw a bounding box around this subgraph. Defaults to `true`.
+    #[cfg_attr(feature = "serde", serde(default = "default_is_cluster"))]
+    pub is_cluster: bool,
+    /// Emit `rank = same`. Defaults to `true`.
+    #[cfg_attr(feature = "serde", serde(default = "default_same_rank"))]
+    pub same_rank: bool,
+    pub label: Option<Label>,
+    pub color: Option<Color>,
+    pub fontcolor: Option<Color>,
+    pub fontname: Option<FontName>,
+    pub fontsize: Option<FontSize>,
+    pub node_defaults: Option<NodeDefaults>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attributes: Attributes,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub entities: Vec<Entity>,
+  }
+
+  #[cfg(feature = "serde")]
+  fn default_is_cluster() -> bool { true }
+
+  #[cfg(feature = "serde")]
+  fn default_same_rank() -> bool { true }
+
+  impl Subgraph {
+    /// Create an empty subgraph with no attributes set.
+    pub fn new(id: impl Into<Id>) -> Self {
+      Self {
+        id: id.into(),
+        is_cluster: true,
+        same_rank: true,
+        label: None,
+        color: None,
+        fontcolor: None,
+        fontname: None,
+        fontsize: None,
+        node_defaults: None,
+        attributes: Attributes::new(),
+        entities: Vec::new(),
+      }
+    }
+
+    #[allow(missing_docs)]
+    pub fn cluster(mut self, is_cluster: bool) -> Self {
+      self.is_cluster = is_cluster;
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn same_rank(mut self, same_rank: bool) -> Self {
+      self.same_rank = same_rank;
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn label<S: AsRef<str>>(mut self, label: S) -> Self {
+      self.label = Some(Label(label.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn color(mut self, color: Color) -> Self {
+      self.color = Some(color);
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn fontcolor(mut self, fontcolor: Color) -> Self {
+      self.fontcolor = Some(fontcolor);
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn node_defaults(mut self, node_defaults: NodeDefaults) -> Self {
+      self.node_defaults = Some(node_defaults);
+      self
+    }
+
+    /// Set an attribute which has no dedicated field.
+    pub fn attr(mut self, name: impl Into<String>, value: impl Into<AttrValue>) -> Self {
+      self.attributes.insert(name.into(), value.into());
+      self
+    }
+
+    /// Add an entity to the body of this subgraph.
+    pub fn entity(mut self, entity: Entity) -> Self {
+      self.entities.push(entity);
+      self
+    }
+  }
+
+  impl Default for Subgraph {
+    fn default() -> Self { Self::new(Id::random()) }
+  }
+
+  /// A directed edge between two vertices.
+  #[derive(Debug, Clone, PartialEq)]
+  #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+  #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+  #[allow(missing_docs)]
+  pub struct Edge {
+    pub source: Id,
+    pub target: Id,
+    pub label: Option<Label>,
+    pub color: Option<Color>,
+    pub fontcolor: Option<Color>,
+    pub fontname: Option<FontName>,
+    pub fontsize: Option<FontSize>,
+    pub style: Option<Style>,
+    pub arrowhead: Option<ArrowType>,
+    pub arrowtail: Option<ArrowType>,
+    pub dir: Option<DirType>,
+    pub headport: Option<Port>,
+    pub tailport: Option<Port>,
+    pub headlabel: Option<Label>,
+    pub taillabel: Option<Label>,
+    /// How strongly to keep this edge short and straight. Must be non-negative.
+    pub weight: Option<f64>,
+    /// The minimum difference in rank between the head and tail.
+    pub minlen: Option<u32>,
+    /// If `false`, this edge is ignored when ranking vertices, e.g. for
+    /// back-references.
+    pub constraint: Option<bool>,
+    /// Edges with the same `samehead` value and head vertex converge on the
+    /// same point.
+    pub samehead: Option<String>,
+    /// Edges with the same `sametail` value and tail vertex leave from the same
+    /// point.
+    pub sametail: Option<String>,
+    /// The layers this edge is drawn in, e.g. `"all"` or `"a:c"`.
+    pub layer: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attributes: Attributes,
+  }
+
+  impl Edge {
+    /// Create an edge from `source` to `target` with no attributes set.
+    pub fn between(source: impl Into<Id>, target: impl Into<Id>) -> Self {
+      Self {
+        source: source.into(),
+        target: target.into(),
+        ..Default::default()
+      }
+    }
+
+    #[allow(missing_docs)]
+    pub fn label<S: AsRef<str>>(mut self, label: S) -> Self {
+      self.label = Some(Label(label.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn color(mut self, color: Color) -> Self {
+      self.color = Some(color);
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn fontcolor(mut self, fontcolor: Color) -> Self {
+      self.fontcolor = Some(fontcolor);
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn style<S: AsRef<str>>(mut self, style: S) -> Self {
+      self.style = Some(Style(style.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn arrowhead<S: AsRef<str>>(mut self, arrowhead: S) -> Self {
+      self.arrowhead = Some(ArrowType(arrowhead.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn arrowtail<S: AsRef<str>>(mut self, arrowtail: S) -> Self {
+      self.arrowtail = Some(ArrowType(arrowtail.as_ref().to_string()));
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn weight(mut self, weight: f64) -> Self {
+      self.weight = Some(weight);
+      self
+    }
+
+    #[allow(missing_docs)]
+    pub fn constraint(mut self, constraint: bool) -> Self {
+      self.constraint = Some(constraint);
+      self
+    }
+
+    /// Set an attribute which has no dedicated field.
+    pub fn attr(mut self, name: impl Into<String>, value: impl Into<AttrValue>) -> Self {
+      self.attributes.insert(name.into(), value.into());
+      self
+    }
+
+    /// An edge which constrains the layout but isn't drawn.
+    pub fn invisible(source: Id, target: Id) -> Self {
+      Self {
+        source,
+        target,
+        label: None,
+        color: None,
+        fontcolor: None,
+        fontname: None,
+        fontsize: None,
+        style: Some(Style::invis()),
+        arrowhead: None,
+        arrowtail: None,
+        dir: None,
+        headport: None,
+        tailport: None,
+        headlabel: None,
+        taillabel: None,
+        weight: None,
+        minlen: None,
+        constraint: None,
+        samehead: None,
+        sametail: None,
+        layer: None,
+        attributes: Attributes::new(),
+      }
+    }
+  }
+
+  impl Default for Edge {
+    fn default() -> Self {
+      Self {
+        source: Id::new(""),
+        target: Id::new(""),
+        label: None,
+        color: None,
+        fontcolor: None,
+        fontname: None,
+        fontsize: None,
+        style: None,
+        arrowhead: None,
+        arrowtail: None,
+        dir: None,
+        headport: None,
+        tailport: None,
+        headlabel: None,
+        taillabel: None,
+        weight: None,
+        minlen: None,
+        constraint: None,
+        samehead: None,
+        sametail: None,
+        layer: None,
+        attributes: Attributes::new(),
+      }
+    }
+  }
+}
+
+/// Convert [`Entity`](entities::Entity) instances into a `.dot` file.
+pub mod generator {
+  use super::{
+    attr_table::{self, AttrProblem, UsedBy},
+    diagnostics::{Diagnostic, Diagnostics, EntityRef, Severity},
+    entities::*,
+    ids::{IdGenerator, IdNamespace, Uuids},
+    render::html,
+    theme::Theme,
+  };
+
+  use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io, mem,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+  };
+
+  /// The text of a `.dot` file.
+  #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+  pub struct DotOutput(pub String);
+
+  impl DotOutput {
+    /// Write the whole document to `w`.
+    pub fn write_to(&self, mut w: impl io::Write) -> io::Result<()> {
+      w.write_all(self.0.as_bytes())?;
+      w.flush()
+    }
+
+    /// The smallest equivalent document, for embedding in URLs or HTTP
+    /// responses. See [`crate::minify`].
+    pub fn minify(&self) -> DotOutput { DotOutput(crate::minify::minify(&self.0)) }
+  }
+
+  impl fmt::Display for DotOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.0) }
+  }
+
+  /// Renders the DOT statement for this entity, as it would appear at the top
+  /// level of a graph with the default [`QuotingPolicy`].
+  impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      GraphBuilder::write_entity(f, self, 0, Printer::default())
+    }
+  }
+
+  /// Renders the DOT statement for this vertex, e.g. `a[label="A"];`.
+  impl fmt::Display for Vertex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      GraphBuilder::write_vertex(f, self, 0, Printer::default())
+    }
+  }
+
+  /// Renders the DOT statement for this edge, e.g. `a -> b;`.
+  impl fmt::Display for Edge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      GraphBuilder::write_edge(f, self, 0, Printer::default())
+    }
+  }
+
+  /// Renders the `subgraph` block for this subgraph and all its contents.
+  impl fmt::Display for Subgraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      GraphBuilder::write_subgraph(f, self, 0, Printer::default())
+    }
+  }
+
+  /// Like the [`Display`](fmt::Display) impl for [`Entity`], but for a
+  /// statement within an undirected `graph`, so every edge (including those
+  /// within subgraphs) is written with `--`.
+  pub(crate) fn undirected_statement(entity: &Entity) -> String {
+    let printer = Printer {
+      undirected: true,
+      ..Printer::default()
+    };
+    let mut ret = String::new();
+    GraphBuilder::write_entity(&mut ret, entity, 0, printer).unwrap();
+    ret
+  }
+
+  /// A handle to a vertex added with [`GraphBuilder::add_vertex`].
+  ///
+  /// Vertices are identified by their id, so this handle remains valid if the
+  /// vertex is moved into a subgraph.
+  #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+  pub struct VertexRef(Id);
+
+  impl VertexRef {
+    /// The id of the vertex.
+    pub fn id(&self) -> &Id { &self.0 }
+  }
+
+  /// A handle to a top-level edge added with [`GraphBuilder::add_edge`].
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+  pub struct EdgeRef(u64);
+
+  /// A handle to a subgraph added with [`GraphBuilder::add_subgraph`].
+  #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+  pub struct SubgraphRef(Id);
+
+  impl SubgraphRef {
+    /// The id of the subgraph.
+    pub fn id(&self) -> &Id { &self.0 }
+  }
+
+  fn find_vertex_mut<'a, I: Iterator<Item=&'a mut Entity>>(
+    entities: I,
+    id: &Id,
+  ) -> Option<&'a mut Vertex> {
+    for e in entities {
+      let found = match e {
+        Entity::Vertex(v) if &v.id == id => Some(v),
+        Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => None,
+        Entity::Subgraph(sg) => find_vertex_mut(sg.entities.iter_mut(), id),
+        Entity::Group(g) => find_vertex_mut(g.entities.iter_mut(), id),
+      };
+      if found.is_some() {
+        return found;
+      }
+    }
+    None
+  }
+
+  fn find_subgraph_mut<'a, I: Iterator<Item=&'a mut Entity>>(
+    entities: I,
+    id: &Id,
+  ) -> Option<&'a mut Subgraph> {
+    for e in entities {
+      let found = match e {
+        Entity::Subgraph(sg) => {
+          if &sg.id == id {
+            Some(sg)
+          } else {
+            find_subgraph_mut(sg.entities.iter_mut(), id)
+          }
+        },
+        Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => None,
+        Entity::Group(g) => find_subgraph_mut(g.entities.iter_mut(), id),
+      };
+      if found.is_some() {
+        return found;
+      }
+    }
+    None
+  }
+
+  /// Where each vertex and edge is in a [`GraphBuilder`], as indices from its
+  /// top level down through subgraphs and groups, so duplicates can be found
+  /// without searching the whole graph.
+  #[derive(Debug, Default)]
+  struct EntityIndex {
+    /* The first vertex with each id, as found by find_vertex_mut(). */
+    vertices: HashMap<Id, Vec<usize>>,
+    edges: HashMap<(Id, Id), Vec<Vec<usize>>>,
+  }
+
+  impl EntityIndex {
+    fn build(entities: &[(u64, Entity)]) -> Self {
+      let mut ret = Self::default();
+      for (i, (_, e)) in entities.iter().enumerate() {
+        ret.record(&mut vec![i], e);
+      }
+      ret
+    }
+
+    /// Record `e`, found at `path`, and everything within it.
+    fn record(&mut self, path: &mut Vec<usize>, e: &Entity) {
+      match e {
+        Entity::Vertex(v) => {
+          self
+            .vertices
+            .entry(v.id.clone())
+            .or_insert_with(|| path.clone());
+        },
+        Entity::Edge(e) => self
+          .edges
+          .entry((e.source.clone(), e.target.clone()))
+          .or_default()
+          .push(path.clone()),
+        Entity::Comment(_) => (),
+        Entity::Subgraph(Subgraph { entities, .. }) | Entity::Group(Group { entities, .. }) => {
+          for (i, e) in entities.iter().enumerate() {
+            path.push(i);
+            self.record(path, e);
+            path.pop();
+          }
+        },
+      }
+    }
+  }
+
+  fn entity_at<'a>(entities: &'a [(u64, Entity)], path: &[usize]) -> &'a Entity {
+    let mut ret = &entities[path[0]].1;
+    for &i in path[1..].iter() {
+      ret = match ret {
+        Entity::Subgraph(Subgraph { entities, .. }) | Entity::Group(Group { entities, .. }) => {
+          &entities[i]
+        },
+        Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => unreachable!(),
+      };
+    }
+    ret
+  }
+
+  fn entity_at_mut<'a>(entities: &'a mut [(u64, Entity)], path: &[usize]) -> &'a mut Entity {
+    let mut ret = &mut entities[path[0]].1;
+    for &i in path[1..].iter() {
+      ret = match ret {
+        Entity::Subgraph(Subgraph { entities, .. }) | Entity::Group(Group { entities, .. }) => {
+          &mut entities[i]
+        },
+        Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => unreachable!(),
+      };
+    }
+    ret
+  }
+
+  /// Every entity in a [`GraphBuilder`], each followed by those nested
+  /// within it.
+  struct AllEntities<'a> {
+    top: std::slice::Iter<'a, (u64, Entity)>,
+    /* Only allocated once a subgraph or group is entered. */
+    nested: Vec<std::slice::Iter<'a, Entity>>,
+  }
+
+  impl<'a> Iterator for AllEntities<'a> {
+    type Item = &'a Entity;
+
+    fn next(&mut self) -> Option<&'a Entity> {
+      let e = loop {
+        match self.nested.last_mut() {
+          Some(entities) => match entities.next() {
+            Some(e) => break e,
+            None => {
+              self.nested.pop();
+            },
+          },
+          None => break &self.top.next()?.1,
+        }
+      };
+      match e {
+        Entity::Subgraph(Subgraph { entities, .. }) | Entity::Group(Group { entities, .. }) => {
+          self.nested.push(entities.iter())
+        },
+        Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => (),
+      }
+      Some(e)
+    }
+  }
+
+  /// Which kinds of entity in the [`attr_table`] `sg` counts as.
+  fn subgraph_kind(sg: &Subgraph) -> UsedBy {
+    if sg.is_cluster {
+      UsedBy::CLUSTER | UsedBy::SUBGRAPH
+    } else {
+      UsedBy::SUBGRAPH
+    }
+  }
+
+  /// Remove every attribute set within `e` (recursively) which
+  /// [`attr_table::check`] rejects.
+  fn strip_invalid_attributes(e: &mut Entity) {
+    match e {
+      Entity::Vertex(v) => v
+        .attributes
+        .retain(|name, _| attr_table::check(name, UsedBy::NODE).is_ok()),
+      Entity::Edge(edge) => edge
+        .attributes
+        .retain(|name, _| attr_table::check(name, UsedBy::EDGE).is_ok()),
+      Entity::Subgraph(sg) => {
+        let kind = subgraph_kind(sg);
+        sg.attributes
+          .retain(|name, _| attr_table::check(name, kind).is_ok());
+        sg.entities.iter_mut().for_each(strip_invalid_attributes);
+      },
+      Entity::Group(g) => g.entities.iter_mut().for_each(strip_invalid_attributes),
+      Entity::Comment(_) => (),
+    }
+  }
+
+  /// What an empty id is replaced with in [`BuildMode::Lenient`].
+  pub const EMPTY_ID_PLACEHOLDER: &str = "_empty";
+
+  fn is_empty_label(label: &Option<Label>) -> bool {
+    matches!(label, Some(Label(l)) if l.is_empty())
+  }
+
+  /// Report every vertex, edge, and subgraph within `e` (recursively) with an
+  /// empty id or label.
+  fn find_empty(e: &Entity, out: &mut Vec<GraphError>) {
+    match e {
+      Entity::Vertex(v) => {
+        if v.id.as_str().is_empty() {
+          out.push(GraphError::EmptyId(EntityRef::Vertex(v.id.clone())));
+        }
+        if is_empty_label(&v.label) {
+          out.push(GraphError::EmptyLabel(EntityRef::Vertex(v.id.clone())));
+        }
+      },
+      Entity::Edge(edge) => {
+        let entity = || EntityRef::Edge {
+          source: edge.source.clone(),
+          target: edge.target.clone(),
+        };
+        if edge.source.as_str().is_empty() || edge.target.as_str().is_empty() {
+          out.push(GraphError::EmptyId(entity()));
+        }
+        if is_empty_label(&edge.label) {
+          out.push(GraphError::EmptyLabel(entity()));
+        }
+      },
+      Entity::Subgraph(sg) => {
+        if sg.id.as_str().is_empty() {
+          out.push(GraphError::EmptyId(EntityRef::Subgraph(sg.id.clone())));
+        }
+        if is_empty_label(&sg.label) {
+          out.push(GraphError::EmptyLabel(EntityRef::Subgraph(sg.id.clone())));
+        }
+        for e in sg.entities.iter() {
+          find_empty(e, out);
+        }
+      },
+      Entity::Group(g) => {
+        for e in g.entities.iter() {
+          find_empty(e, out);
+        }
+      },
+      Entity::Comment(_) => (),
+    }
+  }
+
+  /// Replace every empty id within `e` (recursively) with
+  /// [`EMPTY_ID_PLACEHOLDER`]. Empty vertex and edge labels are removed, so
+  /// vertices fall back to displaying their id, and empty subgraph labels are
+  /// replaced with the subgraph's id.
+  fn fill_empty(e: &mut Entity) {
+    let fill = |id: &mut Id| {
+      if id.as_str().is_empty() {
+        *id = Id::new(EMPTY_ID_PLACEHOLDER);
+      }
+    };
+    match e {
+      Entity::Vertex(v) => {
+        fill(&mut v.id);
+        if is_empty_label(&v.label) {
+          v.label = None;
+        }
+      },
+      Entity::Edge(edge) => {
+        fill(&mut edge.source);
+        fill(&mut edge.target);
+        if is_empty_label(&edge.label) {
+          edge.label = None;
+        }
+      },
+      Entity::Subgraph(sg) => {
+        fill(&mut sg.id);
+        if is_empty_label(&sg.label) {
+          sg.label = Some(Label(sg.id.as_str().to_string()));
+        }
+        sg.entities.iter_mut().for_each(fill_empty);
+      },
+      Entity::Group(g) => g.entities.iter_mut().for_each(fill_empty),
+      Entity::Comment(_) => (),
+    }
+  }
+
+  /// Remove every edge within `entities` (recursively) for which `keep`
+  /// returns `false`.
+  fn retain_edges<F: Fn(&Edge) -> bool>(entities: &mut Vec<Entity>, keep: &F) {
+    entities.retain_mut(|e| match e {
+      Entity::Edge(e) => keep(e),
+      Entity::Subgraph(sg) => {
+        retain_edges(&mut sg.entities, keep);
+        true
+      },
+      Entity::Group(g) => {
+        retain_edges(&mut g.entities, keep);
+        true
+      },
+      Entity::Vertex(_) | Entity::Comment(_) => true,
+    });
+  }
+
+  /// The position of `e` in a stable ordering: vertices by id, then subgraphs
+  /// by id, then groups, then edges by source and target, then comments.
+  fn order_key(e: &Entity) -> (u8, &str, &str) {
+    match e {
+      Entity::Vertex(v) => (0, v.id.as_str(), ""),
+      Entity::Subgraph(sg) => (1, sg.id.as_str(), ""),
+      Entity::Group(_) => (2, "", ""),
+      Entity::Edge(e) => (3, e.source.as_str(), e.target.as_str()),
+      Entity::Comment(text) => (4, text.as_str(), ""),
+    }
+  }
+
+  /// [`order_key`], with ties broken by the printed statement so identical
+  /// entities always sort together.
+  fn canonical_key(e: &Entity) -> (u8, String, String, String) {
+    let (rank, a, b) = order_key(e);
+    (rank, a.to_string(), b.to_string(), e.to_string())
+  }
+
+  /// `entities` in the order they should be printed.
+  fn print_order<'e, I: Iterator<Item=&'e Entity>>(entities: I, stable: bool) -> Vec<&'e Entity> {
+    let mut ret: Vec<&Entity> = entities.collect();
+    if stable {
+      ret.sort_by(|a, b| order_key(a).cmp(&order_key(b)));
+    }
+    ret
+  }
+
+  /// Sort `entities` and the contents of every subgraph and group within them
+  /// by [`canonical_key`].
+  fn sort_canonically(entities: &mut [Entity]) {
+    for e in entities.iter_mut() {
+      match e {
+        Entity::Subgraph(sg) => sort_canonically(&mut sg.entities),
+        Entity::Group(g) => sort_canonically(&mut g.entities),
+        Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => (),
+      }
+    }
+    entities.sort_by_cached_key(canonical_key);
+  }
+
+  /// Remove the vertex with this id from anywhere within `entities`.
+  fn take_vertex(entities: &mut Vec<Entity>, id: &Id) -> Option<Vertex> {
+    if let Some(i) = entities
+      .iter()
+      .position(|e| matches!(e, Entity::Vertex(v) if &v.id == id))
+    {
+      return match entities.remove(i) {
+        Entity::Vertex(v) => Some(v),
+        _ => unreachable!(),
+      };
+    }
+    for e in entities.iter_mut() {
+      let found = match e {
+        Entity::Subgraph(sg) => take_vertex(&mut sg.entities, id),
+        Entity::Group(g) => take_vertex(&mut g.entities, id),
+        Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => None,
+      };
+      if found.is_some() {
+        return found;
+      }
+    }
+    None
+  }
+
+  /// Accumulates entities, then prints them all at once with [`Self::build`].
+  pub struct GraphBuilder {
+    /* Each top-level entity is keyed so that an EdgeRef can find it again. */
+    entities: Vec<(u64, Entity)>,
+    next_key: u64,
+    theme: Option<Theme>,
+    graph_attributes: GraphAttributes,
+    ids: HashSet<Id>,
+    /* Dropped whenever entities may have been changed in place or removed,
+     * and rebuilt when next needed. */
+    index: Option<EntityIndex>,
+    auto_vertex: Option<Box<VertexFactory>>,
+    dedupe: bool,
+    merge_strategy: MergeStrategy,
+    id_generator: Box<dyn IdGenerator>,
+    quoting_policy: QuotingPolicy,
+    verify_ids: bool,
+    validate_edges: bool,
+    validate_vertices: bool,
+    validate_attributes: bool,
+    attr_list_style: AttrListStyle,
+    format_options: FormatOptions,
+    stable_order: bool,
+    cluster_naming: ClusterNaming,
+    directed: bool,
+    build_mode: Option<BuildMode>,
+    preamble: Vec<String>,
+    generated_by: Option<GeneratedBy>,
+  }
+
+  /// Where a generated document came from, written as a header comment by
+  /// [`GraphBuilder::set_generated_by`].
+  #[derive(Debug, Clone, Default, PartialEq, Eq)]
+  pub struct GeneratedBy {
+    /// Free-form provenance, e.g. the command which produced the graph.
+    pub provenance: Option<String>,
+    /// When the graph was generated. Defaults to the time it is built.
+    pub timestamp: Option<SystemTime>,
+  }
+
+  impl GeneratedBy {
+    /// `t` as an RFC 3339 UTC timestamp, with second precision.
+    fn format_timestamp(t: SystemTime) -> String {
+      let secs = t
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+      let (days, rem) = (secs / 86400, secs % 86400);
+      /* Convert days since the epoch to a civil date (Howard Hinnant's
+       * days_from_civil, inverted). */
+      let z = days as i64 + 719468;
+      let era = z.div_euclid(146097);
+      let doe = z.rem_euclid(146097);
+      let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+      let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+      let mp = (5 * doy + 2) / 153;
+      let day = doy - (153 * mp + 2) / 5 + 1;
+      let month = if mp < 10 { mp + 3 } else { mp - 9 };
+      let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+      format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+      )
+    }
+
+    fn comment(&self) -> String {
+      let timestamp = self.timestamp.unwrap_or_else(SystemTime::now);
+      let mut ret = format!(
+        "Generated by {} {} at {}.",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        Self::format_timestamp(timestamp),
+      );
+      if let Some(ref provenance) = self.provenance {
+        ret.push_str(&format!("\nProvenance: {}", provenance));
+      }
+      ret
+    }
+  }
+
+  /// How to separate the attributes within a `[...]` list.
+  #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+  pub enum AttrSeparator {
+    /// `[a="x", b="y"]`
+    #[default]
+    Comma,
+    /// `[a="x"; b="y"]`
+    Semicolon,
+    /// `[a="x" b="y"]`
+    Space,
+  }
+
+  impl AttrSeparator {
+    fn as_str(self) -> &'static str {
+      match self {
+        Self::Comma => ", ",
+        Self::Semicolon => "; ",
+        Self::Space => " ",
+      }
+    }
+  }
+
+  /// How to format the `[...]` attribute lists of vertices, edges, and node
+  /// defaults.
+  #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+  pub struct AttrListStyle {
+    #[allow(missing_docs)]
+    pub separator: AttrSeparator,
+    /// Emit `a = "x"` instead of `a="x"`.
+    pub spaced_equals: bool,
+  }
+
+  /// Layout of the generated document.
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  pub struct FormatOptions {
+    /// Spaces per nesting level. Ignored if [`Self::use_tabs`] is set.
+    pub indent_width: usize,
+    /// Indent with one tab per nesting level instead of spaces.
+    pub use_tabs: bool,
+    /// Empty lines between top-level entities.
+    pub blank_lines: usize,
+    /// Put each attribute of a vertex, edge, or node defaults on its own line.
+    pub attrs_per_line: bool,
+  }
+
+  impl Default for FormatOptions {
+    fn default() -> Self {
+      Self {
+        indent_width: 2,
+        use_tabs: false,
+        blank_lines: 1,
+        attrs_per_line: false,
+      }
+    }
+  }
+
+  /// How [`GraphBuilder::build`] treats the findings of its validation passes,
+  /// overriding the individual settings like [`GraphBuilder::validate_edges`].
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  pub enum BuildMode {
+    /// Run every validation pass, including [`GraphBuilder::verify_ids`], and
+    /// fail on the first finding. Empty ids and labels are also rejected.
+    Strict,
+    /// Never fail on a finding. Create a plain vertex for each undefined edge
+    /// endpoint (unless [`GraphBuilder::auto_create_vertices`] is set), skip
+    /// invalid attributes and layer names, and replace empty ids and labels as described by
+    /// [`EMPTY_ID_PLACEHOLDER`]. Findings are recorded as warnings by
+    /// [`GraphBuilder::build_with_diagnostics`].
+    Lenient,
+  }
+
+  /// How to name subgraphs with [`Subgraph::is_cluster`] set.
+  ///
+  /// Graphviz releases before 2.47 ignore the `cluster = true;` attribute and
+  /// only draw a subgraph as a cluster if its id starts with `cluster`.
+  #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+  pub enum ClusterNaming {
+    /// Print every subgraph id as given.
+    #[default]
+    AsIs,
+    /// Prepend `cluster_` to the id of every cluster which doesn't already
+    /// start with `cluster`.
+    Prefix,
+    /// Like [`Self::Prefix`], and also label any renamed cluster without a
+    /// label with its original id.
+    PrefixAndLabel,
+  }
+
+  /// How [`GraphBuilder::build`] formats ids, attribute lists, and whitespace.
+  #[derive(Debug, Clone, Copy, Default)]
+  struct Printer {
+    policy: QuotingPolicy,
+    verify: bool,
+    attr_list: AttrListStyle,
+    format: FormatOptions,
+    stable_order: bool,
+    cluster_naming: ClusterNaming,
+    /* Write edges as `a -- b`, for an undirected `graph`. */
+    undirected: bool,
+  }
+
+  impl Printer {
+    fn newline<W: fmt::Write>(self, w: &mut W) -> fmt::Result { w.write_char('\n') }
+
+    /// Start a new line nested `indent` levels deep.
+    fn newline_indent<W: fmt::Write>(self, w: &mut W, indent: usize) -> fmt::Result {
+      self.newline(w)?;
+      if self.format.use_tabs {
+        for _ in 0..indent {
+          w.write_char('\t')?;
+        }
+      } else {
+        for _ in 0..(indent * self.format.indent_width) {
+          w.write_char(' ')?;
+        }
+      }
+      Ok(())
+    }
+
+    fn blank_lines<W: fmt::Write>(self, w: &mut W) -> fmt::Result {
+      for _ in 0..self.format.blank_lines {
+        self.newline(w)?;
+      }
+      Ok(())
+    }
+
+    fn write<W: fmt::Write>(self, w: &mut W, id: &Id) -> fmt::Result {
+      if !self.verify {
+        return id.write_escaped(w, self.policy);
+      }
+      let token = id.clone().escaped(self.policy);
+      if Id::is_valid_token(&token) {
+        return w.write_str(&token);
+      }
+      /* Every id was already checked by `verify_ids()` before printing. */
+      let quoted = id.clone().escaped(QuotingPolicy::Always);
+      debug_assert!(Id::is_valid_token(&quoted), "{}", quoted);
+      w.write_str(&quoted)
+    }
+  }
+
+  /// The name of an attribute from an [`Attributes`] map, quoted if it
+  /// isn't a valid ID, e.g. `graphics.fill` as imported from GML.
+  struct AttrName<'a>(&'a str);
+
+  impl fmt::Display for AttrName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write_escaped_text(f, self.0, QuotingPolicy::Minimal)
+    }
+  }
+
+  /// Writes a bracketed list of `name=value` attributes, opening the bracket
+  /// only once the first attribute is written and separating each from the
+  /// next without any trailing punctuation.
+  struct AttrList<'w, W> {
+    w: &'w mut W,
+    printer: Printer,
+    indent: usize,
+    open: bool,
+  }
+
+  impl<'w, W: fmt::Write> AttrList<'w, W> {
+    fn new(w: &'w mut W, printer: Printer, indent: usize) -> Self {
+      Self {
+        w,
+        printer,
+        indent,
+        open: false,
+      }
+    }
+
+    fn raw(&mut self, name: impl fmt::Display, value: impl fmt::Display) -> fmt::Result {
+      let Printer {
+        attr_list: style,
+        format,
+        ..
+      } = self.printer;
+      if self.open {
+        let separator = style.separator.as_str();
+        if format.attrs_per_line {
+          self.w.write_str(separator.trim_end())?;
+        } else {
+          self.w.write_str(separator)?;
+        }
+      } else {
+        self.w.write_char('[')?;
+        self.open = true;
+      }
+      if format.attrs_per_line {
+        self.printer.newline_indent(self.w, self.indent + 1)?;
+      }
+      let equals = if style.spaced_equals { " = " } else { "=" };
+      write!(self.w, "{}{}{}", name, equals, value)
+    }
+
+    fn quoted(&mut self, name: &str, value: impl fmt::Display) -> fmt::Result {
+      self.raw(name, format_args!("\"{}\"", value))
+    }
+
+    fn finish(self) -> fmt::Result {
+      if self.open {
+        if self.printer.format.attrs_per_line {
+          self.printer.newline_indent(self.w, self.indent)?;
+        }
+        self.w.write_char(']')?;
+      }
+      Ok(())
+    }
+  }
+
+  /// How to handle a vertex added with the same id as an existing vertex, when
+  /// [`GraphBuilder::dedupe`] is enabled.
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  pub enum MergeStrategy {
+    /// Keep the existing vertex and drop the new one.
+    FirstWins,
+    /// Replace the existing vertex with the new one.
+    LastWins,
+    /// Reject the new vertex if its attributes differ from the existing one.
+    Error,
+  }
+
+  /// A vertex was added with the same id but different attributes than an
+  /// existing vertex, with [`MergeStrategy::Error`].
+  #[derive(Debug, Clone)]
+  pub struct DuplicateVertex {
+    #[allow(missing_docs)]
+    pub existing: Box<Vertex>,
+    #[allow(missing_docs)]
+    pub new: Box<Vertex>,
+  }
+
+  impl fmt::Display for DuplicateVertex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(
+        f,
+        "conflicting vertices with the same id: {:?} and {:?}",
+        self.existing, self.new
+      )
+    }
+  }
+
+  impl std::error::Error for DuplicateVertex {}
+
+  /// An infinite weight passed to
+  /// [`GraphBuilder::from_weighted_adjacency_matrix`], which can't be scaled
+  /// against the others.
+  #[derive(Debug, Clone, PartialEq)]
+  pub struct InfiniteWeight {
+    #[allow(missing_docs)]
+    pub row: usize,
+    #[allow(missing_docs)]
+    pub column: usize,
+  }
+
+  impl fmt::Display for InfiniteWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "infinite weight at row {}, column {}", self.row, self.column)
+    }
+  }
+
+  impl std::error::Error for InfiniteWeight {}
+
+  /// An edge with an endpoint which was never added as a vertex or subgraph,
+  /// reported by [`GraphBuilder::validate_edges`].
+  #[derive(Debug, Clone, PartialEq)]
+  pub struct DanglingEdge {
+    #[allow(missing_docs)]
+    pub source: Id,
+    #[allow(missing_docs)]
+    pub target: Id,
+    /// Whichever of `source` and `target` are missing.
+    pub missing: Vec<Id>,
+  }
+
+  impl DanglingEdge {
+    fn describe_missing(&self) -> String {
+      let missing: Vec<_> = self
+        .missing
+        .iter()
+        .map(|id| format!("{:?}", id.as_str()))
+        .collect();
+      format!("refers to undefined {}", missing.join(" and "))
+    }
+  }
+
+  impl fmt::Display for DanglingEdge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(
+        f,
+        "edge {:?} -> {:?} {}",
+        self.source.as_str(),
+        self.target.as_str(),
+        self.describe_missing()
+      )
+    }
+  }
+
+  /// An attribute set through an [`Attributes`] map which Graphviz would
+  /// ignore, reported by [`GraphBuilder::validate_attributes`].
+  #[derive(Debug, Clone, PartialEq)]
+  #[allow(missing_docs)]
+  pub struct InvalidAttribute {
+    pub entity: EntityRef,
+    pub name: String,
+    pub problem: AttrProblem,
+  }
+
+  impl fmt::Display for InvalidAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "{}: {:?}: {}", self.entity, self.name, self.problem)
+    }
+  }
+
+  /// Why a graph could not be built.
+  #[derive(Debug)]
+  pub enum GraphError {
+    /// No valid DOT id could be formed from this id, even by quoting it. Only
+    /// checked with [`GraphBuilder::verify_ids`].
+    InvalidId(Id),
+    /// Two vertices with the same id have different attributes, either added
+    /// with [`MergeStrategy::Error`] or found by
+    /// [`GraphBuilder::validate_vertices`].
+    DuplicateVertex(DuplicateVertex),
+    /// Every edge with an undefined endpoint. Only checked with
+    /// [`GraphBuilder::validate_edges`].
+    DanglingEdges(Vec<DanglingEdge>),
+    /// The first unknown or misplaced attribute found by
+    /// [`GraphBuilder::validate_attributes`].
+    InvalidAttribute(InvalidAttribute),
+    /// A vertex, edge endpoint, or subgraph with an empty id, found in
+    /// [`BuildMode::Strict`].
+    EmptyId(EntityRef),
+    /// A vertex, edge, or subgraph with an empty label, found in
+    /// [`BuildMode::Strict`].
+    EmptyLabel(EntityRef),
+    /// A name in [`GraphAttributes::layers`] containing a separator
+    /// character, so graphviz would split it into several layers. Dropped
+    /// instead in [`BuildMode::Lenient`].
+    InvalidLayerName(String),
+    /// The output file could not be written.
+    Io(io::Error),
+    /// The [`fmt::Write`] passed to [`GraphBuilder::build_into`] failed.
+    Fmt(fmt::Error),
+  }
+
+  impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+        Self::InvalidId(id) => write!(f, "could not form a valid DOT id from {:?}", id.as_str()),
+        Self::DuplicateVertex(e) => e.fmt(f),
+        Self::DanglingEdges(edges) => {
+          let edges: Vec<_> = edges.iter().map(|e| e.to_string()).collect();
+          write!(f, "{}", edges.join("; "))
+        },
+        Self::InvalidAttribute(e) => e.fmt(f),
+        Self::EmptyId(e) => write!(f, "{}: empty id", e),
+        Self::EmptyLabel(e) => write!(f, "{}: empty label", e),
+        Self::InvalidLayerName(name) => {
+          write!(f, "layer name {:?} contains a separator character", name)
+        },
+        Self::Io(e) => write!(f, "failed to write output: {}", e),
+        Self::Fmt(e) => write!(f, "failed to format output: {}", e),
+      }
+    }
+  }
+
+  impl std::error::Error for GraphError {
+    fn source(&self) -> Option<&(dyn std::error::Error+'static)> {
+      match self {
+        Self::InvalidId(_)
+        | Self::DanglingEdges(_)
+        | Self::InvalidAttribute(_)
+        | Self::EmptyId(_)
+        | Self::EmptyLabel(_)
+        | Self::InvalidLayerName(_) => None,
+        Self::DuplicateVertex(e) => Some(e),
+        Self::Io(e) => Some(e),
+        Self::Fmt(e) => Some(e),
+      }
+    }
+  }
+
+  impl From<DuplicateVertex> for GraphError {
+    fn from(e: DuplicateVertex) -> Self { Self::DuplicateVertex(e) }
+  }
+
+  impl From<io::Error> for GraphError {
+    fn from(e: io::Error) -> Self { Self::Io(e) }
+  }
+
+  impl From<fmt::Error> for GraphError {
+    fn from(e: fmt::Error) -> Self { Self::Fmt(e) }
+  }
+
+  /// Every name in `attrs.layers` which contains one of the characters
+  /// separating layer names.
+  fn invalid_layer_names(attrs: &GraphAttributes) -> Vec<String> {
+    let separators = attrs.layersep.as_deref().unwrap_or(":\t ");
+    attrs
+      .layers
+      .iter()
+      .flatten()
+      .filter(|name| name.contains(|c| separators.contains(c)))
+      .cloned()
+      .collect()
+  }
+
+  /// What [`GraphBuilder`]'s validation found which a lenient build skips or
+  /// replaces rather than failing on.
+  #[derive(Debug, Default)]
+  struct Findings {
+    invalid_attributes: Vec<InvalidAttribute>,
+    empty_ids_and_labels: Vec<GraphError>,
+    invalid_layers: Vec<String>,
+  }
+
+  /// Check that `id` can be printed as a valid DOT id, quoting it if `policy`
+  /// alone doesn't produce one.
+  fn verify_id(id: &Id, policy: QuotingPolicy) -> Result<(), GraphError> {
+    if Id::is_valid_token(&id.clone().escaped(policy))
+      || Id::is_valid_token(&id.clone().escaped(QuotingPolicy::Always))
+    {
+      Ok(())
+    } else {
+      Err(GraphError::InvalidId(id.clone()))
+    }
+  }
+
+  /// Check every id referenced within `e` with [`verify_id`].
+  fn verify_ids(e: &Entity, policy: QuotingPolicy) -> Result<(), GraphError> {
+    match e {
+      Entity::Vertex(v) => verify_id(&v.id, policy),
+      Entity::Edge(edge) => {
+        verify_id(&edge.source, policy)?;
+        verify_id(&edge.target, policy)
+      },
+      Entity::Subgraph(sg) => {
+        verify_id(&sg.id, policy)?;
+        sg.entities.iter().try_for_each(|e| verify_ids(e, policy))
+      },
+      Entity::Group(g) => g.entities.iter().try_for_each(|e| verify_ids(e, policy)),
+      Entity::Comment(_) => Ok(()),
+    }
+  }
+
+  /// Generates a vertex for an id, e.g. with
+  /// [`GraphBuilder::auto_create_vertices`].
+  pub type VertexFactory = dyn Fn(&Id) -> Vertex;
+
+  /// Record the ids of every vertex and subgraph within `e`.
+  fn record_ids(ids: &mut HashSet<Id>, e: &Entity) {
+    match e {
+      Entity::Vertex(v) => {
+        ids.insert(v.id.clone());
+      },
+      Entity::Edge(_) | Entity::Comment(_) => (),
+      Entity::Subgraph(sg) => {
+        ids.insert(sg.id.clone());
+        for e in sg.entities.iter() {
+          record_ids(ids, e);
+        }
+      },
+      Entity::Group(g) => {
+        for e in g.entities.iter() {
+          record_ids(ids, e);
+        }
+      },
+    }
+  }
+
+  /// Adds entities to a subgraph within the scope of
+  /// [`GraphBuilder::subgraph`].
+  ///
+  /// Ids are tracked across every scope along with the parent
+  /// [`GraphBuilder`], so [`Self::contains_id`] sees the whole graph built so
+  /// far.
+  pub struct SubgraphBuilder<'a> {
+    subgraph: Subgraph,
+    ids: &'a mut HashSet<Id>,
+  }
+
+  impl<'a> SubgraphBuilder<'a> {
+    /// Modify the attributes of the subgraph itself.
+    pub fn attributes(&mut self) -> &mut Subgraph { &mut self.subgraph }
+
+    /// Add an entity to the body of this subgraph.
+    pub fn entity(&mut self, e: Entity) {
+      record_ids(self.ids, &e);
+      self.subgraph.entities.push(e);
+    }
+
+    /// Add a vertex to the body of this subgraph.
+    pub fn vertex(&mut self, v: Vertex) { self.entity(Entity::Vertex(v)); }
+
+    /// Add an edge to the body of this subgraph.
+    pub fn edge(&mut self, e: Edge) { self.entity(Entity::Edge(e)); }
+
+    /// Whether a vertex or subgraph with this id has been added anywhere in the
+    /// graph.
+    pub fn contains_id(&self, id: &Id) -> bool { self.ids.contains(id) }
+
+    /// Add a nested subgraph, populated within the scope of `f`.
+    pub fn subgraph<F: FnOnce(&mut SubgraphBuilder)>(&mut self, id: impl Into<Id>, f: F) {
+      let id = id.into();
+      self.ids.insert(id.clone());
+      let mut child = SubgraphBuilder {
+        subgraph: Subgraph::new(id),
+        ids: self.ids,
+      };
+      f(&mut child);
+      let SubgraphBuilder { subgraph, .. } = child;
+      self.subgraph.entities.push(Entity::Subgraph(subgraph));
+    }
+  }
+
+  impl GraphBuilder {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+      Self {
+        entities: Vec::new(),
+        next_key: 0,
+        theme: None,
+        graph_attributes: GraphAttributes::default(),
+        ids: HashSet::new(),
+        index: Some(EntityIndex::default()),
+        auto_vertex: None,
+        dedupe: false,
+        merge_strategy: MergeStrategy::FirstWins,
+        id_generator: Box::new(Uuids),
+        quoting_policy: QuotingPolicy::default(),
+        verify_ids: false,
+        validate_edges: false,
+        validate_vertices: false,
+        validate_attributes: false,
+        attr_list_style: AttrListStyle::default(),
+        format_options: FormatOptions::default(),
+        stable_order: false,
+        cluster_naming: ClusterNaming::default(),
+        directed: true,
+        build_mode: None,
+        preamble: Vec::new(),
+        generated_by: None,
+      }
+    }
+
+    /// Start the document with a comment naming this crate and its version,
+    /// when the graph was generated, and `header`'s provenance if any.
+    pub fn set_generated_by(&mut self, header: GeneratedBy) { self.generated_by = Some(header); }
+
+    /// Add a comment to the top of the document, before the `digraph`
+    /// statement. Comments are emitted in the order they were added.
+    pub fn add_preamble_comment(&mut self, text: impl Into<String>) {
+      self.preamble.push(text.into());
+    }
+
+    /// Add a comment to the top level of the graph body, e.g. as a section
+    /// header for the entities added after it.
+    pub fn add_comment(&mut self, text: impl Into<String>) {
+      self.accept_entity(Entity::Comment(text.into()));
+    }
+
+    /// Print vertices sorted by id, then subgraphs by id, then groups, then
+    /// edges by source and target, within the graph and within each subgraph,
+    /// instead of in insertion order. Entities which compare equal keep their
+    /// insertion order.
+    ///
+    /// This keeps output stable when entities are added while iterating over
+    /// e.g. a [`HashMap`](std::collections::HashMap).
+    pub fn stable_order(&mut self, enabled: bool) { self.stable_order = enabled; }
+
+    /// Write a `digraph` with `->` edges, rather than an undirected `graph`
+    /// with `--` edges. Defaults to `true`.
+    pub fn set_directed(&mut self, directed: bool) { self.directed = directed; }
+
+    /// Whether the graph is directed, as set by [`Self::set_directed`].
+    pub fn is_directed(&self) -> bool { self.directed }
+
+    /// How to name clusters so that older Graphviz releases draw them. Defaults
+    /// to [`ClusterNaming::AsIs`].
+    pub fn set_cluster_naming(&mut self, naming: ClusterNaming) { self.cluster_naming = naming; }
+
+    /// Configure indentation and line breaks in the output of [`Self::build`].
+    pub fn set_format_options(&mut self, options: FormatOptions) { self.format_options = options; }
+
+    /// How to format the `[...]` attribute lists of vertices, edges, and node
+    /// defaults. Defaults to `[a="x", b="y"]`.
+    pub fn set_attr_list_style(&mut self, style: AttrListStyle) { self.attr_list_style = style; }
+
+    /// Re-lex every id emitted by [`Self::build`] with an independent checker,
+    /// quoting any which would not form a valid DOT ID. This guards against
+    /// bugs in the quoting heuristics at some cost in speed.
+    ///
+    /// [`Self::build`] returns [`GraphError::InvalidId`] if even the quoted
+    /// form of an id fails to lex.
+    pub fn verify_ids(&mut self, enabled: bool) { self.verify_ids = enabled; }
+
+    /// Check that the endpoints of every edge were added as vertices or
+    /// subgraphs, including any created by [`Self::auto_create_vertices`].
+    /// Graphviz would otherwise silently create a vertex for each, which also
+    /// hides edges with empty endpoints such as [`Edge::default`].
+    ///
+    /// [`Self::build`] returns [`GraphError::DanglingEdges`] listing every
+    /// such edge.
+    pub fn validate_edges(&mut self, enabled: bool) { self.validate_edges = enabled; }
+
+    /// Check that any vertices sharing an id, anywhere in the graph, have
+    /// identical attributes. Graphviz would otherwise silently merge them.
+    ///
+    /// [`Self::build`] returns [`GraphError::DuplicateVertex`] for the first
+    /// conflict found. See [`Self::conflicting_vertices`] to list them all,
+    /// e.g. to report them as warnings instead.
+    pub fn validate_vertices(&mut self, enabled: bool) { self.validate_vertices = enabled; }
+
+    /// Check the names set through the [`Attributes`] map of every vertex,
+    /// edge, and subgraph against the [`attr_table`], catching typos like
+    /// `colour` as well as attributes which don't apply to that kind of
+    /// entity.
+    ///
+    /// [`Self::build`] returns [`GraphError::InvalidAttribute`] for the first
+    /// problem found. See [`Self::invalid_attributes`] to list them all.
+    pub fn validate_attributes(&mut self, enabled: bool) { self.validate_attributes = enabled; }
+
+    /// Enforce or relax every validation pass at once. By default, each is
+    /// controlled by its own setting.
+    pub fn set_build_mode(&mut self, mode: BuildMode) { self.build_mode = Some(mode); }
+
+    /// When to quote vertex, edge endpoint, subgraph, and graph ids. Defaults
+    /// to [`QuotingPolicy::Minimal`].
+    pub fn set_quoting_policy(&mut self, policy: QuotingPolicy) { self.quoting_policy = policy; }
+
+    /// Use `generator` to mint ids in [`Self::fresh_id`]. Defaults to
+    /// [`Uuids`].
+    pub fn set_id_generator(&mut self, generator: impl IdGenerator+'static) {
+      self.id_generator = Box::new(generator);
+    }
+
+    /// Mint an id from the configured [`IdGenerator`].
+    pub fn fresh_id(&mut self) -> Id { self.id_generator.next_id() }
+
+    /// A vertex with no attributes and an id from [`Self::fresh_id`].
+    pub fn fresh_vertex(&mut self) -> Vertex { Vertex::new(self.fresh_id()) }
+
+    /// A subgraph with no attributes and an id from [`Self::fresh_id`].
+    pub fn fresh_subgraph(&mut self) -> Subgraph { Subgraph::new(self.fresh_id()) }
+
+    /// Keep only one of any vertices with the same id (merged according to
+    /// [`Self::set_merge_strategy`]), and only one of any identical edges.
+    pub fn dedupe(&mut self, enabled: bool) { self.dedupe = enabled; }
+
+    /// How to merge vertices with the same id when [`Self::dedupe`] is
+    /// enabled. Defaults to [`MergeStrategy::FirstWins`].
+    pub fn set_merge_strategy(&mut self, strategy: MergeStrategy) {
+      self.merge_strategy = strategy;
+    }
+
+    fn index(&mut self) -> &EntityIndex {
+      let entities = &self.entities;
+      self
+        .index
+        .get_or_insert_with(|| EntityIndex::build(entities))
+    }
+
+    /// Where an edge identical to `e` is, anywhere in the graph.
+    fn find_duplicate_edge(&mut self, e: &Edge) -> Option<Vec<usize>> {
+      self.index();
+      let Self {
+        index, entities, ..
+      } = self;
+      index
+        .as_ref()
+        .unwrap()
+        .edges
+        .get(&(e.source.clone(), e.target.clone()))?
+        .iter()
+        .find(|path| matches!(entity_at(entities, path), Entity::Edge(existing) if existing == e))
+        .cloned()
+    }
+
+    /// Put `e` at `path`, one past the last entity at the top level or within
+    /// a subgraph or group, without recording its ids.
+    fn insert_entity(&mut self, path: &[usize], e: Entity) {
+      if let Some(index) = &mut self.index {
+        index.record(&mut path.to_vec(), &e);
+      }
+      match path {
+        [_] => {
+          let key = self.next_key;
+          self.next_key += 1;
+          self.entities.push((key, e));
+        },
+        [parent @ .., _] => match entity_at_mut(&mut self.entities, parent) {
+          Entity::Subgraph(Subgraph { entities, .. }) | Entity::Group(Group { entities, .. }) => {
+            entities.push(e)
+          },
+          Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => unreachable!(),
+        },
+        [] => unreachable!(),
+      }
+    }
+
+    /// Insert `e` at `path` as by [`Self::insert_entity`], unless it is merged
+    /// into an existing entity. The contents of a subgraph or group are
+    /// inserted one at a time after it, so they're merged with each other as
+    /// well as with the rest of the graph.
+    fn dedupe_entity(&mut self, path: &mut Vec<usize>, e: Entity) -> Result<(), DuplicateVertex> {
+      let strategy = self.merge_strategy;
+      match e {
+        Entity::Vertex(v) => {
+          let existing = match self.index().vertices.get(&v.id) {
+            None => {
+              self.insert_entity(path, Entity::Vertex(v));
+              return Ok(());
+            },
+            Some(existing) => existing.clone(),
+          };
+          let existing = match entity_at_mut(&mut self.entities, &existing) {
+            Entity::Vertex(existing) => existing,
+            _ => unreachable!(),
+          };
+          match strategy {
+            _ if *existing == v => Ok(()),
+            MergeStrategy::FirstWins => Ok(()),
+            MergeStrategy::LastWins => {
+              *existing = v;
+              Ok(())
+            },
+            MergeStrategy::Error => Err(DuplicateVertex {
+              existing: Box::new(existing.clone()),
+              new: Box::new(v),
+            }),
+          }
+        },
+        Entity::Edge(edge) => {
+          if self.find_duplicate_edge(&edge).is_none() {
+            self.insert_entity(path, Entity::Edge(edge));
+          }
+          Ok(())
+        },
+        Entity::Subgraph(mut sg) => {
+          let entities = mem::take(&mut sg.entities);
+          self.insert_entity(path, Entity::Subgraph(sg));
+          self.dedupe_entities(path, entities)
+        },
+        Entity::Group(mut g) => {
+          let entities = mem::take(&mut g.entities);
+          self.insert_entity(path, Entity::Group(g));
+          self.dedupe_entities(path, entities)
+        },
+        e @ Entity::Comment(_) => {
+          self.insert_entity(path, e);
+          Ok(())
+        },
+      }
+    }
+
+    /// Apply [`Self::dedupe_entity`] to each of `entities`, adding them to the
+    /// subgraph or group at `path`.
+    fn dedupe_entities(
+      &mut self,
+      path: &mut Vec<usize>,
+      entities: Vec<Entity>,
+    ) -> Result<(), DuplicateVertex> {
+      for e in entities.into_iter() {
+        let len = match entity_at(&self.entities, path) {
+          Entity::Subgraph(Subgraph { entities, .. }) | Entity::Group(Group { entities, .. }) => {
+            entities.len()
+          },
+          Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => unreachable!(),
+        };
+        path.push(len);
+        let result = self.dedupe_entity(path, e);
+        path.pop();
+        result?;
+      }
+      Ok(())
+    }
+
+    /// Add an entity to the top level of the graph, returning an error instead
+    /// of panicking if it conflicts with an existing vertex.
+    pub fn try_accept_entity(&mut self, e: Entity) -> Result<(), DuplicateVertex> {
+      if !self.dedupe {
+        self.push_entity(e);
+        return Ok(());
+      }
+      let len = self.entities.len();
+      if let Err(e) = self.dedupe_entity(&mut vec![len], e) {
+        /* Don't leave part of a subgraph behind. */
+        if self.entities.len() > len {
+          self.entities.truncate(len);
+          self.index = None;
+        }
+        return Err(e);
+      }
+      if let Some((_, e)) = self.entities.get(len) {
+        record_ids(&mut self.ids, e);
+      }
+      Ok(())
+    }
+
+    fn push_entity(&mut self, e: Entity) -> u64 {
+      record_ids(&mut self.ids, &e);
+      let key = self.next_key;
+      self.insert_entity(&[self.entities.len()], e);
+      key
+    }
+
+    /// Add an entity to the top level of the graph.
+    ///
+    /// # Panics
+    /// If [`Self::dedupe`] is enabled with [`MergeStrategy::Error`] and `e`
+    /// conflicts with an existing vertex. Use [`Self::try_accept_entity`] to
+    /// handle this case.
+    pub fn accept_entity(&mut self, e: Entity) {
+      if let Err(e) = self.try_accept_entity(e) {
+        panic!("{}", e);
+      }
+    }
+
+    fn all_entities(&self) -> AllEntities<'_> {
+      AllEntities {
+        top: self.entities.iter(),
+        nested: Vec::new(),
+      }
+    }
+
+    /// The top-level entities added so far, in the order they were added.
+    pub fn entities(&self) -> impl Iterator<Item=&Entity> { self.entities.iter().map(|(_, e)| e) }
+
+    /// Every vertex added so far, including those within subgraphs.
+    pub fn vertices(&self) -> impl Iterator<Item=&Vertex> {
+      self.all_entities().filter_map(|e| match e {
+        Entity::Vertex(v) => Some(v),
+        _ => None,
+      })
+    }
+
+    /// Every edge added so far, including those within subgraphs.
+    pub fn edges(&self) -> impl Iterator<Item=&Edge> {
+      self.all_entities().filter_map(|e| match e {
+        Entity::Edge(e) => Some(e),
+        _ => None,
+      })
+    }
+
+    /// Every subgraph added so far, including those nested within other
+    /// subgraphs.
+    pub fn subgraphs(&self) -> impl Iterator<Item=&Subgraph> {
+      self.all_entities().filter_map(|e| match e {
+        Entity::Subgraph(sg) => Some(sg),
+        _ => None,
+      })
+    }
+
+    /// Whether a vertex with this id has been added anywhere in the graph.
+    pub fn contains_vertex(&self, id: &Id) -> bool {
+      match &self.index {
+        Some(index) => index.vertices.contains_key(id),
+        None => self.vertices().any(|v| &v.id == id),
+      }
+    }
+
+    /// Every vertex whose attributes differ from those of the first vertex
+    /// added with the same id, anywhere in the graph.
+    pub fn conflicting_vertices(&self) -> Vec<DuplicateVertex> {
+      let mut first: HashMap<&Id, &Vertex> = HashMap::new();
+      let mut ret = Vec::new();
+      for v in self.vertices() {
+        match first.get(&v.id) {
+          None => {
+            first.insert(&v.id, v);
+          },
+          Some(existing) if *existing == v => (),
+          Some(existing) => ret.push(DuplicateVertex {
+            existing: Box::new((*existing).clone()),
+            new: Box::new(v.clone()),
+          }),
+        }
+      }
+      ret
+    }
+
+    /// Every attribute set through an [`Attributes`] map, anywhere in the
+    /// graph, which is unknown to Graphviz or doesn't apply to its entity.
+    pub fn invalid_attributes(&self) -> Vec<InvalidAttribute> {
+      let mut ret = Vec::new();
+      for e in self.all_entities() {
+        let (entity, kind, attributes) = match e {
+          Entity::Vertex(v) => (EntityRef::Vertex(v.id.clone()), UsedBy::NODE, &v.attributes),
+          Entity::Edge(e) => (
+            EntityRef::Edge {
+              source: e.source.clone(),
+              target: e.target.clone(),
+            },
+            UsedBy::EDGE,
+            &e.attributes,
+          ),
+          Entity::Subgraph(sg) => (
+            EntityRef::Subgraph(sg.id.clone()),
+            subgraph_kind(sg),
+            &sg.attributes,
+          ),
+          Entity::Group(_) | Entity::Comment(_) => continue,
+        };
+        for name in attributes.keys() {
+          if let Err(problem) = attr_table::check(name, kind) {
+            ret.push(InvalidAttribute {
+              entity: entity.clone(),
+              name: name.clone(),
+              problem,
+            });
+          }
+        }
+      }
+      ret
+    }
+
+    /// Every vertex, edge, and subgraph with an empty id or label, as
+    /// [`GraphError::EmptyId`] or [`GraphError::EmptyLabel`].
+    fn empty_ids_and_labels(&self) -> Vec<GraphError> {
+      let mut ret = Vec::new();
+      for (_, e) in self.entities.iter() {
+        find_empty(e, &mut ret);
+      }
+      ret
+    }
+
+    /// Every edge from `source` to `target`.
+    pub fn find_edges_between<'a>(
+      &'a self,
+      source: &'a Id,
+      target: &'a Id,
+    ) -> impl Iterator<Item=&'a Edge> {
+      self
+        .edges()
+        .filter(move |e| &e.source == source && &e.target == target)
+    }
+
+    /// Add a vertex to the top level of the graph, returning a handle to it.
+    ///
+    /// # Panics
+    /// Under the same conditions as [`Self::accept_entity`].
+    pub fn add_vertex(&mut self, v: Vertex) -> VertexRef {
+      let id = v.id.clone();
+      self.accept_entity(Entity::Vertex(v));
+      VertexRef(id)
+    }
+
+    /// Add an edge to the top level of the graph, returning a handle to it.
+    ///
+    /// If [`Self::dedupe`] is enabled and an identical edge already exists,
+    /// `e` is dropped as by [`Self::accept_entity`]. The handle then refers to
+    /// the existing edge if it is at the top level, or else to no edge, as
+    /// if it had been removed.
+    pub fn add_edge(&mut self, e: Edge) -> EdgeRef {
+      if self.dedupe {
+        if let Some(path) = self.find_duplicate_edge(&e) {
+          if let [i] = path[..] {
+            return EdgeRef(self.entities[i].0);
+          }
+          let key = self.next_key;
+          self.next_key += 1;
+          return EdgeRef(key);
+        }
+      }
+      EdgeRef(self.push_entity(Entity::Edge(e)))
+    }
+
+    /// Add a subgraph to the top level of the graph, returning a handle to it.
+    ///
+    /// If [`Self::dedupe`] is enabled, any of its contents which duplicate an
+    /// existing vertex or edge are merged as by [`Self::accept_entity`].
+    pub fn add_subgraph(&mut self, sg: Subgraph) -> SubgraphRef {
+      let id = sg.id.clone();
+      self.accept_entity(Entity::Subgraph(sg));
+      SubgraphRef(id)
+    }
+
+    /// Add an edge with no attributes between two vertices.
+    pub fn connect(&mut self, source: &VertexRef, target: &VertexRef) -> EdgeRef {
+      self.add_edge(Edge {
+        source: source.id().clone(),
+        target: target.id().clone(),
+        ..Default::default()
+      })
+    }
+
+    /// Modify a vertex before the graph is built. Returns [`None`] if the
+    /// vertex has been removed.
+    pub fn vertex_mut(&mut self, v: &VertexRef) -> Option<&mut Vertex> {
+      self.index = None;
+      find_vertex_mut(self.entities.iter_mut().map(|(_, e)| e), v.id())
+    }
+
+    /// Modify an edge before the graph is built. Returns [`None`] if the edge
+    /// has been removed.
+    pub fn edge_mut(&mut self, e: &EdgeRef) -> Option<&mut Edge> {
+      self.index = None;
+      let EdgeRef(key) = e;
+      self
+        .entities
+        .iter_mut()
+        .find(|(k, _)| k == key)
+        .and_then(|(_, e)| match e {
+          Entity::Edge(e) => Some(e),
+          _ => None,
+        })
+    }
+
+    /// Modify a subgraph before the graph is built. Returns [`None`] if the
+    /// subgraph has been removed.
+    pub fn subgraph_mut(&mut self, sg: &SubgraphRef) -> Option<&mut Subgraph> {
+      self.index = None;
+      find_subgraph_mut(self.entities.iter_mut().map(|(_, e)| e), sg.id())
+    }
+
+    fn take_vertex(&mut self, id: &Id) -> Option<Vertex> {
+      self.index = None;
+      match self
+        .entities
+        .iter()
+        .position(|(_, e)| matches!(e, Entity::Vertex(v) if &v.id == id))
+      {
+        Some(i) => match self.entities.remove(i) {
+          (_, Entity::Vertex(v)) => Some(v),
+          _ => unreachable!(),
+        },
+        None => self.entities.iter_mut().find_map(|(_, e)| match e {
+          Entity::Subgraph(sg) => take_vertex(&mut sg.entities, id),
+          Entity::Group(g) => take_vertex(&mut g.entities, id),
+          Entity::Vertex(_) | Entity::Edge(_) | Entity::Comment(_) => None,
+        }),
+      }
+    }
+
+    /// Stop tracking `id` once no vertex or subgraph uses it anymore.
+    fn forget_id_if_unused(&mut self, id: &Id) {
+      if !self.known_ids().contains(id) {
+        self.ids.remove(id);
+      }
+    }
+
+    /// Remove the vertex with this id from anywhere in the graph. If `cascade`
+    /// is set, every edge into or out of it is removed as well.
+    pub fn remove_vertex(&mut self, id: &Id, cascade: bool) -> Option<Vertex> {
+      let ret = self.take_vertex(id)?;
+      self.forget_id_if_unused(id);
+      if cascade {
+        let incident = |e: &Edge| &e.source == id || &e.target == id;
+        self.entities.retain_mut(|(_, e)| match e {
+          Entity::Edge(e) => !incident(e),
+          Entity::Subgraph(sg) => {
+            retain_edges(&mut sg.entities, &|e| !incident(e));
+            true
+          },
+          Entity::Group(g) => {
+            retain_edges(&mut g.entities, &|e| !incident(e));
+            true
+          },
+          Entity::Vertex(_) | Entity::Comment(_) => true,
+        });
+      }
+      Some(ret)
+    }
+
+    /// Remove a top-level edge.
+    pub fn remove_edge(&mut self, e: &EdgeRef) -> Option<Edge> {
+      self.index = None;
+      let EdgeRef(key) = e;
+      let i = self
+        .entities
+        .iter()
+        .position(|(k, e)| k == key && matches!(e, Entity::Edge(_)))?;
+      match self.entities.remove(i) {
+        (_, Entity::Edge(e)) => Some(e),
+        _ => unreachable!(),
+      }
+    }
+
+    /// Remove every edge from `source` to `target` anywhere in the graph.
+    pub fn remove_edges_between(&mut self, source: &Id, target: &Id) {
+      self.index = None;
+      let keep = |e: &Edge| !(&e.source == source && &e.target == target);
+      self.entities.retain_mut(|(_, e)| match e {
+        Entity::Edge(e) => keep(e),
+        Entity::Subgraph(sg) => {
+          retain_edges(&mut sg.entities, &keep);
+          true
+        },
+        Entity::Group(g) => {
+          retain_edges(&mut g.entities, &keep);
+          true
+        },
+        Entity::Vertex(_) | Entity::Comment(_) => true,
+      });
+    }
+
+    /// Modify the vertex with this id, wherever it is in the graph. Returns
+    /// `false` if no such vertex exists. The vertex may be given a new id.
+    pub fn update_vertex<F: FnOnce(&mut Vertex)>(&mut self, id: &Id, f: F) -> bool {
+      self.index = None;
+      let new_id = match find_vertex_mut(self.entities.iter_mut().map(|(_, e)| e), id) {
+        Some(v) => {
+          f(v);
+          v.id.clone()
+        },
+        None => return false,
+      };
+      if &new_id != id {
+        self.ids.insert(new_id);
+        self.forget_id_if_unused(id);
+      }
+      true
+    }
+
+    /// Move a vertex from wherever it is in the graph into the body of a
+    /// subgraph. Returns `false` if either could not be found.
+    pub fn move_into(&mut self, v: &VertexRef, sg: &SubgraphRef) -> bool {
+      if self.subgraph_mut(sg).is_none() {
+        return false;
+      }
+      let vertex = self.take_vertex(v.id());
+      match vertex {
+        Some(vertex) => {
+          self
+            .subgraph_mut(sg)
+            .unwrap()
+            .entities
+            .push(Entity::Vertex(vertex));
+          true
+        },
+        None => false,
+      }
+    }
+
+    /// Whether a vertex or subgraph with this id has been added anywhere in the
+    /// graph.
+    pub fn contains_id(&self, id: &Id) -> bool { self.ids.contains(id) }
+
+    /// Add a subgraph to the top level of the graph, populated within the
+    /// scope of `f`.
+    pub fn subgraph<F: FnOnce(&mut SubgraphBuilder)>(&mut self, id: impl Into<Id>, f: F) {
+      let id = id.into();
+      self.ids.insert(id.clone());
+      let mut sg = SubgraphBuilder {
+        subgraph: Subgraph::new(id),
+        ids: &mut self.ids,
+      };
+      f(&mut sg);
+      let SubgraphBuilder { subgraph, .. } = sg;
+      self.accept_entity(Entity::Subgraph(subgraph));
+    }
+
+    /// An empty graph with the same settings as this one, which takes over
+    /// its [`IdGenerator`] until it is handed back.
+    fn child_builder(&mut self) -> GraphBuilder {
+      GraphBuilder {
+        theme: self.theme.clone(),
+        dedupe: self.dedupe,
+        merge_strategy: self.merge_strategy,
+        id_generator: mem::replace(&mut self.id_generator, Box::new(Uuids)),
+        quoting_policy: self.quoting_policy,
+        verify_ids: self.verify_ids,
+        validate_edges: self.validate_edges,
+        validate_vertices: self.validate_vertices,
+        validate_attributes: self.validate_attributes,
+        attr_list_style: self.attr_list_style,
+        format_options: self.format_options,
+        stable_order: self.stable_order,
+        cluster_naming: self.cluster_naming,
+        directed: self.directed,
+        build_mode: self.build_mode,
+        ..GraphBuilder::new()
+      }
+    }
+
+    /// Build a graph within the scope of `f`, then add all of its entities to
+    /// this graph with every id qualified by `namespace`.
+    ///
+    /// The scope starts with the same settings as this graph, such as
+    /// [`Self::dedupe`] and [`Self::set_id_generator`], but none of its
+    /// entities. Edge endpoints are rewritten too, so edges within the scope
+    /// keep pointing at the same vertices. To connect to vertices outside the
+    /// scope, add those edges to `self` after `f` returns, using
+    /// [`IdNamespace::qualify`].
+    pub fn scoped<F: FnOnce(&mut GraphBuilder)>(
+      &mut self,
+      namespace: impl Into<IdNamespace>,
+      f: F,
+    ) {
+      let namespace = namespace.into();
+      let mut child = self.child_builder();
+      f(&mut child);
+      self.id_generator = child.id_generator;
+      for (_, e) in child.entities.into_iter() {
+        self.accept_entity(namespace.apply(e));
+      }
+    }
+
+    /// Add an edge between each consecutive pair of `ids`, like the DOT
+    /// statement `a -> b -> c`.
+    pub fn chain<I, T>(&mut self, ids: I)
+    where
+      I: IntoIterator<Item=T>,
+      T: Into<Id>,
+    {
+      self.chain_with(ids, Edge::default());
+    }
+
+    /// Add an edge between each consecutive pair of `ids`, copying all other
+    /// attributes from `template`.
+    pub fn chain_with<I, T>(&mut self, ids: I, template: Edge)
+    where
+      I: IntoIterator<Item=T>,
+      T: Into<Id>,
+    {
+      let mut ids = ids.into_iter().map(T::into);
+      if let Some(mut prev) = ids.next() {
+        for id in ids {
+          self.accept_entity(Entity::Edge(Edge {
+            source: prev,
+            target: id.clone(),
+            ..template.clone()
+          }));
+          prev = id;
+        }
+      }
+    }
+
+    /// Chain each of `ids` together in order with invisible edges, nudging
+    /// graphviz to lay them out next to each other.
+    pub fn align<I: IntoIterator<Item=Id>>(&mut self, ids: I) {
+      self.chain_with(ids, Edge::invisible(Id::new(""), Id::new("")));
+    }
+
+    /// When [`Self::build`] is called, add a vertex for every edge endpoint
+    /// which was never added as a vertex or subgraph, instead of letting graphviz
+    /// invent one. `make` generates the vertex for each missing id, e.g. to
+    /// highlight possible typos.
+    pub fn auto_create_vertices<F: Fn(&Id) -> Vertex+'static>(&mut self, make: F) {
+      self.auto_vertex = Some(Box::new(make));
+    }
+
+    /// The ids an edge endpoint may refer to: every vertex and subgraph.
+    fn known_ids(&self) -> HashSet<&Id> {
+      self
+        .vertices()
+        .map(|v| &v.id)
+        .chain(self.subgraphs().map(|sg| &sg.id))
+        .collect()
+    }
+
+    /// Every edge with an endpoint which is neither a vertex nor a subgraph,
+    /// counting `synthesized` vertices as well.
+    fn dangling_edges(&self, synthesized: &[Entity]) -> Vec<DanglingEdge> {
+      let mut known = self.known_ids();
+      known.extend(synthesized.iter().filter_map(|e| match e {
+        Entity::Vertex(v) => Some(&v.id),
+        _ => None,
+      }));
+      self
+        .edges()
+        .filter_map(|e| {
+          let mut missing: Vec<Id> = [&e.source, &e.target]
+            .into_iter()
+            .filter(|id| !known.contains(id))
+            .cloned()
+            .collect();
+          missing.dedup();
+          if missing.is_empty() {
+            None
+          } else {
+            Some(DanglingEdge {
+              source: e.source.clone(),
+              target: e.target.clone(),
+              missing,
+            })
+          }
+        })
+        .collect()
+    }
+
+    /// Synthesize a vertex for every edge endpoint which doesn't refer to an
+    /// existing vertex or subgraph, appending them to the top level of the
+    /// graph.
+    fn synthesize_vertices(&self, make: &VertexFactory) -> Vec<Entity> {
+      let mut known = self.known_ids();
+      let mut ret = Vec::new();
+      for e in self.edges() {
+        for id in [&e.source, &e.target] {
+          if known.insert(id) {
+            ret.push(Entity::Vertex(make(id)));
+          }
+        }
+      }
+      ret
+    }
+
+    /// Style the graph with `theme` when [`Self::build`] is called. See the
+    /// [`theme`](crate::theme) module for how it's applied.
+    pub fn set_theme(&mut self, theme: Theme) { self.theme = Some(theme); }
+
+    /// Set attributes applying to the whole graph.
+    pub fn set_graph_attributes(&mut self, attrs: GraphAttributes) {
+      self.graph_attributes = attrs;
+    }
+
+    fn bump_indent(indent: &mut usize) { *indent += 1; }
+
+    /// Nesting is balanced by construction, so a failure here is a bug in the
+    /// printer rather than in the graph being printed.
+    fn unbump_indent(indent: &mut usize) {
+      assert!(*indent >= 1);
+      *indent -= 1;
+    }
+
+    /// Escape backslashes and double quotes so `s` can be placed within a
+    /// double-quoted string.
+    fn escape_quoted(s: &str) -> String {
+      let mut ret = String::with_capacity(s.len());
+      for c in s.chars() {
+        match c {
+          '\\' | '"' => {
+            ret.push('\\');
+            ret.push(c);
+          },
+          c => ret.push(c),
+        }
+      }
+      ret
+    }
+
+    fn write_font_attrs<W: fmt::Write>(
+      attrs: &mut AttrList<'_, W>,
+      fontname: &Option<FontName>,
+      fontsize: &Option<FontSize>,
+    ) -> fmt::Result {
+      if let Some(FontName(fontname)) = fontname {
+        attrs.quoted("fontname", fontname)?;
+      }
+      if let Some(FontSize(fontsize)) = fontsize {
+        attrs.quoted("fontsize", fontsize)?;
+      }
+      Ok(())
+    }
+
+    fn write_geometry_attrs<W: fmt::Write>(
+      attrs: &mut AttrList<'_, W>,
+      fixedsize: &Option<FixedSize>,
+      width: &Option<f64>,
+      height: &Option<f64>,
+      peripheries: &Option<u32>,
+    ) -> fmt::Result {
+      if let Some(fixedsize) = fixedsize {
+        attrs.quoted("fixedsize", fixedsize.format())?;
+      }
+      if let Some(width) = width {
+        attrs.quoted("width", width)?;
+      }
+      if let Some(height) = height {
+        attrs.quoted("height", height)?;
+      }
+      if let Some(peripheries) = peripheries {
+        attrs.quoted("peripheries", peripheries)?;
+      }
+      Ok(())
+    }
+
+    fn write_vertex<W: fmt::Write>(
+      w: &mut W,
+      vertex: &Vertex,
+      indent: usize,
+      printer: Printer,
+    ) -> fmt::Result {
+      let Vertex {
+        id,
+        label,
+        html_label,
+        color,
+        fontcolor,
+        shape,
+        fontname,
+        fontsize,
+        style,
+        layer,
+        fixedsize,
+        width,
+        height,
+        peripheries,
+        polygon,
+        shapefile,
+        attributes,
+      } = vertex;
+      printer.write(w, id)?;
+
+      let mut attrs = AttrList::new(w, printer, indent);
+      if let Some(HtmlLabel(html_label)) = html_label {
+        attrs.raw("label", format_args!("<{}>", html_label))?;
+      } else if let Some(Label(label)) = label {
+        attrs.quoted("label", label)?;
+      }
+      if let Some(Color(color)) = color {
+        attrs.quoted("color", color)?;
+      }
+      if let Some(Color(fontcolor)) = fontcolor {
+        attrs.quoted("fontcolor", fontcolor)?;
+      }
+      if let Some(Shape(shape)) = shape {
+        attrs.quoted("shape", shape)?;
+      } else if let Some(shapefile) = shapefile {
+        attrs.quoted("shape", shapefile.shape())?;
+      } else if polygon.is_some() {
+        attrs.quoted("shape", "polygon")?;
+      }
+      if let Some(shapefile) = shapefile {
+        attrs.quoted(
+          "shapefile",
+          Self::escape_quoted(&shapefile.path().to_string_lossy()),
+        )?;
+      }
+      if let Some(Polygon {
+        sides,
+        skew,
+        distortion,
+        orientation,
+        regular,
+      }) = polygon
+      {
+        if let Some(sides) = sides {
+          attrs.quoted("sides", sides)?;
+        }
+        if let Some(skew) = skew {
+          attrs.quoted("skew", skew)?;
+        }
+        if let Some(distortion) = distortion {
+          attrs.quoted("distortion", distortion)?;
+        }
+        if let Some(orientation) = orientation {
+          attrs.quoted("orientation", orientation)?;
+        }
+        if let Some(regular) = regular {
+          attrs.quoted("regular", regular)?;
+        }
+      }
+      Self::write_font_attrs(&mut attrs, fontname, fontsize)?;
+      if let Some(Style(style)) = style {
+        attrs.quoted("style", style)?;
+      }
+      if let Some(layer) = layer {
+        attrs.quoted("layer", Self::escape_quoted(layer))?;
+      }
+      Self::write_geometry_attrs(&mut attrs, fixedsize, width, height, peripheries)?;
+      for (name, value) in attributes.iter() {
+        attrs.raw(AttrName(name), value.format())?;
+      }
+      attrs.finish()?;
+
+      w.write_char(';')
+    }
+
+    fn write_edge<W: fmt::Write>(
+      w: &mut W,
+      edge: &Edge,
+      indent: usize,
+      printer: Printer,
+    ) -> fmt::Result {
+      let Edge {
+        source,
+        target,
+        label,
+        color,
+        fontcolor,
+        fontname,
+        fontsize,
+        style,
+        arrowhead,
+        arrowtail,
+        dir,
+        headport,
+        tailport,
+        headlabel,
+        taillabel,
+        weight,
+        minlen,
+        constraint,
+        samehead,
+        sametail,
+        layer,
+        attributes,
+      } = edge;
+      printer.write(w, source)?;
+      w.write_str(if printer.undirected { " -- " } else { " -> " })?;
+      printer.write(w, target)?;
+
+      let mut attrs = AttrList::new(w, printer, indent);
+      if let Some(Label(label)) = label {
+        attrs.quoted("label", label)?;
+      }
+      if let Some(Color(color)) = color {
+        attrs.quoted("color", color)?;
+      }
+      if let Some(Color(fontcolor)) = fontcolor {
+        attrs.quoted("fontcolor", fontcolor)?;
+      }
+      Self::write_font_attrs(&mut attrs, fontname, fontsize)?;
+      if let Some(Style(style)) = style {
+        attrs.quoted("style", style)?;
+      }
+      if let Some(ArrowType(arrowhead)) = arrowhead {
+        attrs.quoted("arrowhead", arrowhead)?;
+      }
+      if let Some(ArrowType(arrowtail)) = arrowtail {
+        attrs.quoted("arrowtail", arrowtail)?;
+      }
+      if let Some(DirType(dir)) = dir {
+        attrs.quoted("dir", dir)?;
+      }
+      if let Some(Port(headport)) = headport {
+        attrs.quoted("headport", headport)?;
+      }
+      if let Some(Port(tailport)) = tailport {
+        attrs.quoted("tailport", tailport)?;
+      }
+      if let Some(Label(headlabel)) = headlabel {
+        attrs.quoted("headlabel", headlabel)?;
+      }
+      if let Some(Label(taillabel)) = taillabel {
+        attrs.quoted("taillabel", taillabel)?;
+      }
+      if let Some(weight) = weight {
+        attrs.quoted("weight", weight)?;
+      }
+      if let Some(minlen) = minlen {
+        attrs.quoted("minlen", minlen)?;
+      }
+      if let Some(constraint) = constraint {
+        attrs.quoted("constraint", constraint)?;
+      }
+      if let Some(samehead) = samehead {
+        attrs.quoted("samehead", Self::escape_quoted(samehead))?;
+      }
+      if let Some(sametail) = sametail {
+        attrs.quoted("sametail", Self::escape_quoted(sametail))?;
+      }
+      if let Some(layer) = layer {
+        attrs.quoted("layer", Self::escape_quoted(layer))?;
+      }
+      for (name, value) in attributes.iter() {
+        attrs.raw(AttrName(name), value.format())?;
+      }
+      attrs.finish()?;
+
+      w.write_char(';')
+    }
+
+    fn write_subgraph<W: fmt::Write>(
+      w: &mut W,
+      subgraph: &Subgraph,
+      mut indent: usize,
+      printer: Printer,
+    ) -> fmt::Result {
+      let Subgraph {
+        id,
+        is_cluster,
+        same_rank,
+        label,
+        color,
+        fontcolor,
+        fontname,
+        fontsize,
+        node_defaults,
+        attributes,
+        entities,
+      } = subgraph;
+      let renamed = *is_cluster
+        && printer.cluster_naming != ClusterNaming::AsIs
+        && !id.as_str().starts_with("cluster");
+      w.write_str("subgraph ")?;
+      if renamed {
+        printer.write(w, &id.prefixed("cluster_"))?;
+      } else {
+        printer.write(w, id)?;
+      }
+      w.write_str(" {")?;
+      Self::bump_indent(&mut indent);
+
+      if let Some(Label(label)) = label {
+        printer.newline_indent(w, indent)?;
+        write!(w, "label = \"{}\";", label)?;
+      } else if renamed && printer.cluster_naming == ClusterNaming::PrefixAndLabel {
+        printer.newline_indent(w, indent)?;
+        write!(w, "label = \"{}\";", Self::escape_quoted(id.as_str()))?;
+      }
+      if *is_cluster {
+        printer.newline_indent(w, indent)?;
+        w.write_str("cluster = true;")?;
+      }
+      if *same_rank {
+        printer.newline_indent(w, indent)?;
+        w.write_str("rank = same;")?;
+      }
+      printer.newline(w)?;
+
+      if let Some(Color(color)) = color {
+        printer.newline_indent(w, indent)?;
+        write!(w, "color = \"{}\";", color)?;
+      }
+      if let Some(Color(fontcolor)) = fontcolor {
+        printer.newline_indent(w, indent)?;
+        write!(w, "fontcolor = \"{}\";", fontcolor)?;
+      }
+      if let Some(FontName(fontname)) = fontname {
+        printer.newline_indent(w, indent)?;
+        write!(w, "fontname = \"{}\";", fontname)?;
+      }
+      if let Some(FontSize(fontsize)) = fontsize {
+        printer.newline_indent(w, indent)?;
+        write!(w, "fontsize = \"{}\";", fontsize)?;
+      }
+      for (name, value) in attributes.iter() {
+        printer.newline_indent(w, indent)?;
+        write!(w, "{} = {};", AttrName(name), value.format())?;
+      }
+      if let Some(node_defaults) = node_defaults {
+        Self::write_node_defaults(w, node_defaults, indent, printer)?;
+      }
+      printer.newline(w)?;
+
+      for e in print_order(entities.iter(), printer.stable_order) {
+        printer.newline_indent(w, indent)?;
+        Self::write_entity(w, e, indent, printer)?;
+      }
+
+      Self::unbump_indent(&mut indent);
+      printer.newline_indent(w, indent)?;
+      w.write_char('}')
+    }
+
+    fn write_entity<W: fmt::Write>(
+      w: &mut W,
+      entity: &Entity,
+      mut indent: usize,
+      printer: Printer,
+    ) -> fmt::Result {
+      match entity {
+        Entity::Vertex(v) => Self::write_vertex(w, v, indent, printer),
+        Entity::Edge(e) => Self::write_edge(w, e, indent, printer),
+        Entity::Subgraph(sg) => Self::write_subgraph(w, sg, indent, printer),
+        Entity::Comment(text) => Self::write_comment(w, text, indent, printer),
+        Entity::Group(Group {
+          node_defaults,
+          entities,
+        }) => {
+          w.write_char('{')?;
+          Self::bump_indent(&mut indent);
+
+          if let Some(node_defaults) = node_defaults {
+            Self::write_node_defaults(w, node_defaults, indent, printer)?;
+            printer.newline(w)?;
+          }
+
+          for e in print_order(entities.iter(), printer.stable_order) {
+            printer.newline_indent(w, indent)?;
+            Self::write_entity(w, e, indent, printer)?;
+          }
+
+          Self::unbump_indent(&mut indent);
+          printer.newline_indent(w, indent)?;
+          w.write_char('}')
+        },
+      }
+    }
+
+    /// Write `text` as a `//` comment, or as a `/* */` block if it spans
+    /// multiple lines. Any `*/` within `text` is broken up so it can't end the
+    /// block early.
+    fn write_comment<W: fmt::Write>(
+      w: &mut W,
+      text: &str,
+      indent: usize,
+      printer: Printer,
+    ) -> fmt::Result {
+      if !text.contains('\n') {
+        return write!(w, "// {}", text);
+      }
+      w.write_str("/*")?;
+      for line in text.lines() {
+        printer.newline_indent(w, indent)?;
+        w.write_str(" *")?;
+        if !line.is_empty() {
+          write!(w, " {}", line.replace("*/", "* /"))?;
+        }
+      }
+      printer.newline_indent(w, indent)?;
+      w.write_str(" */")
+    }
+
+    fn write_node_defaults<W: fmt::Write>(
+      w: &mut W,
+      node_defaults: &NodeDefaults,
+      indent: usize,
+      printer: Printer,
+    ) -> fmt::Result {
+      if *node_defaults == NodeDefaults::default() {
+        return Ok(());
+      }
+      let NodeDefaults {
+        color,
+        fontcolor,
+        fixedsize,
+        width,
+        height,
+        peripheries,
+      } = node_defaults;
+
+      printer.newline_indent(w, indent)?;
+      w.write_str("node ")?;
+      let mut attrs = AttrList::new(w, printer, indent);
+      if let Some(Color(color)) = color {
+        attrs.quoted("color", color)?;
+      }
+      if let Some(Color(fontcolor)) = fontcolor {
+        attrs.quoted("fontcolor", fontcolor)?;
+      }
+      Self::write_geometry_attrs(&mut attrs, fixedsize, width, height, peripheries)?;
+      attrs.finish()?;
+      w.write_char(';')
+    }
+
+    /// Write `node [...]` and `edge [...]` statements with the defaults from
+    /// `theme`, so vertices which only exist as edge endpoints are styled too.
+    fn write_theme_defaults<W: fmt::Write>(
+      w: &mut W,
+      theme: &Theme,
+      indent: usize,
+      printer: Printer,
+    ) -> fmt::Result {
+      let Theme {
+        node_shape,
+        node_style,
+        edge_style,
+        fontname,
+        fontsize,
+        colors,
+      } = theme;
+
+      if theme.has_node_defaults() {
+        printer.newline_indent(w, indent)?;
+        w.write_str("node ")?;
+        let mut attrs = AttrList::new(w, printer, indent);
+        if let Some(Color(color)) = &colors.node {
+          attrs.quoted("color", color)?;
+        }
+        if let Some(Color(fontcolor)) = &colors.node_font {
+          attrs.quoted("fontcolor", fontcolor)?;
+        }
+        if let Some(Shape(shape)) = node_shape {
+          attrs.quoted("shape", shape)?;
+        }
+        Self::write_font_attrs(&mut attrs, fontname, fontsize)?;
+        if let Some(Style(style)) = node_style {
+          attrs.quoted("style", style)?;
+        }
+        attrs.finish()?;
+        w.write_char(';')?;
+      }
+      if theme.has_edge_defaults() {
+        printer.newline_indent(w, indent)?;
+        w.write_str("edge ")?;
+        let mut attrs = AttrList::new(w, printer, indent);
+        if let Some(Color(color)) = &colors.edge {
+          attrs.quoted("color", color)?;
+        }
+        if let Some(Color(fontcolor)) = &colors.edge_font {
+          attrs.quoted("fontcolor", fontcolor)?;
+        }
+        Self::write_font_attrs(&mut attrs, fontname, fontsize)?;
+        if let Some(Style(style)) = edge_style {
+          attrs.quoted("style", style)?;
+        }
+        attrs.finish()?;
+        w.write_char(';')?;
+      }
+      Ok(())
+    }
+
+    fn write_graph_attributes<W: fmt::Write>(
+      w: &mut W,
+      attrs: &GraphAttributes,
+      indent: usize,
+      printer: Printer,
+    ) -> fmt::Result {
+      let GraphAttributes {
+        bgcolor,
+        concentrate,
+        newrank,
+        nodesep,
+        ranksep,
+        margin,
+        pad,
+        size,
+        ratio,
+        dpi,
+        layers,
+        layersep,
+        layerselect,
+        pack,
+        packmode,
+        outputorder,
+        engine,
+        attributes,
+      } = attrs;
+
+      if let Some(Color(bgcolor)) = bgcolor {
+        printer.newline_indent(w, indent)?;
+        write!(w, "bgcolor = \"{}\";", bgcolor)?;
+      }
+      if let Some(concentrate) = concentrate {
+        printer.newline_indent(w, indent)?;
+        write!(w, "concentrate = {};", concentrate)?;
+      }
+      if let Some(newrank) = newrank {
+        printer.newline_indent(w, indent)?;
+        write!(w, "newrank = {};", newrank)?;
+      }
+      if let Some(nodesep) = nodesep {
+        printer.newline_indent(w, indent)?;
+        write!(w, "nodesep = \"{}\";", nodesep)?;
+      }
+      if let Some(ranksep) = ranksep {
+        printer.newline_indent(w, indent)?;
+        write!(w, "ranksep = \"{}\";", ranksep.format())?;
+      }
+      if let Some(margin) = margin {
+        printer.newline_indent(w, indent)?;
+        write!(w, "margin = \"{}\";", margin.format())?;
+      }
+      if let Some(pad) = pad {
+        printer.newline_indent(w, indent)?;
+        write!(w, "pad = \"{}\";", pad.format())?;
+      }
+      if let Some(size) = size {
+        printer.newline_indent(w, indent)?;
+        write!(w, "size = \"{}\";", size.format())?;
+      }
+      if let Some(ratio) = ratio {
+        printer.newline_indent(w, indent)?;
+        write!(w, "ratio = \"{}\";", ratio.format())?;
+      }
+      if let Some(dpi) = dpi {
+        printer.newline_indent(w, indent)?;
+        write!(w, "dpi = \"{}\";", dpi)?;
+      }
+      if let Some(layers) = layers {
+        let sep = layersep
+          .as_ref()
+          .and_then(|s| s.chars().next())
+          .unwrap_or(':')
+          .to_string();
+        printer.newline_indent(w, indent)?;
+        write!(
+          w,
+          "layers = \"{}\";",
+          Self::escape_quoted(&layers.join(&sep))
+        )?;
+      }
+      if let Some(layersep) = layersep {
+        printer.newline_indent(w, indent)?;
+        write!(w, "layersep = \"{}\";", Self::escape_quoted(layersep))?;
+      }
+      if let Some(layerselect) = layerselect {
+        printer.newline_indent(w, indent)?;
+        write!(
+          w,
+          "layerselect = \"{}\";",
+          Self::escape_quoted(layerselect)
+        )?;
+      }
+      if let Some(pack) = pack {
+        printer.newline_indent(w, indent)?;
+        write!(w, "pack = \"{}\";", pack.format())?;
+      }
+      if let Some(packmode) = packmode {
+        printer.newline_indent(w, indent)?;
+        write!(w, "packmode = \"{}\";", packmode.format())?;
+      }
+      if let Some(outputorder) = outputorder {
+        printer.newline_indent(w, indent)?;
+        write!(w, "outputorder = \"{}\";", outputorder.format())?;
+      }
+      if let Some(engine) = engine {
+        for (name, value) in engine.statements().into_iter() {
+          printer.newline_indent(w, indent)?;
+          write!(w, "{} = \"{}\";", name, value)?;
+        }
+      }
+      for (name, value) in attributes.iter() {
+        printer.newline_indent(w, indent)?;
+        write!(w, "{} = {};", AttrName(name), value.format())?;
+      }
+      Ok(())
+    }
+
+    /// Create an empty graph with room for `nodes` vertices and `edges` edges
+    /// before reallocating.
+    pub fn with_capacity(nodes: usize, edges: usize) -> Self {
+      let mut ret = Self::new();
+      ret.entities.reserve(nodes + edges);
+      ret.ids.reserve(nodes);
+      ret
+    }
+
+    /// A graph with a vertex for each row of the square matrix `adjacent`,
+    /// named by `name` from its index, and an edge from row `i` to column `j`
+    /// wherever `adjacent[i][j]` is set.
+    ///
+    /// # Panics
+    /// If `adjacent` isn't square.
+    pub fn from_adjacency_matrix<F: Fn(usize) -> Id>(adjacent: &[Vec<bool>], name: F) -> Self {
+      Self::from_matrix(adjacent, name, |&set| set.then(Edge::default))
+    }
+
+    /// Like [`Self::from_adjacency_matrix`], but with an edge wherever the
+    /// weight is nonzero (and not NaN). Each edge is labeled with its weight,
+    /// and drawn with a `penwidth` from 1 to 5 in proportion to the magnitude
+    /// of its weight relative to the largest in the matrix. Returns
+    /// [`InfiniteWeight`] for the first infinite weight found.
+    ///
+    /// # Panics
+    /// If `weights` isn't square.
+    pub fn from_weighted_adjacency_matrix<F: Fn(usize) -> Id>(
+      weights: &[Vec<f64>],
+      name: F,
+    ) -> Result<Self, InfiniteWeight> {
+      for (row, weights) in weights.iter().enumerate() {
+        if let Some(column) = weights.iter().position(|w| w.is_infinite()) {
+          return Err(InfiniteWeight { row, column });
+        }
+      }
+      let max = weights
+        .iter()
+        .flatten()
+        .map(|w| w.abs())
+        .filter(|w| !w.is_nan())
+        .fold(0.0, f64::max);
+      Ok(Self::from_matrix(weights, name, |&w| {
+        (w != 0.0 && !w.is_nan()).then(|| {
+          Edge::default()
+            .label(w.to_string())
+            .attr("penwidth", (1.0 + 4.0 * w.abs() / max).to_string())
+        })
+      }))
+    }
+
+    /// A graph with a vertex for each key of `list` and each of its
+    /// neighbors, and an edge from each key to each of its neighbors.
+    ///
+    /// Each vertex is added once, where its id first appears, and repeated
+    /// edges are only added once. Iterate over a
+    /// [`BTreeMap`](std::collections::BTreeMap) or a [`Vec`] rather than a
+    /// [`HashMap`] for the output to be stable.
+    ///
+    ///```
+    /// use graphvizier::generator::GraphBuilder;
+    ///
+    /// let gb = GraphBuilder::from_adjacency_list(vec![("a", vec!["b", "c"]), ("b", vec!["c", "c"])]);
+    /// assert_eq!(gb.vertices().count(), 3);
+    /// assert_eq!(gb.edges().count(), 3);
+    /// ```
+    pub fn from_adjacency_list<K, I>(list: I) -> Self
+    where
+      K: Into<Id>,
+      I: IntoIterator<Item=(K, Vec<K>)>,
+    {
+      Self::from_adjacency_list_with(
+        list,
+        |id| Vertex::new(id.clone()),
+        |source, target| Edge::between(source.clone(), target.clone()),
+      )
+    }
+
+    /// Like [`Self::from_adjacency_list`], but creating each vertex with
+    /// `vertex` and each edge with `edge`, to style them as they're added.
+    pub fn from_adjacency_list_with<K, I, V, E>(list: I, vertex: V, edge: E) -> Self
+    where
+      K: Into<Id>,
+      I: IntoIterator<Item=(K, Vec<K>)>,
+      V: Fn(&Id) -> Vertex,
+      E: Fn(&Id, &Id) -> Edge,
+    {
+      let mut ret = Self::new();
+      let mut vertices: HashSet<Id> = HashSet::new();
+      let mut edges: HashSet<(Id, Id)> = HashSet::new();
+      for (source, targets) in list.into_iter() {
+        let source: Id = source.into();
+        if vertices.insert(source.clone()) {
+          ret.accept_entity(Entity::Vertex(vertex(&source)));
+        }
+        for target in targets.into_iter() {
+          let target: Id = target.into();
+          if vertices.insert(target.clone()) {
+            ret.accept_entity(Entity::Vertex(vertex(&target)));
+          }
+          if edges.insert((source.clone(), target.clone())) {
+            ret.accept_entity(Entity::Edge(edge(&source, &target)));
+          }
+        }
+      }
+      ret
+    }
+
+    fn from_matrix<T, F: Fn(usize) -> Id, G: Fn(&T) -> Option<Edge>>(
+      matrix: &[Vec<T>],
+      name: F,
+      edge: G,
+    ) -> Self {
+      let n = matrix.len();
+      let ids: Vec<Id> = (0..n).map(name).collect();
+      let mut ret = Self::with_capacity(n, n);
+      for id in ids.iter() {
+        ret.accept_entity(Entity::Vertex(Vertex::new(id.clone())));
+      }
+      for (i, row) in matrix.iter().enumerate() {
+        assert_eq!(
+          row.len(),
+          n,
+          "row {} of an adjacency matrix with {} rows",
+          i,
+          n
+        );
+        for (j, cell) in row.iter().enumerate() {
+          if let Some(e) = edge(cell) {
+            ret.accept_entity(Entity::Edge(Edge {
+              source: ids[i].clone(),
+              target: ids[j].clone(),
+              ..e
+            }));
+          }
+        }
+      }
+      ret
+    }
+
+    /// A rough upper bound on the length of the statement printed for `e`,
+    /// used to size the output buffer once up front.
+    fn estimated_len(e: &Entity) -> usize {
+      /* Indentation, separators, and the attributes we don't account for
+       * individually. */
+      const OVERHEAD: usize = 16;
+      let label_len = |l: &Option<Label>| l.as_ref().map(|Label(l)| l.len() + 10).unwrap_or(0);
+      match e {
+        Entity::Vertex(v) => {
+          OVERHEAD
+            + v.id.as_str().len()
+            + label_len(&v.label)
+            + v
+              .html_label
+              .as_ref()
+              .map(|HtmlLabel(l)| l.len() + 10)
+              .unwrap_or(0)
+            + v
+              .attributes
+              .iter()
+              .map(|(k, v)| {
+                k.len()
+                  + 4
+                  + match v {
+                    AttrValue::Quoted(s) | AttrValue::Html(s) => s.len(),
+                  }
+              })
+              .sum::<usize>()
+        },
+        Entity::Edge(e) => {
+          OVERHEAD + e.source.as_str().len() + e.target.as_str().len() + label_len(&e.label)
+        },
+        Entity::Subgraph(sg) => {
+          OVERHEAD * 4
+            + sg.id.as_str().len()
+            + label_len(&sg.label)
+            + sg.entities.iter().map(Self::estimated_len).sum::<usize>()
+        },
+        Entity::Group(g) => OVERHEAD + g.entities.iter().map(Self::estimated_len).sum::<usize>(),
+        Entity::Comment(text) => OVERHEAD + text.len(),
+      }
+    }
+
+    /// Print all the accumulated entities into a digraph named `graph_name`.
+    pub fn build(self, graph_name: Id) -> Result<DotOutput, GraphError> {
+      let estimate: usize = 64
+        + graph_name.as_str().len()
+        + self
+          .entities
+          .iter()
+          .map(|(_, e)| Self::estimated_len(e))
+          .sum::<usize>();
+      let mut output = String::with_capacity(estimate);
+      self.build_into(&mut output, graph_name)?;
+      Ok(DotOutput(output))
+    }
+
+    /// Print all the accumulated entities into a digraph named `graph_name`,
+    /// writing each statement to `w` as soon as it is formatted instead of
+    /// accumulating the whole document in memory.
+    pub fn build_into<W: fmt::Write>(&self, w: &mut W, graph_name: Id) -> Result<(), GraphError> {
+      self.write_document(w, graph_name, None)
+    }
+
+    /// Like [`Self::build`], but also run every validation pass which isn't
+    /// enabled on this builder, and return all of their findings.
+    ///
+    /// Findings from enabled passes are [`Severity::Error`]s, and the first
+    /// of them is also returned as the [`GraphError`]. The rest are
+    /// [`Severity::Warning`]s, which don't prevent the graph from being built.
+    pub fn build_with_diagnostics(
+      self,
+      graph_name: Id,
+    ) -> (Result<DotOutput, GraphError>, Diagnostics) {
+      let mut diagnostics = Diagnostics::new();
+      let mut output = String::new();
+      let result = self
+        .write_document(&mut output, graph_name, Some(&mut diagnostics))
+        .map(|()| DotOutput(output));
+      (result, diagnostics)
+    }
+
+    /// Whether `flag`'s validation pass should fail the build, accounting for
+    /// [`Self::set_build_mode`].
+    fn enforced(&self, flag: bool) -> bool {
+      match self.build_mode {
+        Some(BuildMode::Strict) => true,
+        Some(BuildMode::Lenient) => false,
+        None => flag,
+      }
+    }
+
+    /// Run each enforced validation pass, returning the first failure, or else
+    /// the findings for [`Self::write_document`] to fix. If `diagnostics` is
+    /// provided, run the other passes too and record every finding.
+    fn validate(
+      &self,
+      graph_name: &Id,
+      synthesized: &[Entity],
+      mut diagnostics: Option<&mut Diagnostics>,
+    ) -> Result<Findings, GraphError> {
+      if self.enforced(self.verify_ids) {
+        verify_id(graph_name, self.quoting_policy)?;
+        for e in self
+          .entities
+          .iter()
+          .map(|(_, e)| e)
+          .chain(synthesized.iter())
+        {
+          verify_ids(e, self.quoting_policy)?;
+        }
+      }
+      let lenient = self.build_mode == Some(BuildMode::Lenient);
+      let severity = |enabled: bool| {
+        if enabled {
+          Severity::Error
+        } else {
+          Severity::Warning
+        }
+      };
+      let mut failure: Option<GraphError> = None;
+      let mut findings = Findings::default();
+
+      let validate_vertices = self.enforced(self.validate_vertices);
+      if validate_vertices || diagnostics.is_some() {
+        let conflicts = self.conflicting_vertices();
+        if let Some(ref mut diagnostics) = diagnostics {
+          diagnostics.extend(conflicts.iter().map(|c| {
+            Diagnostic::new(
+              severity(validate_vertices),
+              "conflicts with an earlier vertex with the same id",
+            )
+            .entity(EntityRef::Vertex(c.new.id.clone()))
+          }));
+        }
+        if validate_vertices && failure.is_none() {
+          failure = conflicts
+            .into_iter()
+            .next()
+            .map(GraphError::DuplicateVertex);
+        }
+      }
+      let validate_attributes = self.enforced(self.validate_attributes);
+      if validate_attributes || lenient || diagnostics.is_some() {
+        let invalid = self.invalid_attributes();
+        if let Some(ref mut diagnostics) = diagnostics {
+          diagnostics.extend(invalid.iter().map(|a| {
+            let mut message = a.problem.to_string();
+            if lenient {
+              message.push_str("; skipped");
+            }
+            Diagnostic::new(severity(validate_attributes), message)
+              .entity(a.entity.clone())
+              .attribute(a.name.clone())
+          }));
+        }
+        if validate_attributes && failure.is_none() {
+          failure = invalid.first().cloned().map(GraphError::InvalidAttribute);
+        }
+        findings.invalid_attributes = invalid;
+      }
+      let validate_edges = self.enforced(self.validate_edges);
+      if validate_edges || diagnostics.is_some() {
+        let dangling = self.dangling_edges(synthesized);
+        if let Some(ref mut diagnostics) = diagnostics {
+          diagnostics.extend(dangling.iter().map(|e| {
+            Diagnostic::new(severity(validate_edges), e.describe_missing()).entity(
+              EntityRef::Edge {
+                source: e.source.clone(),
+                target: e.target.clone(),
+              },
+            )
+          }));
+        }
+        if validate_edges && failure.is_none() && !dangling.is_empty() {
+          failure = Some(GraphError::DanglingEdges(dangling));
+        }
+      }
+      let invalid_layers = invalid_layer_names(&self.graph_attributes);
+      if let Some(ref mut diagnostics) = diagnostics {
+        diagnostics.extend(invalid_layers.iter().map(|name| {
+          let mut message = GraphError::InvalidLayerName(name.clone()).to_string();
+          if lenient {
+            message.push_str("; skipped");
+          }
+          Diagnostic::new(severity(!lenient), message).attribute("layers")
+        }));
+      }
+      if !lenient && failure.is_none() {
+        failure = invalid_layers
+          .first()
+          .cloned()
+          .map(GraphError::InvalidLayerName);
+      }
+      findings.invalid_layers = invalid_layers;
+      let strict = self.build_mode == Some(BuildMode::Strict);
+      if strict || lenient || diagnostics.is_some() {
+        let empty = self.empty_ids_and_labels();
+        if let Some(ref mut diagnostics) = diagnostics {
+          diagnostics.extend(empty.iter().map(|e| {
+            let (entity, mut message) = match e {
+              GraphError::EmptyId(entity) => (entity.clone(), "empty id".to_string()),
+              GraphError::EmptyLabel(entity) => (entity.clone(), "empty label".to_string()),
+              _ => unreachable!(),
+            };
+            if lenient {
+              message.push_str("; replaced with a placeholder");
+            }
+            Diagnostic::new(severity(strict), message).entity(entity)
+          }));
+        }
+        if strict && failure.is_none() && !empty.is_empty() {
+          failure = empty.into_iter().next();
+        } else {
+          findings.empty_ids_and_labels = empty;
+        }
+      }
+      if let Some(diagnostics) = diagnostics {
+        if !synthesized.is_empty() {
+          diagnostics.push(Diagnostic::new(
+            Severity::Note,
+            format!(
+              "created {} vertices for undefined edge endpoints",
+              synthesized.len()
+            ),
+          ));
+        }
+      }
+
+      match failure {
+        Some(e) => Err(e),
+        None => Ok(findings),
+      }
+    }
+
+    fn write_document<W: fmt::Write>(
+      &self,
+      w: &mut W,
+      graph_name: Id,
+      diagnostics: Option<&mut Diagnostics>,
+    ) -> Result<(), GraphError> {
+      let lenient = self.build_mode == Some(BuildMode::Lenient);
+      let mut synthesized = match (&self.auto_vertex, lenient) {
+        (Some(make), _) => self.synthesize_vertices(make),
+        (None, true) => self.synthesize_vertices(&|id: &Id| Vertex::new(id.clone())),
+        (None, false) => Vec::new(),
+      };
+      let findings = self.validate(&graph_name, &synthesized, diagnostics)?;
+      /* Only copy the graph if there's something to fix within it. */
+      let cleaned: Option<Vec<Entity>> = if lenient
+        && !(findings.invalid_attributes.is_empty() && findings.empty_ids_and_labels.is_empty())
+      {
+        synthesized.iter_mut().for_each(fill_empty);
+        Some(
+          self
+            .entities
+            .iter()
+            .map(|(_, e)| {
+              let mut e = e.clone();
+              strip_invalid_attributes(&mut e);
+              fill_empty(&mut e);
+              e
+            })
+            .collect(),
+        )
+      } else {
+        None
+      };
+      let printer = Printer {
+        policy: self.quoting_policy,
+        verify: self.enforced(self.verify_ids),
+        attr_list: self.attr_list_style,
+        format: self.format_options,
+        stable_order: self.stable_order,
+        cluster_naming: self.cluster_naming,
+        undirected: !self.directed,
+      };
+      let mut graph_attributes = self.graph_attributes.clone();
+      if let Some(ref theme) = self.theme {
+        theme.apply_graph(&mut graph_attributes);
+      }
+      if let Some(ref mut layers) = graph_attributes.layers {
+        layers.retain(|name| !findings.invalid_layers.contains(name));
+      }
+
+      let mut indent: usize = 0;
+      if let Some(ref generated_by) = self.generated_by {
+        Self::write_comment(w, &generated_by.comment(), indent, printer)?;
+        printer.newline(w)?;
+      }
+      for text in self.preamble.iter() {
+        Self::write_comment(w, text, indent, printer)?;
+        printer.newline(w)?;
+      }
+      w.write_str(if self.directed { "digraph " } else { "graph " })?;
+      printer.write(w, &graph_name)?;
+      w.write_str(" {")?;
+      Self::bump_indent(&mut indent);
+
+      printer.newline_indent(w, indent)?;
+      w.write_str("compound = true;")?;
+      Self::write_graph_attributes(w, &graph_attributes, indent, printer)?;
+      if let Some(ref theme) = self.theme {
+        Self::write_theme_defaults(w, theme, indent, printer)?;
+      }
+
+      let entities: Vec<&Entity> = match cleaned {
+        Some(ref cleaned) => cleaned.iter().collect(),
+        None => self.entities.iter().map(|(_, e)| e).collect(),
+      };
+      let entities = entities.into_iter().chain(synthesized.iter());
+      for entity in print_order(entities, self.stable_order) {
+        printer.blank_lines(w)?;
+        printer.newline_indent(w, indent)?;
+        match self.theme {
+          Some(ref theme) => {
+            let mut entity = entity.clone();
+            theme.apply(&mut entity);
+            Self::write_entity(w, &entity, indent, printer)?;
+          },
+          None => Self::write_entity(w, entity, indent, printer)?,
+        }
+      }
+
+      Self::unbump_indent(&mut indent);
+      assert_eq!(indent, 0);
+      printer.newline_indent(w, indent)?;
+      w.write_char('}')?;
+      printer.newline(w)?;
+      Ok(())
+    }
+
+    /// Print a normalized form of the graph, so that two builders holding the
+    /// same entities produce byte-identical output regardless of insertion
+    /// order or formatting settings.
+    ///
+    /// Entities are sorted (vertices by id, then subgraphs, groups, and edges
+    /// by source and target), recursively within each subgraph, and printed
+    /// with the default [`QuotingPolicy`], [`AttrListStyle`], and
+    /// [`FormatOptions`]. Attributes are always printed in a fixed order.
+    pub fn build_canonical(mut self, graph_name: Id) -> Result<DotOutput, GraphError> {
+      if let Some(ref make) = self.auto_vertex {
+        let synthesized = self.synthesize_vertices(make);
+        for e in synthesized.into_iter() {
+          self.push_entity(e);
+        }
+        self.auto_vertex = None;
+      }
+      self.index = None;
+      let mut entities: Vec<Entity> = self.entities.drain(..).map(|(_, e)| e).collect();
+      sort_canonically(&mut entities);
+      self.entities = entities
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| (i as u64, e))
+        .collect();
+
+      self.quoting_policy = QuotingPolicy::default();
+      self.verify_ids = false;
+      self.attr_list_style = AttrListStyle::default();
+      self.format_options = FormatOptions::default();
+      self.stable_order = false;
+      self.build(graph_name)
+    }
+
+    /// Build a digraph named `graph_name` within an HTML page which draws it
+    /// in the browser. See [`crate::render::html`].
+    pub fn build_html(self, graph_name: Id) -> Result<String, GraphError> {
+      let options = html::HtmlOptions {
+        title: graph_name.as_str().to_string(),
+        ..html::HtmlOptions::default()
+      };
+      Ok(html::to_html(&self.build(graph_name)?, &options))
+    }
+
+    /// Build a digraph named `graph_name` and write it to the file at `path`,
+    /// creating or truncating it. The file is left alone if the build fails.
+    pub fn build_to_file(self, path: impl AsRef<Path>, graph_name: Id) -> Result<(), GraphError> {
+      let DotOutput(dot) = self.build(graph_name)?;
+      fs::write(path, dot)?;
+      Ok(())
+    }
+  }
+
+  impl Extend<Entity> for GraphBuilder {
+    fn extend<I: IntoIterator<Item=Entity>>(&mut self, iter: I) {
+      for e in iter.into_iter() {
+        self.accept_entity(e);
+      }
+    }
+  }
+
+  impl Extend<(Id, Id)> for GraphBuilder {
+    fn extend<I: IntoIterator<Item=(Id, Id)>>(&mut self, iter: I) {
+      for (source, target) in iter.into_iter() {
+        self.accept_entity(Entity::Edge(Edge::between(source, target)));
+      }
+    }
+  }
+
+  impl FromIterator<Entity> for GraphBuilder {
+    fn from_iter<I: IntoIterator<Item=Entity>>(iter: I) -> Self {
+      let mut ret = Self::new();
+      ret.extend(iter);
+      ret
+    }
+  }
+
+  /// Create a graph containing an edge with no attributes for each pair.
+  impl FromIterator<(Id, Id)> for GraphBuilder {
+    fn from_iter<I: IntoIterator<Item=(Id, Id)>>(iter: I) -> Self {
+      let mut ret = Self::new();
+      ret.extend(iter);
+      ret
+    }
+  }
+
+  #[cfg(test)]
+  mod test {
+    use super::*;
+    use crate::ids::IdNamespace;
+
+    fn numeric_vertex(index: usize) -> Vertex {
+      let key = format!("node_{}", index);
+      Vertex {
+        id: Id::new(key.clone()),
+        label: Some(Label(key)),
+        ..Default::default()
+      }
+    }
+
+    #[test]
     fn render_single_vertex() {
       let mut gb = GraphBuilder::new();
-      gb.accept_entity(Entity::Vertex(numeric_vertex(0)));
-      let DotOutput(output) = gb.build(Id::new("test_graph"));
+      gb.accept_entity(Entity::Vertex(numeric_vertex(0)));
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             node_0[label=\"node_0\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_single_edge() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Vertex(numeric_vertex(0)));
+      gb.accept_entity(Entity::Vertex(numeric_vertex(1)));
+      gb.accept_entity(Entity::Edge(Edge {
+        source: numeric_vertex(0).id,
+        target: numeric_vertex(1).id,
+        label: Some(Label("asdf".to_string())),
+        ..Default::default()
+      }));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             node_0[label=\"node_0\"];\n\n  \
+             node_1[label=\"node_1\"];\n\n  \
+             node_0 -> node_1[label=\"asdf\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_themed_vertex() {
+      use crate::theme::{ColorRoles, Theme};
+
+      let mut gb = GraphBuilder::new();
+      gb.set_theme(Theme {
+        node_shape: Some(Shape("box".to_string())),
+        colors: ColorRoles {
+          node: Some(Color("red".to_string())),
+          ..Default::default()
+        },
+        ..Default::default()
+      });
+      let mut v = numeric_vertex(0);
+      v.color = Some(Color("blue".to_string()));
+      gb.accept_entity(Entity::Vertex(v));
+      /* The theme must not override the defaults of a nested scope. */
+      gb.accept_entity(Entity::Group(Group {
+        node_defaults: Some(NodeDefaults {
+          color: Some(Color("green".to_string())),
+          ..Default::default()
+        }),
+        entities: vec![Entity::Vertex(numeric_vertex(1))],
+      }));
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             node [color=\"red\", shape=\"box\"];\n\n  \
+             node_0[label=\"node_0\", color=\"blue\"];\n\n  \
+             {\n    \
+               node [color=\"green\"];\n\n    \
+               node_1[label=\"node_1\"];\n  \
+             }\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_escaped_attributes() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Vertex(
+        Vertex::new("a")
+          .attr("label", "C:\\")
+          .attr("tooltip", "say \"hi\"\\n"),
+      ));
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert!(output.contains(r#"a[label="C:\\", tooltip="say \"hi\"\n"];"#));
+      let parsed = crate::parser::parse(&output).unwrap();
+      let Entity::Vertex(ref v) = parsed.entities[0] else {
+        panic!("{:?}", parsed.entities);
+      };
+      assert_eq!(
+        v.attributes["label"],
+        AttrValue::Quoted("C:\\\\".to_string())
+      );
+      assert_eq!(
+        v.attributes["tooltip"],
+        AttrValue::Quoted("say \"hi\"\\n".to_string())
+      );
+    }
+
+    #[test]
+    fn render_dark_theme() {
+      use crate::theme::Theme;
+
+      let mut gb = GraphBuilder::new();
+      gb.set_theme(Theme::dark());
+      gb.accept_entity(Entity::Vertex(numeric_vertex(0)));
+      gb.accept_entity(Entity::Vertex(Vertex::new("a").attr("color", "red")));
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             bgcolor = \"#1e1e1e\";\n  \
+             node [color=\"#d4d4d4\", fontcolor=\"#d4d4d4\"];\n  \
+             edge [color=\"#9cdcfe\", fontcolor=\"#c8c8c8\"];\n\n  \
+             node_0[label=\"node_0\"];\n\n  \
+             a[color=\"red\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_legend() {
+      use crate::legend::Legend;
+
+      let mut gb = GraphBuilder::new();
+      let mut legend = Legend::new("key", Label("Key".to_string()));
+      legend.add_node(Label("input".to_string()), Vertex {
+        shape: Some(Shape("box".to_string())),
+        ..Default::default()
+      });
+      legend.add_edge(Label("depends".to_string()), Edge {
+        style: Some(Style("dashed".to_string())),
+        ..Default::default()
+      });
+      legend.append_to(&mut gb);
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             subgraph cluster_key {\n    \
+               label = \"Key\";\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               key_0[label=\"input\", shape=\"box\"];\n    \
+               key_1_tail[shape=\"point\"];\n    \
+               key_1_head[shape=\"point\"];\n    \
+               key_1_tail -> key_1_head[label=\"depends\", style=\"dashed\"];\n    \
+               key_0 -> key_1_tail[style=\"invis\"];\n  \
+             }\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_edge_layout_attributes() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Edge(Edge {
+        source: numeric_vertex(1).id,
+        target: numeric_vertex(0).id,
+        weight: Some(2.5),
+        minlen: Some(3),
+        constraint: Some(false),
+        ..Default::default()
+      }));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             node_1 -> node_0[weight=\"2.5\", minlen=\"3\", constraint=\"false\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_samehead() {
+      let mut gb = GraphBuilder::new();
+      for i in 1..3 {
+        gb.accept_entity(Entity::Edge(Edge {
+          source: numeric_vertex(i).id,
+          target: numeric_vertex(0).id,
+          samehead: Some("in".to_string()),
+          ..Default::default()
+        }));
+      }
+      gb.accept_entity(Entity::Edge(Edge {
+        source: numeric_vertex(0).id,
+        target: numeric_vertex(3).id,
+        sametail: Some("say \"hi\" C:\\".to_string()),
+        ..Default::default()
+      }));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             node_1 -> node_0[samehead=\"in\"];\n\n  \
+             node_2 -> node_0[samehead=\"in\"];\n\n  \
+             node_0 -> node_3[sametail=\"say \\\"hi\\\" C:\\\\\"];\n\
+           }\n"
+      );
+      crate::parser::parse(&output).unwrap();
+    }
+
+    #[test]
+    fn render_concentrate() {
+      let mut gb = GraphBuilder::new();
+      gb.set_graph_attributes(GraphAttributes {
+        concentrate: Some(true),
+        newrank: Some(true),
+        ..Default::default()
+      });
+      gb.accept_entity(Entity::Vertex(numeric_vertex(0)));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             concentrate = true;\n  \
+             newrank = true;\n\n  \
+             node_0[label=\"node_0\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_spacing() {
+      let mut gb = GraphBuilder::new();
+      gb.set_graph_attributes(GraphAttributes {
+        nodesep: Some(0.25),
+        ranksep: Some(RankSep {
+          inches: 1.5,
+          equally: true,
+        }),
+        margin: Some(Padding::Uniform(0.0)),
+        pad: Some(Padding::XY(0.5, 1.0)),
+        ..Default::default()
+      });
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             nodesep = \"0.25\";\n  \
+             ranksep = \"1.5 equally\";\n  \
+             margin = \"0\";\n  \
+             pad = \"0.5,1\";\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_output_sizing() {
+      let mut gb = GraphBuilder::new();
+      gb.set_graph_attributes(GraphAttributes {
+        size: Some(Size {
+          width: 8.0,
+          height: 11.0,
+          fill: true,
+        }),
+        ratio: Some(Ratio::Fill),
+        dpi: Some(300.0),
+        ..Default::default()
+      });
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             size = \"8,11!\";\n  \
+             ratio = \"fill\";\n  \
+             dpi = \"300\";\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_layers() {
+      let mut gb = GraphBuilder::new();
+      gb.set_graph_attributes(GraphAttributes {
+        layers: Some(vec!["base".to_string(), "overlay".to_string()]),
+        layerselect: Some("overlay".to_string()),
+        ..Default::default()
+      });
+      gb.accept_entity(Entity::Vertex(Vertex {
+        layer: Some("all".to_string()),
+        ..numeric_vertex(0)
+      }));
+      gb.accept_entity(Entity::Edge(Edge {
+        source: numeric_vertex(0).id,
+        target: numeric_vertex(0).id,
+        layer: Some("overlay".to_string()),
+        ..Default::default()
+      }));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             layers = \"base:overlay\";\n  \
+             layerselect = \"overlay\";\n\n  \
+             node_0[label=\"node_0\", layer=\"all\"];\n\n  \
+             node_0 -> node_0[layer=\"overlay\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn escaped_and_invalid_layers() {
+      let mut gb = GraphBuilder::new();
+      gb.set_graph_attributes(GraphAttributes {
+        layers: Some(vec!["say \"hi\"".to_string(), "C:\\".to_string()]),
+        layersep: Some(";".to_string()),
+        layerselect: Some("say \"hi\"".to_string()),
+        ..Default::default()
+      });
+      gb.accept_entity(Entity::Vertex(Vertex {
+        layer: Some("C:\\".to_string()),
+        ..Vertex::new("a")
+      }));
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+      assert!(
+        output.contains(
+          "  layers = \"say \\\"hi\\\";C:\\\\\";\n  \
+             layersep = \";\";\n  \
+             layerselect = \"say \\\"hi\\\"\";\n"
+        ),
+        "{}",
+        output
+      );
+      assert!(output.contains("a[layer=\"C:\\\\\"];"), "{}", output);
+      crate::parser::parse(&output).unwrap();
+
+      let graph = |mode| {
+        let mut gb = GraphBuilder::new();
+        gb.set_build_mode(mode);
+        gb.set_graph_attributes(GraphAttributes {
+          layers: Some(vec!["a b".to_string(), "c".to_string()]),
+          ..Default::default()
+        });
+        gb
+      };
+      assert!(matches!(
+        graph(BuildMode::Strict).build(Id::new("g")),
+        Err(GraphError::InvalidLayerName(name)) if name == "a b"
+      ));
+      let (output, diagnostics) = graph(BuildMode::Lenient).build_with_diagnostics(Id::new("g"));
+      assert!(output.unwrap().0.contains("  layers = \"c\";\n"));
+      assert_eq!(
+        diagnostics.to_string(),
+        "warning: \"layers\": layer name \"a b\" contains a separator character; skipped\n"
+      );
+    }
+
+    #[test]
+    fn render_packing() {
+      let mut gb = GraphBuilder::new();
+      gb.set_graph_attributes(GraphAttributes {
+        pack: Some(Pack::Margin(16)),
+        packmode: Some(PackMode::Array {
+          column_major: true,
+          user_order: false,
+          count: Some(3),
+        }),
+        outputorder: Some(OutputOrder::EdgesFirst),
+        ..Default::default()
+      });
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             pack = \"16\";\n  \
+             packmode = \"array_c3\";\n  \
+             outputorder = \"edgesfirst\";\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_engine_attributes() {
+      use crate::engines::{sfdp, EngineAttributes};
+
+      let mut gb = GraphBuilder::new();
+      gb.set_graph_attributes(GraphAttributes {
+        engine: Some(EngineAttributes::Sfdp(sfdp::Attributes {
+          k: Some(0.5),
+          repulsiveforce: Some(2.0),
+          ..Default::default()
+        })),
+        ..Default::default()
+      });
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             layout = \"sfdp\";\n  \
+             K = \"0.5\";\n  \
+             repulsiveforce = \"2\";\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_engine_root() {
+      use crate::engines::{twopi, EngineAttributes};
+
+      let mut gb = GraphBuilder::new();
+      gb.set_graph_attributes(GraphAttributes {
+        engine: Some(EngineAttributes::Twopi(twopi::Attributes {
+          root: Some(Id::new("my \"root\"")),
+        })),
+        ..Default::default()
+      });
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n  \
+             layout = \"twopi\";\n  \
+             root = \"my \\\"root\\\"\";\n\
+           }\n"
+      );
+      crate::parser::parse(&output).unwrap();
+    }
+
+    #[test]
+    fn render_node_geometry() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Subgraph(Subgraph {
+        id: Id::new("cluster_grid"),
+        node_defaults: Some(NodeDefaults {
+          fixedsize: Some(FixedSize::Enabled(true)),
+          width: Some(1.0),
+          height: Some(0.5),
+          ..Default::default()
+        }),
+        entities: vec![Entity::Vertex(Vertex {
+          peripheries: Some(2),
+          ..numeric_vertex(0)
+        })],
+        ..Default::default()
+      }));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             subgraph cluster_grid {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n    \
+               node [fixedsize=\"true\", width=\"1\", height=\"0.5\"];\n\n    \
+               node_0[label=\"node_0\", peripheries=\"2\"];\n  \
+             }\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_polygon() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Vertex(Vertex {
+        polygon: Some(Polygon {
+          sides: Some(5),
+          skew: Some(0.25),
+          ..Default::default()
+        }),
+        ..numeric_vertex(0)
+      }));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             node_0[label=\"node_0\", shape=\"polygon\", sides=\"5\", skew=\"0.25\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_shapefile() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Vertex(Vertex {
+        shapefile: Some(ShapeFile::Custom("C:\\stencils\\\"db\".png".into())),
+        ..numeric_vertex(0)
+      }));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             node_0[label=\"node_0\", shape=\"custom\", \
+                    shapefile=\"C:\\\\stencils\\\\\\\"db\\\".png\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_alignment() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Vertex(Vertex::invisible(Id::new("spacer"))));
+      gb.align(
+        (0..2)
+          .map(|i| numeric_vertex(i).id)
+          .chain([Id::new("spacer")]),
+      );
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             spacer[style=\"invis\"];\n\n  \
+             node_0 -> node_1[style=\"invis\"];\n\n  \
+             node_1 -> spacer[style=\"invis\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_group() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Group(Group {
+        node_defaults: Some(NodeDefaults {
+          color: Some(Color("red".to_string())),
+          ..Default::default()
+        }),
+        entities: vec![Entity::Vertex(numeric_vertex(0))],
+      }));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             {\n    \
+               node [color=\"red\"];\n\n    \
+               node_0[label=\"node_0\"];\n  \
+             }\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_fluent() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Subgraph(
+        Subgraph::new("cluster_a").label("A").entity(Entity::Vertex(
+          Vertex::new("a").label("A").color(Color::red()),
+        )),
+      ));
+      gb.accept_entity(Entity::Edge(Edge::between("a", "b").label("ok")));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             subgraph cluster_a {\n    \
+               label = \"A\";\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               a[label=\"A\", color=\"red\"];\n  \
+             }\n\n  \
+             a -> b[label=\"ok\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_nested_subgraphs() {
+      let mut gb = GraphBuilder::new();
+      gb.subgraph("cluster_outer", |outer| {
+        outer.attributes().label = Some(Label("outer".to_string()));
+        outer.vertex(Vertex::new("a"));
+        outer.subgraph("cluster_inner", |inner| {
+          inner.vertex(Vertex::new("b"));
+          assert!(inner.contains_id(&Id::new("a")));
+          inner.edge(Edge::between("a", "b"));
+        });
+      });
+      assert!(gb.contains_id(&Id::new("b")));
+      assert!(gb.contains_id(&Id::new("cluster_inner")));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             subgraph cluster_outer {\n    \
+               label = \"outer\";\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               a;\n    \
+               subgraph cluster_inner {\n      \
+                 cluster = true;\n      \
+                 rank = same;\n\n\n      \
+                 b;\n      \
+                 a -> b;\n    \
+               }\n  \
+             }\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_chain() {
+      let mut gb = GraphBuilder::new();
+      gb.chain(["a", "b", "c"]);
+      gb.chain_with(["c", "d"], Edge::default().color(Color::blue()));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             a -> b;\n\n  \
+             b -> c;\n\n  \
+             c -> d[color=\"blue\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn entity_handles() {
+      let mut gb = GraphBuilder::new();
+      let a = gb.add_vertex(Vertex::new("a"));
+      let b = gb.add_vertex(Vertex::new("b"));
+      let sg = gb.add_subgraph(Subgraph::new("cluster_0"));
+      let e = gb.connect(&a, &b);
+
+      gb.vertex_mut(&a).unwrap().label = Some(Label("A".to_string()));
+      gb.edge_mut(&e).unwrap().color = Some(Color::red());
+      assert!(gb.move_into(&b, &sg));
+      assert!(!gb.move_into(&b, &SubgraphRef(Id::new("nonexistent"))));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             a[label=\"A\"];\n\n  \
+             subgraph cluster_0 {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               b;\n  \
+             }\n\n  \
+             a -> b[color=\"red\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn query_entities() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Vertex(Vertex::new("a")));
+      gb.subgraph("cluster_0", |sg| {
+        sg.vertex(Vertex::new("b"));
+        sg.edge(Edge::between("a", "b").label("first"));
+      });
+      gb.accept_entity(Entity::Edge(Edge::between("a", "b").label("second")));
+      gb.accept_entity(Entity::Edge(Edge::between("b", "a")));
+
+      assert_eq!(gb.vertices().count(), 2);
+      assert_eq!(gb.edges().count(), 3);
+      assert_eq!(gb.subgraphs().count(), 1);
+      assert!(gb.contains_vertex(&Id::new("b")));
+      assert!(!gb.contains_vertex(&Id::new("cluster_0")));
+
+      let (a, b) = (Id::new("a"), Id::new("b"));
+      let labels: Vec<String> = gb
+        .find_edges_between(&a, &b)
+        .map(|e| e.label.clone().unwrap().0)
+        .collect();
+      assert_eq!(labels, vec!["first".to_string(), "second".to_string()]);
+    }
+
+    #[test]
+    fn remove_and_update() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Vertex(Vertex::new("a")));
+      gb.subgraph("cluster_0", |sg| {
+        sg.vertex(Vertex::new("b"));
+        sg.edge(Edge::between("a", "b"));
+      });
+      gb.accept_entity(Entity::Vertex(Vertex::new("c")));
+      let bc = gb.add_edge(Edge::between("b", "c"));
+      gb.accept_entity(Entity::Edge(Edge::between("c", "a")));
+      gb.accept_entity(Entity::Edge(Edge::between("a", "c")));
+
+      assert!(gb.remove_vertex(&Id::new("b"), true).is_some());
+      assert!(gb.remove_vertex(&Id::new("b"), true).is_none());
+      assert!(gb.remove_edge(&bc).is_none());
+      gb.remove_edges_between(&Id::new("a"), &Id::new("c"));
+      assert!(gb.update_vertex(&Id::new("c"), |v| v.label = Some(Label("C".to_string()))));
+      assert!(!gb.update_vertex(&Id::new("b"), |_| unreachable!()));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             a;\n\n  \
+             subgraph cluster_0 {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n  \
+             }\n\n  \
+             c[label=\"C\"];\n\n  \
+             c -> a;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn removed_and_renamed_ids() {
+      let mut gb = GraphBuilder::new();
+      gb.accept_entity(Entity::Vertex(Vertex::new("a")));
+      gb.accept_entity(Entity::Vertex(Vertex::new("a")));
+      gb.accept_entity(Entity::Subgraph(Subgraph::new("b")));
+      gb.accept_entity(Entity::Vertex(Vertex::new("b")));
+      gb.accept_entity(Entity::Vertex(Vertex::new("c")));
+
+      gb.remove_vertex(&Id::new("a"), false);
+      assert!(gb.contains_id(&Id::new("a")));
+      gb.remove_vertex(&Id::new("a"), false);
+      assert!(!gb.contains_id(&Id::new("a")));
+      gb.remove_vertex(&Id::new("b"), false);
+      assert!(gb.contains_id(&Id::new("b")));
+
+      assert!(gb.update_vertex(&Id::new("c"), |v| v.id = Id::new("d")));
+      assert!(!gb.contains_id(&Id::new("c")));
+      assert!(gb.contains_id(&Id::new("d")));
+    }
+
+    #[test]
+    fn collect_entities() {
+      let mut gb: GraphBuilder = (0..2).map(|i| Entity::Vertex(numeric_vertex(i))).collect();
+      gb.extend([(numeric_vertex(0).id, numeric_vertex(1).id)]);
+      assert_eq!(gb.vertices().count(), 2);
+      assert_eq!(gb.edges().count(), 1);
+
+      let gb: GraphBuilder = [("a", "b"), ("b", "c")]
+        .into_iter()
+        .map(|(s, t)| (Id::new(s), Id::new(t)))
+        .collect();
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             a -> b;\n\n  \
+             b -> c;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn render_auto_vertices() {
+      let mut gb = GraphBuilder::new();
+      gb.auto_create_vertices(|id| Vertex::new(id.clone()).color(Color::red()));
+      gb.accept_entity(Entity::Vertex(Vertex::new("a")));
+      gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+      gb.accept_entity(Entity::Edge(Edge::between("b", "a")));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             a;\n\n  \
+             a -> b;\n\n  \
+             b -> a;\n\n  \
+             b[color=\"red\"];\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn dedupe_vertices_and_edges() {
+      let mut gb = GraphBuilder::new();
+      gb.dedupe(true);
+      gb.accept_entity(Entity::Vertex(Vertex::new("a").label("first")));
+      gb.accept_entity(Entity::Vertex(Vertex::new("a").label("second")));
+      gb.set_merge_strategy(MergeStrategy::LastWins);
+      gb.accept_entity(Entity::Vertex(Vertex::new("b").label("first")));
+      gb.accept_entity(Entity::Vertex(Vertex::new("b").label("second")));
+      let e1 = gb.add_edge(Edge::between("a", "b"));
+      let e2 = gb.add_edge(Edge::between("a", "b"));
+      assert_eq!(e1, e2);
+      gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+
+      gb.set_merge_strategy(MergeStrategy::Error);
+      assert!(gb
+        .try_accept_entity(Entity::Vertex(Vertex::new("b").label("second")))
+        .is_ok());
+      let err = gb
+        .try_accept_entity(Entity::Vertex(Vertex::new("b").label("third")))
+        .unwrap_err();
+      assert_eq!(err.existing.label, Some(Label("second".to_string())));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             a[label=\"first\"];\n\n  \
+             b[label=\"second\"];\n\n  \
+             a -> b;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn dedupe_nested_and_after_removal() {
+      let mut gb = GraphBuilder::new();
+      gb.dedupe(true);
+      gb.set_merge_strategy(MergeStrategy::LastWins);
+      gb.subgraph("cluster_s", |sg| {
+        sg.vertex(Vertex::new("a").label("first"));
+        sg.edge(Edge::between("a", "b"));
+      });
+      gb.accept_entity(Entity::Vertex(Vertex::new("a").label("second")));
+      let nested = gb.add_edge(Edge::between("a", "b"));
+      assert_eq!(gb.edge_mut(&nested), None);
+      assert_eq!(gb.edges().count(), 1);
+
+      let e = gb.add_edge(Edge::between("b", "a"));
+      assert_eq!(gb.remove_edge(&e), Some(Edge::between("b", "a")));
+      let v = gb.add_vertex(Vertex::new("c"));
+      gb.vertex_mut(&v).unwrap().id = Id::new("d");
+      gb.accept_entity(Entity::Vertex(Vertex::new("d").label("merged")));
+      gb.accept_entity(Entity::Edge(Edge::between("b", "a")));
+      gb.accept_entity(Entity::Edge(Edge::between("b", "a")));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             subgraph cluster_s {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               a[label=\"second\"];\n    \
+               a -> b;\n  \
+             }\n\n  \
+             d[label=\"merged\"];\n\n  \
+             b -> a;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn dedupe_within_subgraphs() {
+      let mut gb = GraphBuilder::new();
+      gb.dedupe(true);
+      gb.add_vertex(Vertex::new("a").label("first"));
+      gb.add_edge(Edge::between("a", "b"));
+      let mut sg = Subgraph::new("cluster_s");
+      sg.entities = vec![
+        Entity::Vertex(Vertex::new("a").label("second")),
+        Entity::Vertex(Vertex::new("c")),
+        Entity::Group(Group {
+          node_defaults: None,
+          entities: vec![Entity::Edge(Edge::between("a", "b"))],
+        }),
+      ];
+      gb.add_subgraph(sg);
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             a[label=\"first\"];\n\n  \
+             a -> b;\n\n  \
+             subgraph cluster_s {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               c;\n    \
+               {\n    \
+               }\n  \
+             }\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn dedupe_siblings_within_subgraph() {
+      let mut gb = GraphBuilder::new();
+      gb.dedupe(true);
+      let mut sg = Subgraph::new("cluster_s");
+      sg.entities = vec![
+        Entity::Vertex(Vertex::new("a")),
+        Entity::Vertex(Vertex::new("a")),
+        Entity::Edge(Edge::between("a", "b")),
+        Entity::Edge(Edge::between("a", "b")),
+        Entity::Group(Group {
+          node_defaults: None,
+          entities: vec![
+            Entity::Vertex(Vertex::new("a")),
+            Entity::Edge(Edge::between("a", "b")),
+          ],
+        }),
+      ];
+      gb.add_subgraph(sg);
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             subgraph cluster_s {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               a;\n    \
+               a -> b;\n    \
+               {\n    \
+               }\n  \
+             }\n\
+           }\n"
+      );
+
+      let mut gb = GraphBuilder::new();
+      gb.dedupe(true);
+      gb.set_merge_strategy(MergeStrategy::Error);
+      let mut sg = Subgraph::new("cluster_t");
+      sg.entities = vec![
+        Entity::Vertex(Vertex::new("c").label("first")),
+        Entity::Vertex(Vertex::new("c").label("second")),
+      ];
+      assert!(gb.try_accept_entity(Entity::Subgraph(sg)).is_err());
+      assert_eq!(gb.entities().count(), 0);
+      assert!(!gb.contains_id(&Id::new("c")));
+    }
+
+    #[test]
+    fn scoped_namespaces() {
+      let mut gb = GraphBuilder::new();
+      gb.add_vertex(Vertex::new("a"));
+      for prefix in ["left", "right"] {
+        gb.scoped(prefix, |scope| {
+          scope.add_vertex(Vertex::new("a"));
+          scope.subgraph("cluster_b", |sg| {
+            sg.vertex(Vertex::new("b"));
+          });
+          scope.add_edge(Edge::between(Id::new("a"), Id::new("b")));
+        });
+      }
+      let ns = IdNamespace::new("left");
+      gb.add_edge(Edge::between(Id::new("a"), ns.qualify(&Id::new("a"))));
+      assert!(gb.contains_id(&Id::new("right_cluster_b")));
+      assert!(gb.contains_vertex(&Id::new("right_b")));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph test_graph {\n  \
+             compound = true;\n\n  \
+             a;\n\n  \
+             left_a;\n\n  \
+             subgraph left_cluster_b {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               left_b;\n  \
+             }\n\n  \
+             left_a -> left_b;\n\n  \
+             right_a;\n\n  \
+             subgraph right_cluster_b {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               right_b;\n  \
+             }\n\n  \
+             right_a -> right_b;\n\n  \
+             a -> left_a;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn contains_vertex_and_nested_iteration() {
+      let mut gb = GraphBuilder::new();
+      gb.subgraph("cluster_s", |sg| {
+        sg.vertex(Vertex::new("a"));
+        sg.subgraph("cluster_t", |t| t.vertex(Vertex::new("b")));
+        sg.edge(Edge::between("a", "b"));
+      });
+      gb.add_vertex(Vertex::new("c"));
+      assert!(gb.contains_vertex(&Id::new("b")));
+      assert!(!gb.contains_vertex(&Id::new("cluster_s")));
+      gb.remove_vertex(&Id::new("b"), true);
+      assert!(!gb.contains_vertex(&Id::new("b")));
+      assert!(gb.contains_vertex(&Id::new("c")));
+
+      let ids: Vec<&str> = gb.vertices().map(|v| v.id.as_str()).collect();
+      assert_eq!(ids, vec!["a", "c"]);
+      let ids: Vec<&str> = gb.subgraphs().map(|sg| sg.id.as_str()).collect();
+      assert_eq!(ids, vec!["cluster_s", "cluster_t"]);
+      assert_eq!(gb.edges().count(), 0);
+    }
+
+    #[test]
+    fn scoped_settings() {
+      let mut gb = GraphBuilder::new();
+      gb.set_id_generator(crate::ids::Sequential::new("n"));
+      gb.dedupe(true);
+      let first = gb.fresh_id();
+      gb.scoped("s", |scope| {
+        let v = scope.fresh_vertex();
+        scope.add_vertex(v.clone());
+        scope.add_vertex(v.label("dropped"));
+        scope.add_vertex(Vertex::new("a"));
+      });
+      assert_eq!(gb.fresh_id(), Id::new("n2"));
+      assert_eq!(first, Id::new("n0"));
+
+      let ids: Vec<&str> = gb.vertices().map(|v| v.id.as_str()).collect();
+      assert_eq!(ids, vec!["s_n1", "s_a"]);
+    }
+
+    #[test]
+    fn id_clones_share_storage() {
+      let a = Id::new("shared");
+      let b = a.clone();
+      assert!(std::ptr::eq(a.as_str(), b.as_str()));
+
+      let mut gb = GraphBuilder::new();
+      gb.add_vertex(Vertex::new(a));
+      assert!(gb.contains_vertex(&b));
+    }
+
+    #[test]
+    fn sanitized_ids() {
+      assert_eq!(Id::sanitized("plain_id").as_str(), "plain_id");
+      let path = Id::sanitized("/usr/lib/libc.so");
+      assert_eq!(path, Id::sanitized("/usr/lib/libc.so"));
+      assert!(path.as_str().starts_with("_usr_lib_libc_so_"));
+      assert_ne!(path, Id::sanitized("/usr/lib/libc_so"));
+      assert!(Id::sanitized("1abc").as_str().starts_with("_1abc_"));
+      assert!(Id::sanitized("").as_str().starts_with('_'));
+      for s in ["a b", "https://x.y/z?q=1", "Vec<Box<dyn Fn()>>"] {
+        let id = Id::sanitized(s);
+        assert_eq!(id.clone().maybe_escaped(), id.as_str());
+      }
+    }
+
+    #[test]
+    fn quote_keywords() {
+      for k in [
+        "node", "edge", "graph", "digraph", "subgraph", "strict", "Node", "EDGE", "Graph",
+        "DiGraph", "SUBGRAPH", "sTrIcT",
+      ] {
+        assert_eq!(Id::new(k).maybe_escaped(), format!("\"{}\"", k));
+      }
+      assert_eq!(Id::new("nodes").maybe_escaped(), "nodes");
+
+      let mut gb = GraphBuilder::new();
+      gb.add_edge(Edge::between("node", "edge"));
+      let DotOutput(output) = gb.build(Id::new("graph")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph \"graph\" {\n  \
+             compound = true;\n\n  \
+             \"node\" -> \"edge\";\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn quoting_policies() {
+      let build = |policy| {
+        let mut gb = GraphBuilder::new();
+        gb.set_quoting_policy(policy);
+        gb.add_edge(Edge::between("a", "-1.5"));
+        let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+        output
+      };
+
+      assert_eq!(
+        build(QuotingPolicy::Always),
+        "digraph \"g\" {\n  \
+             compound = true;\n\n  \
+             \"a\" -> \"-1.5\";\n\
+           }\n"
+      );
+      assert_eq!(
+        build(QuotingPolicy::AlwaysExceptNumerals),
+        "digraph \"g\" {\n  \
+             compound = true;\n\n  \
+             \"a\" -> -1.5;\n\
+           }\n"
+      );
+      assert_eq!(
+        build(QuotingPolicy::Minimal),
+        "digraph g {\n  \
+             compound = true;\n\n  \
+             a -> -1.5;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn anchored_id_classification() {
+      for (id, expected) in [
+        ("abc", "abc"),
+        ("_a1", "_a1"),
+        ("café", "café"),
+        ("1abc", "\"1abc\""),
+        ("foo-bar", "\"foo-bar\""),
+        ("a.b", "\"a.b\""),
+        ("a b", "\"a b\""),
+        ("-1.5", "-1.5"),
+        (".5", ".5"),
+        ("1.", "1."),
+        ("-", "\"-\""),
+        ("1.2.3", "\"1.2.3\""),
+        ("", "\"\""),
+      ] {
+        let escaped = Id::new(id).maybe_escaped();
+        assert_eq!(escaped, expected);
+        assert!(Id::is_valid_token(&escaped), "{}", escaped);
+      }
+      assert!(!Id::is_valid_token("foo-bar"));
+      assert!(!Id::is_valid_token("\"a\"b\""));
+
+      let mut gb = GraphBuilder::new();
+      gb.verify_ids(true);
+      gb.add_edge(Edge::between("1abc", "ok"));
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph g {\n  \
+             compound = true;\n\n  \
+             \"1abc\" -> ok;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn invalid_ids_are_errors() {
+      let mut gb = GraphBuilder::new();
+      gb.verify_ids(true);
+      gb.add_edge(Edge::between("a", Id::html("<b")));
+      match gb.build(Id::new("g")) {
+        Err(GraphError::InvalidId(id)) => assert_eq!(id, Id::html("<b")),
+        r => panic!("unexpected result: {:?}", r),
+      }
+    }
+
+    #[test]
+    fn dangling_edges_are_errors() {
+      let mut gb = GraphBuilder::new();
+      gb.validate_edges(true);
+      gb.add_vertex(Vertex::new("a"));
+      gb.accept_entity(Entity::Subgraph(Subgraph::new("cluster_s")));
+      gb.add_edge(Edge::between("a", "cluster_s"));
+      gb.add_edge(Edge::between("a", "b"));
+      gb.add_edge(Edge::default());
+      match gb.build(Id::new("g")) {
+        Err(GraphError::DanglingEdges(edges)) => assert_eq!(edges, vec![
+          DanglingEdge {
+            source: Id::new("a"),
+            target: Id::new("b"),
+            missing: vec![Id::new("b")],
+          },
+          DanglingEdge {
+            source: Id::new(""),
+            target: Id::new(""),
+            missing: vec![Id::new("")],
+          },
+        ]),
+        r => panic!("unexpected result: {:?}", r),
+      }
+
+      let mut gb = GraphBuilder::new();
+      gb.validate_edges(true);
+      gb.auto_create_vertices(|id| Vertex::new(id.clone()));
+      gb.add_edge(Edge::between("a", "b"));
+      assert!(gb.build(Id::new("g")).is_ok());
+
+      /* An edge to a subgraph is neither dangling nor given a vertex. */
+      let mut gb = GraphBuilder::new();
+      gb.validate_edges(true);
+      gb.auto_create_vertices(|id| Vertex::new(id.clone()).color(Color::red()));
+      gb.accept_entity(Entity::Subgraph(
+        Subgraph::new("cluster_s").entity(Entity::Vertex(Vertex::new("a"))),
+      ));
+      gb.add_edge(Edge::between("a", "cluster_s"));
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+      assert!(!output.contains("red"), "{}", output);
+    }
+
+    #[test]
+    fn conflicting_vertices_are_errors() {
+      let mut gb = GraphBuilder::new();
+      gb.validate_vertices(true);
+      gb.add_vertex(Vertex::new("a").label("A"));
+      gb.add_vertex(Vertex::new("a").label("A"));
+      gb.accept_entity(Entity::Subgraph(
+        Subgraph::new("cluster_s").entity(Entity::Vertex(Vertex::new("a").label("B"))),
+      ));
+      assert_eq!(gb.conflicting_vertices().len(), 1);
+      match gb.build(Id::new("g")) {
+        Err(GraphError::DuplicateVertex(DuplicateVertex { existing, new })) => {
+          assert_eq!(*existing, Vertex::new("a").label("A"));
+          assert_eq!(*new, Vertex::new("a").label("B"));
+        },
+        r => panic!("unexpected result: {:?}", r),
+      }
+    }
+
+    #[test]
+    fn cluster_naming() {
+      let mut gb = GraphBuilder::new();
+      gb.set_cluster_naming(ClusterNaming::PrefixAndLabel);
+      gb.accept_entity(Entity::Subgraph(Subgraph::new("a")));
+      gb.accept_entity(Entity::Subgraph(Subgraph::new("b").label("B")));
+      gb.accept_entity(Entity::Subgraph(Subgraph::new("cluster_c")));
+      gb.accept_entity(Entity::Subgraph(Subgraph::new("d").cluster(false)));
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph g {\n  \
+             compound = true;\n\n  \
+             subgraph cluster_a {\n    \
+               label = \"a\";\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n  \
+             }\n\n  \
+             subgraph cluster_b {\n    \
+               label = \"B\";\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n  \
+             }\n\n  \
+             subgraph cluster_c {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n  \
+             }\n\n  \
+             subgraph d {\n    \
+               rank = same;\n\n\n  \
+             }\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn undirected_output() {
+      let mut gb = GraphBuilder::new();
+      gb.set_directed(false);
+      gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+
+      assert_eq!(
+        output,
+        "graph g {\n  \
+             compound = true;\n\n  \
+             a -- b;\n\
+           }\n"
+      );
+      crate::parser::parse(&output).unwrap();
+    }
+
+    #[test]
+    fn invalid_attributes_are_errors() {
+      let mut gb = GraphBuilder::new();
+      gb.validate_attributes(true);
+      gb.add_vertex(Vertex::new("a").attr("shape", "box").attr("colour", "red"));
+      gb.add_edge(Edge::between("a", "a").attr("shape", "box"));
+      gb.accept_entity(Entity::Subgraph(Subgraph::new("s").attr("pencolor", "red")));
+      gb.accept_entity(Entity::Subgraph(
+        Subgraph::new("t").cluster(false).attr("pencolor", "red"),
+      ));
+
+      assert_eq!(gb.invalid_attributes(), vec![
+        InvalidAttribute {
+          entity: EntityRef::Vertex(Id::new("a")),
+          name: "colour".to_string(),
+          problem: AttrProblem::Unknown {
+            suggestion: Some("color")
+          },
+        },
+        InvalidAttribute {
+          entity: EntityRef::Edge {
+            source: Id::new("a"),
+            target: Id::new("a"),
+          },
+          name: "shape".to_string(),
+          problem: AttrProblem::NotApplicable,
+        },
+        InvalidAttribute {
+          entity: EntityRef::Subgraph(Id::new("t")),
+          name: "pencolor".to_string(),
+          problem: AttrProblem::NotApplicable,
+        },
+      ]);
+      match gb.build(Id::new("g")) {
+        Err(GraphError::InvalidAttribute(e)) => assert_eq!(
+          e.to_string(),
+          "vertex \"a\": \"colour\": unknown attribute (did you mean \"color\"?)"
+        ),
+        r => panic!("unexpected result: {:?}", r),
+      }
+    }
+
+    #[test]
+    fn diagnostics_alongside_output() {
+      let mut gb = GraphBuilder::new();
+      gb.validate_attributes(true);
+      gb.add_vertex(Vertex::new("a"));
+      gb.add_vertex(Vertex::new("a").label("A"));
+      gb.add_edge(Edge::between("a", "b"));
+      let (output, diagnostics) = gb.build_with_diagnostics(Id::new("g"));
+      assert!(output.is_ok());
+      assert_eq!(
+        diagnostics.to_string(),
+        "warning: vertex \"a\": conflicts with an earlier vertex with the same id\n\
+         warning: edge \"a\" -> \"b\": refers to undefined \"b\"\n"
+      );
+
+      let mut gb = GraphBuilder::new();
+      gb.validate_edges(true);
+      gb.auto_create_vertices(|id| Vertex::new(id.clone()));
+      gb.add_edge(Edge::between("a", "b").attr("colour", "red"));
+      let (output, diagnostics) = gb.build_with_diagnostics(Id::new("g"));
+      assert!(output.is_ok());
+      assert_eq!(
+        diagnostics.to_string(),
+        "warning: edge \"a\" -> \"b\": \"colour\": unknown attribute (did you mean \"color\"?)\n\
+         note: created 2 vertices for undefined edge endpoints\n"
+      );
+
+      let mut gb = GraphBuilder::new();
+      gb.validate_edges(true);
+      gb.add_edge(Edge::between("a", "b"));
+      gb.add_edge(Edge::between("b", "c"));
+      let (output, diagnostics) = gb.build_with_diagnostics(Id::new("g"));
+      assert!(matches!(output, Err(GraphError::DanglingEdges(ref e)) if e.len() == 2));
+      assert_eq!(diagnostics.count(Severity::Error), 2);
+    }
+
+    #[test]
+    fn build_modes() {
+      let graph = |mode| {
+        let mut gb = GraphBuilder::new();
+        gb.set_build_mode(mode);
+        gb.add_vertex(Vertex::new("a").attr("colour", "red"));
+        gb.add_edge(Edge::between("a", "b").attr("penwidth", "2"));
+        gb
+      };
+
+      assert!(matches!(
+        graph(BuildMode::Strict).build(Id::new("g")),
+        Err(GraphError::InvalidAttribute(_))
+      ));
+
+      let (output, diagnostics) = graph(BuildMode::Lenient).build_with_diagnostics(Id::new("g"));
+      assert_eq!(
+        output.unwrap().0,
+        "digraph g {\n  \
+             compound = true;\n\n  \
+             a;\n\n  \
+             a -> b[penwidth=\"2\"];\n\n  \
+             b;\n\
+           }\n"
+      );
+      assert_eq!(
+        diagnostics.to_string(),
+        "warning: vertex \"a\": \"colour\": unknown attribute (did you mean \"color\"?); skipped\n\
+         note: created 1 vertices for undefined edge endpoints\n"
+      );
+    }
+
+    #[test]
+    fn empty_ids_and_labels() {
+      let mut gb = GraphBuilder::new();
+      gb.set_build_mode(BuildMode::Strict);
+      gb.add_vertex(Vertex::new("a").label(""));
+      match gb.build(Id::new("g")) {
+        Err(GraphError::EmptyLabel(EntityRef::Vertex(id))) => assert_eq!(id, Id::new("a")),
+        r => panic!("unexpected result: {:?}", r),
+      }
+
+      let mut gb = GraphBuilder::new();
+      gb.set_build_mode(BuildMode::Lenient);
+      gb.add_vertex(Vertex::new("a").label(""));
+      gb.add_edge(Edge::default());
+      gb.accept_entity(Entity::Subgraph(Subgraph::new("s").label("")));
+      let (output, diagnostics) = gb.build_with_diagnostics(Id::new("g"));
+      assert_eq!(
+        output.unwrap().0,
+        "digraph g {\n  \
+             compound = true;\n\n  \
+             a;\n\n  \
+             _empty -> _empty;\n\n  \
+             subgraph s {\n    \
+               label = \"s\";\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n  \
+             }\n\n  \
+             _empty;\n\
+           }\n"
+      );
+      assert_eq!(diagnostics.count(Severity::Warning), 3);
+    }
+
+    #[test]
+    fn adjacency_matrices() {
+      let gb = GraphBuilder::from_adjacency_matrix(&[vec![false, true], vec![true, true]], |i| {
+        Id::new(format!("n{}", i))
+      });
+      let edges: Vec<(&str, &str)> = gb
+        .edges()
+        .map(|e| (e.source.as_str(), e.target.as_str()))
+        .collect();
+      assert_eq!(edges, vec![("n0", "n1"), ("n1", "n0"), ("n1", "n1")]);
+      assert_eq!(gb.vertices().count(), 2);
+
+      let gb = GraphBuilder::from_weighted_adjacency_matrix(
+        &[vec![0.0, -2.0], vec![0.5, f64::NAN]],
+        |i| Id::new(i.to_string()),
+      )
+      .unwrap();
+      let edges: Vec<Edge> = gb.edges().cloned().collect();
+      assert_eq!(edges, vec![
+        Edge::between("0", "1").label("-2").attr("penwidth", "5"),
+        Edge::between("1", "0").label("0.5").attr("penwidth", "2"),
+      ]);
+      match GraphBuilder::from_weighted_adjacency_matrix(
+        &[vec![1.0, 0.0], vec![f64::NEG_INFINITY, 2.0]],
+        |i| Id::new(i.to_string()),
+      ) {
+        Err(e) => assert_eq!(e, InfiniteWeight { row: 1, column: 0 }),
+        Ok(_) => panic!("accepted an infinite weight"),
+      }
+    }
+
+    #[test]
+    fn adjacency_lists() {
+      let gb = GraphBuilder::from_adjacency_list_with(
+        vec![("a", vec!["b", "a"]), ("b", vec!["a"]), ("a", vec!["b"])],
+        |id| Vertex::new(id.clone()).shape("box"),
+        |source, target| Edge::between(source.clone(), target.clone()).color(Color::red()),
+      );
+      assert_eq!(
+        gb.build(Id::new("g")).unwrap().0,
+        "digraph g {\n  \
+           compound = true;\n\n  \
+           a[shape=\"box\"];\n\n  \
+           b[shape=\"box\"];\n\n  \
+           a -> b[color=\"red\"];\n\n  \
+           a -> a[color=\"red\"];\n\n  \
+           b -> a[color=\"red\"];\n\
+         }\n"
+      );
+    }
+
+    #[test]
+    #[should_panic(expected = "row 1 of an adjacency matrix with 2 rows")]
+    fn adjacency_matrix_not_square() {
+      GraphBuilder::from_adjacency_matrix(&[vec![false, true], vec![true]], |i| {
+        Id::new(i.to_string())
+      });
+    }
+
+    #[cfg(all(feature = "serde", feature = "json"))]
+    #[test]
+    fn serde_round_trip() {
+      let entities = vec![
+        Entity::Vertex(Vertex::new("a").label("A").attr("tooltip", "tip")),
+        Entity::Subgraph(
+          Subgraph::new("cluster_x")
+            .label("X")
+            .entity(Entity::Edge(Edge::between("a", Id::html("<b>b</b>")))),
+        ),
+        Entity::Comment("done".to_string()),
+      ];
+      let text = serde_json::to_string(&entities).unwrap();
+      let back: Vec<Entity> = serde_json::from_str(&text).unwrap();
+      assert_eq!(back, entities);
+
+      let config: Vec<Entity> = serde_json::from_str(
+        r#"[
+          {"Vertex": {"id": "a", "label": "A", "shape": "box"}},
+          {"Subgraph": {"id": "s", "entities": [{"Edge": {"source": "a", "target": {"html": "<i>b</i>"}}}]}}
+        ]"#,
+      )
+      .unwrap();
+      assert_eq!(config, vec![
+        Entity::Vertex(Vertex::new("a").label("A").shape("box")),
+        Entity::Subgraph(
+          Subgraph::new("s").entity(Entity::Edge(Edge::between("a", Id::html("<i>b</i>")))),
+        ),
+      ]);
+    }
+
+    #[cfg(all(feature = "schemars", feature = "json"))]
+    #[test]
+    fn entity_json_schema() {
+      let schema = serde_json::to_value(schemars::schema_for!(Entity)).unwrap();
+      assert_eq!(schema["title"], "Entity");
+      let definitions = schema["$defs"].as_object().unwrap();
+      for name in [
+        "Vertex", "Edge", "Subgraph", "Group", "Id", "Label", "Color",
+      ] {
+        assert!(definitions.contains_key(name), "{}", name);
+      }
+      let id = &definitions["Id"]["anyOf"];
+      assert_eq!(id[0]["type"], "string");
+      assert_eq!(id[1]["required"], serde_json::json!(["html"]));
+    }
+
+    #[test]
+    fn graphable_collections() {
+      use crate::Graphable;
+      use std::collections::BTreeMap;
+
+      let list: BTreeMap<&str, Vec<&str>> = [("b", vec!["a"]), ("a", vec!["b", "c"])]
+        .into_iter()
+        .collect();
+      let gb = list.build_graph();
+      let ids: Vec<&str> = gb.vertices().map(|v| v.id.as_str()).collect();
+      assert_eq!(ids, vec!["a", "b", "c"]);
+      assert_eq!(gb.edges().count(), 3);
+
+      let gb = vec![("x", "y"), ("y", "x"), ("x", "y")].build_graph();
+      let ids: Vec<&str> = gb.vertices().map(|v| v.id.as_str()).collect();
+      assert_eq!(ids, vec!["x", "y"]);
+      assert_eq!(gb.edges().count(), 3);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn graphable_json() {
+      use crate::Graphable;
+
+      let gb = serde_json::json!({"a\\b": {"q": "say \"hi\""}, "empty": []}).build_graph();
+      let DotOutput(output) = gb.build(Id::new("j")).unwrap();
+      assert_eq!(
+        output,
+        "digraph j {\n  \
+           compound = true;\n\n  \
+           j0[label=\"{}\"];\n\n  \
+           j1[label=\"{}\"];\n\n  \
+           j2[label=\"\\\"say \\\\\\\"hi\\\\\\\"\\\"\", shape=\"box\"];\n\n  \
+           j1 -> j2[label=\"q\"];\n\n  \
+           j0 -> j1[label=\"a\\\\b\"];\n\n  \
+           j3[label=\"[]\"];\n\n  \
+           j0 -> j3[label=\"empty\"];\n\
+         }\n"
+      );
+      assert!(crate::parser::parse(&output).is_ok());
+    }
+
+    #[test]
+    fn html_ids_and_attributes() {
+      let mut gb = GraphBuilder::new();
+      gb.verify_ids(true);
+      gb.add_vertex(Vertex::new(Id::html("<b>a</b>")).attr("label", Id::html("<i>A</i>")));
+      gb.add_vertex(Vertex::new("b").attr("tooltip", "say \"hi\""));
+      gb.add_edge(Edge::between(Id::html("<b>a</b>"), "b").attr("penwidth", "2"));
+      gb.accept_entity(Entity::Subgraph(
+        Subgraph::new("cluster_c").attr("label", AttrValue::Html("<u>C</u>".to_string())),
+      ));
+
+      let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
 
       assert_eq!(
         output,
         "digraph test_graph {\n  \
              compound = true;\n\n  \
-             node_0[label=\"node_0\", ];\n\
+             <<b>a</b>>[label=<<i>A</i>>];\n\n  \
+             b[tooltip=\"say \\\"hi\\\"\"];\n\n  \
+             <<b>a</b>> -> b[penwidth=\"2\"];\n\n  \
+             subgraph cluster_c {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n    \
+               label = <<u>C</u>>;\n\n  \
+             }\n\
            }\n"
       );
     }
 
     #[test]
-    fn render_single_edge() {
+    fn display_entities() {
+      let v = Vertex::new("a").label("A");
+      assert_eq!(v.to_string(), "a[label=\"A\"];");
+      let e = Edge::between("a", "b c");
+      assert_eq!(format!("{}", e), "a -> \"b c\";");
+      let sg = Subgraph::new("cluster_s").entity(Entity::Vertex(Vertex::new("a")));
+      assert_eq!(
+        sg.to_string(),
+        "subgraph cluster_s {\n  \
+           cluster = true;\n  \
+           rank = same;\n\n\n  \
+           a;\n\
+         }"
+      );
+
       let mut gb = GraphBuilder::new();
-      gb.accept_entity(Entity::Vertex(numeric_vertex(0)));
-      gb.accept_entity(Entity::Vertex(numeric_vertex(1)));
-      gb.accept_entity(Entity::Edge(Edge {
-        source: numeric_vertex(0).id,
-        target: numeric_vertex(1).id,
-        label: Some(Label("asdf".to_string())),
+      gb.add_vertex(v);
+      let output = gb.build(Id::new("g")).unwrap();
+      assert_eq!(output.to_string(), output.0);
+    }
+
+    #[test]
+    fn write_output() {
+      let mut gb = GraphBuilder::new();
+      gb.add_edge(Edge::between("a", "b"));
+      let expected = "digraph g {\n  \
+                        compound = true;\n\n  \
+                        a -> b;\n\
+                      }\n";
+
+      let mut buf: Vec<u8> = Vec::new();
+      DotOutput(expected.to_string()).write_to(&mut buf).unwrap();
+      assert_eq!(buf, expected.as_bytes());
+
+      let path = std::env::temp_dir().join(format!("graphvizier-{}.dot", Id::random().as_str()));
+      gb.build_to_file(&path, Id::new("g")).unwrap();
+      let written = std::fs::read_to_string(&path).unwrap();
+      assert_eq!(written, expected);
+
+      /* A failed build leaves the existing file alone. */
+      let mut gb = GraphBuilder::new();
+      gb.validate_edges(true);
+      gb.add_edge(Edge::between("a", "b"));
+      assert!(gb.build_to_file(&path, Id::new("g")).is_err());
+      let written = std::fs::read_to_string(&path).unwrap();
+      std::fs::remove_file(&path).unwrap();
+      assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn build_into_writer() {
+      let mut gb = GraphBuilder::new();
+      gb.set_theme(Theme::light());
+      gb.add_edge(Edge::between("a", "b"));
+      gb.add_vertex(Vertex::new("a"));
+
+      let mut streamed = String::new();
+      gb.build_into(&mut streamed, Id::new("g")).unwrap();
+      let DotOutput(built) = gb.build(Id::new("g")).unwrap();
+      assert_eq!(streamed, built);
+    }
+
+    #[test]
+    fn shared_ids_are_not_copied() {
+      use std::sync::Arc;
+
+      let key: Arc<str> = "model_key".into();
+      let v = Vertex::new(&key);
+      assert!(std::ptr::eq(v.id.as_str(), &*key));
+      let e = Edge::between(&key, Arc::clone(&key));
+      assert!(std::ptr::eq(e.source.as_str(), e.target.as_str()));
+    }
+
+    #[test]
+    fn with_capacity_matches_new() {
+      let mut gb = GraphBuilder::with_capacity(10, 10);
+      for i in 0..10 {
+        gb.add_vertex(numeric_vertex(i));
+      }
+      let mut expected = GraphBuilder::new();
+      for i in 0..10 {
+        expected.add_vertex(numeric_vertex(i));
+      }
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+      assert_eq!(output, expected.build(Id::new("g")).unwrap().0);
+    }
+
+    #[test]
+    fn attr_list_styles() {
+      let build = |style| {
+        let mut gb = GraphBuilder::new();
+        gb.set_attr_list_style(style);
+        gb.add_vertex(Vertex::new("a").label("A").color(Color::red()));
+        let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+        output
+      };
+      let vertex_line = |style| build(style).lines().nth(3).unwrap().trim().to_string();
+
+      assert_eq!(
+        vertex_line(AttrListStyle::default()),
+        "a[label=\"A\", color=\"red\"];"
+      );
+      assert_eq!(
+        vertex_line(AttrListStyle {
+          separator: AttrSeparator::Space,
+          spaced_equals: false,
+        }),
+        "a[label=\"A\" color=\"red\"];"
+      );
+      assert_eq!(
+        vertex_line(AttrListStyle {
+          separator: AttrSeparator::Semicolon,
+          spaced_equals: true,
+        }),
+        "a[label = \"A\"; color = \"red\"];"
+      );
+    }
+
+    #[test]
+    fn format_options() {
+      let mut gb = GraphBuilder::new();
+      gb.set_format_options(FormatOptions {
+        use_tabs: true,
+        blank_lines: 0,
+        attrs_per_line: true,
         ..Default::default()
-      }));
+      });
+      gb.add_vertex(Vertex::new("a").label("A").color(Color::red()));
+      gb.subgraph("cluster_s", |sg| {
+        sg.vertex(Vertex::new("b"));
+      });
 
-      let DotOutput(output) = gb.build(Id::new("test_graph"));
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
 
       assert_eq!(
         output,
-        "digraph test_graph {\n  \
+        "digraph g {\n\
+         \tcompound = true;\n\
+         \ta[\n\
+         \t\tlabel=\"A\",\n\
+         \t\tcolor=\"red\"\n\
+         \t];\n\
+         \tsubgraph cluster_s {\n\
+         \t\tcluster = true;\n\
+         \t\trank = same;\n\n\n\
+         \t\tb;\n\
+         \t}\n\
+         }\n"
+      );
+
+      let mut gb = GraphBuilder::new();
+      gb.set_format_options(FormatOptions {
+        indent_width: 4,
+        ..Default::default()
+      });
+      gb.add_vertex(Vertex::new("a"));
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+      assert_eq!(output, "digraph g {\n    compound = true;\n\n    a;\n}\n");
+    }
+
+    #[test]
+    fn minified_output() {
+      let mut gb = GraphBuilder::new();
+      gb.add_vertex(Vertex::new("a").label("A b").color(Color::red()));
+      gb.add_edge(Edge::between("a", "-1"));
+      gb.subgraph("cluster_s", |sg| {
+        sg.vertex(Vertex::new("c"));
+      });
+
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap().minify();
+
+      assert_eq!(
+        output,
+        "digraph g{compound=true;a[label=\"A b\",color=\"red\"];a->-1;\
+         subgraph cluster_s{cluster=true;rank=same;c}}"
+      );
+    }
+
+    #[test]
+    fn canonical_output() {
+      let mut a = GraphBuilder::new();
+      a.set_quoting_policy(QuotingPolicy::Always);
+      a.add_edge(Edge::between("y", "x"));
+      a.add_edge(Edge::between("x", "y").label("1"));
+      a.add_vertex(Vertex::new("y"));
+      a.subgraph("cluster_s", |sg| {
+        sg.vertex(Vertex::new("b"));
+        sg.vertex(Vertex::new("a"));
+      });
+      a.add_vertex(Vertex::new("x"));
+
+      let mut b = GraphBuilder::new();
+      b.set_format_options(FormatOptions {
+        attrs_per_line: true,
+        ..Default::default()
+      });
+      b.add_vertex(Vertex::new("x"));
+      b.subgraph("cluster_s", |sg| {
+        sg.vertex(Vertex::new("a"));
+        sg.vertex(Vertex::new("b"));
+      });
+      b.add_vertex(Vertex::new("y"));
+      b.add_edge(Edge::between("x", "y").label("1"));
+      b.add_edge(Edge::between("y", "x"));
+
+      let DotOutput(output) = a.build_canonical(Id::new("g")).unwrap();
+      assert_eq!(output, b.build_canonical(Id::new("g")).unwrap().0);
+      assert_eq!(
+        output,
+        "digraph g {\n  \
+             compound = true;\n\n  \
+             x;\n\n  \
+             y;\n\n  \
+             subgraph cluster_s {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               a;\n    \
+               b;\n  \
+             }\n\n  \
+             x -> y[label=\"1\"];\n\n  \
+             y -> x;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn stable_ordering() {
+      let mut gb = GraphBuilder::new();
+      gb.stable_order(true);
+      gb.add_edge(Edge::between("b", "a"));
+      gb.add_edge(Edge::between("a", "b").label("2"));
+      gb.add_edge(Edge::between("a", "b").label("1"));
+      gb.add_vertex(Vertex::new("b"));
+      gb.subgraph("cluster_s", |sg| {
+        sg.edge(Edge::between("d", "c"));
+        sg.vertex(Vertex::new("d"));
+        sg.vertex(Vertex::new("c"));
+      });
+      gb.add_vertex(Vertex::new("a"));
+
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+
+      assert_eq!(
+        output,
+        "digraph g {\n  \
+             compound = true;\n\n  \
+             a;\n\n  \
+             b;\n\n  \
+             subgraph cluster_s {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               c;\n    \
+               d;\n    \
+               d -> c;\n  \
+             }\n\n  \
+             a -> b[label=\"2\"];\n\n  \
+             a -> b[label=\"1\"];\n\n  \
+             b -> a;\n\
+           }\n"
+      );
+    }
+
+    #[test]
+    fn comments() {
+      let mut gb = GraphBuilder::new();
+      gb.add_preamble_comment("Generated from deps.toml.");
+      gb.add_comment("Crates");
+      gb.add_vertex(Vertex::new("a"));
+      gb.subgraph("cluster_s", |sg| {
+        sg.entity(Entity::Comment("first line\n\nclosed */ early".to_string()));
+      });
+
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+
+      assert_eq!(
+        output,
+        "// Generated from deps.toml.\n\
+         digraph g {\n  \
              compound = true;\n\n  \
-             node_0[label=\"node_0\", ];\n\n  \
-             node_1[label=\"node_1\", ];\n\n  \
-             node_0 -> node_1[label=\"asdf\", ];\n\
+             // Crates\n\n  \
+             a;\n\n  \
+             subgraph cluster_s {\n    \
+               cluster = true;\n    \
+               rank = same;\n\n\n    \
+               /*\n     \
+                * first line\n     \
+                *\n     \
+                * closed * / early\n     \
+                */\n  \
+             }\n\
            }\n"
       );
     }
+
+    #[test]
+    fn generated_by_header() {
+      use std::time::{Duration, UNIX_EPOCH};
+
+      let mut gb = GraphBuilder::new();
+      gb.set_generated_by(GeneratedBy {
+        provenance: Some("cargo run --bin deps".to_string()),
+        timestamp: Some(UNIX_EPOCH + Duration::from_secs(1_709_210_096)),
+      });
+      gb.add_preamble_comment("note");
+
+      let DotOutput(output) = gb.build(Id::new("g")).unwrap();
+
+      assert_eq!(
+        output,
+        format!(
+          "/*\n \
+             * Generated by graphvizier {} at 2024-02-29T12:34:56Z.\n \
+             * Provenance: cargo run --bin deps\n \
+             */\n\
+           // note\n\
+           digraph g {{\n  \
+             compound = true;\n\
+           }}\n",
+          env!("CARGO_PKG_VERSION")
+        )
+      );
+    }
   }
 }
 
+pub mod attr_table;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod deps;
+pub mod diagnostics;
+pub mod diagrams;
+
+pub mod engines;
+pub mod export;
+pub mod ids;
+pub mod import;
+
+pub mod legend;
+
+pub mod macros;
+pub mod minify;
+pub mod parser;
+#[cfg(feature = "petgraph")]
+pub mod petgraph;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+pub mod render;
+pub mod sources;
+
+pub mod theme;
+pub mod tree;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod xdot;
+
 /// Implement this trait to expose a graphviz implementation of your type.
+///
+/// With the `derive` feature, `#[derive(Graphable)]` generates this impl for
+/// structs whose fields are annotated with `#[graphvizier(node)]` or
+/// `#[graphvizier(edge(source, target))]`.
 pub trait Graphable {
   /// This impl will often be somewhat complex!
   fn build_graph(self) -> generator::GraphBuilder;
 }
+
+/// An adjacency list, with an edge from each key to each of its neighbors.
+/// See [`GraphBuilder::from_adjacency_list`](generator::GraphBuilder::from_adjacency_list).
+impl<K: Into<entities::Id>> Graphable for std::collections::BTreeMap<K, Vec<K>> {
+  fn build_graph(self) -> generator::GraphBuilder {
+    generator::GraphBuilder::from_adjacency_list(self)
+  }
+}
+
+/// An edge list, with an edge for each pair. Each vertex is declared where
+/// its id first appears.
+impl<K: Into<entities::Id>> Graphable for Vec<(K, K)> {
+  fn build_graph(self) -> generator::GraphBuilder {
+    use entities::{Edge, Entity, Vertex};
+
+    let mut gb = generator::GraphBuilder::new();
+    let mut seen = std::collections::HashSet::new();
+    for (source, target) in self {
+      let (source, target) = (source.into(), target.into());
+      for id in [&source, &target] {
+        if seen.insert(id.clone()) {
+          gb.accept_entity(Entity::Vertex(Vertex::new(id.clone())));
+        }
+      }
+      gb.accept_entity(Entity::Edge(Edge::between(source, target)));
+    }
+    gb
+  }
+}
+
+/// The structure of a JSON document: a vertex for each value, with an edge
+/// from each object or array to each of its members, labelled with its key
+/// or index. Objects and arrays are drawn as `{}` and `[]`, and scalars as
+/// boxes containing their JSON text.
+///
+///```
+/// use graphvizier::{entities::Id, Graphable};
+/// use serde_json::json;
+///
+/// let gb = json!({"name": "x", "ports": [80, 443]}).build_graph();
+/// assert_eq!(gb.vertices().count(), 5);
+/// let dot = gb.build(Id::new("config")).unwrap().0;
+/// assert!(dot.contains("j0 -> j2[label=\"ports\"];"));
+/// assert!(dot.contains("j2 -> j4[label=\"[1]\"];"));
+/// ```
+#[cfg(feature = "json")]
+impl Graphable for serde_json::Value {
+  fn build_graph(self) -> generator::GraphBuilder {
+    use entities::{style::Label, Edge, Entity, Id, Vertex};
+    use serde_json::Value;
+
+    fn walk(value: &Value, gb: &mut generator::GraphBuilder, next: &mut usize) -> Id {
+      let id = Id::new(format!("j{}", next));
+      *next += 1;
+      let vertex = match value {
+        Value::Object(_) => Vertex::new(id.clone()).label("{}"),
+        Value::Array(_) => Vertex::new(id.clone()).label("[]"),
+        scalar => Vertex::new(id.clone())
+          .label(Label::escape_text(scalar.to_string()))
+          .shape("box"),
+      };
+      gb.accept_entity(Entity::Vertex(vertex));
+      let members: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Array(values) => values
+          .iter()
+          .enumerate()
+          .map(|(i, v)| (format!("[{}]", i), v))
+          .collect(),
+        _ => Vec::new(),
+      };
+      for (key, member) in members {
+        let member_id = walk(member, gb, next);
+        gb.accept_entity(Entity::Edge(
+          Edge::between(id.clone(), member_id).label(Label::escape_text(key)),
+        ));
+      }
+      id
+    }
+
+    let mut gb = generator::GraphBuilder::new();
+    walk(&self, &mut gb, &mut 0);
+    gb
+  }
+}
+
+#[cfg(feature = "derive")]
+pub use graphvizier_derive::Graphable;
+
+/* The derive macro refers to this crate by name. */
+#[cfg(all(test, feature = "derive"))]
+extern crate self as graphvizier;
+
+#[cfg(all(test, feature = "derive"))]
+mod test {
+  use super::{entities::*, generator::DotOutput, Graphable};
+
+  struct Dep {
+    from: &'static str,
+    to: &'static str,
+    kind: &'static str,
+  }
+
+  #[derive(Graphable)]
+  struct Model {
+    #[graphvizier(node)]
+    crates: Vec<Vertex>,
+    #[graphvizier(edge(from, to, kind))]
+    deps: Vec<Dep>,
+    #[allow(dead_code)]
+    name: String,
+  }
+
+  #[test]
+  fn derive_graphable() {
+    let model = Model {
+      crates: vec![Vertex::new("a"), Vertex::new("b")],
+      deps: vec![Dep {
+        from: "a",
+        to: "b",
+        kind: "dev",
+      }],
+      name: "model".to_string(),
+    };
+
+    let DotOutput(output) = model.build_graph().build(Id::new("test_graph")).unwrap();
+
+    assert_eq!(
+      output,
+      "digraph test_graph {\n  \
+           compound = true;\n\n  \
+           a;\n\n  \
+           b;\n\n  \
+           a -> b[label=\"dev\"];\n\
+         }\n"
+    );
+  }
+
+  #[test]
+  fn derive_escaped_labels() {
+    let model = Model {
+      crates: vec![],
+      deps: vec![Dep {
+        from: "a",
+        to: "b",
+        kind: "say \"hi\" C:\\",
+      }],
+      name: "model".to_string(),
+    };
+
+    let DotOutput(output) = model.build_graph().build(Id::new("test_graph")).unwrap();
+
+    assert!(output.contains("a -> b[label=\"say \\\"hi\\\" C:\\\\\"];"));
+    crate::parser::parse(&output).unwrap();
+  }
+}
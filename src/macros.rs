@@ -0,0 +1,163 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A declarative DSL mirroring the DOT language, via [`graph!`](crate::graph).
+
+/// Build a [`GraphBuilder`](crate::generator::GraphBuilder) from statements
+/// resembling the [DOT language](https://www.graphviz.org/doc/info/lang.html).
+///
+/// Ids may be identifiers or string literals. Vertices, edge chains, and
+/// nested subgraphs are supported, along with a bracketed list of attributes
+/// which are set with the fluent methods of
+/// [`Vertex`](crate::entities::Vertex) and [`Edge`](crate::entities::Edge),
+/// so misspelled attributes fail to compile:
+///
+///```
+/// use graphvizier::{entities::Id, generator::DotOutput, graph};
+///
+/// let gb = graph! {
+///   a -> b [label = "x"];
+///   subgraph cluster_c {
+///     d [shape = "box", color = "red"];
+///     e -> "f" -> d;
+///   }
+/// };
+/// let DotOutput(output) = gb.build(Id::new("example")).unwrap();
+/// assert!(output.contains("a -> b[label=\"x\"];"));
+/// assert!(output.contains("e -> f;"));
+/// ```
+#[macro_export]
+macro_rules! graph {
+  ($($body:tt)*) => {{
+    let mut entities: ::std::vec::Vec<$crate::entities::Entity> = ::std::vec::Vec::new();
+    $crate::__graph_stmts!(entities; $($body)*);
+    entities
+      .into_iter()
+      .collect::<$crate::generator::GraphBuilder>()
+  }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __graph_id {
+  ($id:ident) => {
+    $crate::entities::Id::new(stringify!($id))
+  };
+  ($id:literal) => {
+    $crate::entities::Id::new($id)
+  };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __graph_stmts {
+  ($out:ident;) => {};
+  ($out:ident; subgraph $id:tt { $($inner:tt)* } ; $($rest:tt)*) => {
+    $crate::__graph_stmts!($out; subgraph $id { $($inner)* } $($rest)*);
+  };
+  ($out:ident; subgraph $id:tt { $($inner:tt)* } $($rest:tt)*) => {
+    let mut inner: ::std::vec::Vec<$crate::entities::Entity> = ::std::vec::Vec::new();
+    $crate::__graph_stmts!(inner; $($inner)*);
+    $out.push($crate::entities::Entity::Subgraph($crate::entities::Subgraph {
+      entities: inner,
+      ..$crate::entities::Subgraph::new($crate::__graph_id!($id))
+    }));
+    $crate::__graph_stmts!($out; $($rest)*);
+  };
+  ($out:ident; $src:tt $(-> $dst:tt)+ $([ $($attrs:tt)* ])? ; $($rest:tt)*) => {
+    #[allow(unused_mut)]
+    let mut template = $crate::entities::Edge::default();
+    $($crate::__graph_attrs!(template; $($attrs)*);)?
+    let ids = [$crate::__graph_id!($src) $(, $crate::__graph_id!($dst))+];
+    for pair in ids.windows(2) {
+      $out.push($crate::entities::Entity::Edge($crate::entities::Edge {
+        source: pair[0].clone(),
+        target: pair[1].clone(),
+        ..template.clone()
+      }));
+    }
+    $crate::__graph_stmts!($out; $($rest)*);
+  };
+  ($out:ident; $id:tt $([ $($attrs:tt)* ])? ; $($rest:tt)*) => {
+    #[allow(unused_mut)]
+    let mut vertex = $crate::entities::Vertex::new($crate::__graph_id!($id));
+    $($crate::__graph_attrs!(vertex; $($attrs)*);)?
+    $out.push($crate::entities::Entity::Vertex(vertex));
+    $crate::__graph_stmts!($out; $($rest)*);
+  };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __graph_attrs {
+  ($target:ident;) => {};
+  ($target:ident; color = $val:expr $(, $($rest:tt)*)?) => {
+    $target = $target.color($crate::entities::Color::new($val));
+    $crate::__graph_attrs!($target; $($($rest)*)?);
+  };
+  ($target:ident; fontcolor = $val:expr $(, $($rest:tt)*)?) => {
+    $target = $target.fontcolor($crate::entities::Color::new($val));
+    $crate::__graph_attrs!($target; $($($rest)*)?);
+  };
+  ($target:ident; $key:ident = $val:expr $(, $($rest:tt)*)?) => {
+    $target = $target.$key($val);
+    $crate::__graph_attrs!($target; $($($rest)*)?);
+  };
+}
+
+#[cfg(test)]
+mod test {
+  use crate::{entities::Id, generator::DotOutput};
+
+  #[test]
+  fn render_macro() {
+    let gb = graph! {
+      a;
+      a -> b [label = "x", weight = 2.0];
+      subgraph cluster_c {
+        d [shape = "box", color = "red"];
+        subgraph "cluster_d" {
+          e -> f -> d;
+        };
+      }
+    };
+
+    let DotOutput(output) = gb.build(Id::new("test_graph")).unwrap();
+
+    assert_eq!(
+      output,
+      "digraph test_graph {\n  \
+           compound = true;\n\n  \
+           a;\n\n  \
+           a -> b[label=\"x\", weight=\"2\"];\n\n  \
+           subgraph cluster_c {\n    \
+             cluster = true;\n    \
+             rank = same;\n\n\n    \
+             d[color=\"red\", shape=\"box\"];\n    \
+             subgraph cluster_d {\n      \
+               cluster = true;\n      \
+               rank = same;\n\n\n      \
+               e -> f;\n      \
+               f -> d;\n    \
+             }\n  \
+           }\n\
+         }\n"
+    );
+  }
+}
@@ -0,0 +1,176 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Strip a DOT document down to the fewest bytes which still parse the same.
+//!
+//! Indentation, newlines, comments, and whitespace around punctuation are
+//! removed, along with the optional `;` or `,` before a closing `}` or `]`.
+//! Quoted and HTML strings are copied through untouched.
+//!
+//!```
+//! use graphvizier::minify::minify;
+//!
+//! let dot = "digraph g {\n  compound = true;\n\n  a -> b[label=\"x y\", color=\"red\"];\n}\n";
+//! assert_eq!(
+//!   minify(dot),
+//!   "digraph g{compound=true;a->b[label=\"x y\",color=\"red\"]}"
+//! );
+//! ```
+
+/// Characters which may run together into a single unquoted ID, so must stay
+/// separated by whitespace from each other.
+fn is_word(c: char) -> bool { c.is_ascii_alphanumeric() || c == '_' || c == '.' || !c.is_ascii() }
+
+/// Minify the DOT document `dot`. See the [module docs](self).
+pub fn minify(dot: &str) -> String {
+  let mut out = String::with_capacity(dot.len());
+  let mut chars = dot.chars().peekable();
+  /* Whether we skipped whitespace since the last character written. */
+  let mut pending_space = false;
+  /* Whether only whitespace precedes the current char on its line of the
+   * input, so that `#` starts a preprocessor line. */
+  let mut line_start = true;
+
+  while let Some(c) = chars.next() {
+    match c {
+      '\n' => {
+        line_start = true;
+        pending_space = true;
+      },
+      c if c.is_ascii_whitespace() => pending_space = true,
+      '/' if chars.peek() == Some(&'/') => {
+        for c in chars.by_ref() {
+          if c == '\n' {
+            break;
+          }
+        }
+        line_start = true;
+        pending_space = true;
+      },
+      '/' if chars.peek() == Some(&'*') => {
+        chars.next();
+        let mut prev = '\0';
+        for c in chars.by_ref() {
+          if prev == '*' && c == '/' {
+            break;
+          }
+          if c == '\n' {
+            line_start = true;
+          }
+          prev = c;
+        }
+        pending_space = true;
+      },
+      '#' if line_start => {
+        /* A C preprocessor output line. */
+        for c in chars.by_ref() {
+          if c == '\n' {
+            break;
+          }
+        }
+        pending_space = true;
+      },
+      '}' | ']' => {
+        line_start = false;
+        if out.ends_with(';') || out.ends_with(',') {
+          out.pop();
+        }
+        out.push(c);
+        pending_space = false;
+      },
+      _ => {
+        line_start = false;
+        let starts_word = is_word(c)
+          || (c == '-'
+            && chars
+              .peek()
+              .map(|&n| n.is_ascii_digit() || n == '.')
+              .unwrap_or(false));
+        let ends_word = out
+          .chars()
+          .next_back()
+          .map(|p| is_word(p) || p == '"' || p == '>')
+          .unwrap_or(false);
+        if pending_space && starts_word && ends_word && !out.ends_with("->") {
+          out.push(' ');
+        }
+        pending_space = false;
+        out.push(c);
+        match c {
+          '"' => {
+            let mut escaped = false;
+            for c in chars.by_ref() {
+              out.push(c);
+              match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => break,
+                _ => (),
+              }
+            }
+          },
+          '<' => {
+            let mut depth = 1_usize;
+            for c in chars.by_ref() {
+              out.push(c);
+              match c {
+                '<' => depth += 1,
+                '>' => {
+                  depth -= 1;
+                  if depth == 0 {
+                    break;
+                  }
+                },
+                _ => (),
+              }
+            }
+          },
+          _ => (),
+        }
+      },
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn keeps_ids_apart() {
+    assert_eq!(
+      minify("digraph \"g\" {\n  node [shape = box];\n  // c\n  a b -> -1 /* x */ ;\n}\n"),
+      "digraph\"g\"{node[shape=box];a b->-1}"
+    );
+    assert_eq!(minify("graph g { a -- b; c; }"), "graph g{a--b;c}");
+    assert_eq!(
+      minify("digraph g { a [label=<<b> x </b>>]; }"),
+      "digraph g{a[label=<<b> x </b>>]}"
+    );
+  }
+
+  #[test]
+  fn preprocessor_lines() {
+    assert_eq!(
+      minify("# 1 \"g.dot\"\ndigraph g {\n  a\n# 3 \"g.dot\"\n  b -> c; /*\n */ # 4\n}\n"),
+      "digraph g{a b->c}"
+    );
+  }
+}
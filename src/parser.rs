@@ -0,0 +1,1042 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Read DOT documents into the crate's [`Entity`] tree.
+//!
+//! This covers the [DOT language](https://www.graphviz.org/doc/info/lang.html)
+//! except for a few corners: vertices which are only ever mentioned as edge
+//! endpoints don't get a [`Vertex`] of their own, and `node` and `edge`
+//! defaults are copied into each vertex or edge declared after them instead
+//! of being kept as separate statements. Every attribute is placed into the
+//! [`Attributes`] map of its entity.
+//!
+//! To edit an existing file without reformatting it, use [`Document`], which
+//! also keeps the original text of each statement.
+//!
+//!```
+//! use graphvizier::{entities::*, parser::parse};
+//!
+//! let graph = parse("digraph g { node [shape=box]; a [label=\"A\"]; a -> b }").unwrap();
+//! assert_eq!(graph.id, Some(Id::new("g")));
+//! match &graph.entities[..] {
+//!   [Entity::Vertex(a), Entity::Edge(e)] => {
+//!     assert_eq!(a.attributes.get("shape"), Some(&AttrValue::from("box")));
+//!     assert_eq!(a.attributes.get("label"), Some(&AttrValue::from("A")));
+//!     assert_eq!((e.source.as_str(), e.target.as_str()), ("a", "b"));
+//!   },
+//!   entities => panic!("{:?}", entities),
+//! }
+//! ```
+
+use crate::{
+  entities::{AttrValue, Attributes, Edge, Entity, Group, Id, Label, Subgraph, Vertex},
+  generator::undirected_statement,
+};
+
+use std::{collections::HashSet, fmt, ops::Range};
+
+/// A graph read by [`parse`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedGraph {
+  /// Whether the graph was declared `strict`.
+  pub strict: bool,
+  /// `digraph` rather than `graph`.
+  pub directed: bool,
+  #[allow(missing_docs)]
+  pub id: Option<Id>,
+  /// Attributes of the graph itself, from `graph [...]` or `name = value`
+  /// statements at the top level.
+  pub attributes: Attributes,
+  #[allow(missing_docs)]
+  pub entities: Vec<Entity>,
+}
+
+/// Why [`parse`] failed, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+  /// 1-based line number.
+  pub line: usize,
+  /// 1-based column number, in chars.
+  pub column: usize,
+  #[allow(missing_docs)]
+  pub message: String,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}: {}", self.line, self.column, self.message)
+  }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keyword {
+  Strict,
+  Graph,
+  Digraph,
+  Node,
+  Edge,
+  Subgraph,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Id {
+    text: String,
+    html: bool,
+    quoted: bool,
+  },
+  Keyword(Keyword),
+  LBrace,
+  RBrace,
+  LBracket,
+  RBracket,
+  Colon,
+  Semi,
+  Comma,
+  Equals,
+  Plus,
+  /// `->` if `true`, else `--`.
+  EdgeOp(bool),
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Id { text, .. } => write!(f, "{:?}", text),
+      Self::Keyword(k) => write!(f, "{}", format!("{:?}", k).to_lowercase()),
+      Self::LBrace => f.write_str("'{'"),
+      Self::RBrace => f.write_str("'}'"),
+      Self::LBracket => f.write_str("'['"),
+      Self::RBracket => f.write_str("']'"),
+      Self::Colon => f.write_str("':'"),
+      Self::Semi => f.write_str("';'"),
+      Self::Comma => f.write_str("','"),
+      Self::Equals => f.write_str("'='"),
+      Self::Plus => f.write_str("'+'"),
+      Self::EdgeOp(true) => f.write_str("'->'"),
+      Self::EdgeOp(false) => f.write_str("'--'"),
+    }
+  }
+}
+
+fn is_id_start(c: char) -> bool { c.is_ascii_alphabetic() || c == '_' || !c.is_ascii() }
+
+/// A token and its byte range within the document.
+type Spanned = (Range<usize>, Token);
+
+/// Split `text` into tokens, or report the offset of the first lexical error.
+fn lex(text: &str) -> Result<Vec<Spanned>, (usize, String)> {
+  let mut ret = Vec::new();
+  let mut chars = text.char_indices().peekable();
+  /* Whether only whitespace precedes the current char on its line, so that
+   * `#` starts a preprocessor line. */
+  let mut line_start = true;
+  while let Some((start, c)) = chars.next() {
+    let punct = match c {
+      '\n' => {
+        line_start = true;
+        continue;
+      },
+      /* Non-ASCII characters are all letters to DOT, even U+0085. */
+      c if c.is_ascii_whitespace() => continue,
+      '#' if line_start => {
+        while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+        continue;
+      },
+      '/' if chars.next_if(|&(_, c)| c == '/').is_some() => {
+        while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+        continue;
+      },
+      '/' if chars.next_if(|&(_, c)| c == '*').is_some() => {
+        let mut prev = ' ';
+        loop {
+          match chars.next() {
+            None => return Err((start, "unterminated comment".to_string())),
+            Some((_, '/')) if prev == '*' => break,
+            Some((_, c)) => {
+              if c == '\n' {
+                line_start = true;
+              }
+              prev = c;
+            },
+          }
+        }
+        continue;
+      },
+      '{' => Some(Token::LBrace),
+      '}' => Some(Token::RBrace),
+      '[' => Some(Token::LBracket),
+      ']' => Some(Token::RBracket),
+      ':' => Some(Token::Colon),
+      ';' => Some(Token::Semi),
+      ',' => Some(Token::Comma),
+      '=' => Some(Token::Equals),
+      '+' => Some(Token::Plus),
+      '-' if chars.next_if(|&(_, c)| c == '>').is_some() => Some(Token::EdgeOp(true)),
+      '-' if chars.next_if(|&(_, c)| c == '-').is_some() => Some(Token::EdgeOp(false)),
+      _ => None,
+    };
+    line_start = false;
+    if let Some(token) = punct {
+      let end = chars.peek().map(|&(i, _)| i).unwrap_or(text.len());
+      ret.push((start..end, token));
+      continue;
+    }
+
+    let token = match c {
+      '"' => {
+        let mut s = String::new();
+        loop {
+          match chars.next() {
+            None => return Err((start, "unterminated string".to_string())),
+            Some((_, '"')) => break,
+            Some((_, '\\')) => match chars.next() {
+              Some((_, '"')) => s.push('"'),
+              /* A backslash before a newline continues the line. */
+              Some((_, '\n')) => (),
+              Some((_, c)) => {
+                s.push('\\');
+                s.push(c);
+              },
+              None => return Err((start, "unterminated string".to_string())),
+            },
+            Some((_, c)) => s.push(c),
+          }
+        }
+        Token::Id {
+          text: s,
+          html: false,
+          quoted: true,
+        }
+      },
+      '<' => {
+        let mut depth = 1_usize;
+        let end = loop {
+          match chars.next() {
+            None => return Err((start, "unterminated HTML string".to_string())),
+            Some((_, '<')) => depth += 1,
+            Some((i, '>')) => {
+              depth -= 1;
+              if depth == 0 {
+                break i;
+              }
+            },
+            Some(_) => (),
+          }
+        };
+        Token::Id {
+          text: text[start + 1..end].to_string(),
+          html: true,
+          quoted: false,
+        }
+      },
+      c if c.is_ascii_digit() || c == '.' || c == '-' => {
+        let mut end = start + c.len_utf8();
+        while let Some((i, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.') {
+          end = i + c.len_utf8();
+        }
+        let s = &text[start..end];
+        if s == "-" || s == "." || s == "-." || s.matches('.').count() > 1 {
+          return Err((start, format!("invalid numeral {:?}", s)));
+        }
+        Token::Id {
+          text: s.to_string(),
+          html: false,
+          quoted: false,
+        }
+      },
+      c if is_id_start(c) => {
+        let mut end = start + c.len_utf8();
+        while let Some((i, c)) = chars.next_if(|&(_, c)| is_id_start(c) || c.is_ascii_digit()) {
+          end = i + c.len_utf8();
+        }
+        let s = &text[start..end];
+        let keyword = [
+          ("strict", Keyword::Strict),
+          ("graph", Keyword::Graph),
+          ("digraph", Keyword::Digraph),
+          ("node", Keyword::Node),
+          ("edge", Keyword::Edge),
+          ("subgraph", Keyword::Subgraph),
+        ]
+        .into_iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(s));
+        match keyword {
+          Some((_, k)) => Token::Keyword(k),
+          None => Token::Id {
+            text: s.to_string(),
+            html: false,
+            quoted: false,
+          },
+        }
+      },
+      c => return Err((start, format!("unexpected character {:?}", c))),
+    };
+    let end = chars.peek().map(|&(i, _)| i).unwrap_or(text.len());
+    ret.push((start..end, token));
+  }
+  Ok(ret)
+}
+
+/// `node` and `edge` defaults in effect within a graph or subgraph.
+#[derive(Clone, Default)]
+struct Scope {
+  node: Attributes,
+  edge: Attributes,
+}
+
+struct Parser<'a> {
+  text: &'a str,
+  tokens: Vec<Spanned>,
+  pos: usize,
+  directed: bool,
+  anonymous: usize,
+  /// How many subgraphs we are within.
+  depth: usize,
+  /// The end of the graph's opening `{`.
+  body_start: usize,
+  /// The text and produced entities of each top-level statement, for
+  /// [`Document`].
+  statements: Vec<(Range<usize>, Range<usize>)>,
+}
+
+/// One side of an edge statement: either a vertex with an optional port, or
+/// every vertex within a subgraph.
+enum Operand {
+  Vertex(Id, Option<String>),
+  Subgraph(Vec<Id>),
+}
+
+impl<'a> Parser<'a> {
+  fn error_at(&self, offset: usize, message: impl Into<String>) -> ParseError {
+    let before = &self.text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before[before.rfind('\n').map(|i| i + 1).unwrap_or(0)..]
+      .chars()
+      .count()
+      + 1;
+    ParseError {
+      line,
+      column,
+      message: message.into(),
+    }
+  }
+
+  fn error(&self, message: impl Into<String>) -> ParseError {
+    let offset = self
+      .tokens
+      .get(self.pos)
+      .map(|(span, _)| span.start)
+      .unwrap_or(self.text.len());
+    self.error_at(offset, message)
+  }
+
+  fn peek(&self) -> Option<&Token> { self.tokens.get(self.pos).map(|(_, t)| t) }
+
+  fn peek_at(&self, n: usize) -> Option<&Token> { self.tokens.get(self.pos + n).map(|(_, t)| t) }
+
+  fn bump(&mut self) -> Option<Token> {
+    let ret = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+    self.pos += 1;
+    ret
+  }
+
+  fn eat(&mut self, token: &Token) -> bool {
+    if self.peek() == Some(token) {
+      self.pos += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expect(&mut self, token: &Token) -> Result<(), ParseError> {
+    if self.eat(token) {
+      Ok(())
+    } else {
+      Err(self.unexpected(&format!("{}", token)))
+    }
+  }
+
+  fn unexpected(&self, expected: &str) -> ParseError {
+    match self.peek() {
+      Some(t) => self.error(format!("expected {}, found {}", expected, t)),
+      None => self.error(format!("expected {}, found end of input", expected)),
+    }
+  }
+
+  /// An ID, joining `"a" + "b"` concatenations of quoted strings.
+  fn id(&mut self) -> Result<(String, bool), ParseError> {
+    let Some(Token::Id {
+      mut text,
+      html,
+      quoted,
+    }) = self.bump()
+    else {
+      self.pos -= 1;
+      return Err(self.unexpected("an ID"));
+    };
+    while quoted
+      && self.peek() == Some(&Token::Plus)
+      && matches!(self.peek_at(1), Some(Token::Id { quoted: true, .. }))
+    {
+      self.pos += 1;
+      if let Some(Token::Id { text: more, .. }) = self.bump() {
+        text.push_str(&more);
+      }
+    }
+    Ok((text, html))
+  }
+
+  /// Quoted text keeps escapes other than `\"`, but ids hold unescaped text
+  /// and are escaped again when written.
+  fn to_id((text, html): (String, bool)) -> Id {
+    if html {
+      Id::html(text)
+    } else {
+      Id::new(Label::unescape_text(text))
+    }
+  }
+
+  fn to_value((text, html): (String, bool)) -> AttrValue {
+    if html {
+      AttrValue::Html(text)
+    } else {
+      AttrValue::Quoted(text)
+    }
+  }
+
+  /// Zero or more `[a=b, c=d]` lists, merged into `attrs`.
+  fn attr_lists(&mut self, attrs: &mut Attributes) -> Result<(), ParseError> {
+    while self.eat(&Token::LBracket) {
+      while !self.eat(&Token::RBracket) {
+        let (name, _) = self.id()?;
+        let value = if self.eat(&Token::Equals) {
+          Self::to_value(self.id()?)
+        } else {
+          AttrValue::Quoted("true".to_string())
+        };
+        attrs.insert(name, value);
+        if !self.eat(&Token::Comma) {
+          self.eat(&Token::Semi);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn graph(&mut self) -> Result<ParsedGraph, ParseError> {
+    let strict = self.eat(&Token::Keyword(Keyword::Strict));
+    self.directed = match self.bump() {
+      Some(Token::Keyword(Keyword::Digraph)) => true,
+      Some(Token::Keyword(Keyword::Graph)) => false,
+      _ => {
+        self.pos -= 1;
+        return Err(self.unexpected("'graph' or 'digraph'"));
+      },
+    };
+    let id = match self.peek() {
+      Some(Token::Id { .. }) => Some(Self::to_id(self.id()?)),
+      _ => None,
+    };
+    self.expect(&Token::LBrace)?;
+    self.body_start = self.tokens[self.pos - 1].0.end;
+    let mut attributes = Attributes::new();
+    let mut entities = Vec::new();
+    self.stmt_list(&mut Scope::default(), &mut attributes, &mut entities)?;
+    if self.pos < self.tokens.len() {
+      return Err(self.error("expected end of input after the graph"));
+    }
+    Ok(ParsedGraph {
+      strict,
+      directed: self.directed,
+      id,
+      attributes,
+      entities,
+    })
+  }
+
+  /// Statements up to and including the closing `}`.
+  fn stmt_list(
+    &mut self,
+    scope: &mut Scope,
+    attributes: &mut Attributes,
+    out: &mut Vec<Entity>,
+  ) -> Result<(), ParseError> {
+    while !self.eat(&Token::RBrace) {
+      let first = self.pos;
+      let produced = out.len();
+      match self.peek() {
+        None => return Err(self.unexpected("'}'")),
+        Some(Token::Keyword(Keyword::Graph)) => {
+          self.pos += 1;
+          self.attr_lists(attributes)?;
+        },
+        Some(Token::Keyword(Keyword::Node)) => {
+          self.pos += 1;
+          self.attr_lists(&mut scope.node)?;
+        },
+        Some(Token::Keyword(Keyword::Edge)) => {
+          self.pos += 1;
+          self.attr_lists(&mut scope.edge)?;
+        },
+        Some(Token::Id { .. }) if self.peek_at(1) == Some(&Token::Equals) => {
+          let (name, _) = self.id()?;
+          self.pos += 1;
+          let value = Self::to_value(self.id()?);
+          attributes.insert(name, value);
+        },
+        _ => self.node_or_edge_stmt(scope, out)?,
+      }
+      self.eat(&Token::Semi);
+      if self.depth == 0 {
+        let span = self.tokens[first].0.start..self.tokens[self.pos - 1].0.end;
+        self.statements.push((span, produced..out.len()));
+      }
+    }
+    Ok(())
+  }
+
+  fn operand(&mut self, scope: &Scope, out: &mut Vec<Entity>) -> Result<Operand, ParseError> {
+    match self.peek() {
+      Some(Token::Keyword(Keyword::Subgraph)) | Some(Token::LBrace) => {
+        let sg = self.subgraph(scope)?;
+        let mut ids = Vec::new();
+        let mut seen = HashSet::new();
+        collect_vertex_ids(&sg, &mut seen, &mut ids);
+        out.push(sg);
+        Ok(Operand::Subgraph(ids))
+      },
+      _ => {
+        let id = Self::to_id(self.id()?);
+        let mut port: Option<String> = None;
+        while self.eat(&Token::Colon) {
+          let (part, _) = self.id()?;
+          port = Some(match port {
+            Some(p) => format!("{}:{}", p, part),
+            None => part,
+          });
+        }
+        Ok(Operand::Vertex(id, port))
+      },
+    }
+  }
+
+  fn node_or_edge_stmt(&mut self, scope: &Scope, out: &mut Vec<Entity>) -> Result<(), ParseError> {
+    let mut operands = vec![self.operand(scope, out)?];
+    while let Some(Token::EdgeOp(directed)) = self.peek() {
+      if *directed != self.directed {
+        return Err(self.error(if self.directed {
+          "'--' in a digraph"
+        } else {
+          "'->' in an undirected graph"
+        }));
+      }
+      self.pos += 1;
+      operands.push(self.operand(scope, out)?);
+    }
+    let mut attrs = Attributes::new();
+    self.attr_lists(&mut attrs)?;
+
+    if let [operand] = &operands[..] {
+      if let Operand::Vertex(id, _) = operand {
+        let mut v = Vertex::new(id.clone());
+        v.attributes = scope.node.clone();
+        v.attributes.extend(attrs);
+        out.push(Entity::Vertex(v));
+      }
+      return Ok(());
+    }
+
+    for pair in operands.windows(2) {
+      let endpoints = |o: &Operand| match o {
+        Operand::Vertex(id, port) => vec![(id.clone(), port.clone())],
+        Operand::Subgraph(ids) => ids.iter().map(|id| (id.clone(), None)).collect(),
+      };
+      for (source, tailport) in endpoints(&pair[0]) {
+        for (target, headport) in endpoints(&pair[1]) {
+          let mut e = Edge::between(source.clone(), target);
+          e.attributes = scope.edge.clone();
+          e.attributes.extend(attrs.clone());
+          if let Some(ref port) = tailport {
+            e.attributes
+              .insert("tailport".to_string(), AttrValue::from(port.as_str()));
+          }
+          if let Some(port) = headport {
+            e.attributes
+              .insert("headport".to_string(), AttrValue::from(port));
+          }
+          out.push(Entity::Edge(e));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// A `subgraph name { ... }` or anonymous `{ ... }` block.
+  ///
+  /// Clusters become a [`Subgraph`] with [`Subgraph::is_cluster`] set, as do
+  /// other named subgraphs and anonymous ones with attributes, but with it
+  /// unset. Other anonymous blocks become a [`Group`]. [`Subgraph::same_rank`]
+  /// is always unset, so only a `rank` from the source is written back.
+  fn subgraph(&mut self, scope: &Scope) -> Result<Entity, ParseError> {
+    let id = if self.eat(&Token::Keyword(Keyword::Subgraph)) {
+      match self.peek() {
+        Some(Token::Id { .. }) => Some(Self::to_id(self.id()?)),
+        _ => None,
+      }
+    } else {
+      None
+    };
+    self.expect(&Token::LBrace)?;
+    let mut scope = scope.clone();
+    let mut attributes = Attributes::new();
+    let mut entities = Vec::new();
+    self.depth += 1;
+    self.stmt_list(&mut scope, &mut attributes, &mut entities)?;
+    self.depth -= 1;
+
+    if id.is_none() && attributes.is_empty() {
+      return Ok(Entity::Group(Group {
+        node_defaults: None,
+        entities,
+      }));
+    }
+    let id = id.unwrap_or_else(|| {
+      self.anonymous += 1;
+      Id::new(format!("_anonymous_{}", self.anonymous - 1))
+    });
+    let is_cluster = match attributes.remove("cluster") {
+      Some(AttrValue::Quoted(v)) => v == "true",
+      _ => id.as_str().starts_with("cluster"),
+    };
+    Ok(Entity::Subgraph(Subgraph {
+      attributes,
+      entities,
+      ..Subgraph::new(id).cluster(is_cluster).same_rank(false)
+    }))
+  }
+}
+
+/// Every vertex declared or used as an edge endpoint within `e`, in order of
+/// first appearance.
+fn collect_vertex_ids(e: &Entity, seen: &mut HashSet<Id>, out: &mut Vec<Id>) {
+  let mut add = |id: &Id| {
+    if seen.insert(id.clone()) {
+      out.push(id.clone());
+    }
+  };
+  match e {
+    Entity::Vertex(v) => add(&v.id),
+    Entity::Edge(edge) => {
+      add(&edge.source);
+      add(&edge.target);
+    },
+    Entity::Subgraph(Subgraph { entities, .. }) | Entity::Group(Group { entities, .. }) => {
+      for e in entities.iter() {
+        collect_vertex_ids(e, seen, out);
+      }
+    },
+    Entity::Comment(_) => (),
+  }
+}
+
+fn run_parser(text: &str) -> Result<(ParsedGraph, Parser<'_>), ParseError> {
+  let mut parser = Parser {
+    text,
+    tokens: Vec::new(),
+    pos: 0,
+    directed: true,
+    anonymous: 0,
+    depth: 0,
+    body_start: 0,
+    statements: Vec::new(),
+  };
+  parser.tokens = lex(text).map_err(|(offset, message)| parser.error_at(offset, message))?;
+  let graph = parser.graph()?;
+  Ok((graph, parser))
+}
+
+/// Parse a single graph from `text`. See the [module docs](self).
+pub fn parse(text: &str) -> Result<ParsedGraph, ParseError> { run_parser(text).map(|(g, _)| g) }
+
+/// A top-level statement of a [`Document`].
+#[derive(Debug, Clone, PartialEq)]
+struct Statement {
+  /// Whitespace and comments between the previous statement and this one.
+  leading: String,
+  text: String,
+  entities: Vec<Entity>,
+}
+
+/// A DOT document which can be edited statement by statement, while keeping
+/// the text of every untouched statement exactly as written, along with all
+/// comments and whitespace.
+///
+/// Each top-level statement of the graph body is tracked separately, along
+/// with the entities [`parse`] produces for it (none for attribute
+/// statements). Inserted and replaced statements are formatted like
+/// [`Entity`]'s [`Display`](fmt::Display) output, and indented to match the
+/// statements around them.
+///
+///```
+/// use graphvizier::{entities::*, parser::Document};
+///
+/// let mut doc = Document::parse("digraph g {\n  // hand-written\n  a -> b;\n}\n").unwrap();
+/// doc.push(Entity::Vertex(Vertex::new("c").label("C")));
+/// assert_eq!(
+///   doc.to_string(),
+///   "digraph g {\n  // hand-written\n  a -> b;\n  c[label=\"C\"];\n}\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+  directed: bool,
+  id: Option<Id>,
+  /// Everything up to and including the opening `{`.
+  head: String,
+  statements: Vec<Statement>,
+  /// Everything after the last statement, including the closing `}`.
+  tail: String,
+}
+
+impl Document {
+  /// Parse a single graph from `text`, like [`parse`].
+  pub fn parse(text: &str) -> Result<Self, ParseError> {
+    let (graph, parser) = run_parser(text)?;
+    let mut prev = parser.body_start;
+    let mut statements = Vec::with_capacity(parser.statements.len());
+    for (span, produced) in parser.statements.into_iter() {
+      statements.push(Statement {
+        leading: text[prev..span.start].to_string(),
+        text: text[span.clone()].to_string(),
+        entities: graph.entities[produced].to_vec(),
+      });
+      prev = span.end;
+    }
+    Ok(Self {
+      directed: graph.directed,
+      id: graph.id,
+      head: text[..parser.body_start].to_string(),
+      statements,
+      tail: text[prev..].to_string(),
+    })
+  }
+
+  /// `digraph` rather than `graph`.
+  pub fn directed(&self) -> bool { self.directed }
+
+  #[allow(missing_docs)]
+  pub fn id(&self) -> Option<&Id> { self.id.as_ref() }
+
+  /// The number of top-level statements.
+  pub fn len(&self) -> usize { self.statements.len() }
+
+  #[allow(missing_docs)]
+  pub fn is_empty(&self) -> bool { self.statements.is_empty() }
+
+  /// The text of statement `i`, without surrounding whitespace or comments.
+  pub fn statement(&self, i: usize) -> &str { &self.statements[i].text }
+
+  /// The entities produced by statement `i`.
+  pub fn entities(&self, i: usize) -> &[Entity] { &self.statements[i].entities }
+
+  /// The first top-level statement declaring a vertex with this id.
+  pub fn find_vertex(&self, id: &Id) -> Option<usize> {
+    self.statements.iter().position(|s| {
+      s.entities
+        .iter()
+        .any(|e| matches!(e, Entity::Vertex(v) if &v.id == id))
+    })
+  }
+
+  /// The indentation of the statement at `i`, or of the last statement if
+  /// `i` is past the end.
+  fn indentation(&self, i: usize) -> String {
+    let leading = match self.statements.get(i).or(self.statements.last()) {
+      Some(s) => &s.leading,
+      None => return "  ".to_string(),
+    };
+    let last_line = &leading[leading.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+    if last_line.chars().all(char::is_whitespace) {
+      last_line.to_string()
+    } else {
+      "  ".to_string()
+    }
+  }
+
+  fn format(&self, entity: Entity, indent: &str) -> Statement {
+    let text = if self.directed {
+      entity.to_string()
+    } else {
+      undirected_statement(&entity)
+    };
+    let text = text
+      .split('\n')
+      .enumerate()
+      .map(|(i, line)| {
+        if i == 0 || line.is_empty() {
+          line.to_string()
+        } else {
+          format!("{}{}", indent, line)
+        }
+      })
+      .collect::<Vec<_>>()
+      .join("\n");
+    Statement {
+      leading: format!("\n{}", indent),
+      text,
+      entities: vec![entity],
+    }
+  }
+
+  /// Add a statement for `entity` before statement `i`, after any comments
+  /// preceding statement `i - 1`.
+  pub fn insert(&mut self, i: usize, entity: Entity) {
+    let indent = self.indentation(i);
+    let statement = self.format(entity, &indent);
+    self.statements.insert(i, statement);
+  }
+
+  /// Add a statement for `entity` after every other statement.
+  pub fn push(&mut self, entity: Entity) { self.insert(self.statements.len(), entity); }
+
+  /// Replace the text of statement `i` with a statement for `entity`, keeping
+  /// the comments and whitespace before it.
+  pub fn replace(&mut self, i: usize, entity: Entity) {
+    let indent = self.indentation(i);
+    let Statement { text, entities, .. } = self.format(entity, &indent);
+    self.statements[i].text = text;
+    self.statements[i].entities = entities;
+  }
+
+  /// Remove statement `i` along with the comments and whitespace before it.
+  pub fn remove(&mut self, i: usize) { self.statements.remove(i); }
+}
+
+impl fmt::Display for Document {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&self.head)?;
+    for s in self.statements.iter() {
+      f.write_str(&s.leading)?;
+      f.write_str(&s.text)?;
+    }
+    f.write_str(&self.tail)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::generator::{DotOutput, GraphBuilder};
+
+  #[test]
+  fn parse_statements() {
+    let graph = parse(
+      "/* header */\n\
+       # 1 \"preprocessed.gv\"\n\
+       strict graph {\n\
+         rankdir = LR; // trailing\n\
+         edge [color=red]\n\
+         a:p:n -- {b c} -- d [weight=2];\n\
+         subgraph cluster_x { e [label=<<b>E</b>>] }\n\
+         { rank=same; f g }\n\
+         \"h\" + \"i\";\n\
+       }",
+    )
+    .unwrap();
+    assert!(graph.strict);
+    assert!(!graph.directed);
+    assert_eq!(graph.id, None);
+    assert_eq!(
+      graph.attributes.get("rankdir"),
+      Some(&AttrValue::from("LR"))
+    );
+
+    let edges: Vec<_> = graph
+      .entities
+      .iter()
+      .filter_map(|e| match e {
+        Entity::Edge(e) => Some((e.source.as_str(), e.target.as_str())),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(edges, vec![("a", "b"), ("a", "c"), ("b", "d"), ("c", "d")]);
+    match &graph.entities[1] {
+      Entity::Edge(e) => {
+        assert_eq!(e.attributes.get("tailport"), Some(&AttrValue::from("p:n")));
+        assert_eq!(e.attributes.get("color"), Some(&AttrValue::from("red")));
+        assert_eq!(e.attributes.get("weight"), Some(&AttrValue::from("2")));
+      },
+      e => panic!("{:?}", e),
+    }
+    match &graph.entities[5] {
+      Entity::Subgraph(sg) => {
+        assert!(sg.is_cluster);
+        assert_eq!(sg.entities, vec![Entity::Vertex(
+          Vertex::new("e").attr("label", AttrValue::Html("<b>E</b>".to_string()))
+        )]);
+      },
+      e => panic!("{:?}", e),
+    }
+    match &graph.entities[6] {
+      Entity::Subgraph(sg) => {
+        assert!(!sg.is_cluster);
+        assert_eq!(sg.id, Id::new("_anonymous_0"));
+        assert_eq!(sg.attributes.get("rank"), Some(&AttrValue::from("same")));
+      },
+      e => panic!("{:?}", e),
+    }
+    assert_eq!(graph.entities[7], Entity::Vertex(Vertex::new("hi")));
+  }
+
+  #[test]
+  fn reemit_parsed_graph() {
+    let graph = parse("digraph g { a -> b [label=\"x \\\"y\\\"\"] }").unwrap();
+    let DotOutput(output) = graph
+      .entities
+      .into_iter()
+      .collect::<GraphBuilder>()
+      .build(graph.id.unwrap())
+      .unwrap();
+    assert_eq!(
+      output,
+      "digraph g {\n  \
+         compound = true;\n\n  \
+         a -> b[label=\"x \\\"y\\\"\"];\n\
+       }\n"
+    );
+  }
+
+  #[test]
+  fn reemit_escaped_id() {
+    let graph = parse("digraph { \"a\\\\b\" -> \"say \\\"hi\\\"\" }").unwrap();
+    let Entity::Edge(ref e) = graph.entities[0] else {
+      panic!("{:?}", graph.entities);
+    };
+    assert_eq!(e.source.as_str(), "a\\b");
+    assert_eq!(e.target.as_str(), "say \"hi\"");
+    let DotOutput(output) = graph
+      .entities
+      .iter()
+      .cloned()
+      .collect::<GraphBuilder>()
+      .build(Id::new("g"))
+      .unwrap();
+    assert!(
+      output.contains("\"a\\\\b\" -> \"say \\\"hi\\\"\";"),
+      "{}",
+      output
+    );
+    assert_eq!(parse(&output).unwrap().entities, graph.entities);
+  }
+
+  #[test]
+  fn reparse_subgraphs() {
+    let graph = parse(
+      "digraph g {\n\
+         subgraph cluster_x { a }\n\
+         subgraph y { rank = min; b }\n\
+       }",
+    )
+    .unwrap();
+    let DotOutput(output) = graph
+      .entities
+      .iter()
+      .cloned()
+      .collect::<GraphBuilder>()
+      .build(Id::new("g"))
+      .unwrap();
+    assert!(!output.contains("rank = same"), "{}", output);
+    assert_eq!(output.matches("rank = ").count(), 1, "{}", output);
+    assert_eq!(parse(&output).unwrap().entities, graph.entities);
+  }
+
+  #[test]
+  fn lossless_round_trip() {
+    let text = "/* generated by hand */\n\
+                graph g {\n\
+                \trankdir=LR\n\
+                \n\
+                \t// the important part\n\
+                \ta -- b [ color = red ] ;\n\
+                \tc;   d\n\
+                }\n";
+    let mut doc = Document::parse(text).unwrap();
+    assert_eq!(doc.to_string(), text);
+    assert_eq!(doc.len(), 4);
+    assert_eq!(doc.statement(1), "a -- b [ color = red ] ;");
+
+    let c = doc.find_vertex(&Id::new("c")).unwrap();
+    doc.replace(c, Entity::Vertex(Vertex::new("c").label("C")));
+    doc.remove(3);
+    doc.push(Entity::Edge(Edge::between("c", "e")));
+    doc.insert(1, Entity::Subgraph(Subgraph::new("cluster_x")));
+    assert_eq!(
+      doc.to_string(),
+      "/* generated by hand */\n\
+       graph g {\n\
+       \trankdir=LR\n\
+       \tsubgraph cluster_x {\n\
+       \t  cluster = true;\n\
+       \t  rank = same;\n\
+       \n\
+       \n\
+       \t}\n\
+       \n\
+       \t// the important part\n\
+       \ta -- b [ color = red ] ;\n\
+       \tc[label=\"C\"];\n\
+       \tc -- e;\n\
+       }\n"
+    );
+    assert!(parse(&doc.to_string()).is_ok());
+  }
+
+  #[test]
+  fn undirected_nested_edges() {
+    let mut doc = Document::parse("graph g {\n  a;\n}\n").unwrap();
+    let mut group = Group::default();
+    group.entities.push(Entity::Edge(Edge::between("a", "b")));
+    doc.push(Entity::Group(group));
+    assert_eq!(
+      doc.to_string(),
+      "graph g {\n  a;\n  {\n    a -- b;\n  }\n}\n"
+    );
+    assert!(parse(&doc.to_string()).is_ok());
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert_eq!(
+      parse("digraph {\n  a -- b\n}").unwrap_err().to_string(),
+      "2:5: '--' in a digraph"
+    );
+    assert_eq!(
+      parse("digraph { a -> }").unwrap_err().to_string(),
+      "1:16: expected an ID, found '}'"
+    );
+    assert_eq!(
+      parse("graph { \"a }").unwrap_err().to_string(),
+      "1:9: unterminated string"
+    );
+  }
+}
@@ -0,0 +1,264 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversions between [petgraph](https://docs.rs/petgraph) graphs and
+//! [`GraphBuilder`], for drawing graphs built or analyzed with petgraph.
+//!
+//! [`from_petgraph`] works with any petgraph graph which can list its nodes
+//! and edges by reference, such as [`Graph`](::petgraph::graph::Graph),
+//! [`StableGraph`](::petgraph::stable_graph::StableGraph), and
+//! [`GraphMap`](::petgraph::graphmap::GraphMap). Closures turn each node and
+//! edge into a [`Vertex`] and [`Edge`], with full control over their ids and
+//! styling. Edges of undirected graphs are drawn from their source to their
+//! target.
+//!
+//! In the other direction, [`GraphBuilder::to_petgraph`] copies a graph into a
+//! [`StableDiGraph`], so petgraph's algorithms can be run over it and their
+//! results fed back into styling.
+//!
+//! Requires the `petgraph` feature.
+//!
+//!```
+//! use graphvizier::{entities::*, petgraph::from_petgraph, Graphable};
+//! use petgraph::{graph::Graph, visit::NodeRef};
+//!
+//! let mut g = Graph::<&str, u32>::new();
+//! let a = g.add_node("a");
+//! let b = g.add_node("b");
+//! g.add_edge(a, b, 7);
+//!
+//! let gb = from_petgraph(
+//!   &g,
+//!   |n| Vertex::new(format!("n{}", n.id().index())).label(n.weight()),
+//!   |e| Edge::default().label(e.weight().to_string()),
+//! )
+//! .build_graph();
+//! assert_eq!(
+//!   gb.build(Id::new("g")).unwrap().0,
+//!   "digraph g {\n  \
+//!      compound = true;\n\n  \
+//!      n0[label=\"a\"];\n\n  \
+//!      n1[label=\"b\"];\n\n  \
+//!      n0 -> n1[label=\"7\"];\n\
+//!    }\n"
+//! );
+//! ```
+
+use crate::{
+  entities::{Edge, Entity, Id, Vertex},
+  export,
+  generator::GraphBuilder,
+  Graphable,
+};
+
+use ::petgraph::{
+  stable_graph::StableDiGraph,
+  visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences, NodeIndexable, NodeRef},
+};
+
+use std::collections::HashMap;
+
+/// A petgraph graph, and how to draw its nodes and edges. See
+/// [`from_petgraph`].
+pub struct FromPetgraph<G, N, E> {
+  graph: G,
+  vertex: N,
+  edge: E,
+}
+
+/// Draw each node of `graph` as the [`Vertex`] returned by `vertex`, and
+/// each edge as the [`Edge`] returned by `edge`. The source and target of
+/// each edge are filled in from the ids of the vertices at its endpoints.
+///
+/// If `vertex` returns two differing vertices with the same id,
+/// [`Graphable::build_graph`] keeps both, and Graphviz merges them when the
+/// graph is drawn. Call [`GraphBuilder::validate_vertices`] on the result to
+/// have [`GraphBuilder::build`] return a
+/// [`GraphError::DuplicateVertex`](crate::generator::GraphError::DuplicateVertex)
+/// instead.
+pub fn from_petgraph<G, N, E>(graph: G, vertex: N, edge: E) -> FromPetgraph<G, N, E>
+where
+  G: IntoNodeReferences+IntoEdgeReferences+NodeIndexable,
+  N: Fn(G::NodeRef) -> Vertex,
+  E: Fn(G::EdgeRef) -> Edge,
+{
+  FromPetgraph {
+    graph,
+    vertex,
+    edge,
+  }
+}
+
+impl<G, N, E> Graphable for FromPetgraph<G, N, E>
+where
+  G: IntoNodeReferences+IntoEdgeReferences+NodeIndexable,
+  N: Fn(G::NodeRef) -> Vertex,
+  E: Fn(G::EdgeRef) -> Edge,
+{
+  fn build_graph(self) -> GraphBuilder {
+    let Self {
+      graph,
+      vertex,
+      edge,
+    } = self;
+    let mut ids: Vec<Option<Id>> = vec![None; graph.node_bound()];
+    let mut ret = GraphBuilder::new();
+    for n in graph.node_references() {
+      let v = vertex(n);
+      ids[graph.to_index(n.id())] = Some(v.id.clone());
+      ret.accept_entity(Entity::Vertex(v));
+    }
+    let id = |n: G::NodeId| {
+      ids[graph.to_index(n)]
+        .clone()
+        .expect("every edge endpoint is a node")
+    };
+    for e in graph.edge_references() {
+      ret.accept_entity(Entity::Edge(Edge {
+        source: id(e.source()),
+        target: id(e.target()),
+        ..edge(e)
+      }));
+    }
+    ret
+  }
+}
+
+impl GraphBuilder {
+  /// Copy every vertex and edge of this graph, including those inside
+  /// subgraphs, into a petgraph [`StableDiGraph`]. Vertices which are only
+  /// mentioned as edge endpoints are added with no attributes. Subgraphs
+  /// and comments are dropped.
+  ///
+  ///```
+  /// use graphvizier::{entities::*, generator::GraphBuilder};
+  /// use petgraph::algo::toposort;
+  ///
+  /// let mut gb = GraphBuilder::new();
+  /// gb.accept_entity(Entity::Edge(Edge::between("b", "c")));
+  /// gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+  ///
+  /// let g = gb.to_petgraph();
+  /// let order: Vec<&str> = toposort(&g, None)
+  ///   .unwrap()
+  ///   .into_iter()
+  ///   .map(|n| g[n].id.as_str())
+  ///   .collect();
+  /// assert_eq!(order, vec!["a", "b", "c"]);
+  /// ```
+  pub fn to_petgraph(&self) -> StableDiGraph<Vertex, Edge> {
+    let vertices = export::all_vertices(self.entities());
+    let edges = export::all_edges(self.entities());
+    let mut g = StableDiGraph::with_capacity(vertices.len(), edges.len());
+    let mut indices = HashMap::with_capacity(vertices.len());
+    for v in vertices {
+      let id = v.id.clone();
+      indices.insert(id, g.add_node(v));
+    }
+    for e in edges {
+      g.add_edge(indices[&e.source], indices[&e.target], e.clone());
+    }
+    g
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::entities::Subgraph;
+
+  use ::petgraph::{graphmap::UnGraphMap, stable_graph::StableGraph};
+
+  #[test]
+  fn stable_graph_with_holes() {
+    let mut g = StableGraph::<char, ()>::new();
+    let a = g.add_node('a');
+    let b = g.add_node('b');
+    let c = g.add_node('c');
+    g.add_edge(a, c, ());
+    g.add_edge(b, c, ());
+    g.remove_node(b);
+
+    let gb = from_petgraph(
+      &g,
+      |n| Vertex::new(n.weight().to_string()),
+      |_| Edge::default(),
+    )
+    .build_graph();
+    let edges: Vec<(&str, &str)> = gb
+      .edges()
+      .map(|e| (e.source.as_str(), e.target.as_str()))
+      .collect();
+    assert_eq!(edges, vec![("a", "c")]);
+    assert_eq!(gb.vertices().count(), 2);
+  }
+
+  #[test]
+  fn round_trip_through_petgraph() {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Vertex(Vertex::new("a").label("A")));
+    gb.accept_entity(Entity::Subgraph(
+      Subgraph::new("cluster_x").entity(Entity::Edge(Edge::between("a", "b").label("ab"))),
+    ));
+
+    let g = gb.to_petgraph();
+    assert_eq!(g.node_count(), 2);
+    let back = from_petgraph(&g, |(_, v)| v.clone(), |e| e.weight().clone()).build_graph();
+    let vertices: Vec<Vertex> = back.vertices().cloned().collect();
+    assert_eq!(vertices, vec![
+      Vertex::new("a").label("A"),
+      Vertex::new("b")
+    ]);
+    let edges: Vec<Edge> = back.edges().cloned().collect();
+    assert_eq!(edges, vec![Edge::between("a", "b").label("ab")]);
+  }
+
+  #[test]
+  fn conflicting_vertices() {
+    let g = UnGraphMap::<u32, ()>::from_edges([(1, 2)]);
+    let mut gb = from_petgraph(
+      &g,
+      |(n, _)| Vertex::new("x").label(n.to_string()),
+      |_| Edge::default(),
+    )
+    .build_graph();
+    assert_eq!(gb.vertices().count(), 2);
+    gb.validate_vertices(true);
+    assert!(matches!(
+      gb.build(Id::new("g")),
+      Err(crate::generator::GraphError::DuplicateVertex(_))
+    ));
+  }
+
+  #[test]
+  fn graph_map() {
+    let g = UnGraphMap::<u32, &str>::from_edges([(1, 2, "x"), (2, 3, "y")]);
+    let gb = from_petgraph(
+      &g,
+      |(n, _)| Vertex::new(n.to_string()),
+      |e| Edge::default().label(*e.weight()),
+    )
+    .build_graph();
+    let edges: Vec<Edge> = gb.edges().cloned().collect();
+    assert_eq!(edges, vec![
+      Edge::between("1", "2").label("x"),
+      Edge::between("2", "3").label("y"),
+    ]);
+  }
+}
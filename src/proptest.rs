@@ -0,0 +1,202 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! [proptest](https://docs.rs/proptest) strategies for random entities, for
+//! property-testing code which generates or consumes graphs.
+//!
+//! Ids and labels are drawn to exercise escaping: they include quotes,
+//! backslashes, newlines, DOT punctuation, keywords, numerals, and non-ASCII
+//! text. [`entities`] nests vertices and edges within subgraphs and groups.
+//! [`Id`], [`Label`], [`Vertex`], [`Edge`], and [`Entity`] also implement
+//! [`Arbitrary`], so they can be used with [`any`].
+//!
+//! Requires the `proptest` feature.
+//!
+//!```
+//! use graphvizier::{generator::GraphBuilder, parser, proptest::entities};
+//! use proptest::{prelude::*, test_runner::TestRunner};
+//!
+//! TestRunner::default()
+//!   .run(&entities(), |entities| {
+//!     let gb: GraphBuilder = entities.into_iter().collect();
+//!     let dot = gb.build("g".into()).unwrap();
+//!     prop_assert!(parser::parse(&dot.0).is_ok());
+//!     Ok(())
+//!   })
+//!   .unwrap();
+//! ```
+
+use crate::entities::{
+  style::{Color, Label, Shape},
+  Edge, Entity, Group, Id, Subgraph, Vertex,
+};
+
+use ::proptest::{
+  arbitrary::Arbitrary, collection::vec, option, prelude::*, sample::select,
+  strategy::BoxedStrategy,
+};
+
+/// Printable ASCII (including `"` and `\`), whitespace, and some multi-byte
+/// characters.
+const TEXT: &str = "[ -~\t\n\r\u{e9}\u{4e2d}\u{1f980}]";
+
+const KEYWORDS: &[&str] = &[
+  "graph", "digraph", "subgraph", "node", "edge", "strict", "NODE",
+];
+
+const COLORS: &[&str] = &["black", "red", "lightgoldenrod", "/blues9/3", "0.5 0.5 0.5"];
+
+const SHAPES: &[&str] = &["box", "ellipse", "record", "plaintext", "none"];
+
+/// Free text of up to `max` characters.
+fn text(min: usize, max: usize) -> impl Strategy<Value=String> {
+  ::proptest::string::string_regex(&format!("{}{{{},{}}}", TEXT, min, max))
+    .expect("TEXT is a valid regex")
+}
+
+/// Identifiers, numerals, keywords, HTML strings, and arbitrary quoted text.
+pub fn id() -> impl Strategy<Value=Id> {
+  prop_oneof![
+    3 => "[a-zA-Z_\u{80}-\u{ff}][a-zA-Z0-9_]{0,8}".prop_map(Id::from),
+    1 => "-?([0-9]{1,4}|[0-9]{0,3}\\.[0-9]{1,3})".prop_map(Id::from),
+    1 => select(KEYWORDS).prop_map(Id::from),
+    1 => "[a-z &;]{0,8}".prop_map(|s| Id::html(format!("<b>{}</b>", s.replace('&', "&amp;")))),
+    3 => text(1, 16).prop_map(Id::from),
+  ]
+}
+
+/// Escape sequences which may appear in a label. Labels are written to the
+/// document verbatim, so a bare `"` or a trailing `\` is invalid.
+const ESCAPES: &[&str] = &["\\\"", "\\\\", "\\n", "\\l", "\\r", "\\N", "\\G"];
+
+/// Arbitrary text mixed with escape sequences, possibly empty.
+pub fn label() -> impl Strategy<Value=Label> {
+  let part = prop_oneof![
+    4 => text(1, 4).prop_map(|s| s.replace(['"', '\\'], "")),
+    1 => select(ESCAPES).prop_map(str::to_string),
+  ];
+  vec(part, 0..8).prop_map(|parts| Label(parts.concat()))
+}
+
+/// A color name, scheme color, HSV triple, or hex color.
+pub fn color() -> impl Strategy<Value=Color> {
+  prop_oneof![
+    select(COLORS).prop_map(str::to_string),
+    "#[0-9a-f]{6}([0-9a-f]{2})?"
+  ]
+  .prop_map(Color)
+}
+
+/// A vertex with a random id and optional label, color, and shape.
+pub fn vertex() -> impl Strategy<Value=Vertex> {
+  (
+    id(),
+    option::of(label()),
+    option::of(color()),
+    option::of(select(SHAPES)),
+  )
+    .prop_map(|(id, label, color, shape)| {
+      let mut v = Vertex::new(id);
+      v.label = label;
+      v.color = color;
+      v.shape = shape.map(|s| Shape(s.to_string()));
+      v
+    })
+}
+
+/// An edge between random ids, with an optional label and color.
+pub fn edge() -> impl Strategy<Value=Edge> {
+  (id(), id(), option::of(label()), option::of(color())).prop_map(
+    |(source, target, label, color)| {
+      let mut e = Edge::between(source, target);
+      e.label = label;
+      e.color = color;
+      e
+    },
+  )
+}
+
+/// A vertex, edge, or comment, or a subgraph or group of up to three levels
+/// of further entities.
+pub fn entity() -> impl Strategy<Value=Entity> {
+  let leaf = prop_oneof![
+    4 => vertex().prop_map(Entity::Vertex),
+    4 => edge().prop_map(Entity::Edge),
+    1 => text(0, 24).prop_map(Entity::Comment),
+  ];
+  leaf.prop_recursive(3, 32, 6, |inner| {
+    prop_oneof![
+      (
+        id(),
+        any::<bool>(),
+        option::of(label()),
+        vec(inner.clone(), 0..6)
+      )
+        .prop_map(|(id, is_cluster, label, entities)| {
+          let mut sg = Subgraph::new(id).cluster(is_cluster);
+          sg.label = label;
+          sg.entities = entities;
+          Entity::Subgraph(sg)
+        }),
+      vec(inner, 0..6).prop_map(|entities| Entity::Group(Group {
+        node_defaults: None,
+        entities,
+      })),
+    ]
+  })
+}
+
+/// The top-level entities of a graph.
+pub fn entities() -> impl Strategy<Value=Vec<Entity>> { vec(entity(), 0..8) }
+
+macro_rules! arbitrary {
+  ($t:ty, $strategy:expr) => {
+    impl Arbitrary for $t {
+      type Parameters = ();
+      type Strategy = BoxedStrategy<Self>;
+
+      fn arbitrary_with((): ()) -> Self::Strategy { $strategy.boxed() }
+    }
+  };
+}
+
+arbitrary![Id, id()];
+arbitrary![Label, label()];
+arbitrary![Vertex, vertex()];
+arbitrary![Edge, edge()];
+arbitrary![Entity, entity()];
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{generator::GraphBuilder, parser};
+
+  proptest! {
+    #[test]
+    fn generated_dot_parses(entities in entities()) {
+      let gb: GraphBuilder = entities.into_iter().collect();
+      let edges = gb.edges().count();
+      let dot = gb.build("g".into()).unwrap();
+      let parsed = parser::parse(&dot.0)
+        .map_err(|e| TestCaseError::fail(format!("{}\n{}", e, dot.0)))?;
+      let back: GraphBuilder = parsed.entities.into_iter().collect();
+      prop_assert_eq!(back.edges().count(), edges);
+    }
+  }
+}
@@ -0,0 +1,203 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Bindings to the Graphviz C library, so graphs can be laid out and drawn
+//! without spawning `dot`. Requires the `gvc` feature, and links against
+//! `libgvc` and `libcgraph`, e.g. from the `libgraphviz-dev` package. Only
+//! little-endian targets are supported, since the width of one argument to
+//! `gvRenderData` depends on the Graphviz version.
+
+#![allow(unsafe_code)]
+
+#[cfg(not(target_endian = "little"))]
+compile_error!("the `gvc` feature requires a little-endian target");
+
+use super::{Engine, Format};
+use crate::generator::DotOutput;
+
+use lazy_static::lazy_static;
+
+use std::{
+  ffi::{c_char, c_int, CString},
+  fmt, ptr, slice,
+  sync::Mutex,
+};
+
+#[repr(C)]
+struct GvcT {
+  _private: [u8; 0],
+}
+
+#[repr(C)]
+struct AgraphT {
+  _private: [u8; 0],
+}
+
+#[link(name = "gvc")]
+#[link(name = "cgraph")]
+extern "C" {
+  fn gvContext() -> *mut GvcT;
+  fn gvLayout(gvc: *mut GvcT, g: *mut AgraphT, engine: *const c_char) -> c_int;
+  /* `length` is an `unsigned int *` before Graphviz 3.0, and a `size_t *`
+   * after. Either is read correctly from a zeroed usize on little-endian
+   * targets, which is why big-endian targets are rejected above. */
+  fn gvRenderData(
+    gvc: *mut GvcT,
+    g: *mut AgraphT,
+    format: *const c_char,
+    result: *mut *mut c_char,
+    length: *mut usize,
+  ) -> c_int;
+  fn gvFreeRenderData(data: *mut c_char);
+  fn gvFreeLayout(gvc: *mut GvcT, g: *mut AgraphT) -> c_int;
+  fn agmemread(text: *const c_char) -> *mut AgraphT;
+  fn agclose(g: *mut AgraphT) -> c_int;
+}
+
+/// Why [`layout`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+  /// The document contains a NUL byte, so can't be passed to Graphviz.
+  Nul,
+  /// Graphviz couldn't parse the document.
+  Syntax,
+  /// The engine isn't available, or failed to lay out the graph.
+  Layout(Engine),
+  /// The format isn't available, or the drawing failed.
+  Render(Format),
+}
+
+impl fmt::Display for LayoutError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Nul => write!(f, "document contains a NUL byte"),
+      Self::Syntax => write!(f, "graphviz could not parse the document"),
+      Self::Layout(engine) => write!(f, "layout with {:?} failed", engine.name()),
+      Self::Render(format) => write!(f, "rendering to {:?} failed", format.name()),
+    }
+  }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Graphviz keeps global state, so all calls into it hold this lock. The
+/// context is created once, since that loads every plugin.
+struct Context(*mut GvcT);
+
+unsafe impl Send for Context {}
+
+lazy_static! {
+  static ref CONTEXT: Mutex<Context> = Mutex::new(Context(unsafe { gvContext() }));
+}
+
+/// Closes the graph, and frees its layout if there is one.
+struct Graph<'a> {
+  gvc: &'a Context,
+  g: *mut AgraphT,
+  laid_out: bool,
+}
+
+impl Drop for Graph<'_> {
+  fn drop(&mut self) {
+    unsafe {
+      if self.laid_out {
+        gvFreeLayout(self.gvc.0, self.g);
+      }
+      agclose(self.g);
+    }
+  }
+}
+
+/// Lay out `dot` with `engine` and draw it in `format`, within this process.
+///
+///```no_run
+/// use graphvizier::{
+///   entities::*,
+///   generator::GraphBuilder,
+///   render::{layout, Engine, Format},
+/// };
+///
+/// let mut gb = GraphBuilder::new();
+/// gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+/// let dot = gb.build(Id::new("g")).unwrap();
+/// let svg = layout(&dot, Engine::Dot, Format::Svg).unwrap();
+/// assert!(String::from_utf8(svg).unwrap().contains("<svg"));
+/// ```
+pub fn layout(dot: &DotOutput, engine: Engine, format: Format) -> Result<Vec<u8>, LayoutError> {
+  let text = CString::new(dot.0.as_bytes()).map_err(|_| LayoutError::Nul)?;
+  let engine_name = CString::new(engine.name()).unwrap();
+  let format_name = CString::new(format.name()).unwrap();
+
+  let gvc = CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+  let g = unsafe { agmemread(text.as_ptr()) };
+  if g.is_null() {
+    return Err(LayoutError::Syntax);
+  }
+  let mut graph = Graph {
+    gvc: &gvc,
+    g,
+    laid_out: false,
+  };
+
+  if unsafe { gvLayout(gvc.0, graph.g, engine_name.as_ptr()) } != 0 {
+    return Err(LayoutError::Layout(engine));
+  }
+  graph.laid_out = true;
+
+  let mut data: *mut c_char = ptr::null_mut();
+  let mut length: usize = 0;
+  let status =
+    unsafe { gvRenderData(gvc.0, graph.g, format_name.as_ptr(), &mut data, &mut length) };
+  if status != 0 || data.is_null() {
+    if !data.is_null() {
+      unsafe { gvFreeRenderData(data) };
+    }
+    return Err(LayoutError::Render(format));
+  }
+  let ret = unsafe { slice::from_raw_parts(data.cast::<u8>().cast_const(), length) }.to_vec();
+  unsafe { gvFreeRenderData(data) };
+  Ok(ret)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn svg() {
+    let dot = DotOutput("digraph g { a -> b; }".to_string());
+    let svg = String::from_utf8(layout(&dot, Engine::Dot, Format::Svg).unwrap()).unwrap();
+    assert!(svg.contains("<svg"));
+    assert!(svg.contains("<title>a&#45;&gt;b</title>"));
+  }
+
+  #[test]
+  fn errors() {
+    let dot = DotOutput("digraph g { a -> ".to_string());
+    assert_eq!(
+      layout(&dot, Engine::Dot, Format::Svg),
+      Err(LayoutError::Syntax)
+    );
+    let dot = DotOutput("digraph g { a\0 }".to_string());
+    assert_eq!(
+      layout(&dot, Engine::Neato, Format::Png),
+      Err(LayoutError::Nul)
+    );
+  }
+}
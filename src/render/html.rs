@@ -0,0 +1,193 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Wrap a DOT document in a single HTML page which draws it in the browser,
+//! so it can be viewed without installing Graphviz.
+//!
+//! The page draws the graph with [viz-js](https://github.com/mdaines/viz-js),
+//! Graphviz compiled to WebAssembly, loaded from [`HtmlOptions::viz_js_url`].
+//! Everything else is inline: the DOT source, mouse wheel zooming, dragging
+//! to pan, and a search box which fades out every vertex whose text doesn't
+//! contain the query.
+//!
+//!```
+//! use graphvizier::{entities::*, generator::GraphBuilder};
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+//! let html = gb.build_html(Id::new("g")).unwrap();
+//! assert!(html.starts_with("<!DOCTYPE html>"));
+//! assert!(html.contains("<title>g</title>"));
+//! ```
+
+use crate::{entities::style::HtmlLabel, generator::DotOutput};
+
+/// The standalone build of viz-js loaded by default.
+pub const VIZ_JS_URL: &str =
+  "https://cdn.jsdelivr.net/npm/@viz-js/viz@3.11.0/lib/viz-standalone.js";
+
+/// How to write the page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlOptions {
+  /// The title of the page.
+  pub title: String,
+  /// Where to load viz-js from. Replace this with a local copy of
+  /// `viz-standalone.js` to view the page offline.
+  pub viz_js_url: String,
+}
+
+impl Default for HtmlOptions {
+  fn default() -> Self {
+    Self {
+      title: "graph".to_string(),
+      viz_js_url: VIZ_JS_URL.to_string(),
+    }
+  }
+}
+
+/// `s` as a JavaScript string literal which can't end a `<script>` element.
+fn js_string(s: &str) -> String {
+  let mut ret = String::with_capacity(s.len() + 2);
+  ret.push('"');
+  for c in s.chars() {
+    match c {
+      '\\' => ret.push_str("\\\\"),
+      '"' => ret.push_str("\\\""),
+      '\n' => ret.push_str("\\n"),
+      '\r' => ret.push_str("\\r"),
+      '<' => ret.push_str("\\u003c"),
+      '\u{2028}' => ret.push_str("\\u2028"),
+      '\u{2029}' => ret.push_str("\\u2029"),
+      c => ret.push(c),
+    }
+  }
+  ret.push('"');
+  ret
+}
+
+const STYLE: &str = "\
+html, body { margin: 0; height: 100%; font-family: sans-serif; }
+body { display: flex; flex-direction: column; }
+#toolbar { padding: 0.5em; border-bottom: 1px solid #ccc; }
+#graph { flex: 1; overflow: hidden; cursor: grab; }
+#graph svg { width: 100%; height: 100%; }
+";
+
+const SCRIPT: &str = r#"
+Viz.instance().then(function (viz) {
+  var container = document.getElementById("graph");
+  var svg = viz.renderSVGElement(dot);
+  svg.removeAttribute("width");
+  svg.removeAttribute("height");
+  container.appendChild(svg);
+
+  var box = svg.viewBox.baseVal;
+  var view = { x: box.x, y: box.y, w: box.width, h: box.height };
+  function apply() {
+    svg.setAttribute("viewBox", [view.x, view.y, view.w, view.h].join(" "));
+  }
+  container.addEventListener("wheel", function (e) {
+    e.preventDefault();
+    var r = svg.getBoundingClientRect();
+    var scale = e.deltaY < 0 ? 0.9 : 1.1;
+    var px = view.x + (e.clientX - r.left) / r.width * view.w;
+    var py = view.y + (e.clientY - r.top) / r.height * view.h;
+    view = {
+      x: px - (px - view.x) * scale,
+      y: py - (py - view.y) * scale,
+      w: view.w * scale,
+      h: view.h * scale,
+    };
+    apply();
+  }, { passive: false });
+
+  var drag = null;
+  container.addEventListener("pointerdown", function (e) {
+    drag = { x: e.clientX, y: e.clientY };
+    container.setPointerCapture(e.pointerId);
+  });
+  container.addEventListener("pointermove", function (e) {
+    if (!drag) return;
+    var r = svg.getBoundingClientRect();
+    view.x -= (e.clientX - drag.x) / r.width * view.w;
+    view.y -= (e.clientY - drag.y) / r.height * view.h;
+    drag = { x: e.clientX, y: e.clientY };
+    apply();
+  });
+  container.addEventListener("pointerup", function () { drag = null; });
+
+  document.getElementById("search").addEventListener("input", function (e) {
+    var query = e.target.value.toLowerCase();
+    svg.querySelectorAll("g.node").forEach(function (node) {
+      var match = !query || node.textContent.toLowerCase().indexOf(query) >= 0;
+      node.style.opacity = match ? "" : "0.2";
+    });
+  });
+}).catch(function (err) {
+  document.getElementById("graph").textContent = String(err);
+});
+"#;
+
+/// Write the page drawing `dot`. See the [module docs](self).
+pub fn to_html(dot: &DotOutput, options: &HtmlOptions) -> String {
+  let HtmlOptions { title, viz_js_url } = options;
+  format!(
+    "<!DOCTYPE html>\n\
+     <html>\n\
+     <head>\n\
+     <meta charset=\"utf-8\">\n\
+     <title>{}</title>\n\
+     <style>\n{}</style>\n\
+     <script src=\"{}\"></script>\n\
+     </head>\n\
+     <body>\n\
+     <div id=\"toolbar\"><input id=\"search\" type=\"search\" placeholder=\"Search\"></div>\n\
+     <div id=\"graph\"></div>\n\
+     <script>\n\
+     var dot = {};\n\
+     {}</script>\n\
+     </body>\n\
+     </html>\n",
+    HtmlLabel::escape_text(title),
+    STYLE,
+    HtmlLabel::escape_text(viz_js_url),
+    js_string(&dot.0),
+    SCRIPT.trim_start(),
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn escaping() {
+    let dot =
+      DotOutput("digraph g {\n  a [label=<<b>x</b>>, tooltip=\"\\\"q\\\"\"];\n}\n".to_string());
+    let html = to_html(&dot, &HtmlOptions {
+      title: "a < b".to_string(),
+      ..HtmlOptions::default()
+    });
+    assert!(html.contains("<title>a &lt; b</title>"));
+    assert!(html.contains(
+      r#"var dot = "digraph g {\n  a [label=\u003c\u003cb>x\u003c/b>>, tooltip=\"\\\"q\\\"\"];\n}\n";"#
+    ));
+    assert_eq!(html.matches("</script>").count(), 2);
+  }
+}
@@ -0,0 +1,97 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Ways to draw generated graphs.
+
+#[cfg(feature = "gvc")]
+mod gvc;
+pub mod html;
+pub mod process;
+
+#[cfg(feature = "gvc")]
+pub use gvc::{layout, LayoutError};
+
+/// A Graphviz [layout engine](https://www.graphviz.org/docs/layouts/).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum Engine {
+  Dot,
+  Neato,
+  Fdp,
+  Sfdp,
+  Twopi,
+  Circo,
+  Osage,
+  Patchwork,
+}
+
+impl Engine {
+  /// The name Graphviz knows this engine by, as passed to `-K`.
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Dot => "dot",
+      Self::Neato => "neato",
+      Self::Fdp => "fdp",
+      Self::Sfdp => "sfdp",
+      Self::Twopi => "twopi",
+      Self::Circo => "circo",
+      Self::Osage => "osage",
+      Self::Patchwork => "patchwork",
+    }
+  }
+}
+
+/// A Graphviz [output format](https://www.graphviz.org/docs/outputs/).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum Format {
+  Svg,
+  Png,
+  Pdf,
+  Jpeg,
+  Gif,
+  Ps,
+  /// The input with layout information added, e.g. `pos`.
+  Dot,
+  /// Like [`Self::Dot`], with drawing instructions which
+  /// [`crate::xdot`] can read.
+  Xdot,
+  /// The output of [`Self::Xdot`] as JSON.
+  Json,
+  /// A simple line-based listing of positions.
+  Plain,
+}
+
+impl Format {
+  /// The name Graphviz knows this format by, as passed to `-T`.
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Svg => "svg",
+      Self::Png => "png",
+      Self::Pdf => "pdf",
+      Self::Jpeg => "jpg",
+      Self::Gif => "gif",
+      Self::Ps => "ps",
+      Self::Dot => "dot",
+      Self::Xdot => "xdot",
+      Self::Json => "json",
+      Self::Plain => "plain",
+    }
+  }
+}
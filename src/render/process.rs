@@ -0,0 +1,229 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Draw graphs by piping them to the Graphviz `dot` program.
+//!
+//! [`RenderOptions::program`] is found on the `PATH` unless it is a path
+//! itself. It defaults to the value of the `GRAPHVIZ_DOT` environment
+//! variable, or else `dot`.
+//!
+//!```no_run
+//! use graphvizier::{
+//!   entities::*,
+//!   generator::GraphBuilder,
+//!   render::{process::{render_to_file, RenderOptions}, Format},
+//! };
+//!
+//! let mut gb = GraphBuilder::new();
+//! gb.accept_entity(Entity::Edge(Edge::between("a", "b")));
+//! let dot = gb.build(Id::new("g")).unwrap();
+//! let options = RenderOptions {
+//!   format: Format::Png,
+//!   ..Default::default()
+//! };
+//! render_to_file(&dot, &options, "g.png").unwrap();
+//! ```
+
+use super::{Engine, Format};
+use crate::generator::DotOutput;
+
+use std::{
+  env, fmt, fs,
+  io::{self, Write},
+  path::{Path, PathBuf},
+  process::{Command, ExitStatus, Stdio},
+  thread,
+};
+
+/// How to run Graphviz.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+  /// The executable to run, which must accept `dot`'s `-K` and `-T` flags.
+  pub program: PathBuf,
+  #[allow(missing_docs)]
+  pub engine: Engine,
+  #[allow(missing_docs)]
+  pub format: Format,
+}
+
+impl Default for RenderOptions {
+  fn default() -> Self {
+    Self {
+      program: env::var_os("GRAPHVIZ_DOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("dot")),
+      engine: Engine::Dot,
+      format: Format::Svg,
+    }
+  }
+}
+
+/// Why [`render`] or [`render_to_file`] failed.
+#[derive(Debug)]
+pub enum RenderError {
+  /// The program doesn't exist, e.g. because Graphviz isn't installed.
+  NotFound(PathBuf),
+  /// The program couldn't be run or talked to, or the output couldn't be
+  /// written.
+  Io(io::Error),
+  /// The program exited unsuccessfully, e.g. because the document isn't
+  /// valid DOT.
+  Failed {
+    #[allow(missing_docs)]
+    status: ExitStatus,
+    /// What the program printed to stderr, which describes the problem.
+    stderr: String,
+  },
+}
+
+impl fmt::Display for RenderError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::NotFound(program) => write!(f, "{}: not found", program.display()),
+      Self::Io(e) => write!(f, "{}", e),
+      Self::Failed { status, stderr } => write!(f, "{}: {}", status, stderr.trim_end()),
+    }
+  }
+}
+
+impl std::error::Error for RenderError {
+  fn source(&self) -> Option<&(dyn std::error::Error+'static)> {
+    match self {
+      Self::Io(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for RenderError {
+  fn from(e: io::Error) -> Self { Self::Io(e) }
+}
+
+/// Draw `dot` as described by `options`, returning the output of the
+/// program.
+pub fn render(dot: &DotOutput, options: &RenderOptions) -> Result<Vec<u8>, RenderError> {
+  let mut child = Command::new(&options.program)
+    .arg(format!("-K{}", options.engine.name()))
+    .arg(format!("-T{}", options.format.name()))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| match e.kind() {
+      io::ErrorKind::NotFound => RenderError::NotFound(options.program.clone()),
+      _ => RenderError::Io(e),
+    })?;
+
+  /* Write from another thread, so a program which writes before it has
+   * read everything can't deadlock against us. */
+  let mut stdin = child.stdin.take().unwrap();
+  let text = dot.0.clone();
+  let writer = thread::spawn(move || stdin.write_all(text.as_bytes()));
+  let output = child.wait_with_output()?;
+  let written = writer.join().unwrap();
+
+  if !output.status.success() {
+    return Err(RenderError::Failed {
+      status: output.status,
+      stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    });
+  }
+  /* The program may exit successfully without reading all its input. */
+  match written {
+    Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+    _ => (),
+  }
+  Ok(output.stdout)
+}
+
+/// Draw `dot` as described by `options`, and write the output to the file at
+/// `path`.
+pub fn render_to_file(
+  dot: &DotOutput,
+  options: &RenderOptions,
+  path: impl AsRef<Path>,
+) -> Result<(), RenderError> {
+  fs::write(path, render(dot, options)?)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn dot() -> DotOutput { DotOutput("digraph g { a -> b; }".to_string()) }
+
+  #[test]
+  fn not_found() {
+    let options = RenderOptions {
+      program: PathBuf::from("graphvizier-no-such-program"),
+      ..Default::default()
+    };
+    match render(&dot(), &options) {
+      Err(RenderError::NotFound(program)) => {
+        assert_eq!(program, PathBuf::from("graphvizier-no-such-program"))
+      },
+      r => panic!("{:?}", r),
+    }
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn subprocess() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!(
+      "graphvizier-{}",
+      crate::entities::Id::random().as_str()
+    ));
+    fs::create_dir(&dir).unwrap();
+    let program = dir.join("fake-dot");
+    fs::write(
+      &program,
+      "#!/bin/sh\n\
+       [ \"$1\" = -Kneato ] || { echo \"bad engine $1\" >&2; exit 2; }\n\
+       echo \"$2\"\n\
+       cat\n",
+    )
+    .unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut options = RenderOptions {
+      program,
+      engine: Engine::Neato,
+      format: Format::Pdf,
+    };
+    let path = dir.join("g.pdf");
+    render_to_file(&dot(), &options, &path).unwrap();
+    assert_eq!(
+      fs::read_to_string(&path).unwrap(),
+      "-Tpdf\ndigraph g { a -> b; }"
+    );
+
+    options.engine = Engine::Dot;
+    match render(&dot(), &options) {
+      Err(RenderError::Failed { status, stderr }) => {
+        assert_eq!(status.code(), Some(2));
+        assert_eq!(stderr, "bad engine -Kdot\n");
+      },
+      r => panic!("{:?}", r),
+    }
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
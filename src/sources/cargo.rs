@@ -0,0 +1,192 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Draw the dependency graph described by `cargo metadata`.
+//!
+//! [`Metadata`] implements [`Graphable`]: each package is a vertex labelled
+//! with its name and version, with workspace members inside a `workspace`
+//! cluster. Each dependency is an edge from the dependent package, styled by
+//! its kind: solid for normal dependencies, dashed for dev-dependencies, and
+//! dotted for build-dependencies.
+//!
+//! Requires the `cargo-metadata` feature.
+//!
+//!```no_run
+//! use cargo_metadata::MetadataCommand;
+//! use graphvizier::{entities::Id, Graphable};
+//!
+//! let metadata = MetadataCommand::new().exec().unwrap();
+//! metadata.build_graph().build_to_file("deps.dot", Id::new("deps")).unwrap();
+//! ```
+
+use crate::{
+  entities::{style::Label, Edge, Entity, Id, Subgraph, Vertex},
+  generator::GraphBuilder,
+  Graphable,
+};
+
+use cargo_metadata::{DependencyKind, Metadata, Package, PackageId};
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+fn package_id(package: &Package) -> Id { Id::new(format!("{}@{}", package.name, package.version)) }
+
+fn package_vertex(package: &Package) -> Vertex {
+  Vertex::new(package_id(package)).label(format!(
+    "{}\\n{}",
+    Label::escape_text(package.name.as_str()),
+    package.version
+  ))
+}
+
+/// The edge drawn for a dependency of `kind`.
+fn dependency_edge(source: Id, target: Id, kind: DependencyKind) -> Edge {
+  let edge = Edge::between(source, target);
+  match kind {
+    DependencyKind::Development => edge.style("dashed"),
+    DependencyKind::Build => edge.style("dotted"),
+    _ => edge,
+  }
+}
+
+/// Each distinct dependency as `(dependent, dependency, kind)`, from the
+/// resolved graph if present, or else by matching names among
+/// [`Metadata::packages`] (as with `cargo metadata --no-deps`).
+fn dependencies(metadata: &Metadata) -> Vec<(&PackageId, &PackageId, DependencyKind)> {
+  let mut ret = Vec::new();
+  if let Some(resolve) = &metadata.resolve {
+    for node in &resolve.nodes {
+      for dep in &node.deps {
+        for info in &dep.dep_kinds {
+          ret.push((&node.id, &dep.pkg, info.kind));
+        }
+      }
+    }
+  } else {
+    let by_name: HashMap<&str, &PackageId> = metadata
+      .packages
+      .iter()
+      .map(|p| (p.name.as_str(), &p.id))
+      .collect();
+    for package in &metadata.packages {
+      for dep in &package.dependencies {
+        if let Some(id) = by_name.get(dep.name.as_str()) {
+          ret.push((&package.id, *id, dep.kind));
+        }
+      }
+    }
+  }
+  let mut seen = HashSet::new();
+  ret.retain(|dep| seen.insert(*dep));
+  ret
+}
+
+impl Graphable for Metadata {
+  fn build_graph(self) -> GraphBuilder {
+    let packages: HashMap<&PackageId, &Package> =
+      self.packages.iter().map(|p| (&p.id, p)).collect();
+    let members: BTreeSet<&PackageId> = self.workspace_members.iter().collect();
+
+    let mut gb = GraphBuilder::new();
+    /* Members depend on each other, so can't share a rank. */
+    let mut workspace = Subgraph::new("workspace")
+      .label("workspace")
+      .same_rank(false);
+    for package in &self.packages {
+      let vertex = Entity::Vertex(package_vertex(package));
+      if members.contains(&package.id) {
+        workspace = workspace.entity(vertex);
+      } else {
+        gb.accept_entity(vertex);
+      }
+    }
+    gb.accept_entity(Entity::Subgraph(workspace));
+    for (source, target, kind) in dependencies(&self) {
+      if let (Some(source), Some(target)) = (packages.get(source), packages.get(target)) {
+        gb.accept_entity(Entity::Edge(dependency_edge(
+          package_id(source),
+          package_id(target),
+          kind,
+        )));
+      }
+    }
+    gb
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use cargo_metadata::{CargoOpt, MetadataCommand};
+
+  #[test]
+  fn this_workspace() {
+    let metadata = MetadataCommand::new()
+      .manifest_path(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+      .features(CargoOpt::AllFeatures)
+      .exec()
+      .unwrap();
+    let version = env!("CARGO_PKG_VERSION");
+    let this = format!("graphvizier@{}", version);
+    let derive = format!("graphvizier-derive@{}", version);
+
+    let gb = metadata.build_graph();
+    let workspace = gb
+      .entities()
+      .find_map(|e| match e {
+        Entity::Subgraph(sg) => Some(sg),
+        _ => None,
+      })
+      .unwrap();
+    let mut members: Vec<&str> = workspace
+      .entities
+      .iter()
+      .filter_map(|e| match e {
+        Entity::Vertex(v) => Some(v.id.as_str()),
+        _ => None,
+      })
+      .collect();
+    members.sort_unstable();
+    assert_eq!(members, vec![derive.as_str(), this.as_str()]);
+    assert!(gb
+      .edges()
+      .any(|e| e.source.as_str() == this && e.target.as_str() == derive && e.style.is_none()));
+    assert!(gb
+      .edges()
+      .any(|e| e.source.as_str() == derive && e.target.as_str().starts_with("syn@")));
+    let dot = gb.build(Id::new("deps")).unwrap().0;
+    assert!(!dot.contains("rank = same"), "{}", dot);
+  }
+
+  #[test]
+  fn dependency_styles() {
+    let style = |kind| {
+      dependency_edge("a".into(), "b".into(), kind)
+        .style
+        .map(|s| s.0)
+    };
+    assert_eq!(style(DependencyKind::Normal), None);
+    assert_eq!(
+      style(DependencyKind::Development).as_deref(),
+      Some("dashed")
+    );
+    assert_eq!(style(DependencyKind::Build).as_deref(), Some("dotted"));
+  }
+}
@@ -0,0 +1,330 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Draw a directory tree, with a cluster for each directory.
+//!
+//! [`walk`] reads the tree into a [`DirTree`], which implements
+//! [`Graphable`]. Each directory becomes a cluster containing a `folder`
+//! vertex for itself, a `note` vertex for each file, and a nested cluster for
+//! each subdirectory. Ids are paths relative to the root, with `/` separators.
+//!
+//!```
+//! use graphvizier::{
+//!   entities::Id,
+//!   sources::fs::{walk, WalkOptions},
+//!   Graphable,
+//! };
+//!
+//! let tree = walk(".", &WalkOptions::new().max_depth(2).ignore("target").ignore("*.lock"))
+//!   .unwrap();
+//! let dot = tree.build_graph().build(Id::new("files")).unwrap().0;
+//! assert!(dot.contains("\"src/lib.rs\"[label=\"lib.rs\""));
+//! ```
+
+use crate::{
+  entities::{style::Label, Entity, Id, Subgraph, Vertex},
+  generator::GraphBuilder,
+  Graphable,
+};
+
+use regex::Regex;
+
+use std::{fs, io, path::Path};
+
+/// Limits on how much of a directory tree [`walk`] reads.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+  max_depth: Option<usize>,
+  max_entries: Option<usize>,
+  /* Each glob, and whether it contains a `/` and so matches whole paths. */
+  ignore: Vec<(Regex, bool)>,
+}
+
+impl WalkOptions {
+  /// Read the whole tree.
+  pub fn new() -> Self { Self::default() }
+
+  /// Stop descending `depth` directories below the root. A depth of 0 reads
+  /// only the root's own entries.
+  pub fn max_depth(mut self, depth: usize) -> Self {
+    self.max_depth = Some(depth);
+    self
+  }
+
+  /// Stop after reading `count` files and directories in total.
+  pub fn max_entries(mut self, count: usize) -> Self {
+    self.max_entries = Some(count);
+    self
+  }
+
+  /// Skip files and directories matching `glob`. `*` and `?` match within a
+  /// single path component, and `**` matches across components. A glob
+  /// without a `/` is matched against each entry's name, and otherwise
+  /// against its path relative to the root.
+  pub fn ignore(mut self, glob: &str) -> Self {
+    self.ignore.push((glob_regex(glob), glob.contains('/')));
+    self
+  }
+
+  fn ignored(&self, name: &str, path: &str) -> bool {
+    self
+      .ignore
+      .iter()
+      .any(|(re, whole_path)| re.is_match(if *whole_path { path } else { name }))
+  }
+}
+
+fn glob_regex(glob: &str) -> Regex {
+  let mut re = String::from("^");
+  let mut chars = glob.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '*' if chars.peek() == Some(&'*') => {
+        chars.next();
+        re.push_str(".*");
+      },
+      '*' => re.push_str("[^/]*"),
+      '?' => re.push_str("[^/]"),
+      c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+    }
+  }
+  re.push('$');
+  Regex::new(&re).expect("every glob character is escaped")
+}
+
+/// A file or directory read by [`walk`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+  #[allow(missing_docs)]
+  Dir(DirTree),
+  /// Anything other than a directory, including symlinks, which are not
+  /// followed.
+  File {
+    #[allow(missing_docs)]
+    name: String,
+    /// Relative to the root.
+    path: String,
+    /// In bytes.
+    size: u64,
+  },
+}
+
+/// A directory and the entries read from it, sorted by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirTree {
+  #[allow(missing_docs)]
+  pub name: String,
+  /// Relative to the root, or `.` for the root itself.
+  pub path: String,
+  #[allow(missing_docs)]
+  pub entries: Vec<Node>,
+  /// Whether some entries were left out by [`WalkOptions::max_depth`] or
+  /// [`WalkOptions::max_entries`].
+  pub truncated: bool,
+}
+
+/// Read the directory tree under `root`, within the limits of `options`.
+pub fn walk(root: impl AsRef<Path>, options: &WalkOptions) -> io::Result<DirTree> {
+  let root = root.as_ref();
+  let name = root.canonicalize()?.file_name().map_or_else(
+    || root.display().to_string(),
+    |n| n.to_string_lossy().into_owned(),
+  );
+  let mut remaining = options.max_entries.unwrap_or(usize::MAX);
+  read_dir(root, name, ".".to_string(), 0, options, &mut remaining)
+}
+
+fn read_dir(
+  dir: &Path,
+  name: String,
+  path: String,
+  depth: usize,
+  options: &WalkOptions,
+  remaining: &mut usize,
+) -> io::Result<DirTree> {
+  let mut tree = DirTree {
+    name,
+    path,
+    entries: Vec::new(),
+    truncated: false,
+  };
+  let mut children: Vec<fs::DirEntry> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+  children.sort_by_key(fs::DirEntry::file_name);
+  for child in children {
+    let name = child.file_name().to_string_lossy().into_owned();
+    let path = if tree.path == "." {
+      name.clone()
+    } else {
+      format!("{}/{}", tree.path, name)
+    };
+    if options.ignored(&name, &path) {
+      continue;
+    }
+    if *remaining == 0 {
+      tree.truncated = true;
+      break;
+    }
+    *remaining -= 1;
+    let metadata = fs::symlink_metadata(child.path())?;
+    if metadata.is_dir() {
+      if options.max_depth.map_or(false, |max| depth >= max) {
+        tree.entries.push(Node::Dir(DirTree {
+          name,
+          path,
+          entries: Vec::new(),
+          truncated: true,
+        }));
+      } else {
+        let subtree = read_dir(&child.path(), name, path, depth + 1, options, remaining)?;
+        tree.entries.push(Node::Dir(subtree));
+      }
+    } else {
+      tree.entries.push(Node::File {
+        name,
+        path,
+        size: metadata.len(),
+      });
+    }
+  }
+  Ok(tree)
+}
+
+impl DirTree {
+  fn subgraph(self) -> Subgraph {
+    let Self {
+      name,
+      path,
+      entries,
+      truncated,
+    } = self;
+    let mut sg = Subgraph::new(path.clone()).entity(Entity::Vertex(
+      Vertex::new(path.clone())
+        .label(Label::escape_text(&name))
+        .shape("folder"),
+    ));
+    for entry in entries {
+      sg = sg.entity(match entry {
+        Node::Dir(tree) => Entity::Subgraph(tree.subgraph()),
+        Node::File { name, path, size } => Entity::Vertex(
+          Vertex::new(path)
+            .label(Label::escape_text(&name))
+            .shape("note")
+            .attr("tooltip", format!("{} bytes", size)),
+        ),
+      });
+    }
+    if truncated {
+      sg = sg.entity(Entity::Vertex(
+        Vertex::new(Id::new(format!("{}/...", path)))
+          .label("...")
+          .shape("plaintext"),
+      ));
+    }
+    sg
+  }
+}
+
+impl Graphable for DirTree {
+  fn build_graph(self) -> GraphBuilder {
+    let mut gb = GraphBuilder::new();
+    gb.accept_entity(Entity::Subgraph(self.subgraph()));
+    gb
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  struct TempDir(std::path::PathBuf);
+
+  impl TempDir {
+    fn new(files: &[&str]) -> Self {
+      let root = std::env::temp_dir().join(format!("graphvizier-{}", Id::random().as_str()));
+      for file in files {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, file).unwrap();
+      }
+      Self(root)
+    }
+  }
+
+  impl Drop for TempDir {
+    fn drop(&mut self) { let _ = fs::remove_dir_all(&self.0); }
+  }
+
+  fn ids(gb: &GraphBuilder) -> Vec<&str> { gb.vertices().map(|v| v.id.as_str()).collect() }
+
+  #[test]
+  fn walk_tree() {
+    let dir = TempDir::new(&[
+      "b.txt",
+      "a/x.rs",
+      "a/b/y.rs",
+      "a/target/z",
+      "a/Cargo.lock",
+      "c.lock",
+    ]);
+    let tree = walk(
+      &dir.0,
+      &WalkOptions::new().ignore("*.lock").ignore("a/target"),
+    )
+    .unwrap();
+    let gb = tree.build_graph();
+    assert_eq!(ids(&gb), vec![
+      ".", "a", "a/b", "a/b/y.rs", "a/x.rs", "b.txt"
+    ]);
+    let root = match gb.entities().next().unwrap() {
+      Entity::Subgraph(sg) => sg,
+      e => panic!("{:?}", e),
+    };
+    assert_eq!(root.id.as_str(), ".");
+    assert!(root
+      .entities
+      .iter()
+      .any(|e| matches!(e, Entity::Subgraph(sg) if sg.id.as_str() == "a")));
+
+    let dot = gb.build(Id::new("files")).unwrap();
+    assert!(crate::parser::parse(&dot.0).is_ok());
+  }
+
+  #[test]
+  fn limits() {
+    let dir = TempDir::new(&["a/b/c/d", "e", "f"]);
+    let tree = walk(&dir.0, &WalkOptions::new().max_depth(1)).unwrap();
+    assert_eq!(ids(&tree.build_graph()), vec![
+      ".", "a", "a/b", "a/b/...", "e", "f"
+    ]);
+
+    let tree = walk(&dir.0, &WalkOptions::new().max_entries(2)).unwrap();
+    assert_eq!(ids(&tree.build_graph()), vec![
+      ".", "a", "a/b", "a/b/...", "./..."
+    ]);
+  }
+
+  #[test]
+  fn globs() {
+    assert!(glob_regex("*.rs").is_match("lib.rs"));
+    assert!(!glob_regex("*.rs").is_match("src/lib.rs"));
+    assert!(glob_regex("src/**.rs").is_match("src/a/lib.rs"));
+    assert!(glob_regex("?.t+t").is_match("a.t+t"));
+    assert!(!glob_regex("?.t+t").is_match("a.ttt"));
+  }
+}
@@ -0,0 +1,265 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Draw the commit graph of a git repository with
+//! [gix](https://docs.rs/gix).
+//!
+//! [`commit_graph`] walks a range of commits and draws each as a vertex
+//! labelled with its short hash and summary, with an edge to each of its
+//! parents within the range. Edges to the second and later parents of a merge
+//! are dashed, and commits at the tip of a local branch are filled and
+//! labelled with the branch names.
+//!
+//! Requires the `gix` feature.
+//!
+//!```no_run
+//! use graphvizier::{entities::Id, sources::git::commit_graph};
+//!
+//! let repo = gix::discover(".").unwrap();
+//! let gb = commit_graph(&repo, "main..HEAD").unwrap();
+//! gb.build_to_file("branch.dot", Id::new("branch")).unwrap();
+//! ```
+
+use crate::{
+  entities::{style::Label, Edge, Entity, Id, Vertex},
+  generator::GraphBuilder,
+};
+
+use gix::{ObjectId, Repository};
+
+use std::{
+  collections::{HashMap, HashSet},
+  fmt,
+};
+
+/// Why [`commit_graph`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitError {
+  /// A revision in the range could not be resolved to a commit.
+  Revision(String),
+  /// The repository could not be read.
+  Repository(String),
+}
+
+impl fmt::Display for GitError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Revision(message) => write!(f, "invalid revision: {}", message),
+      Self::Repository(message) => write!(f, "failed to read repository: {}", message),
+    }
+  }
+}
+
+impl std::error::Error for GitError {}
+
+fn repository_error(e: impl fmt::Display) -> GitError { GitError::Repository(e.to_string()) }
+
+fn resolve(repo: &Repository, spec: &str) -> Result<ObjectId, GitError> {
+  repo
+    .rev_parse_single(spec)
+    .map_err(|e| GitError::Revision(format!("{}: {}", spec, e)))?
+    .object()
+    .map_err(repository_error)?
+    .peel_to_kind(gix::object::Kind::Commit)
+    .map(|c| c.id)
+    .map_err(|e| GitError::Revision(format!("{}: {}", spec, e)))
+}
+
+/// Local branch names by the commit at their tip.
+fn branch_heads(repo: &Repository) -> Result<HashMap<ObjectId, Vec<String>>, GitError> {
+  let mut heads: HashMap<ObjectId, Vec<String>> = HashMap::new();
+  let references = repo.references().map_err(repository_error)?;
+  for reference in references.local_branches().map_err(repository_error)? {
+    let mut reference = reference.map_err(repository_error)?;
+    let name = reference.name().shorten().to_string();
+    let id = reference.peel_to_id_in_place().map_err(repository_error)?.detach();
+    heads.entry(id).or_default().push(name);
+  }
+  Ok(heads)
+}
+
+/// Draw the commits in `range`, which is either a single revision such as
+/// `HEAD`, meaning it and all of its ancestors, or `base..tip`, meaning the
+/// ancestors of `tip` which are not ancestors of `base`.
+///
+/// Vertex ids are full commit hashes, and commits are added newest first.
+pub fn commit_graph(repo: &Repository, range: &str) -> Result<GraphBuilder, GitError> {
+  let (hidden, tip) = match range.split_once("..") {
+    Some((base, tip)) => (Some(resolve(repo, base)?), resolve(repo, tip)?),
+    None => (None, resolve(repo, range)?),
+  };
+  let heads = branch_heads(repo)?;
+
+  /* Everything reachable from `base` is excluded, along with its ancestry. */
+  let mut hidden_ids = HashSet::new();
+  if let Some(base) = hidden {
+    for info in repo.rev_walk([base]).all().map_err(repository_error)? {
+      hidden_ids.insert(info.map_err(repository_error)?.id);
+    }
+  }
+
+  let mut commits = Vec::new();
+  for info in repo
+    .rev_walk([tip])
+    .selected(move |id| !hidden_ids.contains(id))
+    .map_err(repository_error)?
+  {
+    let info = info.map_err(repository_error)?;
+    commits.push((info.id, info.parent_ids.to_vec()));
+  }
+  let walked: HashSet<ObjectId> = commits.iter().map(|(id, _)| *id).collect();
+
+  let mut gb = GraphBuilder::new();
+  for (id, parents) in commits {
+    let commit = repo
+      .find_object(id)
+      .map_err(repository_error)?
+      .try_into_commit()
+      .map_err(repository_error)?;
+    let message = commit.message().map_err(repository_error)?;
+    let mut label = format!(
+      "{}\\n{}",
+      id.to_hex_with_len(7),
+      Label::escape_text(message.summary().to_string())
+    );
+    let mut vertex = Vertex::new(Id::new(id.to_string())).shape("box");
+    if let Some(names) = heads.get(&id) {
+      label = format!("[{}]\\n{}", Label::escape_text(names.join(", ")), label);
+      vertex = vertex.style("filled").attr("fillcolor", "lightblue");
+    }
+    gb.accept_entity(Entity::Vertex(vertex.label(label)));
+    for (i, parent) in parents.into_iter().enumerate() {
+      if !walked.contains(&parent) {
+        continue;
+      }
+      let edge = Edge::between(Id::new(id.to_string()), Id::new(parent.to_string()));
+      gb.accept_entity(Entity::Edge(if i == 0 {
+        edge
+      } else {
+        edge.style("dashed")
+      }));
+    }
+  }
+  Ok(gb)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::{path::Path, process::Command};
+
+  fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+      .current_dir(dir)
+      .args([
+        "-c",
+        "user.name=t",
+        "-c",
+        "user.email=t@t",
+        "-c",
+        "init.defaultBranch=main",
+      ])
+      .args(args)
+      .output()
+      .unwrap();
+    assert!(
+      output.status.success(),
+      "{}",
+      String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+  }
+
+  #[test]
+  fn merge_topology() {
+    let dir = std::env::temp_dir().join(format!("graphvizier-{}", Id::random().as_str()));
+    std::fs::create_dir_all(&dir).unwrap();
+    git(&dir, &["init", "-q"]);
+    git(&dir, &["commit", "-q", "--allow-empty", "-m", "first"]);
+    let first = git(&dir, &["rev-parse", "HEAD"]);
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    git(&dir, &[
+      "commit",
+      "-q",
+      "--allow-empty",
+      "-m",
+      "on \"feature\"",
+    ]);
+    let feature = git(&dir, &["rev-parse", "HEAD"]);
+    git(&dir, &["checkout", "-q", "main"]);
+    git(&dir, &[
+      "commit",
+      "-q",
+      "--allow-empty",
+      "-m",
+      "second\n\nbody",
+    ]);
+    let second = git(&dir, &["rev-parse", "HEAD"]);
+    git(&dir, &["merge", "-q", "--no-ff", "-m", "merge", "feature"]);
+    let merge = git(&dir, &["rev-parse", "HEAD"]);
+
+    let repo = gix::open(&dir).unwrap();
+    let gb = commit_graph(&repo, "HEAD").unwrap();
+    let ids: Vec<&str> = gb.vertices().map(|v| v.id.as_str()).collect();
+    assert_eq!(ids.len(), 4);
+    assert_eq!(ids[0], merge);
+    let labels: HashMap<&str, &str> = gb
+      .vertices()
+      .map(|v| (v.id.as_str(), v.label.as_ref().unwrap().0.as_str()))
+      .collect();
+    assert_eq!(
+      labels[merge.as_str()],
+      format!("[main]\\n{}\\nmerge", &merge[..7])
+    );
+    assert_eq!(
+      labels[feature.as_str()],
+      format!("[feature]\\n{}\\non \\\"feature\\\"", &feature[..7])
+    );
+    assert_eq!(
+      labels[second.as_str()],
+      format!("{}\\nsecond", &second[..7])
+    );
+
+    let mut edges: Vec<(&str, &str, bool)> = gb
+      .edges()
+      .map(|e| (e.source.as_str(), e.target.as_str(), e.style.is_some()))
+      .collect();
+    edges.sort_unstable();
+    let mut expected = vec![
+      (merge.as_str(), second.as_str(), false),
+      (merge.as_str(), feature.as_str(), true),
+      (second.as_str(), first.as_str(), false),
+      (feature.as_str(), first.as_str(), false),
+    ];
+    expected.sort_unstable();
+    assert_eq!(edges, expected);
+
+    let gb = commit_graph(&repo, "feature..main").unwrap();
+    let ids: HashSet<&str> = gb.vertices().map(|v| v.id.as_str()).collect();
+    assert_eq!(ids, [merge.as_str(), second.as_str()].into_iter().collect());
+    assert_eq!(gb.edges().count(), 1);
+
+    assert!(matches!(
+      commit_graph(&repo, "nope"),
+      Err(GitError::Revision(_))
+    ));
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
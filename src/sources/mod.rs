@@ -0,0 +1,30 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Build graphs from data outside this crate.
+
+#[cfg(feature = "cargo-metadata")]
+pub mod cargo;
+pub mod fs;
+#[cfg(feature = "gix")]
+pub mod git;
+#[cfg(feature = "syn")]
+pub mod rust;
+#[cfg(feature = "tracing")]
+pub mod tracing;
@@ -0,0 +1,395 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Draw the modules of Rust crates and the `use` declarations between them,
+//! parsed with [syn](https://docs.rs/syn).
+//!
+//! Starting from each crate root, [`ModuleGraph::add_crate`] follows
+//! `mod name;` declarations to `name.rs` or `name/mod.rs` (or a `#[path]`
+//! attribute) and descends into inline `mod name { ... }` blocks. Modules
+//! marked `#[cfg(test)]` are skipped.
+//!
+//! Each crate is drawn as a cluster, and each module with submodules as a
+//! nested cluster, with a vertex for the module itself. Vertex ids are module
+//! paths such as `my_crate::a::b`. Each `use` of a path within one of the
+//! crates becomes an edge to the innermost module it names, so `use
+//! crate::a::Thing;` in `my_crate::b` draws `my_crate::b -> my_crate::a`.
+//!
+//! Requires the `syn` feature.
+//!
+//!```
+//! use graphvizier::{sources::rust::ModuleGraph, Graphable};
+//!
+//! let mut modules = ModuleGraph::new();
+//! modules.add_crate("graphvizier", "src/lib.rs").unwrap();
+//! let gb = modules.build_graph();
+//! assert!(gb
+//!   .edges()
+//!   .any(|e| e.source.as_str() == "graphvizier::sources::fs" && e.target.as_str() == "graphvizier"));
+//! ```
+
+use crate::{
+  entities::{style::Label, Edge, Entity, Id, Subgraph, Vertex},
+  generator::GraphBuilder,
+  Graphable,
+};
+
+use std::{
+  collections::HashSet,
+  fmt, fs, io,
+  path::{Path, PathBuf},
+};
+
+/// Why a crate could not be read by [`ModuleGraph::add_crate`].
+#[derive(Debug)]
+pub enum ModuleError {
+  /// A source file could not be read.
+  Io(PathBuf, io::Error),
+  /// A source file is not valid Rust.
+  Parse(PathBuf, syn::Error),
+}
+
+impl fmt::Display for ModuleError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+      Self::Parse(path, e) => {
+        let start = e.span().start();
+        write!(
+          f,
+          "{}:{}:{}: {}",
+          path.display(),
+          start.line,
+          start.column + 1,
+          e
+        )
+      },
+    }
+  }
+}
+
+impl std::error::Error for ModuleError {
+  fn source(&self) -> Option<&(dyn std::error::Error+'static)> {
+    match self {
+      Self::Io(_, e) => Some(e),
+      Self::Parse(_, e) => Some(e),
+    }
+  }
+}
+
+/// A module and the paths it imports.
+#[derive(Debug)]
+struct Module {
+  /// Crate name, then each module name.
+  path: Vec<String>,
+  uses: Vec<Vec<String>>,
+  children: Vec<Module>,
+}
+
+impl Module {
+  fn id(&self) -> Id { Id::new(self.path.join("::")) }
+
+  fn find(&self, path: &[String]) -> Option<&Module> {
+    match path.split_first() {
+      None => Some(self),
+      Some((name, rest)) => self
+        .children
+        .iter()
+        .find(|c| c.path.last() == Some(name))
+        .and_then(|c| c.find(rest)),
+    }
+  }
+
+  fn entity(&self) -> Entity {
+    let vertex = Vertex::new(self.id()).label(Label::escape_text(self.path.last().unwrap()));
+    if self.children.is_empty() && self.path.len() > 1 {
+      return Entity::Vertex(vertex);
+    }
+    /* `use` edges run between the members, so they can't share a rank. */
+    let mut sg = Subgraph::new(self.id())
+      .label(Label::escape_text(self.path.last().unwrap()))
+      .same_rank(false)
+      .entity(Entity::Vertex(vertex.shape("box")));
+    for child in &self.children {
+      sg = sg.entity(child.entity());
+    }
+    Entity::Subgraph(sg)
+  }
+}
+
+/// The modules of one or more crates. See the [module docs](self).
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+  crates: Vec<Module>,
+}
+
+fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+  attrs.iter().any(|attr| {
+    attr.path().is_ident("cfg")
+      && matches!(attr.parse_args::<syn::Ident>(), Ok(ident) if ident == "test")
+  })
+}
+
+fn path_attr(attrs: &[syn::Attribute]) -> Option<String> {
+  attrs.iter().find_map(|attr| match &attr.meta {
+    syn::Meta::NameValue(nv) if nv.path.is_ident("path") => match &nv.value {
+      syn::Expr::Lit(syn::ExprLit {
+        lit: syn::Lit::Str(s),
+        ..
+      }) => Some(s.value()),
+      _ => None,
+    },
+    _ => None,
+  })
+}
+
+/// Every path imported by `tree`, with `prefix` prepended. Globs import
+/// their parent path, and `self` within a group imports the group's path.
+fn use_paths(tree: &syn::UseTree, prefix: &mut Vec<String>, out: &mut Vec<Vec<String>>) {
+  match tree {
+    syn::UseTree::Path(p) => {
+      prefix.push(p.ident.to_string());
+      use_paths(&p.tree, prefix, out);
+      prefix.pop();
+    },
+    syn::UseTree::Name(n) if n.ident == "self" => out.push(prefix.clone()),
+    syn::UseTree::Name(syn::UseName { ident })
+    | syn::UseTree::Rename(syn::UseRename { ident, .. }) => {
+      let mut path = prefix.clone();
+      path.push(ident.to_string());
+      out.push(path);
+    },
+    syn::UseTree::Glob(_) => out.push(prefix.clone()),
+    syn::UseTree::Group(g) => {
+      for tree in &g.items {
+        use_paths(tree, prefix, out);
+      }
+    },
+  }
+}
+
+impl ModuleGraph {
+  /// No crates.
+  pub fn new() -> Self { Self::default() }
+
+  /// Read the crate named `name` from its root file, such as `src/lib.rs`.
+  pub fn add_crate(&mut self, name: &str, root: impl AsRef<Path>) -> Result<(), ModuleError> {
+    let root = root.as_ref();
+    let dir = root.parent().unwrap_or(Path::new("")).to_path_buf();
+    let module = Self::read_file(vec![name.replace('-', "_")], root, dir)?;
+    self.crates.push(module);
+    Ok(())
+  }
+
+  /// Read the module at `path` from `file`, whose submodule files are
+  /// within `dir`.
+  fn read_file(path: Vec<String>, file: &Path, dir: PathBuf) -> Result<Module, ModuleError> {
+    let text = fs::read_to_string(file).map_err(|e| ModuleError::Io(file.to_path_buf(), e))?;
+    let ast = syn::parse_file(&text).map_err(|e| ModuleError::Parse(file.to_path_buf(), e))?;
+    let mut module = Module {
+      path,
+      uses: Vec::new(),
+      children: Vec::new(),
+    };
+    Self::read_items(&mut module, &ast.items, file, &dir)?;
+    Ok(module)
+  }
+
+  fn read_items(
+    module: &mut Module,
+    items: &[syn::Item],
+    file: &Path,
+    dir: &Path,
+  ) -> Result<(), ModuleError> {
+    for item in items {
+      match item {
+        syn::Item::Use(u) => use_paths(&u.tree, &mut Vec::new(), &mut module.uses),
+        syn::Item::Mod(m) if !is_cfg_test(&m.attrs) => {
+          let name = m.ident.to_string();
+          let mut path = module.path.clone();
+          path.push(name.clone());
+          let child = if let Some((_, items)) = &m.content {
+            let mut child = Module {
+              path,
+              uses: Vec::new(),
+              children: Vec::new(),
+            };
+            Self::read_items(&mut child, items, file, &dir.join(&name))?;
+            child
+          } else if let Some(p) = path_attr(&m.attrs) {
+            let file = file.parent().unwrap_or(Path::new("")).join(p);
+            let dir = file.with_extension("");
+            Self::read_file(path, &file, dir)?
+          } else {
+            let flat = dir.join(format!("{}.rs", name));
+            let file = if flat.exists() {
+              flat
+            } else {
+              dir.join(&name).join("mod.rs")
+            };
+            Self::read_file(path, &file, dir.join(&name))?
+          };
+          module.children.push(child);
+        },
+        _ => (),
+      }
+    }
+    Ok(())
+  }
+
+  /// The innermost known module named by `path` when used from `from`.
+  fn resolve(&self, from: &Module, path: &[String]) -> Option<&Module> {
+    let krate = self.crates.iter().find(|c| c.path[0] == from.path[0])?;
+    let (base, rest): (Vec<String>, &[String]) = match path.first().map(String::as_str) {
+      Some("crate") => (vec![from.path[0].clone()], &path[1..]),
+      Some("self") => (from.path.clone(), &path[1..]),
+      Some("super") => {
+        let supers = path.iter().take_while(|s| *s == "super").count();
+        let base = from.path[..from.path.len().checked_sub(supers)?].to_vec();
+        (base, &path[supers..])
+      },
+      Some(name) if self.crates.iter().any(|c| c.path[0] == name) => {
+        (vec![name.to_string()], &path[1..])
+      },
+      Some(name)
+        if from
+          .children
+          .iter()
+          .any(|c| c.path.last().map(String::as_str) == Some(name)) =>
+      {
+        (from.path.clone(), path)
+      },
+      _ => return None,
+    };
+    if base.is_empty() {
+      return None;
+    }
+    let target = self
+      .crates
+      .iter()
+      .find(|c| c.path[0] == base[0])
+      .unwrap_or(krate);
+    let mut module = target.find(&base[1..])?;
+    for name in rest {
+      match module.find(std::slice::from_ref(name)) {
+        Some(child) => module = child,
+        None => break,
+      }
+    }
+    Some(module)
+  }
+
+  fn edges(&self, module: &Module, seen: &mut HashSet<(Id, Id)>, out: &mut Vec<Edge>) {
+    for path in &module.uses {
+      if let Some(target) = self.resolve(module, path) {
+        if target.path != module.path && seen.insert((module.id(), target.id())) {
+          out.push(Edge::between(module.id(), target.id()));
+        }
+      }
+    }
+    for child in &module.children {
+      self.edges(child, seen, out);
+    }
+  }
+}
+
+impl Graphable for ModuleGraph {
+  fn build_graph(self) -> GraphBuilder {
+    let mut gb = GraphBuilder::new();
+    let mut edges = Vec::new();
+    let mut seen = HashSet::new();
+    for krate in &self.crates {
+      gb.accept_entity(krate.entity());
+      self.edges(krate, &mut seen, &mut edges);
+    }
+    for edge in edges {
+      gb.accept_entity(Entity::Edge(edge));
+    }
+    gb
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn write(root: &Path, files: &[(&str, &str)]) {
+    for (path, text) in files {
+      let path = root.join(path);
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, text).unwrap();
+    }
+  }
+
+  #[test]
+  fn two_crates() {
+    let root = std::env::temp_dir().join(format!("graphvizier-{}", Id::random().as_str()));
+    write(&root, &[
+      (
+        "a/src/lib.rs",
+        "mod x; pub mod y { pub mod z { pub struct T; } } use crate::y::z::T; #[cfg(test)] mod test;",
+      ),
+      ("a/src/x.rs", "use super::y::{self, z}; use std::fmt; mod inner;"),
+      ("a/src/x/inner.rs", "use crate::y::*; use b::util::f as g;"),
+      ("b/src/lib.rs", "pub mod util; #[path = \"helpers.rs\"] mod h;"),
+      ("b/src/util.rs", "pub fn f() {}"),
+      ("b/src/helpers.rs", "use util::f;"),
+    ]);
+
+    let mut modules = ModuleGraph::new();
+    modules.add_crate("a", root.join("a/src/lib.rs")).unwrap();
+    modules.add_crate("b", root.join("b/src/lib.rs")).unwrap();
+    let gb = modules.build_graph();
+    let ids: Vec<&str> = gb.vertices().map(|v| v.id.as_str()).collect();
+    assert_eq!(ids, vec![
+      "a",
+      "a::x",
+      "a::x::inner",
+      "a::y",
+      "a::y::z",
+      "b",
+      "b::util",
+      "b::h"
+    ]);
+    let edges: Vec<(&str, &str)> = gb
+      .edges()
+      .map(|e| (e.source.as_str(), e.target.as_str()))
+      .collect();
+    assert_eq!(edges, vec![
+      ("a", "a::y::z"),
+      ("a::x", "a::y"),
+      ("a::x", "a::y::z"),
+      ("a::x::inner", "a::y"),
+      ("a::x::inner", "b::util"),
+    ]);
+    assert!(gb.subgraphs().all(|sg| !sg.same_rank));
+
+    let mut modules = ModuleGraph::new();
+    let err = modules
+      .add_crate("c", root.join("c/src/lib.rs"))
+      .unwrap_err();
+    assert!(matches!(err, ModuleError::Io(..)));
+    write(&root, &[("c/src/lib.rs", "fn (")]);
+    let err = modules
+      .add_crate("c", root.join("c/src/lib.rs"))
+      .unwrap_err();
+    assert!(err.to_string().contains("lib.rs:1:"), "{}", err);
+    fs::remove_dir_all(&root).unwrap();
+  }
+}
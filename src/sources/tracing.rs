@@ -0,0 +1,291 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Record the tree of [tracing](https://docs.rs/tracing) spans, and draw it.
+//!
+//! Add a [`SpanTreeLayer`] to a
+//! [`Registry`](tracing_subscriber::Registry), run the code to inspect, then
+//! call [`SpanTreeLayer::flush`] on a clone of the layer. Each span is drawn
+//! as a vertex labelled with its name, the time spent inside it, and the time
+//! from its creation to its close, with an edge from its parent. Edges to
+//! longer-lived spans are drawn thicker. Spans still open at the flush are
+//! dashed.
+//!
+//! Requires the `tracing` feature.
+//!
+//!```
+//! use graphvizier::sources::tracing::SpanTreeLayer;
+//! use tracing_subscriber::layer::SubscriberExt;
+//!
+//! let layer = SpanTreeLayer::new();
+//! let subscriber = tracing_subscriber::registry().with(layer.clone());
+//! tracing::subscriber::with_default(subscriber, || {
+//!   let _request = tracing::info_span!("request").entered();
+//!   tracing::info_span!("query").in_scope(|| ());
+//! });
+//! let gb = layer.flush();
+//! assert_eq!(gb.vertices().count(), 2);
+//! assert_eq!(gb.edges().count(), 1);
+//! ```
+
+use crate::{
+  entities::{style::Label, Edge, Entity, Id, Vertex},
+  generator::GraphBuilder,
+};
+
+use ::tracing_core::{span, Subscriber};
+use ::tracing_subscriber::{
+  layer::Context,
+  registry::{LookupSpan, SpanRef},
+  Layer,
+};
+
+use std::{
+  collections::{BTreeMap, HashMap},
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+/// What is known about a span so far.
+#[derive(Debug)]
+struct Record {
+  name: &'static str,
+  parent: Option<u64>,
+  created: Instant,
+  closed: Option<Instant>,
+  entered: Option<Instant>,
+  busy: Duration,
+}
+
+impl Record {
+  fn lifetime(&self, now: Instant) -> Duration { self.closed.unwrap_or(now) - self.created }
+}
+
+/// Each layer's key for a span, stored in the span's extensions. Several
+/// layers may record the same span, so keys are indexed by the address of the
+/// records they belong to. Unlike [`span::Id`]s, keys are never reused.
+#[derive(Debug, Default)]
+struct Keys(HashMap<usize, u64>);
+
+#[derive(Debug, Default)]
+struct Spans {
+  next: u64,
+  records: BTreeMap<u64, Record>,
+}
+
+/// A [`Layer`] which records the tree of spans. Clones share their records.
+#[derive(Debug, Clone, Default)]
+pub struct SpanTreeLayer {
+  spans: Arc<Mutex<Spans>>,
+}
+
+impl SpanTreeLayer {
+  /// A layer which has recorded nothing yet.
+  pub fn new() -> Self { Self::default() }
+
+  /// Draw every span recorded since the last flush, and forget those which
+  /// have closed.
+  pub fn flush(&self) -> GraphBuilder {
+    let mut spans = self.spans.lock().unwrap();
+    let now = Instant::now();
+    let longest = spans
+      .records
+      .values()
+      .map(|r| r.lifetime(now))
+      .max()
+      .unwrap_or_default();
+
+    let mut gb = GraphBuilder::new();
+    let id = |key: u64| Id::new(format!("s{}", key));
+    for (key, record) in &spans.records {
+      let lifetime = record.lifetime(now);
+      let mut vertex = Vertex::new(id(*key)).shape("box").label(format!(
+        "{}\\nbusy {:.2?}\\ntotal {:.2?}",
+        Label::escape_text(record.name),
+        record.busy,
+        lifetime,
+      ));
+      if record.closed.is_none() {
+        vertex = vertex.style("dashed");
+      }
+      gb.accept_entity(Entity::Vertex(vertex));
+      if let Some(parent) = record.parent.filter(|p| spans.records.contains_key(p)) {
+        let ratio = if longest.is_zero() {
+          0.0
+        } else {
+          lifetime.as_secs_f64() / longest.as_secs_f64()
+        };
+        gb.accept_entity(Entity::Edge(
+          Edge::between(id(parent), id(*key)).attr("penwidth", format!("{:.2}", 1.0 + 4.0 * ratio)),
+        ));
+      }
+    }
+    spans.records.retain(|_, r| r.closed.is_none());
+    gb
+  }
+
+  /// Identifies the records shared by this layer and its clones.
+  fn owner(&self) -> usize { Arc::as_ptr(&self.spans) as usize }
+
+  fn key<S>(&self, span: &SpanRef<'_, S>) -> Option<u64>
+  where S: Subscriber+for<'a> LookupSpan<'a> {
+    span
+      .extensions()
+      .get::<Keys>()
+      .and_then(|keys| keys.0.get(&self.owner()).copied())
+  }
+
+  fn with_record<S>(&self, id: &span::Id, ctx: &Context<'_, S>, f: impl FnOnce(&mut Record))
+  where S: Subscriber+for<'a> LookupSpan<'a> {
+    let Some(key) = ctx.span(id).and_then(|span| self.key(&span)) else {
+      return;
+    };
+    if let Some(record) = self.spans.lock().unwrap().records.get_mut(&key) {
+      f(record);
+    }
+  }
+}
+
+impl<S> Layer<S> for SpanTreeLayer
+where S: Subscriber+for<'a> LookupSpan<'a>
+{
+  fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+    let Some(span) = ctx.span(id) else {
+      return;
+    };
+    /* A clone of this layer has already recorded the span. */
+    if self.key(&span).is_some() {
+      return;
+    }
+    let parent = span.parent().and_then(|p| self.key(&p));
+    let mut spans = self.spans.lock().unwrap();
+    let key = spans.next;
+    spans.next += 1;
+    spans.records.insert(key, Record {
+      name: span.name(),
+      parent,
+      created: Instant::now(),
+      closed: None,
+      entered: None,
+      busy: Duration::ZERO,
+    });
+    let mut extensions = span.extensions_mut();
+    match extensions.get_mut::<Keys>() {
+      Some(keys) => {
+        keys.0.insert(self.owner(), key);
+      },
+      None => extensions.insert(Keys(HashMap::from([(self.owner(), key)]))),
+    }
+  }
+
+  fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+    self.with_record(id, &ctx, |r| r.entered = Some(Instant::now()));
+  }
+
+  fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+    self.with_record(id, &ctx, |r| {
+      if let Some(entered) = r.entered.take() {
+        r.busy += entered.elapsed();
+      }
+    });
+  }
+
+  fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+    self.with_record(&id, &ctx, |r| r.closed = Some(Instant::now()));
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use ::tracing_subscriber::layer::SubscriberExt;
+
+  #[test]
+  fn nested_spans() {
+    let layer = SpanTreeLayer::new();
+    let subscriber = ::tracing_subscriber::registry().with(layer.clone());
+    let open = tracing::subscriber::with_default(subscriber, || {
+      let outer = tracing::info_span!("outer");
+      outer.in_scope(|| {
+        tracing::info_span!("a").in_scope(|| std::thread::sleep(Duration::from_millis(20)));
+        tracing::info_span!("b \"quoted\"").in_scope(|| ());
+      });
+      tracing::info_span!("open")
+    });
+
+    let gb = layer.flush();
+    let labels: Vec<String> = gb
+      .vertices()
+      .map(|v| v.label.as_ref().unwrap().0.clone())
+      .collect();
+    assert_eq!(labels.len(), 4);
+    assert!(labels[0].starts_with("outer\\nbusy "));
+    assert!(labels[2].starts_with("b \\\"quoted\\\"\\n"));
+    let styles: Vec<Option<&str>> = gb
+      .vertices()
+      .map(|v| v.style.as_ref().map(|s| s.0.as_str()))
+      .collect();
+    assert_eq!(styles, vec![None, None, None, Some("dashed")]);
+
+    let edges: Vec<(&str, &str, f64)> = gb
+      .edges()
+      .map(|e| {
+        let width = e.attributes["penwidth"].format();
+        (
+          e.source.as_str(),
+          e.target.as_str(),
+          width.trim_matches('"').parse().unwrap(),
+        )
+      })
+      .collect();
+    assert_eq!(edges.len(), 2);
+    assert_eq!((edges[0].0, edges[0].1), ("s0", "s1"));
+    assert_eq!((edges[1].0, edges[1].1), ("s0", "s2"));
+    assert!(edges[0].2 > edges[1].2);
+
+    let gb = layer.flush();
+    assert_eq!(gb.vertices().count(), 1);
+    drop(open);
+    let gb = layer.flush();
+    assert_eq!(gb.vertices().next().unwrap().style, None);
+    assert_eq!(layer.flush().vertices().count(), 0);
+  }
+
+  #[test]
+  fn several_layers() {
+    let (first, second) = (SpanTreeLayer::new(), SpanTreeLayer::new());
+    let subscriber = ::tracing_subscriber::registry()
+      .with(first.clone())
+      .with(second.clone())
+      .with(first.clone());
+    tracing::subscriber::with_default(subscriber, || {
+      tracing::info_span!("outer").in_scope(|| tracing::info_span!("inner").in_scope(|| ()));
+    });
+    for layer in [first, second] {
+      let gb = layer.flush();
+      assert_eq!(gb.vertices().count(), 2);
+      let edges: Vec<(&str, &str)> = gb
+        .edges()
+        .map(|e| (e.source.as_str(), e.target.as_str()))
+        .collect();
+      assert_eq!(edges, vec![("s0", "s1")]);
+    }
+  }
+}
@@ -0,0 +1,180 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Reusable styling which fills in any attributes left unset on an entity.
+//!
+//! A [`Theme`] is applied by
+//! [`GraphBuilder::build`](crate::generator::GraphBuilder::build)
+//! after [`GraphBuilder::set_theme`](crate::generator::GraphBuilder::set_theme)
+//! is called. Vertices and edges are styled through graph-wide `node` and
+//! `edge` defaults, so the `node_defaults` of a subgraph or group, and attributes
+//! set explicitly on an entity (including those in its free-form
+//! `attributes` map), take precedence. Subgraphs have any unset attributes
+//! filled in directly.
+
+use crate::entities::*;
+
+/// The colors to use for each kind of entity.
+#[derive(Debug, Clone, Default)]
+pub struct ColorRoles {
+  /// Background color for the whole graph.
+  pub background: Option<Color>,
+  /// Outline color for vertices.
+  pub node: Option<Color>,
+  /// Label color for vertices.
+  pub node_font: Option<Color>,
+  /// Line color for edges.
+  pub edge: Option<Color>,
+  /// Label color for edges.
+  pub edge_font: Option<Color>,
+  /// Bounding box color for subgraphs.
+  pub cluster: Option<Color>,
+  /// Label color for subgraphs.
+  pub cluster_font: Option<Color>,
+}
+
+/// A set of default attributes for every entity in a graph.
+#[derive(Debug, Clone, Default)]
+#[allow(missing_docs)]
+pub struct Theme {
+  pub node_shape: Option<Shape>,
+  pub node_style: Option<Style>,
+  pub edge_style: Option<Style>,
+  pub fontname: Option<FontName>,
+  pub fontsize: Option<FontSize>,
+  pub colors: ColorRoles,
+}
+
+impl Theme {
+  fn color(name: &str) -> Option<Color> { Some(Color(name.to_string())) }
+
+  /// Black-on-white colors, matching what graphviz renders without any
+  /// attributes set.
+  pub fn light() -> Self {
+    Self {
+      colors: ColorRoles {
+        background: Self::color("white"),
+        node: Self::color("black"),
+        node_font: Self::color("black"),
+        edge: Self::color("black"),
+        edge_font: Self::color("black"),
+        cluster: Self::color("black"),
+        cluster_font: Self::color("black"),
+      },
+      ..Default::default()
+    }
+  }
+
+  /// Light-on-dark colors, for graphs embedded in dark-mode documentation.
+  pub fn dark() -> Self {
+    Self {
+      colors: ColorRoles {
+        background: Self::color("#1e1e1e"),
+        node: Self::color("#d4d4d4"),
+        node_font: Self::color("#d4d4d4"),
+        edge: Self::color("#9cdcfe"),
+        edge_font: Self::color("#c8c8c8"),
+        cluster: Self::color("#808080"),
+        cluster_font: Self::color("#d4d4d4"),
+      },
+      ..Default::default()
+    }
+  }
+
+  /// Fill `slot` from `default`, unless it's set already, or `name` is set
+  /// through the free-form `attributes` of the entity.
+  fn fill<T: Clone>(
+    slot: &mut Option<T>,
+    default: &Option<T>,
+    attributes: &Attributes,
+    name: &str,
+  ) {
+    if slot.is_none() && !attributes.contains_key(name) {
+      *slot = default.clone();
+    }
+  }
+
+  /// Fill in any unset attributes which apply to the whole graph.
+  pub fn apply_graph(&self, attrs: &mut GraphAttributes) {
+    Self::fill(
+      &mut attrs.bgcolor,
+      &self.colors.background,
+      &attrs.attributes,
+      "bgcolor",
+    );
+  }
+
+  /// Whether this theme sets any attribute for vertices which a `node [...]`
+  /// statement can carry.
+  pub(crate) fn has_node_defaults(&self) -> bool {
+    self.colors.node.is_some()
+      || self.colors.node_font.is_some()
+      || self.node_shape.is_some()
+      || self.node_style.is_some()
+      || self.fontname.is_some()
+      || self.fontsize.is_some()
+  }
+
+  /// Whether this theme sets any attribute for edges which an `edge [...]`
+  /// statement can carry.
+  pub(crate) fn has_edge_defaults(&self) -> bool {
+    self.colors.edge.is_some()
+      || self.colors.edge_font.is_some()
+      || self.edge_style.is_some()
+      || self.fontname.is_some()
+      || self.fontsize.is_some()
+  }
+
+  /// Fill in any unset attributes of the subgraphs within `entity` (and
+  /// `entity` itself, if it's a subgraph) from this theme. Vertices and
+  /// edges are left alone, since the graph-wide `node` and `edge` defaults
+  /// already style them without overriding the `node_defaults` of the
+  /// subgraphs and groups they're in.
+  pub fn apply(&self, entity: &mut Entity) {
+    let Self {
+      fontname,
+      fontsize,
+      colors,
+      ..
+    } = self;
+    match entity {
+      Entity::Vertex(_) | Entity::Edge(_) => (),
+      Entity::Subgraph(sg) => {
+        Self::fill(&mut sg.color, &colors.cluster, &sg.attributes, "color");
+        Self::fill(
+          &mut sg.fontcolor,
+          &colors.cluster_font,
+          &sg.attributes,
+          "fontcolor",
+        );
+        Self::fill(&mut sg.fontname, fontname, &sg.attributes, "fontname");
+        Self::fill(&mut sg.fontsize, fontsize, &sg.attributes, "fontsize");
+        for e in sg.entities.iter_mut() {
+          self.apply(e);
+        }
+      },
+      Entity::Group(g) => {
+        for e in g.entities.iter_mut() {
+          self.apply(e);
+        }
+      },
+      Entity::Comment(_) => (),
+    }
+  }
+}
@@ -0,0 +1,122 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A minimal [`Tree`] type which draws itself with [`Graphable`].
+//!
+//!```
+//! use graphvizier::{entities::Id, tree::Tree, Graphable};
+//!
+//! let tree = Tree::new("root").child(Tree::new("left")).child(Tree::new("right"));
+//! let dot = tree.build_graph().build(Id::new("t")).unwrap().0;
+//! assert!(dot.contains("t0 -> t0_1;"));
+//! ```
+
+use crate::{
+  entities::{Edge, Entity, Id, Label, Vertex},
+  generator::GraphBuilder,
+  Graphable,
+};
+
+use std::fmt;
+
+/// A value and its subtrees.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tree<T> {
+  #[allow(missing_docs)]
+  pub value: T,
+  #[allow(missing_docs)]
+  pub children: Vec<Tree<T>>,
+}
+
+impl<T> Tree<T> {
+  /// A leaf holding `value`.
+  pub fn new(value: T) -> Self {
+    Self {
+      value,
+      children: Vec::new(),
+    }
+  }
+
+  /// Append `child` to this tree's children.
+  pub fn child(mut self, child: Tree<T>) -> Self {
+    self.children.push(child);
+    self
+  }
+}
+
+/// Each node is drawn as a vertex labelled with its value as plain text, with
+/// an edge to each of its children. Vertex ids follow the path from the root,
+/// e.g. `t0_2_1` for the second child of the third child of the root, so equal
+/// values at different positions remain distinct.
+impl<T: fmt::Display> Graphable for Tree<T> {
+  fn build_graph(self) -> GraphBuilder {
+    fn walk<T: fmt::Display>(tree: Tree<T>, id: Id, gb: &mut GraphBuilder) {
+      let Tree { value, children } = tree;
+      gb.accept_entity(Entity::Vertex(
+        Vertex::new(id.clone()).label(Label::escape_text(value.to_string())),
+      ));
+      for (i, child) in children.into_iter().enumerate() {
+        let child_id = Id::new(format!("{}_{}", id.as_str(), i));
+        gb.accept_entity(Entity::Edge(Edge::between(id.clone(), child_id.clone())));
+        walk(child, child_id, gb);
+      }
+    }
+    let mut gb = GraphBuilder::new();
+    walk(self, Id::new("t0"), &mut gb);
+    gb
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn repeated_values() {
+    let tree = Tree::new(1)
+      .child(Tree::new(2).child(Tree::new(1)))
+      .child(Tree::new(2));
+    let gb = tree.build_graph();
+    let vertices: Vec<(&str, &str)> = gb
+      .vertices()
+      .map(|v| (v.id.as_str(), v.label.as_ref().unwrap().0.as_str()))
+      .collect();
+    assert_eq!(vertices, vec![
+      ("t0", "1"),
+      ("t0_0", "2"),
+      ("t0_0_0", "1"),
+      ("t0_1", "2")
+    ]);
+    let edges: Vec<Edge> = gb.edges().cloned().collect();
+    assert_eq!(edges, vec![
+      Edge::between("t0", "t0_0"),
+      Edge::between("t0_0", "t0_0_0"),
+      Edge::between("t0", "t0_1"),
+    ]);
+  }
+
+  #[test]
+  fn escaped_labels() {
+    let gb = Tree::new("say \"hi\"\\n").build_graph();
+    assert_eq!(
+      gb.vertices().next().unwrap().label,
+      Some(Label("say \\\"hi\\\"\\\\n".to_string()))
+    );
+  }
+}
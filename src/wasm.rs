@@ -0,0 +1,218 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! [wasm-bindgen](https://docs.rs/wasm-bindgen) bindings for building graphs
+//! in the browser, e.g. to render with d3-graphviz.
+//!
+//! The exported JavaScript classes mirror the Rust API: `Vertex`, `Edge`, and
+//! `Subgraph` have chainable setters, and `GraphBuilder.build()` returns the
+//! DOT document as a string.
+//!
+//!```js
+//! import { GraphBuilder, Vertex, Edge } from "graphvizier";
+//!
+//! const gb = new GraphBuilder();
+//! gb.addVertex(new Vertex("a").label("A").shape("box"));
+//! gb.addEdge(new Edge("a", "b").style("dashed"));
+//! d3.select("#graph").graphviz().renderDot(gb.build("g"));
+//! ```
+//!
+//! Setters take ownership of the object they're called on and return it, so
+//! only the returned object may be used afterwards. Adding an entity to a
+//! builder or subgraph copies it.
+//!
+//! Requires the `wasm` feature. Build the module and its JavaScript glue
+//! into `pkg/` with [wasm-pack](https://rustwasm.github.io/wasm-pack/):
+//!
+//!```sh
+//! wasm-pack build --target web -- --features wasm
+//! ```
+
+use crate::{
+  entities::{style::Color, Edge, Entity, Id, Subgraph, Vertex},
+  generator::GraphBuilder,
+};
+
+use wasm_bindgen::prelude::*;
+
+/// A single node in the graph.
+#[wasm_bindgen(js_name = Vertex)]
+#[derive(Debug, Clone)]
+pub struct WasmVertex(Vertex);
+
+#[wasm_bindgen(js_class = Vertex)]
+impl WasmVertex {
+  /// A vertex with no attributes set.
+  #[wasm_bindgen(constructor)]
+  pub fn new(id: &str) -> Self { Self(Vertex::new(id)) }
+
+  #[allow(missing_docs)]
+  pub fn label(self, label: &str) -> Self { Self(self.0.label(label)) }
+
+  #[allow(missing_docs)]
+  #[wasm_bindgen(js_name = htmlLabel)]
+  pub fn html_label(self, html_label: &str) -> Self { Self(self.0.html_label(html_label)) }
+
+  #[allow(missing_docs)]
+  pub fn color(self, color: &str) -> Self { Self(self.0.color(Color(color.to_string()))) }
+
+  #[allow(missing_docs)]
+  pub fn shape(self, shape: &str) -> Self { Self(self.0.shape(shape)) }
+
+  #[allow(missing_docs)]
+  pub fn style(self, style: &str) -> Self { Self(self.0.style(style)) }
+
+  /// Set any other attribute.
+  pub fn attr(self, name: &str, value: &str) -> Self { Self(self.0.attr(name, value)) }
+}
+
+/// A connection between two vertices.
+#[wasm_bindgen(js_name = Edge)]
+#[derive(Debug, Clone)]
+pub struct WasmEdge(Edge);
+
+#[wasm_bindgen(js_class = Edge)]
+impl WasmEdge {
+  /// An edge from `source` to `target` with no attributes set.
+  #[wasm_bindgen(constructor)]
+  pub fn new(source: &str, target: &str) -> Self { Self(Edge::between(source, target)) }
+
+  #[allow(missing_docs)]
+  pub fn label(self, label: &str) -> Self { Self(self.0.label(label)) }
+
+  #[allow(missing_docs)]
+  pub fn color(self, color: &str) -> Self { Self(self.0.color(Color(color.to_string()))) }
+
+  #[allow(missing_docs)]
+  pub fn style(self, style: &str) -> Self { Self(self.0.style(style)) }
+
+  #[allow(missing_docs)]
+  pub fn weight(self, weight: f64) -> Self { Self(self.0.weight(weight)) }
+
+  /// Set any other attribute.
+  pub fn attr(self, name: &str, value: &str) -> Self { Self(self.0.attr(name, value)) }
+}
+
+/// A cluster of entities.
+#[wasm_bindgen(js_name = Subgraph)]
+#[derive(Debug, Clone)]
+pub struct WasmSubgraph(Subgraph);
+
+#[wasm_bindgen(js_class = Subgraph)]
+impl WasmSubgraph {
+  /// An empty cluster.
+  #[wasm_bindgen(constructor)]
+  pub fn new(id: &str) -> Self { Self(Subgraph::new(id)) }
+
+  #[allow(missing_docs)]
+  pub fn label(self, label: &str) -> Self { Self(self.0.label(label)) }
+
+  /// Whether to draw a bounding box around this subgraph.
+  pub fn cluster(self, is_cluster: bool) -> Self { Self(self.0.cluster(is_cluster)) }
+
+  /// Set any other attribute.
+  pub fn attr(self, name: &str, value: &str) -> Self { Self(self.0.attr(name, value)) }
+
+  #[allow(missing_docs)]
+  #[wasm_bindgen(js_name = addVertex)]
+  pub fn add_vertex(&mut self, vertex: &WasmVertex) {
+    self.0.entities.push(Entity::Vertex(vertex.0.clone()));
+  }
+
+  #[allow(missing_docs)]
+  #[wasm_bindgen(js_name = addEdge)]
+  pub fn add_edge(&mut self, edge: &WasmEdge) {
+    self.0.entities.push(Entity::Edge(edge.0.clone()));
+  }
+
+  #[allow(missing_docs)]
+  #[wasm_bindgen(js_name = addSubgraph)]
+  pub fn add_subgraph(&mut self, subgraph: &WasmSubgraph) {
+    self.0.entities.push(Entity::Subgraph(subgraph.0.clone()));
+  }
+}
+
+/// Collects entities, and writes them out as a DOT document.
+#[wasm_bindgen(js_name = GraphBuilder)]
+#[derive(Debug, Clone, Default)]
+pub struct WasmGraphBuilder {
+  entities: Vec<Entity>,
+}
+
+#[wasm_bindgen(js_class = GraphBuilder)]
+impl WasmGraphBuilder {
+  /// An empty graph.
+  #[wasm_bindgen(constructor)]
+  pub fn new() -> Self { Self::default() }
+
+  #[allow(missing_docs)]
+  #[wasm_bindgen(js_name = addVertex)]
+  pub fn add_vertex(&mut self, vertex: &WasmVertex) {
+    self.entities.push(Entity::Vertex(vertex.0.clone()));
+  }
+
+  #[allow(missing_docs)]
+  #[wasm_bindgen(js_name = addEdge)]
+  pub fn add_edge(&mut self, edge: &WasmEdge) { self.entities.push(Entity::Edge(edge.0.clone())); }
+
+  #[allow(missing_docs)]
+  #[wasm_bindgen(js_name = addSubgraph)]
+  pub fn add_subgraph(&mut self, subgraph: &WasmSubgraph) {
+    self.entities.push(Entity::Subgraph(subgraph.0.clone()));
+  }
+
+  /// The DOT document for a graph named `name`. The builder is left
+  /// unchanged.
+  pub fn build(&self, name: &str) -> Result<String, JsError> {
+    let mut gb = GraphBuilder::new();
+    for entity in &self.entities {
+      gb.accept_entity(entity.clone());
+    }
+    gb.build(Id::new(name))
+      .map(|output| output.0)
+      .map_err(|e| JsError::new(&e.to_string()))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn build_from_bindings() {
+    let mut sg = WasmSubgraph::new("cluster_s").label("S");
+    sg.add_vertex(&WasmVertex::new("a").label("A").shape("box"));
+    let mut gb = WasmGraphBuilder::new();
+    gb.add_subgraph(&sg);
+    gb.add_edge(&WasmEdge::new("a", "b").style("dashed").attr("tooltip", "t"));
+
+    let expected = "digraph g {\n  \
+                      compound = true;\n\n  \
+                      subgraph cluster_s {\n    \
+                        label = \"S\";\n    \
+                        cluster = true;\n    \
+                        rank = same;\n\n\n    \
+                        a[label=\"A\", shape=\"box\"];\n  \
+                      }\n\n  \
+                      a -> b[style=\"dashed\", tooltip=\"t\"];\n\
+                    }\n";
+    assert_eq!(gb.build("g").unwrap(), expected);
+    assert_eq!(gb.build("g").unwrap(), expected);
+  }
+}
@@ -0,0 +1,413 @@
+/*
+ * Description: A dot file generator.
+ *
+ * Copyright (C) 2023 Danny McClanahan <dmcC2@hypnicjerk.ai>
+ * SPDX-License-Identifier: Apache-2.0
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Typed drawing operations from `dot -Txdot` output.
+//!
+//! The [xdot format](https://graphviz.org/docs/outputs/canon/#xdot) adds
+//! attributes like `_draw_` and `_ldraw_` to each entity, describing how
+//! Graphviz rendered its shape and label as a list of drawing operations.
+//! [`parse_ops`] decodes one such attribute, and [`drawing`] decodes all of
+//! them from an entity's [`Attributes`], e.g. as read by
+//! [`parser::parse`](crate::parser::parse).
+//!
+//!```
+//! use graphvizier::xdot::*;
+//!
+//! let ops = parse_ops("c 7 -#000000 e 27 18 27 18 T 27 14.3 0 5 1 -a ").unwrap();
+//! assert_eq!(ops[0], DrawOp::PenColor("#000000".to_string()));
+//! assert_eq!(
+//!   ops[2],
+//!   DrawOp::Text {
+//!     pos: Point { x: 27.0, y: 14.3 },
+//!     align: Align::Center,
+//!     width: 5.0,
+//!     text: "a".to_string(),
+//!   }
+//! );
+//! ```
+
+use crate::entities::{AttrValue, Attributes};
+
+use std::fmt;
+
+/// The attributes `dot -Txdot` emits drawing operations in.
+pub static DRAW_ATTRIBUTES: [&str; 7] = [
+  "_background",
+  "_draw_",
+  "_ldraw_",
+  "_hdraw_",
+  "_tdraw_",
+  "_hldraw_",
+  "_tldraw_",
+];
+
+/// A position in points, with the origin at the lower left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(missing_docs)]
+pub struct Point {
+  pub x: f64,
+  pub y: f64,
+}
+
+/// How text is positioned relative to its anchor point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Align {
+  Left,
+  Center,
+  Right,
+}
+
+/// A single xdot drawing operation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawOp {
+  /// `E` (filled) or `e`.
+  Ellipse {
+    #[allow(missing_docs)]
+    center: Point,
+    /// The horizontal radius.
+    width: f64,
+    /// The vertical radius.
+    height: f64,
+    #[allow(missing_docs)]
+    filled: bool,
+  },
+  /// `P` (filled) or `p`.
+  #[allow(missing_docs)]
+  Polygon { points: Vec<Point>, filled: bool },
+  /// `L`.
+  Polyline(Vec<Point>),
+  /// `B`, or `b` if filled.
+  #[allow(missing_docs)]
+  BSpline { points: Vec<Point>, filled: bool },
+  /// `T`.
+  Text {
+    /// The baseline point which `align` is relative to.
+    pos: Point,
+    #[allow(missing_docs)]
+    align: Align,
+    /// The width of the rendered text.
+    width: f64,
+    #[allow(missing_docs)]
+    text: String,
+  },
+  /// `C`: the fill color for subsequent operations.
+  FillColor(String),
+  /// `c`: the pen color for subsequent operations.
+  PenColor(String),
+  /// `F`: the font for subsequent text.
+  #[allow(missing_docs)]
+  Font { size: f64, name: String },
+  /// `S`: a style attribute like `dashed` or `setlinewidth(2)`.
+  Style(String),
+  /// `t`: bold, italic, etc. flags for subsequent text.
+  FontCharacteristics(u32),
+  /// `I`: an image scaled into the given box.
+  #[allow(missing_docs)]
+  Image {
+    pos: Point,
+    width: f64,
+    height: f64,
+    name: String,
+  },
+}
+
+impl DrawOp {
+  /// The lower left and upper right corners of the area this operation draws
+  /// in, if it draws a shape. Text has no bounds here, since its height
+  /// isn't recorded in the operation.
+  pub fn bounds(&self) -> Option<(Point, Point)> {
+    let of_points = |points: &[Point]| {
+      let first = points.first()?;
+      Some(points.iter().fold((*first, *first), |(lo, hi), p| {
+        (
+          Point {
+            x: lo.x.min(p.x),
+            y: lo.y.min(p.y),
+          },
+          Point {
+            x: hi.x.max(p.x),
+            y: hi.y.max(p.y),
+          },
+        )
+      }))
+    };
+    match self {
+      Self::Ellipse {
+        center,
+        width,
+        height,
+        ..
+      } => Some((
+        Point {
+          x: center.x - width,
+          y: center.y - height,
+        },
+        Point {
+          x: center.x + width,
+          y: center.y + height,
+        },
+      )),
+      Self::Polygon { points, .. } | Self::Polyline(points) | Self::BSpline { points, .. } => {
+        of_points(points)
+      },
+      Self::Image {
+        pos, width, height, ..
+      } => Some((*pos, Point {
+        x: pos.x + width,
+        y: pos.y + height,
+      })),
+      _ => None,
+    }
+  }
+}
+
+/// Why [`parse_ops`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XdotError {
+  /// The byte offset into the attribute value.
+  pub offset: usize,
+  #[allow(missing_docs)]
+  pub message: String,
+}
+
+impl fmt::Display for XdotError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "at byte {}: {}", self.offset, self.message)
+  }
+}
+
+impl std::error::Error for XdotError {}
+
+struct Reader<'a> {
+  s: &'a str,
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn error(&self, message: impl Into<String>) -> XdotError {
+    XdotError {
+      offset: self.pos,
+      message: message.into(),
+    }
+  }
+
+  fn skip_whitespace(&mut self) {
+    let rest = &self.s[self.pos..];
+    self.pos += rest.len() - rest.trim_start().len();
+  }
+
+  fn word(&mut self) -> Option<&'a str> {
+    self.skip_whitespace();
+    let rest = &self.s[self.pos..];
+    let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    if len == 0 {
+      return None;
+    }
+    self.pos += len;
+    Some(&rest[..len])
+  }
+
+  fn number<T: std::str::FromStr>(&mut self) -> Result<T, XdotError> {
+    let start = self.pos;
+    let word = self.word().ok_or_else(|| self.error("expected a number"))?;
+    word.parse().map_err(|_| XdotError {
+      offset: start,
+      message: format!("expected a number, found {:?}", word),
+    })
+  }
+
+  fn point(&mut self) -> Result<Point, XdotError> {
+    Ok(Point {
+      x: self.number()?,
+      y: self.number()?,
+    })
+  }
+
+  fn points(&mut self) -> Result<Vec<Point>, XdotError> {
+    let n: usize = self.number()?;
+    (0..n).map(|_| self.point()).collect()
+  }
+
+  /// A byte-counted string, written as `n -text`.
+  fn string(&mut self) -> Result<String, XdotError> {
+    let n: usize = self.number()?;
+    self.skip_whitespace();
+    if !self.s[self.pos..].starts_with('-') {
+      return Err(self.error("expected '-' before string"));
+    }
+    let start = self.pos + 1;
+    match start.checked_add(n).and_then(|end| self.s.get(start..end)) {
+      Some(text) => {
+        self.pos = start + n;
+        Ok(text.to_string())
+      },
+      None => Err(self.error(format!("expected a string of {} bytes", n))),
+    }
+  }
+}
+
+/// Parse the value of a single xdot attribute like `_draw_`.
+pub fn parse_ops(s: &str) -> Result<Vec<DrawOp>, XdotError> {
+  let mut r = Reader { s, pos: 0 };
+  let mut ret = Vec::new();
+  loop {
+    let start = r.pos;
+    let op = match r.word() {
+      None => return Ok(ret),
+      Some(op) => op,
+    };
+    ret.push(match op {
+      "E" | "e" => DrawOp::Ellipse {
+        center: r.point()?,
+        width: r.number()?,
+        height: r.number()?,
+        filled: op == "E",
+      },
+      "P" | "p" => DrawOp::Polygon {
+        points: r.points()?,
+        filled: op == "P",
+      },
+      "L" => DrawOp::Polyline(r.points()?),
+      "B" | "b" => DrawOp::BSpline {
+        points: r.points()?,
+        filled: op == "b",
+      },
+      "T" => {
+        let pos = r.point()?;
+        let align = match r.number::<i32>()? {
+          -1 => Align::Left,
+          0 => Align::Center,
+          1 => Align::Right,
+          j => return Err(r.error(format!("invalid text alignment {}", j))),
+        };
+        DrawOp::Text {
+          pos,
+          align,
+          width: r.number()?,
+          text: r.string()?,
+        }
+      },
+      "C" => DrawOp::FillColor(r.string()?),
+      "c" => DrawOp::PenColor(r.string()?),
+      "F" => DrawOp::Font {
+        size: r.number()?,
+        name: r.string()?,
+      },
+      "S" => DrawOp::Style(r.string()?),
+      "t" => DrawOp::FontCharacteristics(r.number()?),
+      "I" => DrawOp::Image {
+        pos: r.point()?,
+        width: r.number()?,
+        height: r.number()?,
+        name: r.string()?,
+      },
+      op => {
+        return Err(XdotError {
+          offset: start,
+          message: format!("unknown operation {:?}", op),
+        })
+      },
+    });
+  }
+}
+
+/// Decode every attribute in [`DRAW_ATTRIBUTES`] set in `attrs`, in that
+/// order.
+pub fn drawing(attrs: &Attributes) -> Result<Vec<(&'static str, Vec<DrawOp>)>, XdotError> {
+  let mut ret = Vec::new();
+  for name in DRAW_ATTRIBUTES.iter() {
+    if let Some(AttrValue::Quoted(value)) = attrs.get(*name) {
+      ret.push((*name, parse_ops(value)?));
+    }
+  }
+  Ok(ret)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{entities::Entity, parser::parse};
+
+  #[test]
+  fn parse_xdot_output() {
+    let graph = parse(
+      "digraph g {\n\
+         a [_draw_=\"c 7 -#000000 p 3 0 0 10 0 5 8 \",\n\
+            _ldraw_=\"F 14 11 -Times-Roman c 7 -#000000 T 5 3 -1 6 2 -é \"];\n\
+         a -> b [_draw_=\"S 6 -dashed B 4 0 0 1 1 2 2 3 3 \"];\n\
+       }",
+    )
+    .unwrap();
+    let Entity::Vertex(ref a) = graph.entities[0] else {
+      panic!("{:?}", graph.entities)
+    };
+    let ops = drawing(&a.attributes).unwrap();
+    assert_eq!(ops.len(), 2);
+    assert_eq!(ops[0].0, "_draw_");
+    assert_eq!(
+      ops[0].1[1].bounds(),
+      Some((Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 8.0 }))
+    );
+    assert_eq!(ops[1].1, vec![
+      DrawOp::Font {
+        size: 14.0,
+        name: "Times-Roman".to_string(),
+      },
+      DrawOp::PenColor("#000000".to_string()),
+      DrawOp::Text {
+        pos: Point { x: 5.0, y: 3.0 },
+        align: Align::Left,
+        width: 6.0,
+        text: "é".to_string(),
+      },
+    ]);
+
+    let Entity::Edge(ref e) = graph.entities[1] else {
+      panic!("{:?}", graph.entities)
+    };
+    let ops = drawing(&e.attributes).unwrap();
+    assert_eq!(ops[0].1[0], DrawOp::Style("dashed".to_string()));
+    assert!(
+      matches!(ops[0].1[1], DrawOp::BSpline { ref points, filled: false } if points.len() == 4)
+    );
+  }
+
+  #[test]
+  fn xdot_errors() {
+    assert_eq!(
+      parse_ops("e 1 2 3").unwrap_err().to_string(),
+      "at byte 7: expected a number"
+    );
+    assert_eq!(
+      parse_ops("c 9 -red").unwrap_err().to_string(),
+      "at byte 4: expected a string of 9 bytes"
+    );
+    assert_eq!(
+      parse_ops("c 18446744073709551615 -red")
+        .unwrap_err()
+        .to_string(),
+      "at byte 23: expected a string of 18446744073709551615 bytes"
+    );
+    assert_eq!(
+      parse_ops("Q 1").unwrap_err().to_string(),
+      "at byte 0: unknown operation \"Q\""
+    );
+  }
+}